@@ -0,0 +1,351 @@
+use std::collections::HashSet;
+
+use amcl_wrapper::group_elem::GroupElement;
+use rayon::prelude::*;
+
+use crate::errors::ForwardSecureSignatureError;
+use crate::signature::Signature;
+use crate::{SignatureGroup, VerkeyGroup};
+
+/// Aggregates signature shares as they arrive. Each share is folded into the running aggregate
+/// immediately so a coordinator never has to hold all shares in memory at once.
+pub struct Aggregator {
+    sigma_1: SignatureGroup,
+    sigma_2: VerkeyGroup,
+    signers: HashSet<usize>,
+}
+
+impl Aggregator {
+    pub fn new() -> Self {
+        Self {
+            sigma_1: SignatureGroup::identity(),
+            sigma_2: VerkeyGroup::identity(),
+            signers: HashSet::new(),
+        }
+    }
+
+    /// Fold the share of signer `id` into the aggregate. Fails if a share of `id` was already
+    /// folded, leaving the aggregate unchanged.
+    pub fn add(&mut self, id: usize, sig: &Signature) -> Result<(), ForwardSecureSignatureError> {
+        if !self.signers.insert(id) {
+            return Err(ForwardSecureSignatureError::DuplicateShare { index: id });
+        }
+        self.sigma_1 += &sig.sigma_1;
+        self.sigma_2 += &sig.sigma_2;
+        Ok(())
+    }
+
+    /// Fold every share yielded by `shares`. Shares are consumed one at a time. Stops at the
+    /// first duplicate share, keeping the shares folded before it.
+    pub fn add_all<I: IntoIterator<Item = (usize, Signature)>>(
+        &mut self,
+        shares: I,
+    ) -> Result<(), ForwardSecureSignatureError> {
+        for (id, sig) in shares {
+            self.add(id, &sig)?;
+        }
+        Ok(())
+    }
+
+    /// Whether a share of signer `id` has been folded
+    pub fn contains(&self, id: usize) -> bool {
+        self.signers.contains(&id)
+    }
+
+    /// Returns the aggregate of all shares seen so far. Can be called any number of times.
+    pub fn aggregate(&self) -> Signature {
+        Signature {
+            sigma_1: self.sigma_1.clone(),
+            sigma_2: self.sigma_2.clone(),
+        }
+    }
+
+    /// Ids of signers whose shares have been folded so far
+    pub fn signers(&self) -> &HashSet<usize> {
+        &self.signers
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.signers.is_empty()
+    }
+
+    /// Consume the aggregator returning the final aggregate and the set of signers
+    pub fn finalize(self) -> (Signature, HashSet<usize>) {
+        (
+            Signature {
+                sigma_1: self.sigma_1,
+                sigma_2: self.sigma_2,
+            },
+            self.signers,
+        )
+    }
+}
+
+impl Default for Aggregator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Number of points each thread sums before the partial sums are added up
+const SUM_CHUNK_SIZE: usize = 1024;
+
+fn parallel_sum<G: GroupElement + Send + Sync>(points: &[G]) -> G {
+    points
+        .par_chunks(SUM_CHUNK_SIZE)
+        .map(|chunk| {
+            let mut sum = G::identity();
+            for p in chunk {
+                sum.add_assign_(p);
+            }
+            sum
+        })
+        .reduce(G::identity, |mut a, b| {
+            a.add_assign_(&b);
+            a
+        })
+}
+
+/// Buffers signature shares to aggregate them all at once, for committees large enough that
+/// folding shares one at a time as `Aggregator` does is the bottleneck. The points of the shares
+/// are stored in one array per group and are checked and summed in parallel over chunks of the
+/// arrays.
+#[derive(Clone, Debug, Default)]
+pub struct AggregateBuffer {
+    ids: Vec<usize>,
+    sigma_1: Vec<SignatureGroup>,
+    sigma_2: Vec<VerkeyGroup>,
+    signers: HashSet<usize>,
+}
+
+impl AggregateBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Buffer for `n` shares before it reallocates
+    pub fn with_capacity(n: usize) -> Self {
+        Self {
+            ids: Vec::with_capacity(n),
+            sigma_1: Vec::with_capacity(n),
+            sigma_2: Vec::with_capacity(n),
+            signers: HashSet::with_capacity(n),
+        }
+    }
+
+    /// Buffer the share of signer `id`. Fails if a share of `id` was already buffered.
+    pub fn push(&mut self, id: usize, sig: &Signature) -> Result<(), ForwardSecureSignatureError> {
+        if !self.signers.insert(id) {
+            return Err(ForwardSecureSignatureError::DuplicateShare { index: id });
+        }
+        self.ids.push(id);
+        self.sigma_1.push(sig.sigma_1.clone());
+        self.sigma_2.push(sig.sigma_2.clone());
+        Ok(())
+    }
+
+    /// Buffer every share yielded by `shares`. Stops at the first duplicate share, keeping the
+    /// shares buffered before it.
+    pub fn extend<I: IntoIterator<Item = (usize, Signature)>>(
+        &mut self,
+        shares: I,
+    ) -> Result<(), ForwardSecureSignatureError> {
+        for (id, sig) in shares {
+            self.push(id, &sig)?;
+        }
+        Ok(())
+    }
+
+    /// Ids of the signers in the order their shares were buffered
+    pub fn ids(&self) -> &[usize] {
+        &self.ids
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Check that the points of every share are in the prime order subgroup. Fails with
+    /// `InvalidShare` for the first share buffered that is not.
+    pub fn check_subgroups(&self) -> Result<(), ForwardSecureSignatureError> {
+        let invalid = (0..self.len()).into_par_iter().find_first(|i| {
+            !self.sigma_1[*i].has_correct_order() || !self.sigma_2[*i].has_correct_order()
+        });
+        match invalid {
+            Some(i) => Err(ForwardSecureSignatureError::InvalidShare { index: self.ids[i] }),
+            None => Ok(()),
+        }
+    }
+
+    /// The aggregate of all shares buffered, without checking them
+    pub fn aggregate(&self) -> Signature {
+        let (sigma_1, sigma_2) = rayon::join(
+            || parallel_sum(&self.sigma_1),
+            || parallel_sum(&self.sigma_2),
+        );
+        Signature { sigma_1, sigma_2 }
+    }
+
+    /// `check_subgroups` and then `aggregate`
+    pub fn aggregate_checked(&self) -> Result<Signature, ForwardSecureSignatureError> {
+        self.check_subgroups()?;
+        Ok(self.aggregate())
+    }
+
+    /// Empty the buffer, keeping its capacity
+    pub fn clear(&mut self) {
+        self.ids.clear();
+        self.sigma_1.clear();
+        self.sigma_2.clear();
+        self.signers.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{InMemorySigKeyDatabase, Keypair, SigManager, Verkey};
+    use crate::util::{calculate_l, GeneratorSet};
+
+    #[test]
+    fn test_streaming_aggregation() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let l = calculate_l(T).unwrap();
+        let t = 1u128;
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let msg = "Hello".as_bytes();
+
+        let mut vks = vec![];
+        let mut sigs = vec![];
+        for _ in 0..4 {
+            let mut db = InMemorySigKeyDatabase::new();
+            let (keypair, _) = Keypair::new(T, &gens, &mut rng, &mut db).unwrap();
+            let sk = SigManager::get_key(t, &db).unwrap();
+            sigs.push(Signature::new(msg, t, l, &gens, sk, &mut rng).unwrap());
+            vks.push(keypair.ver_key);
+        }
+
+        let mut aggregator = Aggregator::new();
+        assert!(aggregator.is_empty());
+
+        // Intermediate aggregate over first 2 shares verifies against the first 2 verkeys
+        aggregator.add(1, &sigs[0]).unwrap();
+        aggregator.add(2, &sigs[1]).unwrap();
+        let partial = aggregator.aggregate();
+        assert!(partial
+            .verify_aggregated(msg, t, l, vec![&vks[0], &vks[1]], &gens)
+            .unwrap());
+        assert_eq!(aggregator.signers().len(), 2);
+
+        // Adding a share twice is rejected without changing the aggregate
+        assert!(aggregator.add(2, &sigs[1]).is_err());
+        assert!(aggregator.add(2, &sigs[2]).is_err());
+        assert_eq!(aggregator.aggregate(), partial);
+        assert!(aggregator.contains(2));
+        assert!(!aggregator.contains(3));
+
+        // Remaining shares are streamed from an iterator
+        aggregator
+            .add_all(
+                sigs.clone()
+                    .into_iter()
+                    .enumerate()
+                    .skip(2)
+                    .map(|(i, s)| (i + 1, s)),
+            )
+            .unwrap();
+        let (asig, signers) = aggregator.finalize();
+        assert_eq!(signers, (1..=4).collect::<HashSet<usize>>());
+        assert_eq!(asig, Signature::aggregate(sigs.iter().collect()));
+        assert!(asig
+            .verify_aggregated(msg, t, l, vks.iter().collect::<Vec<&Verkey>>(), &gens)
+            .unwrap());
+    }
+
+    #[test]
+    fn test_duplicate_shares_in_stream() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let l = calculate_l(T).unwrap();
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let msg = "Hello".as_bytes();
+        let mut sigs = vec![];
+        for _ in 0..3 {
+            let mut db = InMemorySigKeyDatabase::new();
+            Keypair::new(T, &gens, &mut rng, &mut db).unwrap();
+            let sk = SigManager::get_key(1, &db).unwrap();
+            sigs.push(Signature::new(msg, 1, l, &gens, sk, &mut rng).unwrap());
+        }
+        let mut aggregator = Aggregator::new();
+        let shares = vec![
+            (1, sigs[0].clone()),
+            (2, sigs[1].clone()),
+            (1, sigs[0].clone()),
+            (3, sigs[2].clone()),
+        ];
+        match aggregator.add_all(shares) {
+            Err(ForwardSecureSignatureError::DuplicateShare { index }) => assert_eq!(index, 1),
+            _ => panic!("duplicate share accepted"),
+        }
+        assert_eq!(
+            aggregator.aggregate(),
+            Signature::aggregate(vec![&sigs[0], &sigs[1]])
+        );
+        assert!(!aggregator.contains(3));
+    }
+
+    #[test]
+    fn test_aggregate_buffer() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let l = calculate_l(T).unwrap();
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let msg = "Hello".as_bytes();
+        let mut vks = vec![];
+        let mut sigs = vec![];
+        for _ in 0..5 {
+            let mut db = InMemorySigKeyDatabase::new();
+            let (keypair, _) = Keypair::new(T, &gens, &mut rng, &mut db).unwrap();
+            let sk = SigManager::get_key(1, &db).unwrap();
+            sigs.push(Signature::new(msg, 1, l, &gens, sk, &mut rng).unwrap());
+            vks.push(keypair.ver_key);
+        }
+
+        let mut buffer = AggregateBuffer::with_capacity(5);
+        assert!(buffer.is_empty());
+        assert_eq!(
+            buffer.aggregate_checked().unwrap(),
+            Signature::aggregate(vec![])
+        );
+        buffer.push(7, &sigs[0]).unwrap();
+        assert!(buffer.push(7, &sigs[1]).is_err());
+        buffer
+            .extend(sigs.iter().cloned().enumerate().skip(1))
+            .unwrap();
+        assert_eq!(buffer.ids(), &[7, 1, 2, 3, 4]);
+        let asig = buffer.aggregate_checked().unwrap();
+        assert_eq!(asig, Signature::aggregate(sigs.iter().collect()));
+        assert!(asig
+            .verify_aggregated(msg, 1, l, vks.iter().collect::<Vec<&Verkey>>(), &gens)
+            .unwrap());
+
+        // Sums over several chunks match sequential ones
+        let points = (0..2 * SUM_CHUNK_SIZE + 3)
+            .map(|_| SignatureGroup::random())
+            .collect::<Vec<_>>();
+        let mut expected = SignatureGroup::identity();
+        for p in &points {
+            expected += p;
+        }
+        assert_eq!(parallel_sum(&points), expected);
+
+        buffer.clear();
+        assert_eq!(buffer.len(), 0);
+        buffer.push(7, &sigs[0]).unwrap();
+    }
+}
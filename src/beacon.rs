@@ -0,0 +1,223 @@
+//! Signed status statements of a signer for monitoring. A beacon states the signer's current time
+//! period, the fingerprint of its verkey, the digest of its signing policy and its uptime, signed
+//! with the key of that time period. A monitor checking beacons learns that the signer is alive,
+//! its key is on the time period of the wall clock and its policy is as expected. A beacon
+//! signed for a past time period after the key was updated shows a compromised key.
+//!
+//! Beacons are numbered by the `BeaconEmitter` so monitors can spot replayed or missing ones.
+
+use rand::{CryptoRng, RngCore};
+use sha3::{Digest, Sha3_256};
+
+use crate::epoch::EpochConfig;
+use crate::errors::ForwardSecureSignatureError;
+use crate::facade::{Signer, Verifier};
+use crate::keys::{SigKeyDb, SigningLimits, VerkeyFingerprint};
+use crate::signature::Signature;
+
+const BEACON_DST: &[u8] = b"PIXEL-STATUS-BEACON-V1";
+const POLICY_DST: &[u8] = b"PIXEL-SIGNING-POLICY-V1";
+
+/// Digest of a signing policy, the same for signers with the same policy
+pub fn policy_digest(limits: &SigningLimits) -> [u8; 32] {
+    let mut hasher = Sha3_256::new();
+    hasher.input(POLICY_DST);
+    for limit in &[limits.per_period, limits.total] {
+        match limit {
+            Some(n) => {
+                hasher.input([1]);
+                hasher.input(n.to_be_bytes());
+            }
+            None => hasher.input([0]),
+        }
+    }
+    let mut digest = [0; 32];
+    digest.copy_from_slice(&hasher.result());
+    digest
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct StatusBeacon {
+    pub signer: VerkeyFingerprint,
+    /// Time period of the signing key
+    pub t: u128,
+    /// Digest of the signing policy, see `policy_digest`
+    pub policy: [u8; 32],
+    /// Seconds since the signer started
+    pub uptime: u64,
+    /// Wall clock time of the beacon in seconds, as claimed by the signer
+    pub timestamp: u64,
+    /// Number of the beacon since the signer started
+    pub sequence: u64,
+    pub signature: Signature,
+}
+
+/// Creates the beacons of a signer
+#[derive(Clone, Debug)]
+pub struct BeaconEmitter {
+    started: u64,
+    sequence: u64,
+}
+
+impl BeaconEmitter {
+    /// `started` is the wall clock time the signer started at
+    pub fn new(started: u64) -> Self {
+        Self {
+            started,
+            sequence: 0,
+        }
+    }
+
+    /// Sign a beacon for wall clock time `now` with the signer's key for its current time
+    /// period
+    pub fn emit<D: SigKeyDb, R: RngCore + CryptoRng>(
+        &mut self,
+        signer: &mut Signer<D>,
+        now: u64,
+        rng: &mut R,
+    ) -> Result<StatusBeacon, ForwardSecureSignatureError> {
+        let fingerprint = signer.verkey().fingerprint();
+        let t = signer.t();
+        let policy = policy_digest(&signer.sig_manager().signing_limits());
+        let uptime = now.saturating_sub(self.started);
+        let msg = statement(&fingerprint, t, &policy, uptime, now, self.sequence);
+        let beacon = StatusBeacon {
+            signer: fingerprint,
+            t,
+            policy,
+            uptime,
+            timestamp: now,
+            sequence: self.sequence,
+            signature: signer.sign(&msg, rng)?,
+        };
+        self.sequence += 1;
+        Ok(beacon)
+    }
+}
+
+fn invalid(msg: String) -> ForwardSecureSignatureError {
+    ForwardSecureSignatureError::InvalidBeacon { msg }
+}
+
+impl StatusBeacon {
+    /// Check the signature with the verifier of the signer. Returns false if the beacon names
+    /// another signer.
+    pub fn verify(&self, verifier: &Verifier) -> Result<bool, ForwardSecureSignatureError> {
+        if verifier.verkey().fingerprint() != self.signer {
+            return Ok(false);
+        }
+        verifier.verify(&self.statement(), self.t, &self.signature)
+    }
+
+    /// Check the signature and that the beacon is at most `max_age` seconds old at `now`, and
+    /// that the key was on the time period of the wall clock when the beacon was signed. Fails
+    /// with `InvalidBeacon` saying which check failed.
+    pub fn check(
+        &self,
+        verifier: &Verifier,
+        epochs: &EpochConfig,
+        now: u64,
+        max_age: u64,
+    ) -> Result<(), ForwardSecureSignatureError> {
+        if !self.verify(verifier)? {
+            return Err(invalid(String::from("signature does not verify")));
+        }
+        if self.timestamp > now || now - self.timestamp > max_age {
+            return Err(invalid(format!(
+                "signed at {} which is not within {} seconds before {}",
+                self.timestamp, max_age, now
+            )));
+        }
+        let expected_t = epochs.period_at(self.timestamp)?;
+        if self.t != expected_t {
+            return Err(invalid(format!(
+                "key at time period {} instead of {}",
+                self.t, expected_t
+            )));
+        }
+        Ok(())
+    }
+
+    fn statement(&self) -> Vec<u8> {
+        statement(
+            &self.signer,
+            self.t,
+            &self.policy,
+            self.uptime,
+            self.timestamp,
+            self.sequence,
+        )
+    }
+}
+
+/// The message signed by the signer
+fn statement(
+    signer: &VerkeyFingerprint,
+    t: u128,
+    policy: &[u8; 32],
+    uptime: u64,
+    timestamp: u64,
+    sequence: u64,
+) -> Vec<u8> {
+    let mut msg = BEACON_DST.to_vec();
+    msg.extend_from_slice(signer.as_bytes());
+    msg.extend_from_slice(&t.to_be_bytes());
+    msg.extend_from_slice(policy);
+    msg.extend_from_slice(&uptime.to_be_bytes());
+    msg.extend_from_slice(&timestamp.to_be_bytes());
+    msg.extend_from_slice(&sequence.to_be_bytes());
+    msg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::InMemorySigKeyDatabase;
+    use crate::util::GeneratorSet;
+    use std::time::Duration;
+
+    #[test]
+    fn test_status_beacon() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let epochs = EpochConfig::new(1000, Duration::from_secs(60), T).unwrap();
+        let (mut signer, _) =
+            Signer::new(T, gens.clone(), InMemorySigKeyDatabase::new(), &mut rng).unwrap();
+        let (other, _) = Signer::new(T, gens, InMemorySigKeyDatabase::new(), &mut rng).unwrap();
+        let verifier = signer.verifier();
+
+        let mut emitter = BeaconEmitter::new(1000);
+        let beacon = emitter.emit(&mut signer, 1010, &mut rng).unwrap();
+        assert_eq!((beacon.t, beacon.uptime, beacon.sequence), (1, 10, 0));
+        assert_eq!(beacon.policy, policy_digest(&SigningLimits::default()));
+        assert!(beacon.verify(&verifier).unwrap());
+        beacon.check(&verifier, &epochs, 1020, 30).unwrap();
+        assert!(!beacon.verify(&other.verifier()).unwrap());
+
+        // Stale, from the future or on the wrong time period
+        assert!(beacon.check(&verifier, &epochs, 1050, 30).is_err());
+        assert!(beacon.check(&verifier, &epochs, 1005, 30).is_err());
+        let late = emitter.emit(&mut signer, 1070, &mut rng).unwrap();
+        assert_eq!(late.sequence, 1);
+        assert!(late.check(&verifier, &epochs, 1070, 30).is_err());
+        signer.update_to(2, &mut rng).unwrap();
+        let beacon = emitter.emit(&mut signer, 1070, &mut rng).unwrap();
+        beacon.check(&verifier, &epochs, 1070, 30).unwrap();
+
+        let mut tampered = beacon.clone();
+        tampered.uptime += 1;
+        assert!(tampered.check(&verifier, &epochs, 1070, 30).is_err());
+        assert_ne!(
+            policy_digest(&SigningLimits {
+                per_period: Some(10),
+                total: None
+            }),
+            policy_digest(&SigningLimits {
+                per_period: None,
+                total: Some(10)
+            })
+        );
+    }
+}
@@ -0,0 +1,307 @@
+//! Bech32m (BIP-350) encoding of verkeys, signatures and verkey fingerprints, for strings that
+//! are copied by hand, e.g. into configs or support tickets. The human-readable prefix says what
+//! the string holds and a checksum catches typos. Each type has a default prefix and methods
+//! taking another prefix for deployments that distinguish their keys, e.g. `fstestvk` for a
+//! test network.
+//!
+//! Encodings of verkeys and signatures are longer than the 90 characters BIP-350 allows for
+//! addresses, the limit is not enforced. The checksum still detects any error in up to 4
+//! characters of strings up to 1023 characters long, which covers all encodings here.
+
+use crate::errors::ForwardSecureSignatureError;
+use crate::keys::{Verkey, VerkeyFingerprint, VERKEY_FINGERPRINT_SIZE};
+use crate::signature::Signature;
+
+pub const VERKEY_HRP: &str = "fsvk";
+pub const SIGNATURE_HRP: &str = "fssig";
+pub const FINGERPRINT_HRP: &str = "fsfp";
+
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const BECH32M_CONST: u32 = 0x2bc8_30a3;
+const CHECKSUM_LEN: usize = 6;
+
+fn invalid(msg: &str) -> ForwardSecureSignatureError {
+    ForwardSecureSignatureError::InvalidBech32 {
+        msg: msg.to_string(),
+    }
+}
+
+fn polymod(values: &[u8]) -> u32 {
+    const GEN: [u32; 5] = [
+        0x3b6a_57b2,
+        0x2650_8e6d,
+        0x1ea1_19fa,
+        0x3d42_33dd,
+        0x2a14_62b3,
+    ];
+    let mut chk = 1u32;
+    for v in values {
+        let b = chk >> 25;
+        chk = ((chk & 0x1ff_ffff) << 5) ^ u32::from(*v);
+        for (i, g) in GEN.iter().enumerate() {
+            if (b >> i) & 1 == 1 {
+                chk ^= g;
+            }
+        }
+    }
+    chk
+}
+
+fn hrp_expand(hrp: &[u8]) -> Vec<u8> {
+    let mut values = hrp.iter().map(|c| c >> 5).collect::<Vec<_>>();
+    values.push(0);
+    values.extend(hrp.iter().map(|c| c & 31));
+    values
+}
+
+fn check_hrp(hrp: &str) -> Result<(), ForwardSecureSignatureError> {
+    if hrp.is_empty() || hrp.len() > 83 {
+        return Err(invalid("prefix must be 1 to 83 characters long"));
+    }
+    if !hrp.bytes().all(|c| (33..=126).contains(&c)) || hrp.bytes().any(|c| c.is_ascii_uppercase())
+    {
+        return Err(invalid("prefix must be printable lowercase ASCII"));
+    }
+    Ok(())
+}
+
+/// Convert groups of `from` bits to groups of `to` bits. Bits left over are padded with 0 if
+/// `pad`, otherwise they must be fewer than `from` and all 0.
+fn convert_bits(
+    data: &[u8],
+    from: u32,
+    to: u32,
+    pad: bool,
+) -> Result<Vec<u8>, ForwardSecureSignatureError> {
+    let mut acc = 0u32;
+    let mut bits = 0;
+    let max = (1 << to) - 1;
+    let mut out = Vec::with_capacity(data.len() * from as usize / to as usize + 1);
+    for v in data {
+        acc = (acc << from) | u32::from(*v);
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            out.push(((acc >> bits) & max) as u8);
+        }
+    }
+    if pad {
+        if bits > 0 {
+            out.push(((acc << (to - bits)) & max) as u8);
+        }
+    } else if bits >= from || (acc << (to - bits)) & max != 0 {
+        return Err(invalid("non-zero padding"));
+    }
+    Ok(out)
+}
+
+/// Bech32m encoding of `data` with prefix `hrp`, which must be lowercase
+pub fn encode(hrp: &str, data: &[u8]) -> Result<String, ForwardSecureSignatureError> {
+    check_hrp(hrp)?;
+    let values = convert_bits(data, 8, 5, true)?;
+    let mut chk_input = hrp_expand(hrp.as_bytes());
+    chk_input.extend_from_slice(&values);
+    chk_input.extend_from_slice(&[0; CHECKSUM_LEN]);
+    let chk = polymod(&chk_input) ^ BECH32M_CONST;
+
+    let mut s = String::with_capacity(hrp.len() + 1 + values.len() + CHECKSUM_LEN);
+    s.push_str(hrp);
+    s.push('1');
+    for v in values {
+        s.push(CHARSET[v as usize] as char);
+    }
+    for i in 0..CHECKSUM_LEN {
+        s.push(CHARSET[((chk >> (5 * (5 - i))) & 31) as usize] as char);
+    }
+    Ok(s)
+}
+
+/// Decode a bech32m string to its prefix, in lowercase, and data. Strings that are all
+/// uppercase are accepted, mixed case is rejected.
+pub fn decode(s: &str) -> Result<(String, Vec<u8>), ForwardSecureSignatureError> {
+    if s.bytes().any(|c| c.is_ascii_lowercase()) && s.bytes().any(|c| c.is_ascii_uppercase()) {
+        return Err(invalid("mixed case"));
+    }
+    let s = s.to_ascii_lowercase();
+    let sep = s.rfind('1').ok_or_else(|| invalid("no separator"))?;
+    let (hrp, data) = (&s[..sep], &s.as_bytes()[sep + 1..]);
+    check_hrp(hrp)?;
+    if data.len() < CHECKSUM_LEN {
+        return Err(invalid("too short for the checksum"));
+    }
+    let values = data
+        .iter()
+        .map(|c| {
+            CHARSET
+                .iter()
+                .position(|d| d == c)
+                .map(|v| v as u8)
+                .ok_or_else(|| invalid("invalid character"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut chk_input = hrp_expand(hrp.as_bytes());
+    chk_input.extend_from_slice(&values);
+    if polymod(&chk_input) != BECH32M_CONST {
+        return Err(invalid("checksum mismatch"));
+    }
+    let data = convert_bits(&values[..values.len() - CHECKSUM_LEN], 5, 8, false)?;
+    Ok((hrp.to_string(), data))
+}
+
+/// Decode a bech32m string and check that it has prefix `hrp`
+fn decode_with_hrp(s: &str, hrp: &str) -> Result<Vec<u8>, ForwardSecureSignatureError> {
+    let (found, data) = decode(s)?;
+    if found != hrp {
+        return Err(ForwardSecureSignatureError::InvalidBech32 {
+            msg: format!("prefix {} instead of {}", found, hrp),
+        });
+    }
+    Ok(data)
+}
+
+impl Verkey {
+    pub fn to_bech32(&self) -> String {
+        encode(VERKEY_HRP, &self.to_bytes()).expect("default prefix is valid")
+    }
+
+    pub fn to_bech32_with_hrp(&self, hrp: &str) -> Result<String, ForwardSecureSignatureError> {
+        encode(hrp, &self.to_bytes())
+    }
+
+    pub fn from_bech32(s: &str) -> Result<Self, ForwardSecureSignatureError> {
+        Self::from_bech32_with_hrp(s, VERKEY_HRP)
+    }
+
+    pub fn from_bech32_with_hrp(s: &str, hrp: &str) -> Result<Self, ForwardSecureSignatureError> {
+        Self::from_bytes(&decode_with_hrp(s, hrp)?)
+    }
+}
+
+impl Signature {
+    pub fn to_bech32(&self) -> String {
+        encode(SIGNATURE_HRP, &self.to_bytes()).expect("default prefix is valid")
+    }
+
+    pub fn to_bech32_with_hrp(&self, hrp: &str) -> Result<String, ForwardSecureSignatureError> {
+        encode(hrp, &self.to_bytes())
+    }
+
+    /// Decodes without checking the order of the points, which verification does
+    pub fn from_bech32(s: &str) -> Result<Self, ForwardSecureSignatureError> {
+        Self::from_bech32_with_hrp(s, SIGNATURE_HRP)
+    }
+
+    pub fn from_bech32_with_hrp(s: &str, hrp: &str) -> Result<Self, ForwardSecureSignatureError> {
+        Self::from_bytes_unchecked(&decode_with_hrp(s, hrp)?)
+    }
+}
+
+impl VerkeyFingerprint {
+    pub fn to_bech32(&self) -> String {
+        encode(FINGERPRINT_HRP, &self.0).expect("default prefix is valid")
+    }
+
+    pub fn to_bech32_with_hrp(&self, hrp: &str) -> Result<String, ForwardSecureSignatureError> {
+        encode(hrp, &self.0)
+    }
+
+    pub fn from_bech32(s: &str) -> Result<Self, ForwardSecureSignatureError> {
+        Self::from_bech32_with_hrp(s, FINGERPRINT_HRP)
+    }
+
+    pub fn from_bech32_with_hrp(s: &str, hrp: &str) -> Result<Self, ForwardSecureSignatureError> {
+        let data = decode_with_hrp(s, hrp)?;
+        if data.len() != VERKEY_FINGERPRINT_SIZE {
+            return Err(invalid("wrong length for a fingerprint"));
+        }
+        let mut fp = [0; VERKEY_FINGERPRINT_SIZE];
+        fp.copy_from_slice(&data);
+        Ok(Self(fp))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{InMemorySigKeyDatabase, Keypair};
+    use crate::util::{calculate_l, GeneratorSet};
+
+    #[test]
+    fn test_bech32m_vectors() {
+        // Valid bech32m strings of BIP-350
+        assert_eq!(decode("a1lqfn3a").unwrap(), ("a".to_string(), vec![]));
+        assert_eq!(decode("A1LQFN3A").unwrap(), ("a".to_string(), vec![]));
+        let s = "abcdef1l7aum6echk45nj3s0wdvt2fg8x9yrzpqzd3ryx";
+        let data = [
+            0xff, 0xbb, 0xcd, 0xeb, 0x38, 0xbd, 0xab, 0x49, 0xca, 0x30, 0x7b, 0x9a, 0xc5, 0xa9,
+            0x28, 0x39, 0x8a, 0x41, 0x88, 0x20,
+        ];
+        assert_eq!(decode(s).unwrap(), ("abcdef".to_string(), data.to_vec()));
+        assert_eq!(encode("abcdef", &data).unwrap(), s);
+
+        // Invalid strings of BIP-350 and a bech32 (not bech32m) string
+        for s in &[
+            "qyrz8wqd2c9m",
+            "1qyrz8wqd2c9m",
+            "y1b0jsk6g",
+            "lt1igcx5c0",
+            "in1muywd",
+            "mm1crxm3i",
+            "au1s5cgom",
+            "M1VUXWEZ",
+            "16plkw9",
+            "1p2gdwpf",
+            "a12uel5l",
+        ] {
+            assert!(decode(s).is_err(), "{} must be rejected", s);
+        }
+        assert!(decode("aBcdef1l7aum6echk45nj3s0wdvt2fg8x9yrzpqzd3ryx").is_err());
+        assert!(encode("Fsvk", &data).is_err());
+        assert!(encode("", &data).is_err());
+    }
+
+    #[test]
+    fn test_bech32_keys_and_signatures() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let l = calculate_l(T).unwrap();
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let mut db = InMemorySigKeyDatabase::new();
+        let (keypair, manager) = Keypair::new(T, &gens, &mut rng, &mut db).unwrap();
+        let vk = keypair.ver_key;
+        let sk = manager.get_current_key(&db).unwrap();
+        let sig = Signature::new("Hello".as_bytes(), 1, l, &gens, sk, &mut rng).unwrap();
+
+        let s = vk.to_bech32();
+        assert!(s.starts_with("fsvk1"));
+        assert_eq!(Verkey::from_bech32(&s).unwrap().value, vk.value);
+        assert_eq!(
+            Verkey::from_bech32(&s.to_uppercase()).unwrap().value,
+            vk.value
+        );
+        let s = sig.to_bech32();
+        assert!(s.starts_with("fssig1"));
+        assert_eq!(Signature::from_bech32(&s).unwrap(), sig);
+        let fp = vk.fingerprint();
+        let s = fp.to_bech32();
+        assert!(s.starts_with("fsfp1"));
+        assert_eq!(VerkeyFingerprint::from_bech32(&s).unwrap(), fp);
+
+        // Each string only decodes as what it holds
+        assert!(Signature::from_bech32(&vk.to_bech32()).is_err());
+        assert!(Verkey::from_bech32(&fp.to_bech32()).is_err());
+        let s = vk.to_bech32_with_hrp("fstestvk").unwrap();
+        assert!(Verkey::from_bech32(&s).is_err());
+        assert_eq!(
+            Verkey::from_bech32_with_hrp(&s, "fstestvk").unwrap().value,
+            vk.value
+        );
+
+        // A typo fails the checksum
+        let mut typo = fp.to_bech32().into_bytes();
+        let i = typo.len() - 10;
+        typo[i] = if typo[i] == b'q' { b'p' } else { b'q' };
+        assert!(VerkeyFingerprint::from_bech32(std::str::from_utf8(&typo).unwrap()).is_err());
+    }
+}
@@ -0,0 +1,217 @@
+//! Plain BLS signatures and a verification entry point accepting either BLS or Pixel signatures,
+//! for networks moving their members from BLS to forward secure keys one at a time.
+//!
+//! BLS verkeys live in the same group as Pixel verkeys, `vk = g^x` for the group's standard
+//! generator `g`, and signatures are `H(m)^x`. `H` is amcl's hash to curve with a domain
+//! separation tag, not the IETF hash to curve ciphersuite, so signatures only interoperate with
+//! implementations using the same hash. Both kinds of verkeys are identified by a
+//! `VerkeyFingerprint`. The fingerprint of a BLS verkey is computed with another tag than the one
+//! of a Pixel verkey so reusing a secret key for both schemes does not make the ids collide.
+
+use amcl_wrapper::field_elem::FieldElement;
+use amcl_wrapper::group_elem::GroupElement;
+use rand::{CryptoRng, RngCore};
+use sha3::{Digest, Sha3_256};
+
+use crate::errors::ForwardSecureSignatureError;
+use crate::keys::{Verkey, VerkeyFingerprint, VERKEY_FINGERPRINT_SIZE};
+use crate::signature::Signature;
+use crate::util::{calculate_l, random_field_element, GeneratorSetRef};
+use crate::{ate_2_pairing, SignatureGroup, VerkeyGroup};
+
+const BLS_MESSAGE_DST: &[u8] = b"PIXEL-BLS-MESSAGE-V1";
+const BLS_FINGERPRINT_DST: &[u8] = b"PIXEL-BLS-VERKEY-FINGERPRINT-V1";
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BlsVerkey {
+    pub value: VerkeyGroup,
+}
+
+impl BlsVerkey {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.value.to_bytes()
+    }
+
+    pub fn fingerprint(&self) -> VerkeyFingerprint {
+        let mut hasher = Sha3_256::new();
+        hasher.input(BLS_FINGERPRINT_DST);
+        hasher.input(self.to_bytes());
+        let mut fp = [0; VERKEY_FINGERPRINT_SIZE];
+        fp.copy_from_slice(&hasher.result()[..VERKEY_FINGERPRINT_SIZE]);
+        VerkeyFingerprint(fp)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BlsSignature {
+    pub value: SignatureGroup,
+}
+
+impl BlsSignature {
+    pub fn verify(&self, msg: &[u8], verkey: &BlsVerkey) -> bool {
+        if self.value.is_identity() || verkey.value.is_identity() {
+            return false;
+        }
+        // e(sig, -g) * e(H(m), vk) == 1
+        ate_2_pairing(
+            &self.value,
+            &VerkeyGroup::generator().negation(),
+            &hash_message(msg),
+            &verkey.value,
+        )
+        .is_one()
+    }
+}
+
+pub struct BlsKeypair {
+    secret: FieldElement,
+    pub verkey: BlsVerkey,
+}
+
+impl BlsKeypair {
+    pub fn new<R: RngCore + CryptoRng>(rng: &mut R) -> Result<Self, ForwardSecureSignatureError> {
+        let secret = random_field_element(rng)?;
+        let verkey = BlsVerkey {
+            value: VerkeyGroup::generator() * &secret,
+        };
+        Ok(Self { secret, verkey })
+    }
+
+    pub fn sign(&self, msg: &[u8]) -> BlsSignature {
+        BlsSignature {
+            value: hash_message(msg) * &self.secret,
+        }
+    }
+}
+
+/// Verkey of either scheme
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum AnyVerkey {
+    Bls(BlsVerkey),
+    Pixel(Verkey),
+}
+
+impl AnyVerkey {
+    pub fn fingerprint(&self) -> VerkeyFingerprint {
+        match self {
+            AnyVerkey::Bls(vk) => vk.fingerprint(),
+            AnyVerkey::Pixel(vk) => vk.fingerprint(),
+        }
+    }
+}
+
+/// Signature of either scheme. A Pixel signature carries its time period.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum AnySignature {
+    Bls(BlsSignature),
+    Pixel { signature: Box<Signature>, t: u128 },
+}
+
+/// Verifies signatures of both schemes. Pixel signatures are verified with the given
+/// generators and maximum time period.
+pub struct UnifiedVerifier {
+    gens: GeneratorSetRef,
+    l: u8,
+}
+
+impl UnifiedVerifier {
+    pub fn new<G: Into<GeneratorSetRef>>(
+        T: u128,
+        gens: G,
+    ) -> Result<Self, ForwardSecureSignatureError> {
+        Ok(Self {
+            gens: gens.into(),
+            l: calculate_l(T)?,
+        })
+    }
+
+    /// Verify `sig` on `msg`. Returns false if the signature and the verkey are of different
+    /// schemes.
+    pub fn verify(
+        &self,
+        msg: &[u8],
+        sig: &AnySignature,
+        verkey: &AnyVerkey,
+    ) -> Result<bool, ForwardSecureSignatureError> {
+        match (sig, verkey) {
+            (AnySignature::Bls(sig), AnyVerkey::Bls(vk)) => Ok(sig.verify(msg, vk)),
+            (AnySignature::Pixel { signature, t }, AnyVerkey::Pixel(vk)) => {
+                signature.verify(msg, *t, self.l, &self.gens, vk)
+            }
+            _ => Ok(false),
+        }
+    }
+}
+
+fn hash_message(msg: &[u8]) -> SignatureGroup {
+    let mut bytes = BLS_MESSAGE_DST.to_vec();
+    bytes.extend_from_slice(msg);
+    SignatureGroup::from_msg_hash(&bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::facade::Signer;
+    use crate::keys::{InMemorySigKeyDatabase, MasterSecret};
+    use crate::util::GeneratorSet;
+
+    #[test]
+    fn test_bls_signature() {
+        let mut rng = rand::thread_rng();
+        let keypair = BlsKeypair::new(&mut rng).unwrap();
+        let other = BlsKeypair::new(&mut rng).unwrap();
+        let msg = "Hello".as_bytes();
+        let sig = keypair.sign(msg);
+        assert!(sig.verify(msg, &keypair.verkey));
+        assert!(!sig.verify(b"Hi", &keypair.verkey));
+        assert!(!sig.verify(msg, &other.verkey));
+        let identity = BlsSignature {
+            value: SignatureGroup::identity(),
+        };
+        assert!(!identity.verify(msg, &keypair.verkey));
+    }
+
+    #[test]
+    fn test_unified_verification() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let gens = GeneratorSetRef::new(GeneratorSet::new(T, "test_pixel").unwrap());
+        let verifier = UnifiedVerifier::new(T, gens.clone()).unwrap();
+        let msg = "Hello".as_bytes();
+
+        let bls = BlsKeypair::new(&mut rng).unwrap();
+        let (mut signer, _) =
+            Signer::new(T, gens.clone(), InMemorySigKeyDatabase::new(), &mut rng).unwrap();
+        let bls_vk = AnyVerkey::Bls(bls.verkey.clone());
+        let pixel_vk = AnyVerkey::Pixel(signer.verkey().clone());
+        let bls_sig = AnySignature::Bls(bls.sign(msg));
+        let pixel_sig = AnySignature::Pixel {
+            signature: Box::new(signer.sign(msg, &mut rng).unwrap()),
+            t: 1,
+        };
+
+        assert!(verifier.verify(msg, &bls_sig, &bls_vk).unwrap());
+        assert!(verifier.verify(msg, &pixel_sig, &pixel_vk).unwrap());
+        assert!(!verifier.verify(msg, &bls_sig, &pixel_vk).unwrap());
+        assert!(!verifier.verify(msg, &pixel_sig, &bls_vk).unwrap());
+        assert!(!verifier.verify(b"Hi", &bls_sig, &bls_vk).unwrap());
+        let wrong_period = AnySignature::Pixel {
+            signature: Box::new(signer.sign(msg, &mut rng).unwrap()),
+            t: 2,
+        };
+        assert!(!verifier.verify(msg, &wrong_period, &pixel_vk).unwrap());
+
+        // Same point under both schemes gets different ids
+        let secret = MasterSecret::new(&mut rng);
+        let point = Verkey::from_master_secret(&secret, &VerkeyGroup::generator());
+        let as_bls = AnyVerkey::Bls(BlsVerkey {
+            value: point.value.clone(),
+        });
+        assert_ne!(as_bls.fingerprint(), AnyVerkey::Pixel(point).fingerprint());
+    }
+}
@@ -0,0 +1,83 @@
+//! Borsh support for `Signature` and `Verkey`, only compiled with feature `borsh`, for use in
+//! Solana or NEAR program state and instruction data.
+//!
+//! Both are encoded as fixed size byte arrays of their `to_bytes` encoding, without a length
+//! prefix, so they take `SIGNATURE_SIZE` and `VERKEY_GROUP_SIZE` bytes wherever they are embedded.
+//! Decoding has the checks of `from_bytes` and fails with `InvalidData` otherwise.
+
+use std::io::{Error, ErrorKind, Read, Result, Write};
+
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use crate::keys::Verkey;
+use crate::signature::{Signature, SIGNATURE_SIZE};
+use crate::VERKEY_GROUP_SIZE;
+
+fn invalid_data(e: crate::errors::ForwardSecureSignatureError) -> Error {
+    Error::new(ErrorKind::InvalidData, e.to_string())
+}
+
+impl BorshSerialize for Signature {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writer.write_all(&self.to_bytes())
+    }
+}
+
+impl BorshDeserialize for Signature {
+    fn deserialize_reader<R: Read>(reader: &mut R) -> Result<Self> {
+        let mut bytes = [0; SIGNATURE_SIZE];
+        reader.read_exact(&mut bytes)?;
+        Signature::from_bytes(&bytes).map_err(invalid_data)
+    }
+}
+
+impl BorshSerialize for Verkey {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writer.write_all(&self.to_bytes())
+    }
+}
+
+impl BorshDeserialize for Verkey {
+    fn deserialize_reader<R: Read>(reader: &mut R) -> Result<Self> {
+        let mut bytes = [0; VERKEY_GROUP_SIZE];
+        reader.read_exact(&mut bytes)?;
+        Verkey::from_bytes(&bytes).map_err(invalid_data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::facade::Signer;
+    use crate::keys::InMemorySigKeyDatabase;
+    use crate::util::GeneratorSet;
+
+    #[test]
+    fn test_borsh() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let (mut signer, _) =
+            Signer::new(T, gens, InMemorySigKeyDatabase::new(), &mut rng).unwrap();
+        let sig = signer.sign("Hello".as_bytes(), &mut rng).unwrap();
+        let vk = signer.verkey();
+
+        let bytes = borsh::to_vec(&sig).unwrap();
+        assert_eq!(bytes, sig.to_bytes());
+        assert_eq!(borsh::from_slice::<Signature>(&bytes).unwrap(), sig);
+        let bytes = borsh::to_vec(vk).unwrap();
+        assert_eq!(bytes.len(), VERKEY_GROUP_SIZE);
+        assert_eq!(borsh::from_slice::<Verkey>(&bytes).unwrap().value, vk.value);
+
+        // Embedded in other data, e.g. a tuple or a vector
+        let bytes = borsh::to_vec(&(7u32, vec![sig.clone(), sig.clone()])).unwrap();
+        let (n, sigs) = borsh::from_slice::<(u32, Vec<Signature>)>(&bytes).unwrap();
+        assert_eq!((n, sigs), (7, vec![sig.clone(), sig.clone()]));
+
+        let bytes = borsh::to_vec(&sig).unwrap();
+        assert!(borsh::from_slice::<Signature>(&bytes[1..]).is_err());
+        assert!(borsh::from_slice::<Signature>(&[0; SIGNATURE_SIZE]).is_err());
+        let bytes = borsh::to_vec(vk).unwrap();
+        assert!(borsh::from_slice::<Verkey>(&bytes[..VERKEY_GROUP_SIZE - 1]).is_err());
+    }
+}
@@ -0,0 +1,210 @@
+//! Handles splitting the authority over a signer's keys. The `Updater` advances the key to later
+//! time periods, which deletes keys, and sets the signing policy. The `PeriodSigner` only signs
+//! with the key of the current time period, subject to that policy, and can neither update nor
+//! delete keys. Components are handed the one they need, e.g. a signing service gets a
+//! `PeriodSigner` and a scheduler the `Updater`.
+//!
+//! Both handles share the key manager and the database through a lock, signers see updates as
+//! soon as they are made. `PeriodSigner` can be cloned for several signing threads. A lock
+//! poisoned by a panic during an update is taken over, the next update repairs the keys with
+//! `SigManager::repair_keys`.
+//!
+//! The handles separate authority within a process. Separating processes or OS users also needs
+//! a key database only the updater's process can write to.
+
+use rand::{CryptoRng, RngCore};
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::errors::ForwardSecureSignatureError;
+use crate::facade::Verifier;
+use crate::keys::{KeyRepair, SigKeyDb, SigManager, SigManagerEvent, SigningLimits, Verkey};
+use crate::signature::Signature;
+use crate::util::GeneratorSetRef;
+
+struct KeyState<D: SigKeyDb> {
+    manager: SigManager,
+    db: D,
+}
+
+fn lock<D: SigKeyDb>(state: &Mutex<KeyState<D>>) -> MutexGuard<'_, KeyState<D>> {
+    state
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Create the two handles of a key, see `Signer::into_capabilities`
+pub fn split<D: SigKeyDb>(
+    gens: GeneratorSetRef,
+    verkey: Verkey,
+    manager: SigManager,
+    db: D,
+) -> (Updater<D>, PeriodSigner<D>) {
+    let state = Arc::new(Mutex::new(KeyState { manager, db }));
+    (
+        Updater {
+            gens: gens.clone(),
+            state: state.clone(),
+        },
+        PeriodSigner {
+            gens,
+            verkey,
+            state,
+        },
+    )
+}
+
+/// Advances and repairs the keys and sets the signing policy
+pub struct Updater<D: SigKeyDb> {
+    gens: GeneratorSetRef,
+    state: Arc<Mutex<KeyState<D>>>,
+}
+
+impl<D: SigKeyDb> Updater<D> {
+    /// Current time period
+    pub fn t(&self) -> u128 {
+        lock(&self.state).manager.t()
+    }
+
+    /// Update the signing key to time period `t`. Returns the time periods whose keys were
+    /// removed.
+    pub fn update_to<R: RngCore + CryptoRng>(
+        &self,
+        t: u128,
+        rng: &mut R,
+    ) -> Result<Vec<u128>, ForwardSecureSignatureError> {
+        let state = &mut *lock(&self.state);
+        state
+            .manager
+            .fast_forward_update(t, &self.gens, rng, &mut state.db)
+    }
+
+    /// See `SigManager::repair_keys`
+    pub fn repair_keys<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+    ) -> Result<KeyRepair, ForwardSecureSignatureError> {
+        let state = &mut *lock(&self.state);
+        state.manager.repair_keys(&self.gens, rng, &mut state.db)
+    }
+
+    /// Refuse to sign `msg` in the current time period, see `SigManager::puncture_message`
+    pub fn puncture_message(&self, msg: &[u8]) {
+        lock(&self.state).manager.puncture_message(msg)
+    }
+
+    pub fn set_signing_limits(&self, limits: SigningLimits) {
+        lock(&self.state).manager.set_signing_limits(limits)
+    }
+
+    /// Receive the events of the key manager, including those of signing
+    pub fn subscribe(&self) -> Receiver<SigManagerEvent> {
+        lock(&self.state).manager.subscribe()
+    }
+}
+
+/// Signs with the key of the current time period only
+pub struct PeriodSigner<D: SigKeyDb> {
+    gens: GeneratorSetRef,
+    verkey: Verkey,
+    state: Arc<Mutex<KeyState<D>>>,
+}
+
+impl<D: SigKeyDb> Clone for PeriodSigner<D> {
+    fn clone(&self) -> Self {
+        Self {
+            gens: self.gens.clone(),
+            verkey: self.verkey.clone(),
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<D: SigKeyDb> PeriodSigner<D> {
+    /// Current time period
+    pub fn t(&self) -> u128 {
+        lock(&self.state).manager.t()
+    }
+
+    pub fn verkey(&self) -> &Verkey {
+        &self.verkey
+    }
+
+    /// Sign `msg` for the current time period, see `SigManager::sign`. Returns the time period
+    /// along with the signature as the key may be updated right after.
+    pub fn sign<R: RngCore + CryptoRng>(
+        &self,
+        msg: &[u8],
+        rng: &mut R,
+    ) -> Result<(u128, Signature), ForwardSecureSignatureError> {
+        let state = &mut *lock(&self.state);
+        let sig = state.manager.sign(msg, &self.gens, &state.db, rng)?;
+        Ok((state.manager.t(), sig))
+    }
+
+    /// Same as `sign` with hedged randomness, see `SigManager::sign_hedged`
+    pub fn sign_hedged<R: RngCore + CryptoRng>(
+        &self,
+        msg: &[u8],
+        rng: &mut R,
+    ) -> Result<(u128, Signature), ForwardSecureSignatureError> {
+        let state = &mut *lock(&self.state);
+        let sig = state.manager.sign_hedged(msg, &self.gens, &state.db, rng)?;
+        Ok((state.manager.t(), sig))
+    }
+
+    pub fn verifier(&self) -> Verifier {
+        let T = lock(&self.state).manager.T();
+        Verifier::new(T, self.gens.clone(), self.verkey.clone())
+            .expect("maximum time period of a key manager is valid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::facade::Signer;
+    use crate::keys::{InMemorySigKeyDatabase, SigningLimits};
+    use crate::util::GeneratorSet;
+    use std::thread;
+
+    #[test]
+    fn test_capabilities() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let (signer, _) = Signer::new(T, gens, InMemorySigKeyDatabase::new(), &mut rng).unwrap();
+        let (updater, period_signer) = signer.into_capabilities();
+        let verifier = period_signer.verifier();
+        let msg = "Hello".as_bytes();
+
+        let (t, sig) = period_signer.sign(msg, &mut rng).unwrap();
+        assert_eq!(t, 1);
+        assert!(verifier.verify(msg, 1, &sig).unwrap());
+
+        // Signers see updates right away, also on other threads
+        updater.update_to(4, &mut rng).unwrap();
+        let other = period_signer.clone();
+        let (t, sig) = thread::spawn(move || other.sign_hedged(msg, &mut rand::thread_rng()))
+            .join()
+            .unwrap()
+            .unwrap();
+        assert_eq!((t, period_signer.t()), (4, 4));
+        assert!(verifier.verify(msg, 4, &sig).unwrap());
+        assert!(!verifier.verify(msg, 1, &sig).unwrap());
+        assert!(updater.update_to(2, &mut rng).is_err());
+
+        // The signing policy set by the updater binds signers
+        updater.puncture_message("Punctured".as_bytes());
+        assert!(period_signer
+            .sign("Punctured".as_bytes(), &mut rng)
+            .is_err());
+        updater.set_signing_limits(SigningLimits {
+            per_period: Some(1),
+            total: None,
+        });
+        assert!(period_signer.sign(msg, &mut rng).is_err());
+        updater.update_to(5, &mut rng).unwrap();
+        assert_eq!(period_signer.sign(msg, &mut rng).unwrap().0, 5);
+        assert!(updater.repair_keys(&mut rng).unwrap().is_empty());
+    }
+}
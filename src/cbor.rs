@@ -0,0 +1,458 @@
+//! Deterministic CBOR encodings (RFC 8949, section 4.2.1) of signatures, verkeys and signed
+//! envelopes, so hashes of encoded artifacts are the same on every platform. Integers and lengths
+//! use the shortest form, all lengths are definite and map keys are small integers in increasing
+//! order. Integers above `u64::MAX` are unsigned bignums (tag 2) without leading zero bytes.
+//!
+//! A signature or verkey is a byte string of its `to_bytes` encoding. A signed envelope is a map
+//! with the keys
+//!
+//! | key | value                                              |
+//! |-----|----------------------------------------------------|
+//! | 1   | attached payload, byte string                      |
+//! | 2   | digest of a detached payload, byte string          |
+//! | 3   | time period, integer                               |
+//! | 4   | fingerprint of the signer's verkey, byte string    |
+//! | 5   | timestamp, integer, omitted if none                |
+//! | 6   | committee identifier, byte string, omitted if none |
+//! | 7   | signature, byte string                             |
+//!
+//! with exactly one of 1 and 2. Decoding only accepts deterministic encodings.
+//!
+//! The encoder and decoder are also used for COSE structures by `cose`.
+
+use crate::envelope::{EnvelopeContent, SignedEnvelope};
+use crate::errors::ForwardSecureSignatureError;
+use crate::keys::{Verkey, VerkeyFingerprint, VERKEY_FINGERPRINT_SIZE};
+use crate::registry::CommitteeId;
+use crate::signature::{Signature, SIGNATURE_SIZE};
+use crate::VERKEY_GROUP_SIZE;
+
+pub(crate) const MAJOR_UNSIGNED: u8 = 0;
+#[cfg(feature = "cose")]
+pub(crate) const MAJOR_NEGATIVE: u8 = 1;
+pub(crate) const MAJOR_BYTES: u8 = 2;
+#[cfg(feature = "cose")]
+pub(crate) const MAJOR_TEXT: u8 = 3;
+#[cfg(feature = "cose")]
+pub(crate) const MAJOR_ARRAY: u8 = 4;
+pub(crate) const MAJOR_MAP: u8 = 5;
+pub(crate) const MAJOR_TAG: u8 = 6;
+const TAG_UNSIGNED_BIGNUM: u64 = 2;
+
+const KEY_PAYLOAD: u64 = 1;
+const KEY_DIGEST: u64 = 2;
+const KEY_T: u64 = 3;
+const KEY_SIGNER: u64 = 4;
+const KEY_TIMESTAMP: u64 = 5;
+const KEY_COMMITTEE: u64 = 6;
+const KEY_SIGNATURE: u64 = 7;
+
+fn invalid(msg: &str) -> ForwardSecureSignatureError {
+    ForwardSecureSignatureError::InvalidCbor {
+        msg: msg.to_string(),
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct Encoder {
+    pub(crate) bytes: Vec<u8>,
+}
+
+impl Encoder {
+    pub(crate) fn head(&mut self, major: u8, value: u64) {
+        let major = major << 5;
+        if value < 24 {
+            self.bytes.push(major | value as u8);
+        } else if value <= u8::MAX as u64 {
+            self.bytes.extend_from_slice(&[major | 24, value as u8]);
+        } else if value <= u16::MAX as u64 {
+            self.bytes.push(major | 25);
+            self.bytes.extend_from_slice(&(value as u16).to_be_bytes());
+        } else if value <= u32::MAX as u64 {
+            self.bytes.push(major | 26);
+            self.bytes.extend_from_slice(&(value as u32).to_be_bytes());
+        } else {
+            self.bytes.push(major | 27);
+            self.bytes.extend_from_slice(&value.to_be_bytes());
+        }
+    }
+
+    pub(crate) fn uint(&mut self, value: u128) {
+        if value <= u64::MAX as u128 {
+            self.head(MAJOR_UNSIGNED, value as u64);
+        } else {
+            let bytes = value.to_be_bytes();
+            let start = bytes.iter().position(|b| *b != 0).unwrap_or(0);
+            self.head(MAJOR_TAG, TAG_UNSIGNED_BIGNUM);
+            self.byte_string(&bytes[start..]);
+        }
+    }
+
+    pub(crate) fn byte_string(&mut self, bytes: &[u8]) {
+        self.head(MAJOR_BYTES, bytes.len() as u64);
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    #[cfg(feature = "cose")]
+    pub(crate) fn int(&mut self, value: i64) {
+        if value >= 0 {
+            self.head(MAJOR_UNSIGNED, value as u64);
+        } else {
+            self.head(MAJOR_NEGATIVE, !value as u64);
+        }
+    }
+
+    #[cfg(feature = "cose")]
+    pub(crate) fn text_string(&mut self, s: &str) {
+        self.head(MAJOR_TEXT, s.len() as u64);
+        self.bytes.extend_from_slice(s.as_bytes());
+    }
+}
+
+pub(crate) struct Decoder<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Decoder<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    pub(crate) fn take(&mut self, n: usize) -> Result<&'a [u8], ForwardSecureSignatureError> {
+        if self.bytes.len() - self.pos < n {
+            return Err(invalid("unexpected end of input"));
+        }
+        self.pos += n;
+        Ok(&self.bytes[self.pos - n..self.pos])
+    }
+
+    /// Major type and argument of the next item, rejecting arguments not in the shortest form
+    pub(crate) fn head(&mut self) -> Result<(u8, u64), ForwardSecureSignatureError> {
+        let initial = self.take(1)?[0];
+        let info = initial & 0x1f;
+        let (value, min) = match info {
+            0..=23 => (info as u64, 0),
+            24 => (self.take(1)?[0] as u64, 24),
+            25..=27 => {
+                let n = 1 << (info - 24);
+                let mut bytes = [0; 8];
+                bytes[8 - n..].copy_from_slice(self.take(n)?);
+                (u64::from_be_bytes(bytes), 1 << (4 * n))
+            }
+            _ => return Err(invalid("indefinite length or reserved argument")),
+        };
+        if value < min {
+            return Err(invalid("argument not in the shortest form"));
+        }
+        Ok((initial >> 5, value))
+    }
+
+    pub(crate) fn expect(&mut self, major: u8) -> Result<u64, ForwardSecureSignatureError> {
+        let (m, value) = self.head()?;
+        if m != major {
+            return Err(invalid(&format!("major type {} instead of {}", m, major)));
+        }
+        Ok(value)
+    }
+
+    pub(crate) fn uint(&mut self) -> Result<u128, ForwardSecureSignatureError> {
+        match self.head()? {
+            (MAJOR_UNSIGNED, value) => Ok(value as u128),
+            (MAJOR_TAG, TAG_UNSIGNED_BIGNUM) => {
+                let bytes = self.byte_string()?;
+                // Values up to u64::MAX have to be plain integers
+                if bytes.len() <= 8 || bytes.len() > 16 || bytes[0] == 0 {
+                    return Err(invalid("bignum not in the shortest form or too large"));
+                }
+                let mut value = [0; 16];
+                value[16 - bytes.len()..].copy_from_slice(bytes);
+                Ok(u128::from_be_bytes(value))
+            }
+            _ => Err(invalid("expected an unsigned integer")),
+        }
+    }
+
+    pub(crate) fn byte_string(&mut self) -> Result<&'a [u8], ForwardSecureSignatureError> {
+        let len = self.expect(MAJOR_BYTES)?;
+        if len > (self.bytes.len() - self.pos) as u64 {
+            return Err(invalid("unexpected end of input"));
+        }
+        self.take(len as usize)
+    }
+
+    pub(crate) fn fixed_bytes(
+        &mut self,
+        len: usize,
+    ) -> Result<&'a [u8], ForwardSecureSignatureError> {
+        let bytes = self.byte_string()?;
+        if bytes.len() != len {
+            return Err(invalid(&format!(
+                "{} bytes instead of {}",
+                bytes.len(),
+                len
+            )));
+        }
+        Ok(bytes)
+    }
+
+    #[cfg(feature = "cose")]
+    pub(crate) fn int(&mut self) -> Result<i64, ForwardSecureSignatureError> {
+        let (major, value) = self.head()?;
+        if (major != MAJOR_UNSIGNED && major != MAJOR_NEGATIVE) || value > i64::MAX as u64 {
+            return Err(invalid("expected an integer in the range of i64"));
+        }
+        Ok(if major == MAJOR_UNSIGNED {
+            value as i64
+        } else {
+            !(value as i64)
+        })
+    }
+
+    /// Skip the next item, nested at most `depth` levels deep
+    #[cfg(feature = "cose")]
+    pub(crate) fn skip(&mut self, depth: usize) -> Result<(), ForwardSecureSignatureError> {
+        if depth == 0 {
+            return Err(invalid("nested too deeply"));
+        }
+        let (major, value) = self.head()?;
+        match major {
+            MAJOR_BYTES | MAJOR_TEXT => {
+                if value > (self.bytes.len() - self.pos) as u64 {
+                    return Err(invalid("unexpected end of input"));
+                }
+                self.take(value as usize)?;
+            }
+            MAJOR_ARRAY | MAJOR_MAP => {
+                let items = if major == MAJOR_MAP { 2 } else { 1 };
+                for _ in 0..value {
+                    for _ in 0..items {
+                        self.skip(depth - 1)?;
+                    }
+                }
+            }
+            MAJOR_TAG => self.skip(depth - 1)?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    pub(crate) fn finish(&self) -> Result<(), ForwardSecureSignatureError> {
+        if self.pos != self.bytes.len() {
+            return Err(invalid("trailing bytes"));
+        }
+        Ok(())
+    }
+}
+
+impl Signature {
+    pub fn to_cbor(&self) -> Vec<u8> {
+        let mut e = Encoder::default();
+        e.byte_string(&self.to_bytes());
+        e.bytes
+    }
+
+    pub fn from_cbor(bytes: &[u8]) -> Result<Self, ForwardSecureSignatureError> {
+        let mut d = Decoder::new(bytes);
+        let sig = Self::from_bytes(d.fixed_bytes(SIGNATURE_SIZE)?)?;
+        d.finish()?;
+        Ok(sig)
+    }
+}
+
+impl Verkey {
+    pub fn to_cbor(&self) -> Vec<u8> {
+        let mut e = Encoder::default();
+        e.byte_string(&self.to_bytes());
+        e.bytes
+    }
+
+    pub fn from_cbor(bytes: &[u8]) -> Result<Self, ForwardSecureSignatureError> {
+        let mut d = Decoder::new(bytes);
+        let vk = Self::from_bytes(d.fixed_bytes(VERKEY_GROUP_SIZE)?)?;
+        d.finish()?;
+        Ok(vk)
+    }
+}
+
+impl SignedEnvelope {
+    pub fn to_cbor(&self) -> Vec<u8> {
+        let entries = 4 + self.timestamp.is_some() as u64 + self.committee.is_some() as u64;
+        let mut e = Encoder::default();
+        e.head(MAJOR_MAP, entries);
+        match &self.content {
+            EnvelopeContent::Attached(payload) => {
+                e.uint(KEY_PAYLOAD as u128);
+                e.byte_string(payload);
+            }
+            EnvelopeContent::Detached(digest) => {
+                e.uint(KEY_DIGEST as u128);
+                e.byte_string(digest);
+            }
+        }
+        e.uint(KEY_T as u128);
+        e.uint(self.t);
+        e.uint(KEY_SIGNER as u128);
+        e.byte_string(self.signer.as_bytes());
+        if let Some(time) = self.timestamp {
+            e.uint(KEY_TIMESTAMP as u128);
+            e.uint(time as u128);
+        }
+        if let Some(id) = &self.committee {
+            e.uint(KEY_COMMITTEE as u128);
+            e.byte_string(id.as_bytes());
+        }
+        e.uint(KEY_SIGNATURE as u128);
+        e.byte_string(&self.signature.to_bytes());
+        e.bytes
+    }
+
+    pub fn from_cbor(bytes: &[u8]) -> Result<Self, ForwardSecureSignatureError> {
+        let mut d = Decoder::new(bytes);
+        let entries = d.expect(MAJOR_MAP)?;
+        let mut content = None;
+        let mut t = None;
+        let mut signer = None;
+        let mut timestamp = None;
+        let mut committee = None;
+        let mut signature = None;
+        let mut last_key = 0;
+        for _ in 0..entries {
+            let key = d.expect(MAJOR_UNSIGNED)?;
+            if key <= last_key {
+                return Err(invalid("map keys not in increasing order"));
+            }
+            last_key = key;
+            match key {
+                KEY_PAYLOAD => {
+                    content = Some(EnvelopeContent::Attached(d.byte_string()?.to_vec()));
+                }
+                KEY_DIGEST => {
+                    if content.is_some() {
+                        return Err(invalid("both payload and digest"));
+                    }
+                    let mut digest = [0; 32];
+                    digest.copy_from_slice(d.fixed_bytes(32)?);
+                    content = Some(EnvelopeContent::Detached(digest));
+                }
+                KEY_T => t = Some(d.uint()?),
+                KEY_SIGNER => {
+                    let mut fp = [0; VERKEY_FINGERPRINT_SIZE];
+                    fp.copy_from_slice(d.fixed_bytes(VERKEY_FINGERPRINT_SIZE)?);
+                    signer = Some(VerkeyFingerprint(fp));
+                }
+                KEY_TIMESTAMP => {
+                    let time = d.uint()?;
+                    if time > u64::MAX as u128 {
+                        return Err(invalid("timestamp too large"));
+                    }
+                    timestamp = Some(time as u64);
+                }
+                KEY_COMMITTEE => {
+                    let mut id = [0; 32];
+                    id.copy_from_slice(d.fixed_bytes(32)?);
+                    committee = Some(CommitteeId(id));
+                }
+                KEY_SIGNATURE => {
+                    signature = Some(Signature::from_bytes(d.fixed_bytes(SIGNATURE_SIZE)?)?)
+                }
+                _ => return Err(invalid(&format!("unknown map key {}", key))),
+            }
+        }
+        d.finish()?;
+        let missing = |field: &str| invalid(&format!("missing {}", field));
+        Ok(Self {
+            content: content.ok_or_else(|| missing("payload or digest"))?,
+            t: t.ok_or_else(|| missing("time period"))?,
+            signer: signer.ok_or_else(|| missing("signer"))?,
+            timestamp,
+            committee,
+            signature: signature.ok_or_else(|| missing("signature"))?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::facade::Signer;
+    use crate::keys::InMemorySigKeyDatabase;
+    use crate::util::GeneratorSet;
+
+    #[test]
+    fn test_deterministic_cbor() {
+        for (value, bytes) in &[
+            (0u128, vec![0x00]),
+            (23, vec![0x17]),
+            (24, vec![0x18, 0x18]),
+            (1000, vec![0x19, 0x03, 0xe8]),
+            (
+                u64::MAX as u128,
+                vec![0x1b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+            ),
+            (1 << 64, vec![0xc2, 0x49, 0x01, 0, 0, 0, 0, 0, 0, 0, 0]),
+        ] {
+            let mut e = Encoder::default();
+            e.uint(*value);
+            assert_eq!(&e.bytes, bytes);
+            assert_eq!(Decoder::new(bytes).uint().unwrap(), *value);
+        }
+        // Not in the shortest form
+        for bytes in &[
+            vec![0x18, 0x17],
+            vec![0x19, 0x00, 0xff],
+            vec![0xc2, 0x48, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+            vec![0xc2, 0x4a, 0x00, 0x01, 0, 0, 0, 0, 0, 0, 0, 0],
+            vec![0x1f],
+        ] {
+            assert!(Decoder::new(bytes).uint().is_err());
+        }
+
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let (mut signer, _) =
+            Signer::new(T, gens, InMemorySigKeyDatabase::new(), &mut rng).unwrap();
+        let verifier = signer.verifier();
+
+        let sig = signer.sign("Hello".as_bytes(), &mut rng).unwrap();
+        let bytes = sig.to_cbor();
+        assert_eq!(Signature::from_cbor(&bytes).unwrap(), sig);
+        let vk = signer.verkey();
+        assert_eq!(Verkey::from_cbor(&vk.to_cbor()).unwrap().value, vk.value);
+        assert!(Verkey::from_cbor(&bytes).is_err());
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(Signature::from_cbor(&trailing).is_err());
+        assert!(Signature::from_cbor(&bytes[..bytes.len() - 1]).is_err());
+
+        let env =
+            SignedEnvelope::create(b"Hello".to_vec(), Some(1150), &mut signer, &mut rng).unwrap();
+        let bytes = env.to_cbor();
+        assert_eq!(bytes[..3], [0xa5, 0x01, 0x45]);
+        let decoded = SignedEnvelope::from_cbor(&bytes).unwrap();
+        assert!(decoded.verify(&verifier).unwrap());
+        assert_eq!(decoded.to_cbor(), bytes);
+        let env = SignedEnvelope {
+            committee: Some(CommitteeId([7; 32])),
+            timestamp: None,
+            ..env.detach()
+        };
+        let bytes = env.to_cbor();
+        assert_eq!(SignedEnvelope::from_cbor(&bytes).unwrap().to_cbor(), bytes);
+
+        // Keys out of order, missing or duplicated
+        let map = |entries: &[(u64, u128)]| {
+            let mut e = Encoder::default();
+            e.head(MAJOR_MAP, entries.len() as u64);
+            for (key, value) in entries {
+                e.uint(*key as u128);
+                e.uint(*value);
+            }
+            e.bytes
+        };
+        assert!(SignedEnvelope::from_cbor(&map(&[(KEY_T, 1), (KEY_T, 1)])).is_err());
+        assert!(SignedEnvelope::from_cbor(&map(&[(KEY_TIMESTAMP, 1), (KEY_T, 1)])).is_err());
+        assert!(SignedEnvelope::from_cbor(&map(&[(KEY_T, 1)])).is_err());
+    }
+}
@@ -0,0 +1,314 @@
+//! Committee setup. Every member generates its keypair and contributes its verkey and proof of
+//! possession. Once all contributions are in, the members sign a transcript of the ceremony
+//! with their keys for time period 1 so that anyone holding the output can check which keys
+//! make up the committee and that every member agreed to it.
+
+use sha3::{Digest, Sha3_256};
+use std::collections::BTreeMap;
+
+use crate::errors::ForwardSecureSignatureError;
+use crate::keys::{Keypair, ProofOfPossession, Verkey, VerkeyFingerprint};
+use crate::registry::{InMemoryVerkeyRegistry, PopStatus, VerkeyRegistry};
+use crate::session::{AccountableAggregate, MultisigSession};
+use crate::signature::Signature;
+use crate::util::{calculate_l, GeneratorSetRef};
+
+const TRANSCRIPT_DST: &[u8] = b"PIXEL-KEY-CEREMONY-V1";
+
+/// Verkey and proof of possession sent by a committee member
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CeremonyContribution {
+    pub index: usize,
+    pub verkey: Verkey,
+    pub pop: ProofOfPossession,
+}
+
+impl CeremonyContribution {
+    pub fn from_keypair(index: usize, keypair: &Keypair) -> Self {
+        Self {
+            index,
+            verkey: keypair.ver_key.clone(),
+            pop: keypair.pop.clone(),
+        }
+    }
+}
+
+/// What the committee members sign at the end of the ceremony
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CeremonyTranscript {
+    pub T: u128,
+    /// Hash of the serialized generators
+    pub generators_digest: [u8; 32],
+    /// Index and verkey fingerprint of every member in increasing order of index
+    pub members: Vec<(usize, VerkeyFingerprint)>,
+    pub aggregate_verkey: VerkeyFingerprint,
+}
+
+impl CeremonyTranscript {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = TRANSCRIPT_DST.to_vec();
+        bytes.extend_from_slice(&self.T.to_be_bytes());
+        bytes.extend_from_slice(&self.generators_digest);
+        bytes.extend_from_slice(&(self.members.len() as u64).to_be_bytes());
+        for (index, fp) in &self.members {
+            bytes.extend_from_slice(&(*index as u64).to_be_bytes());
+            bytes.extend_from_slice(fp.as_bytes());
+        }
+        bytes.extend_from_slice(self.aggregate_verkey.as_bytes());
+        bytes
+    }
+}
+
+/// Result of the ceremony, to be published
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CeremonyOutput {
+    /// Contributions of all members in increasing order of index
+    pub contributions: Vec<CeremonyContribution>,
+    pub aggregate_verkey: Verkey,
+    pub transcript: CeremonyTranscript,
+    /// Multi-signature of all members on the transcript for time period 1
+    pub transcript_signature: AccountableAggregate,
+}
+
+impl CeremonyOutput {
+    /// Registry of the committee members. Fails if any proof of possession does not verify.
+    pub fn registry(
+        &self,
+        gens: &GeneratorSetRef,
+    ) -> Result<InMemoryVerkeyRegistry, ForwardSecureSignatureError> {
+        let mut registry = InMemoryVerkeyRegistry::new();
+        for c in &self.contributions {
+            registry.add_member(c.index, c.verkey.clone())?;
+            if !registry.verify_pop(c.index, &c.pop, &gens.0)? {
+                return Err(ForwardSecureSignatureError::InvalidPop { index: c.index });
+            }
+        }
+        Ok(registry)
+    }
+
+    /// Check that the transcript matches the members and generators and that every member
+    /// signed it
+    pub fn verify(&self, gens: &GeneratorSetRef) -> Result<bool, ForwardSecureSignatureError> {
+        let registry = self.registry(gens)?;
+        let indices = registry.member_indices();
+        let expected = transcript(self.transcript.T, gens, &registry)?;
+        if expected != self.transcript || self.transcript_signature.signers != indices {
+            return Ok(false);
+        }
+        let l = calculate_l(self.transcript.T)?;
+        self.transcript_signature
+            .verify(&self.transcript.to_bytes(), 1, l, &registry, gens)
+    }
+}
+
+/// Collects the contributions of a committee of `size` members
+pub struct KeyCeremony {
+    T: u128,
+    gens: GeneratorSetRef,
+    size: usize,
+    registry: InMemoryVerkeyRegistry,
+    contributions: BTreeMap<usize, CeremonyContribution>,
+}
+
+impl KeyCeremony {
+    pub fn new<G: Into<GeneratorSetRef>>(
+        T: u128,
+        gens: G,
+        size: usize,
+    ) -> Result<Self, ForwardSecureSignatureError> {
+        calculate_l(T)?;
+        if size == 0 {
+            return Err(ForwardSecureSignatureError::InvalidQuorum {
+                quorum: 0,
+                committee: 0,
+            });
+        }
+        Ok(Self {
+            T,
+            gens: gens.into(),
+            size,
+            registry: InMemoryVerkeyRegistry::new(),
+            contributions: BTreeMap::new(),
+        })
+    }
+
+    /// Verify the proof of possession of the contribution and add the member. Fails if the
+    /// proof is invalid, the member was already added or the committee is complete.
+    pub fn add_contribution(
+        &mut self,
+        contribution: CeremonyContribution,
+    ) -> Result<(), ForwardSecureSignatureError> {
+        if self.is_complete() {
+            return Err(ForwardSecureSignatureError::CeremonyComplete { size: self.size });
+        }
+        let index = contribution.index;
+        if !Keypair::verify_pop(&contribution.pop, &contribution.verkey, &self.gens.0) {
+            return Err(ForwardSecureSignatureError::InvalidPop { index });
+        }
+        self.registry
+            .add_member(index, contribution.verkey.clone())?;
+        self.registry.set_pop_status(index, PopStatus::Verified)?;
+        self.contributions.insert(index, contribution);
+        Ok(())
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.registry.len() == self.size
+    }
+
+    /// Transcript that members need to sign with their key for time period 1. Only available
+    /// once all members contributed.
+    pub fn transcript(&self) -> Result<CeremonyTranscript, ForwardSecureSignatureError> {
+        if !self.is_complete() {
+            return Err(ForwardSecureSignatureError::QuorumNotReached {
+                received: self.registry.len(),
+                quorum: self.size,
+            });
+        }
+        transcript(self.T, &self.gens, &self.registry)
+    }
+
+    /// Aggregate the signatures of all members on the transcript and produce the output
+    pub fn finish(
+        self,
+        transcript_sigs: Vec<(usize, Signature)>,
+    ) -> Result<CeremonyOutput, ForwardSecureSignatureError> {
+        let transcript = self.transcript()?;
+        let indices = self.registry.member_indices();
+        let l = calculate_l(self.T)?;
+        let mut session = MultisigSession::new(
+            &transcript.to_bytes(),
+            1,
+            l,
+            self.gens.clone(),
+            &indices,
+            &self.registry,
+            self.size,
+        )?;
+        for (index, sig) in transcript_sigs {
+            session.add_share(index, &sig)?;
+        }
+        let transcript_signature = session.finalize()?;
+        Ok(CeremonyOutput {
+            contributions: self.contributions.into_values().collect(),
+            aggregate_verkey: self.registry.aggregate_verkey(&indices)?,
+            transcript,
+            transcript_signature,
+        })
+    }
+}
+
+fn transcript<V: VerkeyRegistry>(
+    T: u128,
+    gens: &GeneratorSetRef,
+    registry: &V,
+) -> Result<CeremonyTranscript, ForwardSecureSignatureError> {
+    let indices = registry.member_indices();
+    let mut members = vec![];
+    for index in &indices {
+        members.push((*index, registry.get_by_index(*index)?.fingerprint));
+    }
+    let mut generators_digest = [0; 32];
+    generators_digest.copy_from_slice(&Sha3_256::digest(&gens.to_bytes()));
+    Ok(CeremonyTranscript {
+        T,
+        generators_digest,
+        members,
+        aggregate_verkey: registry.aggregate_verkey(&indices)?.fingerprint(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{InMemorySigKeyDatabase, SigManager};
+    use crate::util::GeneratorSet;
+
+    #[test]
+    fn test_key_ceremony() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let l = calculate_l(T).unwrap();
+        let gens = GeneratorSetRef::new(GeneratorSet::new(T, "test_pixel").unwrap());
+        let mut ceremony = KeyCeremony::new(T, gens.clone(), 3).unwrap();
+
+        let mut dbs = vec![];
+        let mut keypairs = vec![];
+        for i in 1..=3 {
+            let mut db = InMemorySigKeyDatabase::new();
+            let (keypair, _) = Keypair::new(T, &gens, &mut rng, &mut db).unwrap();
+            dbs.push(db);
+            keypairs.push(keypair);
+            assert!(ceremony.transcript().is_err());
+            // A contribution with the PoP of another member is rejected
+            if i > 1 {
+                let mut bad = CeremonyContribution::from_keypair(i, &keypairs[i - 1]);
+                bad.pop = keypairs[0].pop.clone();
+                assert!(ceremony.add_contribution(bad).is_err());
+            }
+            ceremony
+                .add_contribution(CeremonyContribution::from_keypair(i, &keypairs[i - 1]))
+                .unwrap();
+        }
+        assert!(ceremony
+            .add_contribution(CeremonyContribution::from_keypair(4, &keypairs[0]))
+            .is_err());
+
+        let transcript = ceremony.transcript().unwrap();
+        assert_eq!(transcript.members.len(), 3);
+        let msg = transcript.to_bytes();
+        let mut sigs = vec![];
+        for (i, db) in dbs.iter().enumerate() {
+            let sk = SigManager::get_key(1, db).unwrap();
+            sigs.push((
+                i + 1,
+                Signature::new(&msg, 1, l, &gens, sk, &mut rng).unwrap(),
+            ));
+        }
+        let output = ceremony.finish(sigs).unwrap();
+        assert!(output.verify(&gens).unwrap());
+        assert_eq!(
+            output.aggregate_verkey.fingerprint(),
+            output.transcript.aggregate_verkey
+        );
+        let registry = output.registry(&gens).unwrap();
+        assert_eq!(registry.member_indices(), vec![1, 2, 3]);
+
+        // Tampering with the members is detected
+        let mut tampered = output.clone();
+        tampered.contributions.pop();
+        assert!(!tampered.verify(&gens).unwrap());
+        let mut tampered = output.clone();
+        tampered.contributions[0].pop = tampered.contributions[1].pop.clone();
+        assert!(tampered.verify(&gens).is_err());
+
+        // Proofs of possession do not verify with other generators
+        let gens_1 = GeneratorSetRef::new(GeneratorSet::new(T, "test_pixel_1").unwrap());
+        assert!(output.verify(&gens_1).is_err());
+    }
+
+    #[test]
+    fn test_key_ceremony_missing_signature() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let l = calculate_l(T).unwrap();
+        let gens = GeneratorSetRef::new(GeneratorSet::new(T, "test_pixel").unwrap());
+        let mut ceremony = KeyCeremony::new(T, gens.clone(), 2).unwrap();
+        let mut dbs = vec![];
+        for i in 1..=2 {
+            let mut db = InMemorySigKeyDatabase::new();
+            let (keypair, _) = Keypair::new(T, &gens, &mut rng, &mut db).unwrap();
+            ceremony
+                .add_contribution(CeremonyContribution::from_keypair(i, &keypair))
+                .unwrap();
+            dbs.push(db);
+        }
+        let msg = ceremony.transcript().unwrap().to_bytes();
+        let sk = SigManager::get_key(1, &dbs[0]).unwrap();
+        let sig = Signature::new(&msg, 1, l, &gens, sk, &mut rng).unwrap();
+        assert!(ceremony.finish(vec![(1, sig)]).is_err());
+    }
+}
@@ -0,0 +1,228 @@
+//! Certificates binding a subject verkey to a window of time periods. The certificate is signed
+//! by the issuer with its key for the time period of issuance. All keys in a chain must use the
+//! same generators and the same clock, i.e. their time periods must refer to the same epochs.
+
+use rand::{CryptoRng, RngCore};
+
+use crate::errors::ForwardSecureSignatureError;
+use crate::keys::{SigKeyDb, SigManager, Verkey, VerkeyFingerprint};
+use crate::signature::Signature;
+use crate::util::GeneratorSet;
+
+const CERTIFICATE_DST: &[u8] = b"PIXEL-CERTIFICATE-V1";
+
+/// Time periods `from` to `until`, both inclusive
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ValidityWindow {
+    pub from: u128,
+    pub until: u128,
+}
+
+impl ValidityWindow {
+    pub fn new(from: u128, until: u128) -> Result<Self, ForwardSecureSignatureError> {
+        if from == 0 || from > until {
+            return Err(ForwardSecureSignatureError::InvalidValidityWindow { from, until });
+        }
+        Ok(Self { from, until })
+    }
+
+    pub fn contains(&self, t: u128) -> bool {
+        self.from <= t && t <= self.until
+    }
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Certificate {
+    pub subject: Verkey,
+    pub window: ValidityWindow,
+    pub issuer: VerkeyFingerprint,
+    /// Time period of the issuer's key that signed the certificate
+    pub issued_at: u128,
+    pub signature: Signature,
+}
+
+impl Certificate {
+    /// Issue a certificate for `subject` with the issuer's key of its current time period
+    pub fn issue<R: RngCore + CryptoRng>(
+        subject: Verkey,
+        window: ValidityWindow,
+        issuer: &Verkey,
+        manager: &mut SigManager,
+        gens: &GeneratorSet,
+        db: &dyn SigKeyDb,
+        rng: &mut R,
+    ) -> Result<Self, ForwardSecureSignatureError> {
+        let issuer = issuer.fingerprint();
+        let issued_at = manager.t();
+        let msg = statement(&subject, &window, &issuer, issued_at);
+        let signature = manager.sign(&msg, gens, db, rng)?;
+        Ok(Self {
+            subject,
+            window,
+            issuer,
+            issued_at,
+            signature,
+        })
+    }
+
+    /// Check the issuer's signature. The window of the certificate is not checked.
+    pub fn verify(
+        &self,
+        issuer: &Verkey,
+        l: u8,
+        gens: &GeneratorSet,
+    ) -> Result<bool, ForwardSecureSignatureError> {
+        if issuer.fingerprint() != self.issuer {
+            return Ok(false);
+        }
+        let msg = statement(&self.subject, &self.window, &self.issuer, self.issued_at);
+        self.signature.verify(&msg, self.issued_at, l, gens, issuer)
+    }
+
+    /// Whether the subject is certified for time period `t`
+    pub fn is_valid_at(&self, t: u128) -> bool {
+        self.window.contains(t)
+    }
+}
+
+/// Verify a chain of certificates starting with one issued by `root`. Every certificate after
+/// the first must be issued by the subject of the previous one during the window asserted for
+/// it by the previous certificate. Returns the leaf certificate.
+pub fn verify_chain<'a>(
+    chain: &'a [Certificate],
+    root: &Verkey,
+    l: u8,
+    gens: &GeneratorSet,
+) -> Result<&'a Certificate, ForwardSecureSignatureError> {
+    let mut issuer = root;
+    let mut issuer_window: Option<&ValidityWindow> = None;
+    for (i, cert) in chain.iter().enumerate() {
+        if let Some(window) = issuer_window {
+            if !window.contains(cert.issued_at) {
+                return Err(ForwardSecureSignatureError::InvalidCertificate {
+                    position: i,
+                    msg: format!(
+                        "issued at time period {} outside issuer's window {}-{}",
+                        cert.issued_at, window.from, window.until
+                    ),
+                });
+            }
+        }
+        if !cert.verify(issuer, l, gens)? {
+            return Err(ForwardSecureSignatureError::InvalidCertificate {
+                position: i,
+                msg: String::from("signature does not verify"),
+            });
+        }
+        issuer = &cert.subject;
+        issuer_window = Some(&cert.window);
+    }
+    chain
+        .last()
+        .ok_or_else(|| ForwardSecureSignatureError::InvalidCertificate {
+            position: 0,
+            msg: String::from("empty chain"),
+        })
+}
+
+fn statement(
+    subject: &Verkey,
+    window: &ValidityWindow,
+    issuer: &VerkeyFingerprint,
+    issued_at: u128,
+) -> Vec<u8> {
+    let mut bytes = CERTIFICATE_DST.to_vec();
+    bytes.append(&mut subject.to_bytes());
+    bytes.extend_from_slice(&window.from.to_be_bytes());
+    bytes.extend_from_slice(&window.until.to_be_bytes());
+    bytes.extend_from_slice(issuer.as_bytes());
+    bytes.extend_from_slice(&issued_at.to_be_bytes());
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{InMemorySigKeyDatabase, Keypair};
+    use crate::util::calculate_l;
+
+    #[test]
+    fn test_certificate_chain() {
+        let mut rng = rand::thread_rng();
+        let T = 15;
+        let l = calculate_l(T).unwrap();
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let mut dbs = vec![];
+        let mut managers = vec![];
+        let mut verkeys = vec![];
+        for _ in 0..3 {
+            let mut db = InMemorySigKeyDatabase::new();
+            let (keypair, manager) = Keypair::new(T, &gens, &mut rng, &mut db).unwrap();
+            dbs.push(db);
+            managers.push(manager);
+            verkeys.push(keypair.ver_key);
+        }
+        assert!(ValidityWindow::new(5, 4).is_err());
+        assert!(ValidityWindow::new(0, 4).is_err());
+
+        // Root certifies an intermediate for periods 2 to 6 at period 1
+        let intermediate = Certificate::issue(
+            verkeys[1].clone(),
+            ValidityWindow::new(2, 6).unwrap(),
+            &verkeys[0],
+            &mut managers[0],
+            &gens,
+            &dbs[0],
+            &mut rng,
+        )
+        .unwrap();
+        assert!(intermediate.verify(&verkeys[0], l, &gens).unwrap());
+        assert!(!intermediate.verify(&verkeys[1], l, &gens).unwrap());
+        assert!(intermediate.is_valid_at(6));
+        assert!(!intermediate.is_valid_at(7));
+
+        // Intermediate certifies a leaf at period 4, inside its window
+        managers[1]
+            .fast_forward_update(4, &gens, &mut rng, &mut dbs[1])
+            .unwrap();
+        let leaf = Certificate::issue(
+            verkeys[2].clone(),
+            ValidityWindow::new(4, 10).unwrap(),
+            &verkeys[1],
+            &mut managers[1],
+            &gens,
+            &dbs[1],
+            &mut rng,
+        )
+        .unwrap();
+        let chain = vec![intermediate.clone(), leaf.clone()];
+        let verified = verify_chain(&chain, &verkeys[0], l, &gens).unwrap();
+        assert_eq!(verified.subject.value, verkeys[2].value);
+        assert!(verify_chain(&chain, &verkeys[1], l, &gens).is_err());
+        assert!(verify_chain(&[], &verkeys[0], l, &gens).is_err());
+
+        // Tampering with the window invalidates the signature
+        let mut tampered = leaf.clone();
+        tampered.window.until = 12;
+        assert!(verify_chain(&[intermediate.clone(), tampered], &verkeys[0], l, &gens).is_err());
+
+        // Leaf issued at period 7, after the intermediate's window ended
+        managers[1]
+            .fast_forward_update(7, &gens, &mut rng, &mut dbs[1])
+            .unwrap();
+        let late = Certificate::issue(
+            verkeys[2].clone(),
+            ValidityWindow::new(7, 10).unwrap(),
+            &verkeys[1],
+            &mut managers[1],
+            &gens,
+            &dbs[1],
+            &mut rng,
+        )
+        .unwrap();
+        assert!(late.verify(&verkeys[1], l, &gens).unwrap());
+        assert!(verify_chain(&[intermediate, late], &verkeys[0], l, &gens).is_err());
+    }
+}
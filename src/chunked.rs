@@ -0,0 +1,203 @@
+//! Splitting of encoded messages into fragments small enough for a QR code each, for transfer
+//! between air-gapped devices with a camera, and their reassembly. Any encoding can be split,
+//! e.g. a `SignedEnvelope` to be signed offline, a `WrappedSigkey` or a `KeyBackup`.
+//!
+//! Each fragment is a bech32m string with prefix `FRAGMENT_HRP` holding
+//! `index || total || digest || data` with `index` and `total` as 2 byte big endian integers and
+//! `digest` the first 8 bytes of the SHA3-256 hash of the whole message. Fragments are uppercase
+//! so QR codes can use the denser alphanumeric mode. The checksum of a fragment catches misreads,
+//! the digest ties fragments to their message and catches a wrongly reassembled message.
+//! Fragments can be scanned in any order and more than once.
+
+use std::collections::BTreeMap;
+
+use sha3::{Digest, Sha3_256};
+
+use crate::bech32;
+use crate::errors::ForwardSecureSignatureError;
+
+pub const FRAGMENT_HRP: &str = "fsur";
+
+/// Bytes of the message in each fragment by default. A fragment then has 351 characters, which
+/// fits a QR code of version 11 with medium error correction.
+pub const DEFAULT_FRAGMENT_SIZE: usize = 200;
+
+const DIGEST_SIZE: usize = 8;
+const HEADER_SIZE: usize = 4 + DIGEST_SIZE;
+
+fn invalid(msg: &str) -> ForwardSecureSignatureError {
+    ForwardSecureSignatureError::InvalidFragment {
+        msg: msg.to_string(),
+    }
+}
+
+fn message_digest(msg: &[u8]) -> [u8; DIGEST_SIZE] {
+    let mut digest = [0; DIGEST_SIZE];
+    digest.copy_from_slice(&Sha3_256::digest(msg)[..DIGEST_SIZE]);
+    digest
+}
+
+/// Split `msg` into fragments holding at most `fragment_size` bytes of it. Fails if more than
+/// 65535 fragments would be needed.
+pub fn split(msg: &[u8], fragment_size: usize) -> Result<Vec<String>, ForwardSecureSignatureError> {
+    if fragment_size == 0 {
+        return Err(invalid("fragment size must not be 0"));
+    }
+    let chunks = if msg.is_empty() {
+        vec![msg]
+    } else {
+        msg.chunks(fragment_size).collect()
+    };
+    if chunks.len() > u16::MAX as usize {
+        return Err(invalid("too many fragments"));
+    }
+    let total = chunks.len() as u16;
+    let digest = message_digest(msg);
+    chunks
+        .iter()
+        .enumerate()
+        .map(|(i, chunk)| {
+            let mut data = Vec::with_capacity(HEADER_SIZE + chunk.len());
+            data.extend_from_slice(&(i as u16).to_be_bytes());
+            data.extend_from_slice(&total.to_be_bytes());
+            data.extend_from_slice(&digest);
+            data.extend_from_slice(chunk);
+            Ok(bech32::encode(FRAGMENT_HRP, &data)?.to_ascii_uppercase())
+        })
+        .collect()
+}
+
+/// Collects the fragments of one message
+#[derive(Clone, Debug, Default)]
+pub struct Reassembler {
+    /// Total number of fragments and digest of the message, known after the first fragment
+    header: Option<(u16, [u8; DIGEST_SIZE])>,
+    fragments: BTreeMap<u16, Vec<u8>>,
+}
+
+impl Reassembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a fragment and return whether all fragments are there. Fragments added before are
+    /// ignored. Fails for malformed fragments and fragments of another message, which leave the
+    /// fragments collected so far untouched.
+    pub fn add(&mut self, fragment: &str) -> Result<bool, ForwardSecureSignatureError> {
+        let (hrp, data) = bech32::decode(fragment)?;
+        if hrp != FRAGMENT_HRP {
+            return Err(invalid("not a fragment"));
+        }
+        if data.len() < HEADER_SIZE {
+            return Err(invalid("too short"));
+        }
+        let index = u16::from_be_bytes([data[0], data[1]]);
+        let total = u16::from_be_bytes([data[2], data[3]]);
+        let mut digest = [0; DIGEST_SIZE];
+        digest.copy_from_slice(&data[4..HEADER_SIZE]);
+        if index >= total {
+            return Err(invalid("index out of range"));
+        }
+        match self.header {
+            Some(header) if header != (total, digest) => {
+                return Err(invalid("fragment of another message"))
+            }
+            _ => self.header = Some((total, digest)),
+        }
+        self.fragments
+            .entry(index)
+            .or_insert_with(|| data[HEADER_SIZE..].to_vec());
+        Ok(self.is_complete())
+    }
+
+    /// Number of distinct fragments received and total number of fragments, 0 before the first
+    pub fn progress(&self) -> (usize, usize) {
+        (
+            self.fragments.len(),
+            self.header.map_or(0, |(total, _)| total as usize),
+        )
+    }
+
+    pub fn is_complete(&self) -> bool {
+        let (received, total) = self.progress();
+        total > 0 && received == total
+    }
+
+    /// The reassembled message. Fails if fragments are missing or the message does not match
+    /// the digest of the fragments.
+    pub fn message(&self) -> Result<Vec<u8>, ForwardSecureSignatureError> {
+        let (_, digest) = match self.header {
+            Some(header) if self.is_complete() => header,
+            _ => return Err(invalid("fragments missing")),
+        };
+        let msg = self
+            .fragments
+            .values()
+            .flatten()
+            .cloned()
+            .collect::<Vec<_>>();
+        if message_digest(&msg) != digest {
+            return Err(invalid("digest mismatch"));
+        }
+        Ok(msg)
+    }
+}
+
+/// Reassemble a message from all its fragments, given in any order
+pub fn join(fragments: &[&str]) -> Result<Vec<u8>, ForwardSecureSignatureError> {
+    let mut reassembler = Reassembler::new();
+    for fragment in fragments {
+        reassembler.add(fragment)?;
+    }
+    reassembler.message()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::RngCore;
+
+    #[test]
+    fn test_split_and_reassemble() {
+        let mut msg = vec![0; 1000];
+        rand::thread_rng().fill_bytes(&mut msg);
+        let fragments = split(&msg, DEFAULT_FRAGMENT_SIZE).unwrap();
+        assert_eq!(fragments.len(), 5);
+        assert!(fragments
+            .iter()
+            .all(|f| f.len() <= 351 && !f.bytes().any(|c| c.is_ascii_lowercase())));
+        let refs = fragments.iter().map(|f| f.as_str()).collect::<Vec<_>>();
+        assert_eq!(join(&refs).unwrap(), msg);
+
+        // Any order, repeated fragments
+        let mut reassembler = Reassembler::new();
+        assert_eq!(reassembler.progress(), (0, 0));
+        for i in &[3, 1, 3, 0, 4] {
+            assert!(!reassembler.add(&fragments[*i]).unwrap());
+        }
+        assert_eq!(reassembler.progress(), (4, 5));
+        assert!(reassembler.message().is_err());
+        assert!(reassembler.add(&fragments[2]).unwrap());
+        assert_eq!(reassembler.message().unwrap(), msg);
+
+        // Fragments of another message or misread are rejected
+        let other = split(&msg[1..], DEFAULT_FRAGMENT_SIZE).unwrap();
+        assert!(reassembler.add(&other[0]).is_err());
+        assert_eq!(reassembler.message().unwrap(), msg);
+        let mut misread = fragments[0].clone().into_bytes();
+        misread[20] = if misread[20] == b'Q' { b'P' } else { b'Q' };
+        assert!(Reassembler::new()
+            .add(std::str::from_utf8(&misread).unwrap())
+            .is_err());
+        assert!(join(&refs[..4]).is_err());
+        assert!(Reassembler::new()
+            .add(&bech32::encode("fsvk", &msg[..20]).unwrap())
+            .is_err());
+
+        assert_eq!(
+            join(&[&split(&[], 10).unwrap()[0]]).unwrap(),
+            Vec::<u8>::new()
+        );
+        assert!(split(&msg, 0).is_err());
+    }
+}
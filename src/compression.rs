@@ -0,0 +1,264 @@
+//! Compressed encodings of the points of signatures and verkeys, about half the size of the
+//! uncompressed `to_bytes` encodings. A point is encoded as a flag byte followed by its x
+//! coordinate, `x` for G1 and `x.a || x.b` for G2, big endian. The flag is 2 or 3 with the lowest
+//! bit the sign of y, the parity of y for G1 and of `y.b`, or of `y.a` if `y.b` is 0, for G2.
+//! The identity is encoded as a flag of 0 and x of 0.
+//!
+//! Decoding only accepts canonical encodings of points in the prime order subgroup other than
+//! the identity, and fails with `InvalidPoint` otherwise. Points in G1 are encoded as by
+//! `wire::g1_to_compressed_bytes`, which does not check the subgroup on decoding. The
+//! crate-internal `_unchecked` decoders skip the subgroup check and accept the identity, for
+//! `Signature::from_bytes_unchecked`.
+
+use amcl_wrapper::constants::MODBYTES;
+use amcl_wrapper::group_elem::GroupElement;
+use amcl_wrapper::group_elem_g1::G1;
+use amcl_wrapper::group_elem_g2::G2;
+use amcl_wrapper::ECCurve::big::BIG;
+use amcl_wrapper::ECCurve::ecp::ECP;
+use amcl_wrapper::ECCurve::ecp2::ECP2;
+use amcl_wrapper::ECCurve::fp2::FP2;
+
+use crate::errors::ForwardSecureSignatureError;
+use crate::{SignatureGroup, VerkeyGroup};
+
+pub use crate::wire::G1_COMPRESSED_SIZE;
+pub const G2_COMPRESSED_SIZE: usize = 2 * MODBYTES + 1;
+
+#[cfg(feature = "VerkeyG2")]
+pub const SIGNATURE_GROUP_COMPRESSED_SIZE: usize = G1_COMPRESSED_SIZE;
+#[cfg(feature = "VerkeyG2")]
+pub const VERKEY_GROUP_COMPRESSED_SIZE: usize = G2_COMPRESSED_SIZE;
+#[cfg(feature = "VerkeyG1")]
+pub const SIGNATURE_GROUP_COMPRESSED_SIZE: usize = G2_COMPRESSED_SIZE;
+#[cfg(feature = "VerkeyG1")]
+pub const VERKEY_GROUP_COMPRESSED_SIZE: usize = G1_COMPRESSED_SIZE;
+
+fn invalid(msg: &str) -> ForwardSecureSignatureError {
+    ForwardSecureSignatureError::InvalidPoint {
+        msg: msg.to_string(),
+    }
+}
+
+pub fn compress_g1(p: &G1) -> Vec<u8> {
+    let mut bytes = vec![0; G1_COMPRESSED_SIZE];
+    if !p.is_identity() {
+        p.to_ecp().tobytes(&mut bytes, true);
+    }
+    bytes
+}
+
+pub fn decompress_g1(bytes: &[u8]) -> Result<G1, ForwardSecureSignatureError> {
+    decompress_g1_with(bytes, true)
+}
+
+pub(crate) fn decompress_g1_unchecked(bytes: &[u8]) -> Result<G1, ForwardSecureSignatureError> {
+    decompress_g1_with(bytes, false)
+}
+
+fn decompress_g1_with(bytes: &[u8], check: bool) -> Result<G1, ForwardSecureSignatureError> {
+    if bytes.len() != G1_COMPRESSED_SIZE {
+        return Err(invalid(&format!(
+            "{} bytes instead of {}",
+            bytes.len(),
+            G1_COMPRESSED_SIZE
+        )));
+    }
+    if !check && bytes.iter().all(|b| *b == 0) {
+        return Ok(G1::identity());
+    }
+    if bytes[0] != 2 && bytes[0] != 3 {
+        return Err(invalid("identity or invalid flag"));
+    }
+    let p: G1 = ECP::frombytes(bytes).into();
+    if p.is_identity() {
+        return Err(invalid("not on the curve"));
+    }
+    checked(p, bytes, compress_g1, check)
+}
+
+/// Sign of y as defined in the module documentation
+fn g2_y_sign(y: &mut FP2) -> u8 {
+    let b = y.getb();
+    let s = if b.iszilch() {
+        y.geta().parity()
+    } else {
+        b.parity()
+    };
+    s as u8
+}
+
+pub fn compress_g2(p: &G2) -> Vec<u8> {
+    let mut bytes = vec![0; G2_COMPRESSED_SIZE];
+    if p.is_identity() {
+        return bytes;
+    }
+    let ecp = p.to_ecp();
+    let mut x = ecp.getx();
+    bytes[0] = 2 | g2_y_sign(&mut ecp.gety());
+    x.geta().tobytes(&mut bytes[1..MODBYTES + 1]);
+    x.getb().tobytes(&mut bytes[MODBYTES + 1..]);
+    bytes
+}
+
+pub fn decompress_g2(bytes: &[u8]) -> Result<G2, ForwardSecureSignatureError> {
+    decompress_g2_with(bytes, true)
+}
+
+pub(crate) fn decompress_g2_unchecked(bytes: &[u8]) -> Result<G2, ForwardSecureSignatureError> {
+    decompress_g2_with(bytes, false)
+}
+
+fn decompress_g2_with(bytes: &[u8], check: bool) -> Result<G2, ForwardSecureSignatureError> {
+    if bytes.len() != G2_COMPRESSED_SIZE {
+        return Err(invalid(&format!(
+            "{} bytes instead of {}",
+            bytes.len(),
+            G2_COMPRESSED_SIZE
+        )));
+    }
+    if !check && bytes.iter().all(|b| *b == 0) {
+        return Ok(G2::identity());
+    }
+    if bytes[0] != 2 && bytes[0] != 3 {
+        return Err(invalid("identity or invalid flag"));
+    }
+    let x = FP2::new_bigs(
+        &BIG::frombytes(&bytes[1..MODBYTES + 1]),
+        &BIG::frombytes(&bytes[MODBYTES + 1..]),
+    );
+    let mut ecp = ECP2::new_fp2(&x);
+    if ecp.is_infinity() {
+        return Err(invalid("not on the curve"));
+    }
+    if g2_y_sign(&mut ecp.gety()) != bytes[0] & 1 {
+        ecp.neg();
+    }
+    checked(ecp.into(), bytes, compress_g2, check)
+}
+
+/// Reject non-canonical encodings, like an x not reduced modulo the field prime, and if
+/// `check_order` points outside the prime order subgroup
+fn checked<G: GroupElement>(
+    p: G,
+    bytes: &[u8],
+    compress: fn(&G) -> Vec<u8>,
+    check_order: bool,
+) -> Result<G, ForwardSecureSignatureError> {
+    if compress(&p) != bytes {
+        return Err(invalid("not canonical"));
+    }
+    if check_order && !p.has_correct_order() {
+        return Err(invalid("not in the prime order subgroup"));
+    }
+    Ok(p)
+}
+
+#[cfg(feature = "VerkeyG2")]
+pub fn compress_signature_group(p: &SignatureGroup) -> Vec<u8> {
+    compress_g1(p)
+}
+#[cfg(feature = "VerkeyG2")]
+pub fn decompress_signature_group(
+    bytes: &[u8],
+) -> Result<SignatureGroup, ForwardSecureSignatureError> {
+    decompress_g1(bytes)
+}
+#[cfg(feature = "VerkeyG2")]
+pub fn compress_verkey_group(p: &VerkeyGroup) -> Vec<u8> {
+    compress_g2(p)
+}
+#[cfg(feature = "VerkeyG2")]
+pub fn decompress_verkey_group(bytes: &[u8]) -> Result<VerkeyGroup, ForwardSecureSignatureError> {
+    decompress_g2(bytes)
+}
+#[cfg(feature = "VerkeyG2")]
+pub(crate) use self::{
+    decompress_g1_unchecked as decompress_signature_group_unchecked,
+    decompress_g2_unchecked as decompress_verkey_group_unchecked,
+};
+
+#[cfg(feature = "VerkeyG1")]
+pub fn compress_signature_group(p: &SignatureGroup) -> Vec<u8> {
+    compress_g2(p)
+}
+#[cfg(feature = "VerkeyG1")]
+pub fn decompress_signature_group(
+    bytes: &[u8],
+) -> Result<SignatureGroup, ForwardSecureSignatureError> {
+    decompress_g2(bytes)
+}
+#[cfg(feature = "VerkeyG1")]
+pub fn compress_verkey_group(p: &VerkeyGroup) -> Vec<u8> {
+    compress_g1(p)
+}
+#[cfg(feature = "VerkeyG1")]
+pub fn decompress_verkey_group(bytes: &[u8]) -> Result<VerkeyGroup, ForwardSecureSignatureError> {
+    decompress_g1(bytes)
+}
+#[cfg(feature = "VerkeyG1")]
+pub(crate) use self::{
+    decompress_g1_unchecked as decompress_verkey_group_unchecked,
+    decompress_g2_unchecked as decompress_signature_group_unchecked,
+};
+
+/// Point on the curve of G1 but outside the prime order subgroup, for tests of the order checks
+#[cfg(test)]
+pub(crate) fn g1_outside_subgroup() -> G1 {
+    let mut bytes = [0; G1_COMPRESSED_SIZE];
+    bytes[0] = 2;
+    for x in 0..=255 {
+        bytes[G1_COMPRESSED_SIZE - 1] = x;
+        let p: G1 = ECP::frombytes(&bytes).into();
+        if !p.is_identity() && !p.has_correct_order() {
+            return p;
+        }
+    }
+    unreachable!("no point outside the subgroup with a small x")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_point_compression() {
+        for _ in 0..10 {
+            let p = G1::random();
+            let bytes = compress_g1(&p);
+            assert_eq!(bytes.len(), G1_COMPRESSED_SIZE);
+            assert_eq!(decompress_g1(&bytes).unwrap(), p);
+            assert_eq!(decompress_g1(&compress_g1(&-&p)).unwrap(), -&p);
+            let q = G2::random();
+            let bytes = compress_g2(&q);
+            assert_eq!(bytes.len(), G2_COMPRESSED_SIZE);
+            assert_eq!(decompress_g2(&bytes).unwrap(), q);
+            assert_eq!(decompress_g2(&compress_g2(&-&q)).unwrap(), -&q);
+        }
+
+        // Identity, wrong length or flag, x not canonical or off the curve
+        assert!(decompress_g1(&compress_g1(&G1::identity())).is_err());
+        assert!(decompress_g2(&compress_g2(&G2::identity())).is_err());
+        let mut bytes = compress_g1(&G1::random());
+        assert!(decompress_g1(&bytes[1..]).is_err());
+        bytes[0] = 4;
+        assert!(decompress_g1(&bytes).is_err());
+        let mut bytes = vec![0xff; G2_COMPRESSED_SIZE];
+        bytes[0] = 2;
+        assert!(decompress_g2(&bytes).is_err());
+        let mut x = 0u8;
+        let off_curve = loop {
+            let mut bytes = vec![0; G1_COMPRESSED_SIZE];
+            bytes[0] = 2;
+            bytes[G1_COMPRESSED_SIZE - 1] = x;
+            if ECP::frombytes(&bytes).is_infinity() {
+                break bytes;
+            }
+            x += 1;
+        };
+        assert!(decompress_g1(&off_curve).is_err());
+        let p = g1_outside_subgroup();
+        assert!(decompress_g1(&compress_g1(&p)).is_err());
+        assert_eq!(decompress_g1_unchecked(&compress_g1(&p)).unwrap(), p);
+    }
+}
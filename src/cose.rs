@@ -0,0 +1,284 @@
+//! COSE_Sign1 structures (RFC 9052) signed with this scheme, for constrained devices that already
+//! speak COSE. A structure is the tagged array `[protected, unprotected, payload, signature]`
+//! with the protected header `{1: alg, 2: [TIME_PERIOD_LABEL], TIME_PERIOD_LABEL: t}` and the
+//! fingerprint of the signer's verkey as key id (label 4) in the unprotected header. The time
+//! period is marked critical so that COSE libraries that do not know it reject the structure.
+//!
+//! The algorithm identifier and the time period label are from the private use ranges since
+//! none are registered. The signature is the compressed encoding of a signature on the
+//! `Sig_structure` of RFC 9052. Payloads must be attached. Items are decoded with
+//! `cbor::Decoder` so only the shortest forms of integers and lengths are accepted.
+
+use std::collections::BTreeSet;
+
+use rand::{CryptoRng, RngCore};
+
+use crate::cbor::{Decoder, Encoder, MAJOR_ARRAY, MAJOR_MAP, MAJOR_TAG};
+use crate::errors::ForwardSecureSignatureError;
+use crate::facade::{Signer, Verifier};
+use crate::keys::SigKeyDb;
+use crate::signature::Signature;
+
+#[cfg(feature = "VerkeyG2")]
+pub const COSE_ALGORITHM: i64 = -65_537;
+#[cfg(feature = "VerkeyG1")]
+pub const COSE_ALGORITHM: i64 = -65_538;
+
+/// Label of the header parameter holding the time period
+pub const TIME_PERIOD_LABEL: i64 = -65_601;
+
+const COSE_SIGN1_TAG: u64 = 18;
+const LABEL_ALG: i64 = 1;
+const LABEL_CRIT: i64 = 2;
+const LABEL_KID: i64 = 4;
+const SIGNATURE1_CONTEXT: &str = "Signature1";
+/// Nesting allowed in the values of header parameters that are skipped
+const MAX_DEPTH: usize = 16;
+
+fn invalid(msg: &str) -> ForwardSecureSignatureError {
+    ForwardSecureSignatureError::InvalidCose {
+        msg: msg.to_string(),
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CoseSign1 {
+    /// Time period of the signing key
+    pub t: u128,
+    /// Key id of the unprotected header, not covered by the signature
+    pub kid: Option<Vec<u8>>,
+    pub payload: Vec<u8>,
+    pub signature: Signature,
+    /// Encoded protected header, kept as received since the signature covers its bytes
+    protected: Vec<u8>,
+}
+
+impl CoseSign1 {
+    /// Sign `payload` and `external_aad` with the signer's key for its current time period
+    pub fn sign<D: SigKeyDb, R: RngCore + CryptoRng>(
+        payload: Vec<u8>,
+        external_aad: &[u8],
+        signer: &mut Signer<D>,
+        rng: &mut R,
+    ) -> Result<Self, ForwardSecureSignatureError> {
+        let mut e = Encoder::default();
+        e.head(MAJOR_MAP, 3);
+        e.int(LABEL_ALG);
+        e.int(COSE_ALGORITHM);
+        e.int(LABEL_CRIT);
+        e.head(MAJOR_ARRAY, 1);
+        e.int(TIME_PERIOD_LABEL);
+        e.int(TIME_PERIOD_LABEL);
+        e.uint(signer.t());
+        let protected = e.bytes;
+        let signature = signer.sign(&sig_structure(&protected, external_aad, &payload), rng)?;
+        Ok(Self {
+            t: signer.t(),
+            kid: Some(signer.verkey().fingerprint().as_bytes().to_vec()),
+            payload,
+            signature,
+            protected,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut e = Encoder::default();
+        e.head(MAJOR_TAG, COSE_SIGN1_TAG);
+        e.head(MAJOR_ARRAY, 4);
+        e.byte_string(&self.protected);
+        match &self.kid {
+            Some(kid) => {
+                e.head(MAJOR_MAP, 1);
+                e.int(LABEL_KID);
+                e.byte_string(kid);
+            }
+            None => e.head(MAJOR_MAP, 0),
+        }
+        e.byte_string(&self.payload);
+        e.byte_string(&self.signature.to_bytes());
+        e.bytes
+    }
+
+    /// Parse a tagged COSE_Sign1 structure. Fails if it is malformed, for another algorithm or
+    /// has critical header parameters other than the time period, but does not check the
+    /// signature.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ForwardSecureSignatureError> {
+        let mut d = Decoder::new(bytes);
+        if d.expect(MAJOR_TAG)? != COSE_SIGN1_TAG {
+            return Err(invalid("not tagged as COSE_Sign1"));
+        }
+        if d.expect(MAJOR_ARRAY)? != 4 {
+            return Err(invalid("COSE_Sign1 is not an array of 4 items"));
+        }
+        let protected = d.byte_string()?.to_vec();
+        let t = parse_protected(&protected)?;
+        let mut kid = None;
+        let mut labels = BTreeSet::new();
+        for _ in 0..d.expect(MAJOR_MAP)? {
+            let label = d.int()?;
+            if !labels.insert(label) {
+                return Err(invalid(&format!("duplicate header parameter {}", label)));
+            }
+            match label {
+                LABEL_KID => kid = Some(d.byte_string()?.to_vec()),
+                LABEL_ALG | LABEL_CRIT | TIME_PERIOD_LABEL => {
+                    return Err(invalid(&format!(
+                        "header parameter {} must be protected",
+                        label
+                    )))
+                }
+                _ => d.skip(MAX_DEPTH)?,
+            }
+        }
+        let payload = d.byte_string()?.to_vec();
+        let signature = Signature::from_bytes(d.byte_string()?)?;
+        d.finish()?;
+        Ok(Self {
+            t,
+            kid,
+            payload,
+            signature,
+            protected,
+        })
+    }
+
+    /// Check the signature on the payload and `external_aad` with the verifier of the signer.
+    /// Returns false if the key id names another signer.
+    pub fn verify(
+        &self,
+        external_aad: &[u8],
+        verifier: &Verifier,
+    ) -> Result<bool, ForwardSecureSignatureError> {
+        if let Some(kid) = &self.kid {
+            if kid[..] != verifier.verkey().fingerprint().as_bytes()[..] {
+                return Ok(false);
+            }
+        }
+        let msg = sig_structure(&self.protected, external_aad, &self.payload);
+        verifier.verify(&msg, self.t, &self.signature)
+    }
+}
+
+/// Time period of the protected header
+fn parse_protected(protected: &[u8]) -> Result<u128, ForwardSecureSignatureError> {
+    let mut d = Decoder::new(protected);
+    let mut alg = None;
+    let mut crit = None;
+    let mut t = None;
+    let mut labels = BTreeSet::new();
+    for _ in 0..d.expect(MAJOR_MAP)? {
+        let label = d.int()?;
+        if !labels.insert(label) {
+            return Err(invalid(&format!("duplicate header parameter {}", label)));
+        }
+        match label {
+            LABEL_ALG => alg = Some(d.int()?),
+            LABEL_CRIT => {
+                let mut labels = vec![];
+                for _ in 0..d.expect(MAJOR_ARRAY)? {
+                    labels.push(d.int()?);
+                }
+                crit = Some(labels);
+            }
+            TIME_PERIOD_LABEL => t = Some(d.uint()?),
+            _ => d.skip(MAX_DEPTH)?,
+        }
+    }
+    d.finish()?;
+    if alg != Some(COSE_ALGORITHM) {
+        return Err(invalid("missing or unsupported algorithm"));
+    }
+    if crit != Some(vec![TIME_PERIOD_LABEL]) {
+        return Err(invalid(
+            "critical header parameters must be exactly the time period",
+        ));
+    }
+    t.ok_or_else(|| invalid("missing time period"))
+}
+
+/// `["Signature1", protected, external_aad, payload]`
+fn sig_structure(protected: &[u8], external_aad: &[u8], payload: &[u8]) -> Vec<u8> {
+    let mut e = Encoder::default();
+    e.head(MAJOR_ARRAY, 4);
+    e.text_string(SIGNATURE1_CONTEXT);
+    e.byte_string(protected);
+    e.byte_string(external_aad);
+    e.byte_string(payload);
+    e.bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::InMemorySigKeyDatabase;
+    use crate::util::GeneratorSet;
+
+    #[test]
+    fn test_cose_sign1() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let (mut signer, _) =
+            Signer::new(T, gens.clone(), InMemorySigKeyDatabase::new(), &mut rng).unwrap();
+        let (other, _) = Signer::new(T, gens, InMemorySigKeyDatabase::new(), &mut rng).unwrap();
+        let verifier = signer.verifier();
+        signer.update_to(3, &mut rng).unwrap();
+
+        let msg = CoseSign1::sign(b"reading".to_vec(), b"aad", &mut signer, &mut rng).unwrap();
+        let bytes = msg.to_bytes();
+        assert_eq!(bytes[..2], [0xd2, 0x84]);
+        let decoded = CoseSign1::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.t, 3);
+        assert_eq!(decoded.payload, b"reading");
+        assert!(decoded.verify(b"aad", &verifier).unwrap());
+        assert!(!decoded.verify(b"", &verifier).unwrap());
+        assert!(!decoded.verify(b"aad", &other.verifier()).unwrap());
+        assert_eq!(decoded.to_bytes(), bytes);
+
+        // The unprotected key id is optional, payload and time period are signed
+        let mut changed = decoded.clone();
+        changed.kid = None;
+        let changed = CoseSign1::from_bytes(&changed.to_bytes()).unwrap();
+        assert!(changed.verify(b"aad", &verifier).unwrap());
+        let mut changed = decoded.clone();
+        changed.payload = b"tampered".to_vec();
+        assert!(!changed.verify(b"aad", &verifier).unwrap());
+        let mut changed = decoded;
+        changed.t = 4;
+        assert!(!changed.verify(b"aad", &verifier).unwrap());
+
+        // Protected headers with another algorithm, unknown critical parameters or no time
+        // period are refused
+        let protected = |alg: i64, crit: &[i64], t: Option<u128>| {
+            let mut e = Encoder::default();
+            e.head(MAJOR_MAP, 2 + t.is_some() as u64);
+            e.int(LABEL_ALG);
+            e.int(alg);
+            e.int(LABEL_CRIT);
+            e.head(MAJOR_ARRAY, crit.len() as u64);
+            for label in crit {
+                e.int(*label);
+            }
+            if let Some(t) = t {
+                e.int(TIME_PERIOD_LABEL);
+                e.uint(t);
+            }
+            e.bytes
+        };
+        assert_eq!(
+            protected(COSE_ALGORITHM, &[TIME_PERIOD_LABEL], Some(3)),
+            msg.protected
+        );
+        assert!(parse_protected(&protected(-7, &[TIME_PERIOD_LABEL], Some(3))).is_err());
+        assert!(parse_protected(&protected(COSE_ALGORITHM, &[], Some(3))).is_err());
+        assert!(
+            parse_protected(&protected(COSE_ALGORITHM, &[TIME_PERIOD_LABEL, 5], Some(3))).is_err()
+        );
+        assert!(parse_protected(&protected(COSE_ALGORITHM, &[TIME_PERIOD_LABEL], None)).is_err());
+
+        let mut untagged = bytes.clone();
+        untagged[0] = 0xd1;
+        assert!(CoseSign1::from_bytes(&untagged).is_err());
+        assert!(CoseSign1::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,335 @@
+//! DER (X.690) encodings of signatures and verkeys, for embedding them in X.509 like structures,
+//! and the object identifiers of this scheme. No arc is registered for Pixel so the identifiers
+//! are under the UUID arc `2.25.147920191015210016418502832412408665596` (ITU-T X.667, UUID
+//! `6f48647c-8e3a-4830-b5e4-3b84e073d5fc`), which needs no registration:
+//!
+//! | arc | identifies                                     |
+//! |-----|------------------------------------------------|
+//! | .1  | keys of the scheme with the verkey in G2       |
+//! | .2  | keys of the scheme with the verkey in G1       |
+//! | .3  | signatures of the scheme with the verkey in G2 |
+//! | .4  | signatures of the scheme with the verkey in G1 |
+//!
+//! ```text
+//! PixelSignature ::= SEQUENCE { sigma1 OCTET STRING, sigma2 OCTET STRING }
+//! PixelSignatureAlgorithm ::= SEQUENCE { algorithm OBJECT IDENTIFIER, t INTEGER }
+//! ```
+//!
+//! A signature is a `PixelSignature` with the points compressed as in `compression`, e.g. in the
+//! `signatureValue` bit string of a certificate, and its `signatureAlgorithm` is a
+//! `PixelSignatureAlgorithm` carrying the time period. A verkey is a `SubjectPublicKeyInfo`, see
+//! `pem`. Decoding only accepts definite lengths in the shortest form and minimal integers.
+
+use crate::compression::{SIGNATURE_GROUP_COMPRESSED_SIZE, VERKEY_GROUP_COMPRESSED_SIZE};
+use crate::errors::ForwardSecureSignatureError;
+use crate::keys::Verkey;
+use crate::signature::Signature;
+
+pub const PIXEL_OID_ARC: [u128; 3] = [2, 25, 147_920_191_015_210_016_418_502_832_412_408_665_596];
+
+/// Identifier of the key algorithm, with the verkey group of the enabled feature
+#[cfg(feature = "VerkeyG2")]
+pub const KEY_ALGORITHM_OID: [u128; 4] = [PIXEL_OID_ARC[0], PIXEL_OID_ARC[1], PIXEL_OID_ARC[2], 1];
+#[cfg(feature = "VerkeyG1")]
+pub const KEY_ALGORITHM_OID: [u128; 4] = [PIXEL_OID_ARC[0], PIXEL_OID_ARC[1], PIXEL_OID_ARC[2], 2];
+
+/// Identifier of the signature algorithm, with the verkey group of the enabled feature
+#[cfg(feature = "VerkeyG2")]
+pub const SIGNATURE_ALGORITHM_OID: [u128; 4] =
+    [PIXEL_OID_ARC[0], PIXEL_OID_ARC[1], PIXEL_OID_ARC[2], 3];
+#[cfg(feature = "VerkeyG1")]
+pub const SIGNATURE_ALGORITHM_OID: [u128; 4] =
+    [PIXEL_OID_ARC[0], PIXEL_OID_ARC[1], PIXEL_OID_ARC[2], 4];
+
+pub(crate) const TAG_INTEGER: u8 = 0x02;
+pub(crate) const TAG_BIT_STRING: u8 = 0x03;
+pub(crate) const TAG_OCTET_STRING: u8 = 0x04;
+pub(crate) const TAG_OID: u8 = 0x06;
+pub(crate) const TAG_SEQUENCE: u8 = 0x30;
+
+pub(crate) fn invalid(msg: &str) -> ForwardSecureSignatureError {
+    ForwardSecureSignatureError::InvalidDer {
+        msg: msg.to_string(),
+    }
+}
+
+/// Content bytes of an object identifier with at least 2 arcs
+pub(crate) fn encode_oid(arcs: &[u128]) -> Vec<u8> {
+    let mut bytes = vec![];
+    let first = arcs[0] * 40 + arcs[1];
+    for arc in std::iter::once(first).chain(arcs[2..].iter().copied()) {
+        let mut digits = vec![(arc & 0x7f) as u8];
+        let mut rest = arc >> 7;
+        while rest > 0 {
+            digits.push((rest & 0x7f) as u8 | 0x80);
+            rest >>= 7;
+        }
+        bytes.extend(digits.iter().rev());
+    }
+    bytes
+}
+
+/// Tag, length and content of an element
+pub(crate) fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![tag];
+    let len = content.len();
+    if len < 0x80 {
+        bytes.push(len as u8);
+    } else {
+        let len_bytes = len.to_be_bytes();
+        let start = len_bytes.iter().position(|b| *b != 0).unwrap_or(0);
+        bytes.push(0x80 | (len_bytes.len() - start) as u8);
+        bytes.extend_from_slice(&len_bytes[start..]);
+    }
+    bytes.extend_from_slice(content);
+    bytes
+}
+
+/// Content bytes of a non-negative integer
+fn encode_uint(value: u128) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    let start = bytes.iter().position(|b| *b != 0).unwrap_or(15);
+    let mut content = vec![];
+    if bytes[start] & 0x80 != 0 {
+        content.push(0);
+    }
+    content.extend_from_slice(&bytes[start..]);
+    content
+}
+
+fn decode_uint(content: &[u8]) -> Result<u128, ForwardSecureSignatureError> {
+    match content {
+        [] => return Err(invalid("empty integer")),
+        [b, ..] if b & 0x80 != 0 => return Err(invalid("negative integer")),
+        [0, b, ..] if b & 0x80 == 0 => return Err(invalid("integer not in the shortest form")),
+        _ => {}
+    }
+    let content = if content[0] == 0 {
+        &content[1..]
+    } else {
+        content
+    };
+    if content.len() > 16 {
+        return Err(invalid("integer too large"));
+    }
+    Ok(content.iter().fold(0, |value, b| value << 8 | *b as u128))
+}
+
+/// `PixelSignatureAlgorithm` for a signature of time period `t`
+pub fn signature_algorithm_identifier(t: u128) -> Vec<u8> {
+    let mut content = tlv(TAG_OID, &encode_oid(&SIGNATURE_ALGORITHM_OID));
+    content.extend_from_slice(&tlv(TAG_INTEGER, &encode_uint(t)));
+    tlv(TAG_SEQUENCE, &content)
+}
+
+/// Time period of a `PixelSignatureAlgorithm`. Fails for other algorithms.
+pub fn parse_signature_algorithm_identifier(
+    der: &[u8],
+) -> Result<u128, ForwardSecureSignatureError> {
+    let mut outer = DerReader::new(der);
+    let mut alg = DerReader::new(outer.read(TAG_SEQUENCE)?);
+    outer.finish()?;
+    if alg.read(TAG_OID)? != &encode_oid(&SIGNATURE_ALGORITHM_OID)[..] {
+        return Err(invalid("unsupported algorithm"));
+    }
+    let t = decode_uint(alg.read(TAG_INTEGER)?)?;
+    alg.finish()?;
+    Ok(t)
+}
+
+impl Signature {
+    /// `PixelSignature` encoding
+    pub fn to_der(&self) -> Vec<u8> {
+        let bytes = self.to_bytes();
+        let mut content = tlv(TAG_OCTET_STRING, &bytes[..SIGNATURE_GROUP_COMPRESSED_SIZE]);
+        content.extend_from_slice(&tlv(
+            TAG_OCTET_STRING,
+            &bytes[SIGNATURE_GROUP_COMPRESSED_SIZE..],
+        ));
+        tlv(TAG_SEQUENCE, &content)
+    }
+
+    /// Decode a `PixelSignature` with the checks of `from_bytes`
+    pub fn from_der(der: &[u8]) -> Result<Self, ForwardSecureSignatureError> {
+        let mut outer = DerReader::new(der);
+        let mut sig = DerReader::new(outer.read(TAG_SEQUENCE)?);
+        outer.finish()?;
+        let sigma_1 = sig.read(TAG_OCTET_STRING)?;
+        let sigma_2 = sig.read(TAG_OCTET_STRING)?;
+        sig.finish()?;
+        if sigma_1.len() != SIGNATURE_GROUP_COMPRESSED_SIZE
+            || sigma_2.len() != VERKEY_GROUP_COMPRESSED_SIZE
+        {
+            return Err(invalid("points of the wrong size"));
+        }
+        Self::from_bytes(&[sigma_1, sigma_2].concat())
+    }
+}
+
+impl Verkey {
+    /// Same as `to_public_key_der`
+    pub fn to_der(&self) -> Vec<u8> {
+        self.to_public_key_der()
+    }
+
+    pub fn from_der(der: &[u8]) -> Result<Self, ForwardSecureSignatureError> {
+        Self::from_public_key_der(der)
+    }
+}
+
+/// `SEQUENCE { OBJECT IDENTIFIER }` of the key algorithm, without parameters
+pub(crate) fn key_algorithm_identifier() -> Vec<u8> {
+    tlv(TAG_SEQUENCE, &tlv(TAG_OID, &encode_oid(&KEY_ALGORITHM_OID)))
+}
+
+pub(crate) struct DerReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> DerReader<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], ForwardSecureSignatureError> {
+        if self.bytes.len() < n {
+            return Err(invalid("unexpected end of input"));
+        }
+        let (taken, rest) = self.bytes.split_at(n);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    /// Content of the next element, which must have tag `tag`
+    pub(crate) fn read(&mut self, tag: u8) -> Result<&'a [u8], ForwardSecureSignatureError> {
+        let t = self.take(1)?[0];
+        if t != tag {
+            return Err(invalid(&format!("tag {:#04x} instead of {:#04x}", t, tag)));
+        }
+        let first = self.take(1)?[0] as usize;
+        let len = if first < 0x80 {
+            first
+        } else {
+            let n = first & 0x7f;
+            if n == 0 || n > std::mem::size_of::<usize>() {
+                return Err(invalid("indefinite or too large length"));
+            }
+            let len_bytes = self.take(n)?;
+            if len_bytes[0] == 0 {
+                return Err(invalid("length not in the shortest form"));
+            }
+            let len = len_bytes.iter().fold(0, |len, b| len << 8 | *b as usize);
+            if len < 0x80 {
+                return Err(invalid("length not in the shortest form"));
+            }
+            len
+        };
+        self.take(len)
+    }
+
+    /// Read the algorithm identifier of `key_algorithm_identifier`
+    pub(crate) fn read_key_algorithm(&mut self) -> Result<(), ForwardSecureSignatureError> {
+        let mut alg = DerReader::new(self.read(TAG_SEQUENCE)?);
+        if alg.read(TAG_OID)? != &encode_oid(&KEY_ALGORITHM_OID)[..] {
+            return Err(invalid("unsupported algorithm"));
+        }
+        alg.finish()
+    }
+
+    pub(crate) fn finish(&self) -> Result<(), ForwardSecureSignatureError> {
+        if !self.bytes.is_empty() {
+            return Err(invalid("trailing bytes"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::facade::Signer;
+    use crate::keys::InMemorySigKeyDatabase;
+    use crate::util::GeneratorSet;
+
+    #[test]
+    fn test_der() {
+        // Examples of X.690
+        assert_eq!(
+            encode_oid(&[1, 2, 840, 113549]),
+            vec![0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d]
+        );
+        assert_eq!(encode_oid(&[2, 999, 3]), vec![0x88, 0x37, 0x03]);
+        let oid = encode_oid(&PIXEL_OID_ARC);
+        assert_eq!(oid[0], 0x69);
+        assert_eq!(oid.len(), 20);
+
+        for len in &[0, 1, 0x7f, 0x80, 0xff, 0x100, 0x10000] {
+            let content = vec![7; *len];
+            let bytes = tlv(TAG_OCTET_STRING, &content);
+            let mut reader = DerReader::new(&bytes);
+            assert_eq!(reader.read(TAG_OCTET_STRING).unwrap(), &content[..]);
+            reader.finish().unwrap();
+            assert!(DerReader::new(&bytes).read(TAG_BIT_STRING).is_err());
+            assert!(DerReader::new(&bytes[..bytes.len() - 1])
+                .read(TAG_OCTET_STRING)
+                .is_err());
+        }
+        // Lengths not in the shortest form, indefinite
+        for bytes in &[
+            vec![0x04, 0x81, 0x01, 0x00],
+            vec![0x04, 0x82, 0x00, 0x80],
+            vec![0x04, 0x80, 0x00, 0x00],
+        ] {
+            assert!(DerReader::new(bytes).read(TAG_OCTET_STRING).is_err());
+        }
+
+        let alg = key_algorithm_identifier();
+        let mut reader = DerReader::new(&alg);
+        reader.read_key_algorithm().unwrap();
+        reader.finish().unwrap();
+        let other = tlv(
+            TAG_SEQUENCE,
+            &tlv(TAG_OID, &encode_oid(&[1, 2, 840, 113549])),
+        );
+        assert!(DerReader::new(&other).read_key_algorithm().is_err());
+
+        for (t, content) in &[
+            (0u128, vec![0]),
+            (127, vec![0x7f]),
+            (128, vec![0, 0x80]),
+            (256, vec![1, 0]),
+            (u128::MAX, [&[0][..], &[0xff; 16][..]].concat()),
+        ] {
+            assert_eq!(&encode_uint(*t), content);
+            assert_eq!(decode_uint(content).unwrap(), *t);
+            let alg = signature_algorithm_identifier(*t);
+            assert_eq!(parse_signature_algorithm_identifier(&alg).unwrap(), *t);
+        }
+        for content in &[vec![], vec![0x80], vec![0, 0x7f], vec![1; 17]] {
+            assert!(decode_uint(content).is_err());
+        }
+        assert!(parse_signature_algorithm_identifier(&key_algorithm_identifier()).is_err());
+
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let (mut signer, _) =
+            Signer::new(T, gens, InMemorySigKeyDatabase::new(), &mut rng).unwrap();
+        let sig = signer.sign("Hello".as_bytes(), &mut rng).unwrap();
+        let der = sig.to_der();
+        assert_eq!(Signature::from_der(&der).unwrap(), sig);
+        let vk = signer.verkey();
+        assert_eq!(Verkey::from_der(&vk.to_der()).unwrap().value, vk.value);
+        assert!(Signature::from_der(&der[..der.len() - 1]).is_err());
+        let bytes = sig.to_bytes();
+        let swapped = tlv(
+            TAG_SEQUENCE,
+            &[
+                tlv(TAG_OCTET_STRING, &bytes[SIGNATURE_GROUP_COMPRESSED_SIZE..]),
+                tlv(TAG_OCTET_STRING, &bytes[..SIGNATURE_GROUP_COMPRESSED_SIZE]),
+            ]
+            .concat(),
+        );
+        assert!(Signature::from_der(&swapped).is_err());
+    }
+}
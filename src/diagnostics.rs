@@ -0,0 +1,182 @@
+//! Diagnostic verification. Performs the same checks as `Signature::verify` but records the time
+//! spent in and the number of each expensive operation, so the cost of verification in a
+//! deployment can be broken down without a profiler.
+//!
+//! Path factors depend only on the time period, so the verifier caches them. Verifying many
+//! signatures of the same time period shows up as cache hits.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::errors::ForwardSecureSignatureError;
+use crate::keys::Verkey;
+use crate::signature::Signature;
+use crate::util::{calculate_path_factor_using_t_l, GeneratorSet};
+use crate::SignatureGroup;
+
+/// Time spent and operations done by one or more verifications
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VerificationDiagnostics {
+    /// Verifications done
+    pub verifications: u64,
+    /// Verifications that succeeded
+    pub valid: u64,
+    /// Messages hashed to a field element
+    pub hashes: u64,
+    pub hash_time: Duration,
+    /// Identity and subgroup membership checks of the signature and verkey, one per verification
+    pub subgroup_checks: u64,
+    pub subgroup_check_time: Duration,
+    /// Path factors computed, i.e. cache misses
+    pub path_factors: u64,
+    pub path_factor_time: Duration,
+    /// Path factors taken from the cache
+    pub cache_hits: u64,
+    /// Pairings, each verification does a single multi-pairing of 3 pairings
+    pub pairings: u64,
+    pub pairing_time: Duration,
+}
+
+impl VerificationDiagnostics {
+    /// Total time of the recorded operations
+    pub fn total_time(&self) -> Duration {
+        self.hash_time + self.subgroup_check_time + self.path_factor_time + self.pairing_time
+    }
+}
+
+/// Verifies signatures and records `VerificationDiagnostics` of all verifications done
+#[derive(Clone, Debug, Default)]
+pub struct DiagnosticVerifier {
+    path_factors: HashMap<(u128, u8), SignatureGroup>,
+    diagnostics: VerificationDiagnostics,
+}
+
+impl DiagnosticVerifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Verify like `Signature::verify`, adding the cost of the verification to the diagnostics
+    pub fn verify(
+        &mut self,
+        sig: &Signature,
+        msg: &[u8],
+        t: u128,
+        l: u8,
+        gens: &GeneratorSet,
+        verkey: &Verkey,
+    ) -> Result<bool, ForwardSecureSignatureError> {
+        if gens.1.len() < (l as usize + 2) {
+            return Err(ForwardSecureSignatureError::NotEnoughGenerators { n: l as usize + 2 });
+        }
+        self.diagnostics.verifications += 1;
+
+        let start = Instant::now();
+        let well_formed = !sig.is_identity() && !verkey.is_identity() && sig.has_correct_oder();
+        self.diagnostics.subgroup_check_time += start.elapsed();
+        self.diagnostics.subgroup_checks += 1;
+        if !well_formed {
+            return Ok(false);
+        }
+
+        let start = Instant::now();
+        let m = Signature::hash_message(msg);
+        self.diagnostics.hash_time += start.elapsed();
+        self.diagnostics.hashes += 1;
+
+        let pf = match self.path_factors.get(&(t, l)) {
+            Some(pf) => {
+                self.diagnostics.cache_hits += 1;
+                pf.clone()
+            }
+            None => {
+                let start = Instant::now();
+                let pf = calculate_path_factor_using_t_l(t, l, gens)?;
+                self.diagnostics.path_factor_time += start.elapsed();
+                self.diagnostics.path_factors += 1;
+                self.path_factors.insert((t, l), pf.clone());
+                pf
+            }
+        };
+
+        let start = Instant::now();
+        let valid = Signature::verify_naked_with_hashed_msg(
+            &sig.sigma_1,
+            &sig.sigma_2,
+            &verkey.value,
+            &m,
+            &pf,
+            l,
+            gens,
+        );
+        self.diagnostics.pairing_time += start.elapsed();
+        self.diagnostics.pairings += 3;
+        if valid {
+            self.diagnostics.valid += 1;
+        }
+        Ok(valid)
+    }
+
+    pub fn diagnostics(&self) -> &VerificationDiagnostics {
+        &self.diagnostics
+    }
+
+    /// Clear the recorded diagnostics, keeping the cached path factors
+    pub fn reset_diagnostics(&mut self) -> VerificationDiagnostics {
+        std::mem::take(&mut self.diagnostics)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{InMemorySigKeyDatabase, Keypair};
+    use crate::util::calculate_l;
+    use amcl_wrapper::group_elem::GroupElement;
+
+    #[test]
+    fn test_diagnostic_verification() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let l = calculate_l(T).unwrap();
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let mut db = InMemorySigKeyDatabase::new();
+        let (keypair, manager) = Keypair::new(T, &gens, &mut rng, &mut db).unwrap();
+        let sk = manager.get_current_key(&db).unwrap();
+
+        let mut verifier = DiagnosticVerifier::new();
+        for msg in ["a".as_bytes(), "b".as_bytes()].iter() {
+            let sig = Signature::new(msg, 1, l, &gens, sk, &mut rng).unwrap();
+            assert!(verifier
+                .verify(&sig, msg, 1, l, &gens, &keypair.ver_key)
+                .unwrap());
+            assert!(!verifier
+                .verify(&sig, "c".as_bytes(), 1, l, &gens, &keypair.ver_key)
+                .unwrap());
+        }
+        let diagnostics = verifier.diagnostics().clone();
+        assert_eq!(diagnostics.verifications, 4);
+        assert_eq!(diagnostics.valid, 2);
+        assert_eq!(diagnostics.hashes, 4);
+        assert_eq!(diagnostics.subgroup_checks, 4);
+        assert_eq!(diagnostics.path_factors, 1);
+        assert_eq!(diagnostics.cache_hits, 3);
+        assert_eq!(diagnostics.pairings, 12);
+        assert!(diagnostics.total_time() >= diagnostics.pairing_time);
+
+        // Malformed signatures stop after the subgroup checks
+        assert_eq!(verifier.reset_diagnostics(), diagnostics);
+        let sig = Signature {
+            sigma_1: SignatureGroup::identity(),
+            sigma_2: crate::VerkeyGroup::identity(),
+        };
+        assert!(!verifier
+            .verify(&sig, "a".as_bytes(), 1, l, &gens, &keypair.ver_key)
+            .unwrap());
+        let diagnostics = verifier.diagnostics();
+        assert_eq!(diagnostics.verifications, 1);
+        assert_eq!(diagnostics.subgroup_checks, 1);
+        assert_eq!(diagnostics.hashes, 0);
+        assert_eq!(diagnostics.pairings, 0);
+    }
+}
@@ -0,0 +1,153 @@
+//! Deterministic random bit generator for reproducible runs. Every function of this crate that
+//! needs randomness takes it from the `rng` argument, so passing a `SeededDrbg` makes key
+//! generation, key updates and signing bit-exact for a given seed, e.g. for test vectors shared
+//! with other implementations or to reproduce an incident.
+//!
+//! Block `i` of the output is `SHA3-256(DST || seed || i)` with `i` as 8 byte big endian and
+//! blocks are consumed in order. Anyone knowing the seed can recompute every secret derived from
+//! the output so the seed must be treated as a secret key and never reused in production.
+
+use rand::{CryptoRng, Error, RngCore, SeedableRng};
+use sha3::{Digest, Sha3_256};
+
+const DRBG_DST: &[u8] = b"PIXEL-DRBG-SHA3-256-V1";
+
+pub struct SeededDrbg {
+    seed: [u8; 32],
+    counter: u64,
+    block: [u8; 32],
+    /// Number of bytes of `block` already consumed
+    used: usize,
+}
+
+impl SeededDrbg {
+    /// Seed with arbitrary bytes, such as a test name. The bytes are hashed into the seed.
+    pub fn from_seed_bytes(seed: &[u8]) -> Self {
+        let mut s = [0; 32];
+        s.copy_from_slice(&Sha3_256::digest(seed));
+        Self::from_seed(s)
+    }
+
+    fn next_block(&mut self) {
+        let mut hasher = Sha3_256::new();
+        hasher.input(DRBG_DST);
+        hasher.input(self.seed);
+        hasher.input(self.counter.to_be_bytes());
+        self.block.copy_from_slice(&hasher.result());
+        self.counter = self.counter.checked_add(1).expect("DRBG output exhausted");
+        self.used = 0;
+    }
+}
+
+impl SeedableRng for SeededDrbg {
+    type Seed = [u8; 32];
+
+    fn from_seed(seed: Self::Seed) -> Self {
+        Self {
+            seed,
+            counter: 0,
+            block: [0; 32],
+            used: 32,
+        }
+    }
+}
+
+impl RngCore for SeededDrbg {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        let mut filled = 0;
+        while filled < dest.len() {
+            if self.used == self.block.len() {
+                self.next_block();
+            }
+            let n = std::cmp::min(dest.len() - filled, self.block.len() - self.used);
+            dest[filled..filled + n].copy_from_slice(&self.block[self.used..self.used + n]);
+            filled += n;
+            self.used += n;
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl CryptoRng for SeededDrbg {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{InMemorySigKeyDatabase, Keypair};
+    use crate::signature::Signature;
+    use crate::threshold_sig::trusted_party_SSS_keygen;
+    use crate::util::{calculate_l, GeneratorSet};
+
+    #[test]
+    fn test_drbg_output() {
+        let mut rng = SeededDrbg::from_seed([7; 32]);
+        let mut a = [0; 50];
+        rng.fill_bytes(&mut a);
+        // Same output regardless of how it is split into requests
+        let mut rng = SeededDrbg::from_seed([7; 32]);
+        let mut b = [0; 50];
+        rng.fill_bytes(&mut b[..3]);
+        rng.fill_bytes(&mut b[3..40]);
+        rng.fill_bytes(&mut b[40..]);
+        assert_eq!(a.to_vec(), b.to_vec());
+
+        let mut hasher = Sha3_256::new();
+        hasher.input(DRBG_DST);
+        hasher.input([7; 32]);
+        hasher.input(0u64.to_be_bytes());
+        assert_eq!(&a[..32], hasher.result().as_slice());
+
+        let mut rng = SeededDrbg::from_seed([8; 32]);
+        let mut c = [0; 50];
+        rng.fill_bytes(&mut c);
+        assert_ne!(a.to_vec(), c.to_vec());
+    }
+
+    #[test]
+    fn test_reproducible_keys_and_signatures() {
+        let T = 7;
+        let l = calculate_l(T).unwrap();
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let msg = "Hello".as_bytes();
+        let run = |seed: &[u8]| {
+            let mut rng = SeededDrbg::from_seed_bytes(seed);
+            let mut db = InMemorySigKeyDatabase::new();
+            let (keypair, mut manager) = Keypair::new(T, &gens, &mut rng, &mut db).unwrap();
+            manager
+                .fast_forward_update(3, &gens, &mut rng, &mut db)
+                .unwrap();
+            let sk = manager.get_current_key(&db).unwrap();
+            let sig = Signature::new(msg, 3, l, &gens, sk, &mut rng).unwrap();
+            let (secret, signers) =
+                trusted_party_SSS_keygen(2, 3, &mut rng, &gens.0, &gens.1).unwrap();
+            (keypair.ver_key, sig, secret, signers[2].verkey.clone())
+        };
+        let (vk_1, sig_1, secret_1, signer_vk_1) = run(b"seed");
+        let (vk_2, sig_2, secret_2, signer_vk_2) = run(b"seed");
+        assert_eq!(vk_1.to_bytes(), vk_2.to_bytes());
+        assert_eq!(sig_1, sig_2);
+        assert_eq!(secret_1, secret_2);
+        assert_eq!(signer_vk_1.to_bytes(), signer_vk_2.to_bytes());
+        assert!(sig_1.verify(msg, 3, l, &gens, &vk_1).unwrap());
+
+        let (vk_3, sig_3, _, _) = run(b"other seed");
+        assert_ne!(vk_1.to_bytes(), vk_3.to_bytes());
+        assert_ne!(sig_1, sig_3);
+    }
+}
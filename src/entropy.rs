@@ -0,0 +1,257 @@
+//! Pluggable sources of randomness. Every function of this crate that needs randomness takes an
+//! `R: RngCore + CryptoRng`, so deployments that must use an approved source wrap it in an
+//! `EntropyRng` and pass that to key generation, key updates and signing.
+//!
+//! Provided sources are the operating system's generator, an HMAC_DRBG seeded from another
+//! source and a wrapper for hardware or HSM generators given as a closure.
+
+use rand::rngs::OsRng;
+use rand::{CryptoRng, Error, RngCore};
+use sha3::{Digest, Sha3_256};
+
+/// A source of random bytes which can fail, e.g. when a hardware generator is unavailable
+pub trait EntropySource {
+    fn fill_entropy(&mut self, dest: &mut [u8]) -> Result<(), Error>;
+}
+
+/// The operating system's generator
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OsEntropy;
+
+impl EntropySource for OsEntropy {
+    fn fill_entropy(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        OsRng.try_fill_bytes(dest)
+    }
+}
+
+/// A hardware or HSM generator reached through `F`, which fills the buffer or returns an error
+pub struct ExternalEntropy<F: FnMut(&mut [u8]) -> Result<(), Error>>(pub F);
+
+impl<F: FnMut(&mut [u8]) -> Result<(), Error>> EntropySource for ExternalEntropy<F> {
+    fn fill_entropy(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        (self.0)(dest)
+    }
+}
+
+/// Bytes of entropy taken from the source on instantiation and on every reseed
+const DRBG_SEED_SIZE: usize = 32;
+/// Bytes of nonce taken from the source on instantiation
+const DRBG_NONCE_SIZE: usize = 16;
+/// Maximum bytes produced by a single generate call, larger requests are split
+const DRBG_MAX_REQUEST: usize = 1 << 16;
+/// Default number of generate calls between reseeds
+pub const DRBG_RESEED_INTERVAL: u64 = 1 << 20;
+/// SHA3-256 block size for HMAC
+const HMAC_BLOCK_SIZE: usize = 136;
+
+fn hmac(key: &[u8; 32], parts: &[&[u8]]) -> [u8; 32] {
+    let mut padded = [0u8; HMAC_BLOCK_SIZE];
+    padded[..32].copy_from_slice(key);
+    let mut inner = Sha3_256::new();
+    inner.input(padded.iter().map(|b| b ^ 0x36).collect::<Vec<u8>>());
+    for p in parts {
+        inner.input(p);
+    }
+    let mut outer = Sha3_256::new();
+    outer.input(padded.iter().map(|b| b ^ 0x5c).collect::<Vec<u8>>());
+    outer.input(inner.result());
+    let mut out = [0; 32];
+    out.copy_from_slice(&outer.result());
+    out
+}
+
+/// HMAC_DRBG of NIST SP 800-90A with HMAC-SHA3-256, seeded and periodically reseeded from the
+/// source `E`. Deployments needing a validated module should plug the module in as an
+/// `EntropySource` rather than rely on this implementation.
+pub struct HmacDrbg<E: EntropySource> {
+    source: E,
+    key: [u8; 32],
+    v: [u8; 32],
+    reseed_counter: u64,
+    reseed_interval: u64,
+}
+
+impl<E: EntropySource> HmacDrbg<E> {
+    /// Instantiate with entropy and a nonce from `source`. `personalization` distinguishes
+    /// instances, e.g. by the name of the service.
+    pub fn new(mut source: E, personalization: &[u8]) -> Result<Self, Error> {
+        let mut seed = [0; DRBG_SEED_SIZE + DRBG_NONCE_SIZE];
+        source.fill_entropy(&mut seed)?;
+        let mut drbg = Self {
+            source,
+            key: [0; 32],
+            v: [1; 32],
+            reseed_counter: 1,
+            reseed_interval: DRBG_RESEED_INTERVAL,
+        };
+        drbg.update(&[&seed, personalization]);
+        Ok(drbg)
+    }
+
+    pub fn with_reseed_interval(mut self, reseed_interval: u64) -> Self {
+        self.reseed_interval = reseed_interval;
+        self
+    }
+
+    /// Mix fresh entropy from the source and `additional_input` into the state
+    pub fn reseed(&mut self, additional_input: &[u8]) -> Result<(), Error> {
+        let mut entropy = [0; DRBG_SEED_SIZE];
+        self.source.fill_entropy(&mut entropy)?;
+        self.update(&[&entropy, additional_input]);
+        self.reseed_counter = 1;
+        Ok(())
+    }
+
+    fn update(&mut self, provided: &[&[u8]]) {
+        let empty = provided.iter().all(|p| p.is_empty());
+        for round in 0..if empty { 1 } else { 2 } {
+            let round = [round as u8];
+            let mut parts: Vec<&[u8]> = vec![&self.v, &round];
+            parts.extend_from_slice(provided);
+            self.key = hmac(&self.key, &parts);
+            self.v = hmac(&self.key, &[&self.v]);
+        }
+    }
+
+    fn generate(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        for chunk in dest.chunks_mut(DRBG_MAX_REQUEST) {
+            if self.reseed_counter > self.reseed_interval {
+                self.reseed(&[])?;
+            }
+            for block in chunk.chunks_mut(32) {
+                self.v = hmac(&self.key, &[&self.v]);
+                block.copy_from_slice(&self.v[..block.len()]);
+            }
+            self.update(&[]);
+            self.reseed_counter += 1;
+        }
+        Ok(())
+    }
+}
+
+impl<E: EntropySource> EntropySource for HmacDrbg<E> {
+    fn fill_entropy(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.generate(dest)
+    }
+}
+
+/// Adapts an `EntropySource` to the `RngCore` taken by this crate. The infallible methods panic
+/// if the source fails, like `OsRng` does. `try_fill_bytes` returns the error, key generation,
+/// key updates and signing use it and fail with `RngFailure`.
+pub struct EntropyRng<E: EntropySource>(pub E);
+
+impl<E: EntropySource> RngCore for EntropyRng<E> {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        if let Err(e) = self.try_fill_bytes(dest) {
+            panic!("entropy source failed: {}", e);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.0.fill_entropy(dest)
+    }
+}
+
+/// Sources are expected to be cryptographically secure
+impl<E: EntropySource> CryptoRng for EntropyRng<E> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{InMemorySigKeyDatabase, Keypair};
+    use crate::signature::Signature;
+    use crate::util::{calculate_l, GeneratorSet};
+
+    fn fixed_source(byte: u8) -> ExternalEntropy<impl FnMut(&mut [u8]) -> Result<(), Error>> {
+        ExternalEntropy(move |dest: &mut [u8]| {
+            for b in dest.iter_mut() {
+                *b = byte;
+            }
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_hmac_drbg() {
+        let output = |byte, personalization: &[u8], interval| {
+            let mut drbg = HmacDrbg::new(fixed_source(byte), personalization)
+                .unwrap()
+                .with_reseed_interval(interval);
+            let mut out = [0; 100];
+            drbg.fill_entropy(&mut out[..40]).unwrap();
+            drbg.fill_entropy(&mut out[40..]).unwrap();
+            out.to_vec()
+        };
+        let a = output(1, b"a", 10);
+        assert_eq!(a, output(1, b"a", 10));
+        assert_ne!(a, output(2, b"a", 10));
+        assert_ne!(a, output(1, b"b", 10));
+        // Reseeding before the second request changes its output only
+        let reseeded = output(1, b"a", 1);
+        assert_eq!(a[..40], reseeded[..40]);
+        assert_ne!(a[40..], reseeded[40..]);
+
+        let mut drbg = HmacDrbg::new(fixed_source(1), b"a").unwrap();
+        let mut large = vec![0; DRBG_MAX_REQUEST + 10];
+        drbg.fill_entropy(&mut large).unwrap();
+        assert_eq!(drbg.reseed_counter, 3);
+    }
+
+    #[test]
+    fn test_failing_source() {
+        let failing = || ExternalEntropy(|_: &mut [u8]| Err(Error::new("device unavailable")));
+        assert!(HmacDrbg::new(failing(), b"").is_err());
+        let mut rng = EntropyRng(failing());
+        assert!(rng.try_fill_bytes(&mut [0; 8]).is_err());
+
+        // Reseeding fails once the source fails
+        let mut calls = 0;
+        let source = ExternalEntropy(move |dest: &mut [u8]| {
+            calls += 1;
+            if calls > 1 {
+                return Err(Error::new("device unavailable"));
+            }
+            dest.iter_mut().for_each(|b| *b = 1);
+            Ok(())
+        });
+        let mut drbg = HmacDrbg::new(source, b"").unwrap().with_reseed_interval(1);
+        assert!(drbg.fill_entropy(&mut [0; 8]).is_ok());
+        assert!(drbg.fill_entropy(&mut [0; 8]).is_err());
+    }
+
+    #[test]
+    fn test_signing_with_entropy_sources() {
+        let T = 7;
+        let l = calculate_l(T).unwrap();
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let msg = "Hello".as_bytes();
+        let mut os = EntropyRng(OsEntropy);
+        let mut drbg = EntropyRng(HmacDrbg::new(OsEntropy, b"test").unwrap());
+
+        let mut db = InMemorySigKeyDatabase::new();
+        let (keypair, mut manager) = Keypair::new(T, &gens, &mut drbg, &mut db).unwrap();
+        manager.simple_update(&gens, &mut drbg, &mut db).unwrap();
+        let sig = Signature::new(
+            msg,
+            2,
+            l,
+            &gens,
+            manager.get_current_key(&db).unwrap(),
+            &mut os,
+        )
+        .unwrap();
+        assert!(sig.verify(msg, 2, l, &gens, &keypair.ver_key).unwrap());
+    }
+}
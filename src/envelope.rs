@@ -0,0 +1,561 @@
+//! Envelope carrying a signed message along with the time period, the fingerprint of the
+//! signer's verkey and optionally the wall clock time of signing and the identifier of the
+//! committee the signer signs for, so that none of them has to be transported separately. The
+//! signature covers the hash of the message and all other fields.
+//!
+//! The message is either attached to the envelope or referenced by its hash only, e.g. when
+//! signing large documents or streams stored elsewhere. Since only the hash is signed, an
+//! envelope can be converted between both forms without signing again.
+//!
+//! Others can endorse a signed envelope by countersigning it. Each countersignature signs the
+//! envelope and all countersignatures before it so the order of endorsement is fixed and no
+//! countersignature can be removed or reordered without invalidating the later ones. A
+//! countersignature must not be for an earlier time period than the one before it and nobody can
+//! sign the same envelope twice.
+
+use rand::{CryptoRng, RngCore};
+use sha3::{Digest, Sha3_256};
+
+use crate::epoch::EpochConfig;
+use crate::errors::ForwardSecureSignatureError;
+use crate::facade::{Signer, Verifier};
+use crate::keys::{SigKeyDb, VerkeyFingerprint};
+use crate::registry::CommitteeId;
+use crate::signature::Signature;
+
+const ENVELOPE_DST: &[u8] = b"PIXEL-ENVELOPE-V1";
+const COUNTERSIGNATURE_DST: &[u8] = b"PIXEL-COUNTERSIGNATURE-V1";
+
+/// SHA3-256 hash of a message
+pub type PayloadDigest = [u8; 32];
+
+pub fn payload_digest(payload: &[u8]) -> PayloadDigest {
+    let mut digest = [0; 32];
+    digest.copy_from_slice(&Sha3_256::digest(payload));
+    digest
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum EnvelopeContent {
+    Attached(Vec<u8>),
+    Detached(PayloadDigest),
+}
+
+impl EnvelopeContent {
+    pub fn digest(&self) -> PayloadDigest {
+        match self {
+            EnvelopeContent::Attached(payload) => payload_digest(payload),
+            EnvelopeContent::Detached(digest) => *digest,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SignedEnvelope {
+    pub content: EnvelopeContent,
+    /// Time period of the signing key
+    pub t: u128,
+    pub signer: VerkeyFingerprint,
+    /// Wall clock time of signing in seconds, as claimed by the signer
+    pub timestamp: Option<u64>,
+    /// Committee on whose behalf the signer signed, e.g. as a share of a multisignature
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub committee: Option<CommitteeId>,
+    pub signature: Signature,
+}
+
+impl SignedEnvelope {
+    /// Sign `payload` with the signer's key for its current time period. The payload is
+    /// attached to the envelope.
+    pub fn create<D: SigKeyDb, R: RngCore + CryptoRng>(
+        payload: Vec<u8>,
+        timestamp: Option<u64>,
+        signer: &mut Signer<D>,
+        rng: &mut R,
+    ) -> Result<Self, ForwardSecureSignatureError> {
+        Self::sign_content(
+            EnvelopeContent::Attached(payload),
+            timestamp,
+            None,
+            signer,
+            rng,
+        )
+    }
+
+    /// Same as `create` but also signs the identifier of the committee the signer signs for
+    pub fn create_for_committee<D: SigKeyDb, R: RngCore + CryptoRng>(
+        payload: Vec<u8>,
+        timestamp: Option<u64>,
+        committee: CommitteeId,
+        signer: &mut Signer<D>,
+        rng: &mut R,
+    ) -> Result<Self, ForwardSecureSignatureError> {
+        Self::sign_content(
+            EnvelopeContent::Attached(payload),
+            timestamp,
+            Some(committee),
+            signer,
+            rng,
+        )
+    }
+
+    /// Sign a message given only its digest, e.g. computed while streaming it. The envelope is
+    /// detached.
+    pub fn create_detached<D: SigKeyDb, R: RngCore + CryptoRng>(
+        digest: PayloadDigest,
+        timestamp: Option<u64>,
+        signer: &mut Signer<D>,
+        rng: &mut R,
+    ) -> Result<Self, ForwardSecureSignatureError> {
+        Self::sign_content(
+            EnvelopeContent::Detached(digest),
+            timestamp,
+            None,
+            signer,
+            rng,
+        )
+    }
+
+    fn sign_content<D: SigKeyDb, R: RngCore + CryptoRng>(
+        content: EnvelopeContent,
+        timestamp: Option<u64>,
+        committee: Option<CommitteeId>,
+        signer: &mut Signer<D>,
+        rng: &mut R,
+    ) -> Result<Self, ForwardSecureSignatureError> {
+        let t = signer.t();
+        let fingerprint = signer.verkey().fingerprint();
+        let msg = statement(
+            &content.digest(),
+            t,
+            &fingerprint,
+            timestamp,
+            committee.as_ref(),
+        );
+        let signature = signer.sign(&msg, rng)?;
+        Ok(Self {
+            content,
+            t,
+            signer: fingerprint,
+            timestamp,
+            committee,
+            signature,
+        })
+    }
+
+    /// The attached payload, if any
+    pub fn payload(&self) -> Option<&[u8]> {
+        match &self.content {
+            EnvelopeContent::Attached(payload) => Some(payload),
+            EnvelopeContent::Detached(_) => None,
+        }
+    }
+
+    pub fn is_detached(&self) -> bool {
+        matches!(self.content, EnvelopeContent::Detached(_))
+    }
+
+    /// Same envelope with the payload replaced by its digest
+    pub fn detach(&self) -> Self {
+        Self {
+            content: EnvelopeContent::Detached(self.content.digest()),
+            ..self.clone()
+        }
+    }
+
+    /// Same envelope with `payload` attached. Fails if `payload` is not the signed message.
+    pub fn attach(&self, payload: Vec<u8>) -> Result<Self, ForwardSecureSignatureError> {
+        if payload_digest(&payload) != self.content.digest() {
+            return Err(ForwardSecureSignatureError::PayloadMismatch);
+        }
+        Ok(Self {
+            content: EnvelopeContent::Attached(payload),
+            ..self.clone()
+        })
+    }
+
+    /// Check the signature with the verifier of the signer. Returns false if the envelope names
+    /// another signer. For a detached envelope this only shows that the digest was signed, use
+    /// `verify_payload` to check the message as well.
+    pub fn verify(&self, verifier: &Verifier) -> Result<bool, ForwardSecureSignatureError> {
+        if verifier.verkey().fingerprint() != self.signer {
+            return Ok(false);
+        }
+        let msg = statement(
+            &self.content.digest(),
+            self.t,
+            &self.signer,
+            self.timestamp,
+            self.committee.as_ref(),
+        );
+        verifier.verify(&msg, self.t, &self.signature)
+    }
+
+    /// Check that `payload` is the signed message and the signature
+    pub fn verify_payload(
+        &self,
+        payload: &[u8],
+        verifier: &Verifier,
+    ) -> Result<bool, ForwardSecureSignatureError> {
+        if payload_digest(payload) != self.content.digest() {
+            return Ok(false);
+        }
+        self.verify(verifier)
+    }
+
+    /// Whether the timestamp, if any, falls in the envelope's time period
+    pub fn matches_epochs(&self, epochs: &EpochConfig) -> bool {
+        match self.timestamp {
+            Some(time) => epochs.period_at(time).is_ok_and(|t| t == self.t),
+            None => true,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Countersignature {
+    pub signer: VerkeyFingerprint,
+    pub t: u128,
+    pub signature: Signature,
+}
+
+/// Signed envelope with countersignatures in the order they were made
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CountersignedEnvelope {
+    pub envelope: SignedEnvelope,
+    pub countersignatures: Vec<Countersignature>,
+}
+
+impl CountersignedEnvelope {
+    pub fn new(envelope: SignedEnvelope) -> Self {
+        Self {
+            envelope,
+            countersignatures: vec![],
+        }
+    }
+
+    /// Fingerprints of the signer of the envelope followed by the countersigners, in order
+    pub fn signers(&self) -> Vec<VerkeyFingerprint> {
+        let mut signers = vec![self.envelope.signer];
+        signers.extend(self.countersignatures.iter().map(|c| c.signer));
+        signers
+    }
+
+    /// Countersign the envelope and all existing countersignatures with the signer's key for its
+    /// current time period. Fails if the signer already signed or its time period is before the
+    /// one of the last signature.
+    pub fn countersign<D: SigKeyDb, R: RngCore + CryptoRng>(
+        &mut self,
+        signer: &mut Signer<D>,
+        rng: &mut R,
+    ) -> Result<(), ForwardSecureSignatureError> {
+        let position = self.countersignatures.len() + 1;
+        let fingerprint = signer.verkey().fingerprint();
+        self.check_layer(position, &fingerprint, signer.t())?;
+        let msg = self.countersignature_statement(position, &fingerprint, signer.t());
+        let signature = signer.sign(&msg, rng)?;
+        self.countersignatures.push(Countersignature {
+            signer: fingerprint,
+            t: signer.t(),
+            signature,
+        });
+        Ok(())
+    }
+
+    /// Verify the envelope and every countersignature. `verifiers` holds one verifier for each
+    /// signature, in the order of `signers`. Fails with the position of the first invalid
+    /// signature, 0 being the envelope's.
+    pub fn verify(&self, verifiers: &[&Verifier]) -> Result<(), ForwardSecureSignatureError> {
+        if verifiers.len() != self.countersignatures.len() + 1 {
+            return Err(ForwardSecureSignatureError::InvalidCountersignature {
+                position: 0,
+                msg: format!(
+                    "{} verifiers for {} signatures",
+                    verifiers.len(),
+                    self.countersignatures.len() + 1
+                ),
+            });
+        }
+        if !self.envelope.verify(verifiers[0])? {
+            return Err(ForwardSecureSignatureError::InvalidCountersignature {
+                position: 0,
+                msg: String::from("signature does not verify"),
+            });
+        }
+        for (i, (c, verifier)) in self
+            .countersignatures
+            .iter()
+            .zip(verifiers[1..].iter())
+            .enumerate()
+        {
+            let position = i + 1;
+            let layers = Self {
+                envelope: self.envelope.clone(),
+                countersignatures: self.countersignatures[..i].to_vec(),
+            };
+            layers.check_layer(position, &c.signer, c.t)?;
+            let msg = layers.countersignature_statement(position, &c.signer, c.t);
+            if verifier.verkey().fingerprint() != c.signer
+                || !verifier.verify(&msg, c.t, &c.signature)?
+            {
+                return Err(ForwardSecureSignatureError::InvalidCountersignature {
+                    position,
+                    msg: String::from("signature does not verify"),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn check_layer(
+        &self,
+        position: usize,
+        signer: &VerkeyFingerprint,
+        t: u128,
+    ) -> Result<(), ForwardSecureSignatureError> {
+        if self.signers().contains(signer) {
+            return Err(ForwardSecureSignatureError::InvalidCountersignature {
+                position,
+                msg: format!("{} already signed", signer),
+            });
+        }
+        let last_t = self
+            .countersignatures
+            .last()
+            .map_or(self.envelope.t, |c| c.t);
+        if t < last_t {
+            return Err(ForwardSecureSignatureError::InvalidCountersignature {
+                position,
+                msg: format!(
+                    "time period {} before {} of the previous signature",
+                    t, last_t
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    /// Message signed by the countersignature at `position` over the envelope and all
+    /// countersignatures in `self`
+    fn countersignature_statement(
+        &self,
+        position: usize,
+        signer: &VerkeyFingerprint,
+        t: u128,
+    ) -> Vec<u8> {
+        let env = &self.envelope;
+        let mut hasher = Sha3_256::new();
+        hasher.input(statement(
+            &env.content.digest(),
+            env.t,
+            &env.signer,
+            env.timestamp,
+            env.committee.as_ref(),
+        ));
+        hasher.input(env.signature.to_bytes());
+        for c in &self.countersignatures {
+            hasher.input(c.signer.as_bytes());
+            hasher.input(c.t.to_be_bytes());
+            hasher.input(c.signature.to_bytes());
+        }
+        let mut bytes = COUNTERSIGNATURE_DST.to_vec();
+        bytes.extend_from_slice(&hasher.result());
+        bytes.extend_from_slice(&(position as u64).to_be_bytes());
+        bytes.extend_from_slice(signer.as_bytes());
+        bytes.extend_from_slice(&t.to_be_bytes());
+        bytes
+    }
+}
+
+fn statement(
+    digest: &PayloadDigest,
+    t: u128,
+    signer: &VerkeyFingerprint,
+    timestamp: Option<u64>,
+    committee: Option<&CommitteeId>,
+) -> Vec<u8> {
+    let mut bytes = ENVELOPE_DST.to_vec();
+    bytes.extend_from_slice(digest);
+    bytes.extend_from_slice(&t.to_be_bytes());
+    bytes.extend_from_slice(signer.as_bytes());
+    match timestamp {
+        Some(time) => {
+            bytes.push(1);
+            bytes.extend_from_slice(&time.to_be_bytes());
+        }
+        None => bytes.push(0),
+    }
+    // Appended only if present so envelopes signed without a committee stay valid
+    if let Some(id) = committee {
+        bytes.extend_from_slice(id.as_bytes());
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::InMemorySigKeyDatabase;
+    use crate::util::{GeneratorSet, GeneratorSetRef};
+    use std::time::Duration;
+
+    #[test]
+    fn test_signed_envelope() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let gens = GeneratorSetRef::new(GeneratorSet::new(T, "test_pixel").unwrap());
+        let (mut signer, _) =
+            Signer::new(T, gens.clone(), InMemorySigKeyDatabase::new(), &mut rng).unwrap();
+        let (other, _) =
+            Signer::new(T, gens.clone(), InMemorySigKeyDatabase::new(), &mut rng).unwrap();
+        let verifier = signer.verifier();
+        let epochs = EpochConfig::new(1000, Duration::from_secs(60), T).unwrap();
+
+        signer.update_to(3, &mut rng).unwrap();
+        let env =
+            SignedEnvelope::create(b"Hello".to_vec(), Some(1150), &mut signer, &mut rng).unwrap();
+        assert_eq!(env.t, 3);
+        assert!(env.verify(&verifier).unwrap());
+        assert!(!env.verify(&other.verifier()).unwrap());
+        assert!(env.matches_epochs(&epochs));
+
+        // Every field is covered by the signature
+        let mut bad = env.clone();
+        bad.content = EnvelopeContent::Attached(b"Hi".to_vec());
+        assert!(!bad.verify(&verifier).unwrap());
+        let mut bad = env.clone();
+        bad.t = 4;
+        assert!(!bad.verify(&verifier).unwrap());
+        let mut bad = env.clone();
+        bad.timestamp = Some(1210);
+        assert!(!bad.verify(&verifier).unwrap());
+        assert!(!bad.matches_epochs(&epochs));
+        let mut bad = env.clone();
+        bad.timestamp = None;
+        assert!(!bad.verify(&verifier).unwrap());
+
+        let env = SignedEnvelope::create(b"Hello".to_vec(), None, &mut signer, &mut rng).unwrap();
+        assert!(env.verify(&verifier).unwrap());
+        assert!(env.matches_epochs(&epochs));
+
+        let committee = CommitteeId([7; 32]);
+        let env = SignedEnvelope::create_for_committee(
+            b"Hello".to_vec(),
+            None,
+            committee,
+            &mut signer,
+            &mut rng,
+        )
+        .unwrap();
+        assert_eq!(env.committee, Some(committee));
+        assert!(env.verify(&verifier).unwrap());
+        let mut bad = env.clone();
+        bad.committee = Some(CommitteeId([8; 32]));
+        assert!(!bad.verify(&verifier).unwrap());
+        let mut bad = env.clone();
+        bad.committee = None;
+        assert!(!bad.verify(&verifier).unwrap());
+    }
+
+    #[test]
+    fn test_detached_envelope() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let gens = GeneratorSetRef::new(GeneratorSet::new(T, "test_pixel").unwrap());
+        let (mut signer, _) =
+            Signer::new(T, gens.clone(), InMemorySigKeyDatabase::new(), &mut rng).unwrap();
+        let verifier = signer.verifier();
+        let doc = vec![5; 1000];
+
+        let attached = SignedEnvelope::create(doc.clone(), None, &mut signer, &mut rng).unwrap();
+        assert!(!attached.is_detached());
+        assert_eq!(attached.payload(), Some(doc.as_slice()));
+        let detached = attached.detach();
+        assert!(detached.is_detached());
+        assert_eq!(detached.payload(), None);
+        assert_eq!(
+            detached.content,
+            EnvelopeContent::Detached(payload_digest(&doc))
+        );
+        assert!(detached.verify(&verifier).unwrap());
+        assert!(detached.verify_payload(&doc, &verifier).unwrap());
+        assert!(!detached.verify_payload(&doc[1..], &verifier).unwrap());
+
+        assert!(detached.attach(doc[1..].to_vec()).is_err());
+        let reattached = detached.attach(doc.clone()).unwrap();
+        assert!(reattached.verify(&verifier).unwrap());
+        assert_eq!(reattached.content, attached.content);
+
+        // Signed from the digest only, verifies once the payload is attached
+        let streamed =
+            SignedEnvelope::create_detached(payload_digest(&doc), Some(5), &mut signer, &mut rng)
+                .unwrap();
+        assert!(streamed.verify_payload(&doc, &verifier).unwrap());
+        assert!(streamed.attach(doc).unwrap().verify(&verifier).unwrap());
+    }
+
+    #[test]
+    fn test_countersigning() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let gens = GeneratorSetRef::new(GeneratorSet::new(T, "test_pixel").unwrap());
+        let mut signers = vec![];
+        for _ in 0..3 {
+            let (signer, _) =
+                Signer::new(T, gens.clone(), InMemorySigKeyDatabase::new(), &mut rng).unwrap();
+            signers.push(signer);
+        }
+        let verifiers = signers.iter().map(|s| s.verifier()).collect::<Vec<_>>();
+        let v = verifiers.iter().collect::<Vec<_>>();
+
+        let env =
+            SignedEnvelope::create(b"Contract".to_vec(), None, &mut signers[0], &mut rng).unwrap();
+        let mut chain = CountersignedEnvelope::new(env);
+        chain.verify(&v[..1]).unwrap();
+        signers[1].update_to(2, &mut rng).unwrap();
+        chain.countersign(&mut signers[1], &mut rng).unwrap();
+        // The author can't countersign and signers can't go back in time
+        assert!(chain.countersign(&mut signers[0], &mut rng).is_err());
+        assert!(chain.countersign(&mut signers[1], &mut rng).is_err());
+        assert!(chain.countersign(&mut signers[2], &mut rng).is_err());
+        signers[2].update_to(2, &mut rng).unwrap();
+        chain.countersign(&mut signers[2], &mut rng).unwrap();
+        assert_eq!(
+            chain.signers(),
+            verifiers
+                .iter()
+                .map(|v| v.verkey().fingerprint())
+                .collect::<Vec<_>>()
+        );
+        chain.verify(&v).unwrap();
+        assert!(chain.verify(&v[..2]).is_err());
+        assert!(chain.verify(&[v[0], v[2], v[1]]).is_err());
+
+        // Reordering or dropping endorsements invalidates the chain
+        let mut swapped = chain.clone();
+        swapped.countersignatures.swap(0, 1);
+        assert!(swapped.verify(&[v[0], v[2], v[1]]).is_err());
+        let mut dropped = chain.clone();
+        dropped.countersignatures.remove(0);
+        assert!(dropped.verify(&[v[0], v[2]]).is_err());
+        // Countersignatures are bound to the envelope
+        let other =
+            SignedEnvelope::create(b"Other".to_vec(), None, &mut signers[0], &mut rng).unwrap();
+        let mut moved = chain.clone();
+        moved.envelope = other;
+        match moved.verify(&v) {
+            Err(ForwardSecureSignatureError::InvalidCountersignature { position, .. }) => {
+                assert_eq!(position, 1)
+            }
+            _ => panic!("countersignature verified on another envelope"),
+        }
+        // Detaching the payload keeps the countersignatures valid
+        let mut detached = chain.clone();
+        detached.envelope = detached.envelope.detach();
+        detached.verify(&v).unwrap();
+    }
+}
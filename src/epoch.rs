@@ -0,0 +1,86 @@
+use std::convert::TryFrom;
+use std::time::Duration;
+
+use crate::errors::ForwardSecureSignatureError;
+use crate::util::calculate_l;
+
+/// Maps wall clock time to time periods. Time period 1 begins at `genesis_time` and every
+/// period lasts `period_duration` seconds. Times are Unix time in seconds.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct EpochConfig {
+    pub genesis_time: u64,
+    pub period_duration: u64,
+    /// Maximum time period
+    pub T: u128,
+}
+
+impl EpochConfig {
+    pub fn new(
+        genesis_time: u64,
+        period_duration: Duration,
+        T: u128,
+    ) -> Result<Self, ForwardSecureSignatureError> {
+        calculate_l(T)?;
+        let period_duration = period_duration.as_secs();
+        if period_duration == 0 {
+            return Err(ForwardSecureSignatureError::ZeroPeriodInterval);
+        }
+        Ok(Self {
+            genesis_time,
+            period_duration,
+            T,
+        })
+    }
+
+    /// Time period in which `wall_time` falls. Fails if `wall_time` is before genesis or after
+    /// the last time period.
+    pub fn period_at(&self, wall_time: u64) -> Result<u128, ForwardSecureSignatureError> {
+        if wall_time < self.genesis_time {
+            return Err(ForwardSecureSignatureError::BeforeGenesis { time: wall_time });
+        }
+        let t = ((wall_time - self.genesis_time) / self.period_duration) as u128 + 1;
+        if t > self.T {
+            return Err(ForwardSecureSignatureError::KeyExpired { t, T: self.T });
+        }
+        Ok(t)
+    }
+
+    /// Unix time at which time period `t` begins
+    pub fn period_start(&self, t: u128) -> Result<u64, ForwardSecureSignatureError> {
+        if t == 0 || t > self.T {
+            return Err(ForwardSecureSignatureError::KeyExpired { t, T: self.T });
+        }
+        u64::try_from(t - 1)
+            .ok()
+            .and_then(|n| n.checked_mul(self.period_duration))
+            .and_then(|d| d.checked_add(self.genesis_time))
+            .ok_or(ForwardSecureSignatureError::KeyExpired { t, T: self.T })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_epoch_config() {
+        assert!(EpochConfig::new(1000, Duration::from_secs(0), 7).is_err());
+        assert!(EpochConfig::new(1000, Duration::from_secs(60), 8).is_err());
+
+        let epochs = EpochConfig::new(1000, Duration::from_secs(60), 7).unwrap();
+        assert!(epochs.period_at(999).is_err());
+        assert_eq!(epochs.period_at(1000).unwrap(), 1);
+        assert_eq!(epochs.period_at(1059).unwrap(), 1);
+        assert_eq!(epochs.period_at(1060).unwrap(), 2);
+        assert_eq!(epochs.period_at(1000 + 7 * 60 - 1).unwrap(), 7);
+        assert!(epochs.period_at(1000 + 7 * 60).is_err());
+
+        for t in 1..=7 {
+            let start = epochs.period_start(t).unwrap();
+            assert_eq!(epochs.period_at(start).unwrap(), t);
+        }
+        assert!(epochs.period_start(0).is_err());
+        assert!(epochs.period_start(8).is_err());
+    }
+}
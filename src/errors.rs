@@ -1,3 +1,5 @@
+use amcl_wrapper::errors::SerzDeserzError;
+
 #[derive(Debug, Fail)]
 pub enum ForwardSecureSignatureError {
     #[fail(
@@ -23,4 +25,158 @@ pub enum ForwardSecureSignatureError {
     SigkeyUpdateBackward { old_t: u128, current_t: u128 },
     #[fail(display = "Sigkey alrady updated to desired time={}", t)]
     SigkeyAlreadyUpdated { t: u128 },
+    #[fail(display = "Invalid byte length {} for generator set", len)]
+    InvalidGeneratorSetBytes { len: usize },
+    #[fail(display = "Serialization error: {}", msg)]
+    SerzDeserzError { msg: String },
+    #[fail(display = "IO error: {}", msg)]
+    IoError { msg: String },
+    #[fail(display = "Signing interval must be non-zero")]
+    ZeroPeriodInterval,
+    #[fail(display = "Committee member with index {} already registered", index)]
+    DuplicateCommitteeMember { index: usize },
+    #[fail(display = "No committee member with index {}", index)]
+    UnknownCommitteeMember { index: usize },
+    #[fail(
+        display = "No committee member with verkey fingerprint {}",
+        fingerprint
+    )]
+    UnknownVerkeyFingerprint { fingerprint: String },
+    #[fail(
+        display = "Proof of possession of committee member {} not verified",
+        index
+    )]
+    PopNotVerified { index: usize },
+    #[fail(display = "Inconsistent public params: {}", msg)]
+    InconsistentPublicParams { msg: String },
+    #[fail(
+        display = "Quorum {} invalid for committee of size {}",
+        quorum, committee
+    )]
+    InvalidQuorum { quorum: usize, committee: usize },
+    #[fail(display = "Share of signer {} already received", index)]
+    DuplicateShare { index: usize },
+    #[fail(display = "Share of signer {} does not verify", index)]
+    InvalidShare { index: usize },
+    #[fail(display = "Received {} shares but quorum is {}", received, quorum)]
+    QuorumNotReached { received: usize, quorum: usize },
+    #[fail(display = "Invalid wire bytes: {}", msg)]
+    InvalidWireBytes { msg: String },
+    #[fail(display = "Time {} is before genesis", time)]
+    BeforeGenesis { time: u64 },
+    #[fail(display = "Time period {} is past the maximum time period {}", t, T)]
+    KeyExpired { t: u128, T: u128 },
+    #[fail(display = "Invalid key backup: {}", msg)]
+    InvalidBackup { msg: String },
+    #[fail(
+        display = "Signature with recovered key for time period {} does not verify",
+        t
+    )]
+    RecoveryVerificationFailed { t: u128 },
+    #[fail(display = "Proof of possession of member {} does not verify", index)]
+    InvalidPop { index: usize },
+    #[fail(display = "All {} committee members already contributed", size)]
+    CeremonyComplete { size: usize },
+    #[fail(display = "Reshare of dealer {} is invalid", dealer)]
+    InvalidReshare { dealer: usize },
+    #[fail(display = "Key for time period {} is punctured on the message", t)]
+    MessagePunctured { t: u128 },
+    #[fail(display = "Invalid signer bitmap: {}", msg)]
+    InvalidSignerBitmap { msg: String },
+    #[fail(display = "Invalid validity window from {} until {}", from, until)]
+    InvalidValidityWindow { from: u128, until: u128 },
+    #[fail(display = "Certificate at position {} is invalid: {}", position, msg)]
+    InvalidCertificate { position: usize, msg: String },
+    #[fail(
+        display = "Signing limit of {} signatures reached in time period {}",
+        limit, t
+    )]
+    SigningLimitExceeded { limit: u64, t: u128 },
+    #[fail(display = "Invalid revocation statement: {}", msg)]
+    InvalidRevocation { msg: String },
+    #[fail(
+        display = "Verkey {} is revoked as of time period {}",
+        fingerprint, as_of
+    )]
+    VerkeyRevoked { fingerprint: String, as_of: u128 },
+    #[fail(display = "Batch of messages is empty")]
+    EmptyBatch,
+    #[fail(display = "Payload does not match the signed digest")]
+    PayloadMismatch,
+    #[fail(
+        display = "Countersignature at position {} is invalid: {}",
+        position, msg
+    )]
+    InvalidCountersignature { position: usize, msg: String },
+    #[fail(display = "Invalid transition message of member {}: {}", member, msg)]
+    InvalidTransitionMessage { member: usize, msg: String },
+    #[fail(display = "Random number generator failed: {}", msg)]
+    RngFailure { msg: String },
+    #[fail(display = "Stored signing key for time period {} is corrupted", t)]
+    SigkeyCorrupted { t: u128 },
+    #[fail(
+        display = "Wrapped signing key for time period {} can't be unwrapped",
+        t
+    )]
+    KeyUnwrapFailed { t: u128 },
+    #[fail(
+        display = "Wire format version {} is not supported, supported versions are {:?}",
+        version, supported
+    )]
+    UnsupportedWireVersion { version: u8, supported: Vec<u8> },
+    #[fail(display = "Invalid period cache: {}", msg)]
+    InvalidPeriodCache { msg: String },
+    #[fail(
+        display = "Signature at position {} of the batch for time period {} does not verify",
+        position, t
+    )]
+    BatchVerificationFailed { position: usize, t: u128 },
+    #[fail(display = "Key transcript is invalid at update {}: {}", position, msg)]
+    InvalidKeyTranscript { position: usize, msg: String },
+    #[fail(display = "Invalid bech32m string: {}", msg)]
+    InvalidBech32 { msg: String },
+    #[fail(display = "Invalid fragment: {}", msg)]
+    InvalidFragment { msg: String },
+    #[fail(display = "Invalid status beacon: {}", msg)]
+    InvalidBeacon { msg: String },
+    #[fail(display = "Invalid update journal: {}", msg)]
+    InvalidUpdateJournal { msg: String },
+    #[fail(display = "Network mismatch: {}", msg)]
+    NetworkMismatch { msg: String },
+    #[fail(display = "Invalid point encoding: {}", msg)]
+    InvalidPoint { msg: String },
+    #[fail(display = "Invalid hex or Base64 string: {}", msg)]
+    InvalidTextEncoding { msg: String },
+    #[fail(display = "Invalid CBOR encoding: {}", msg)]
+    InvalidCbor { msg: String },
+    #[fail(display = "Invalid protobuf message: {}", msg)]
+    InvalidProtoMessage { msg: String },
+    #[fail(display = "Invalid JWS: {}", msg)]
+    InvalidJws { msg: String },
+    #[fail(display = "Invalid COSE structure: {}", msg)]
+    InvalidCose { msg: String },
+    #[fail(display = "Invalid DER encoding: {}", msg)]
+    InvalidDer { msg: String },
+    #[fail(display = "Invalid PEM: {}", msg)]
+    InvalidPem { msg: String },
+    #[fail(display = "Invalid SSZ encoding: {}", msg)]
+    InvalidSsz { msg: String },
+    #[fail(display = "Invalid multibase encoding: {}", msg)]
+    InvalidMultibase { msg: String },
+    #[fail(display = "Invalid generator cache: {}", msg)]
+    InvalidGeneratorCache { msg: String },
+}
+
+impl From<SerzDeserzError> for ForwardSecureSignatureError {
+    fn from(e: SerzDeserzError) -> Self {
+        ForwardSecureSignatureError::SerzDeserzError {
+            msg: format!("{:?}", e),
+        }
+    }
+}
+
+impl From<std::io::Error> for ForwardSecureSignatureError {
+    fn from(e: std::io::Error) -> Self {
+        ForwardSecureSignatureError::IoError { msg: e.to_string() }
+    }
 }
@@ -0,0 +1,286 @@
+//! Encodings of EIP-2537, the BLS12-381 precompiles of the EVM, so that verkeys, signatures and
+//! pairing check inputs can be passed to verification contracts as they are.
+//!
+//! A base field element is 64 bytes, big endian with 16 leading zero bytes. A point in G1 is
+//! `x || y` (128 bytes) and a point in G2 is `x_c0 || x_c1 || y_c0 || y_c1` (256 bytes). The point
+//! at infinity is all zeros. Decoding rejects non canonical field elements, points not on the
+//! curve and points outside the prime order subgroup.
+
+use amcl_wrapper::constants::MODBYTES;
+use amcl_wrapper::group_elem::GroupElement;
+use amcl_wrapper::group_elem_g1::G1;
+use amcl_wrapper::group_elem_g2::G2;
+use amcl_wrapper::types::GroupG1;
+
+use crate::errors::ForwardSecureSignatureError;
+use crate::keys::Verkey;
+use crate::signature::Signature;
+use crate::util::{calculate_path_factor_using_t_l, GeneratorSet};
+use crate::{SignatureGroup, VerkeyGroup};
+
+/// Size of an encoded base field element
+pub const EVM_FP_SIZE: usize = 64;
+pub const EVM_G1_SIZE: usize = 2 * EVM_FP_SIZE;
+pub const EVM_G2_SIZE: usize = 4 * EVM_FP_SIZE;
+/// Size of one pair of the input of the pairing check precompile
+pub const EVM_PAIR_SIZE: usize = EVM_G1_SIZE + EVM_G2_SIZE;
+
+const PADDING: usize = EVM_FP_SIZE - MODBYTES;
+
+pub fn g1_to_evm_bytes(p: &G1) -> [u8; EVM_G1_SIZE] {
+    let mut out = [0; EVM_G1_SIZE];
+    if !p.is_identity() {
+        // Uncompressed encoding of amcl is 0x04 || x || y
+        let bytes = p.to_bytes();
+        pad_coordinates(&bytes[1..], &mut out);
+    }
+    out
+}
+
+pub fn g1_from_evm_bytes(bytes: &[u8]) -> Result<G1, ForwardSecureSignatureError> {
+    if bytes.len() != EVM_G1_SIZE {
+        return Err(invalid_evm_bytes("invalid size of G1 point"));
+    }
+    let mut uncompressed = vec![0x04];
+    uncompressed.append(&mut unpad_coordinates(bytes)?);
+    if uncompressed[1..].iter().all(|b| *b == 0) {
+        return Ok(G1::identity());
+    }
+    let p: G1 = GroupG1::frombytes(&uncompressed).into();
+    check_point(&p, bytes, &g1_to_evm_bytes(&p))?;
+    Ok(p)
+}
+
+pub fn g2_to_evm_bytes(p: &G2) -> [u8; EVM_G2_SIZE] {
+    let mut out = [0; EVM_G2_SIZE];
+    if !p.is_identity() {
+        // amcl encodes G2 as x_c0 || x_c1 || y_c0 || y_c1
+        pad_coordinates(&p.to_bytes(), &mut out);
+    }
+    out
+}
+
+pub fn g2_from_evm_bytes(bytes: &[u8]) -> Result<G2, ForwardSecureSignatureError> {
+    if bytes.len() != EVM_G2_SIZE {
+        return Err(invalid_evm_bytes("invalid size of G2 point"));
+    }
+    let unpadded = unpad_coordinates(bytes)?;
+    if unpadded.iter().all(|b| *b == 0) {
+        return Ok(G2::identity());
+    }
+    let p = G2::from_bytes(&unpadded)?;
+    check_point(&p, bytes, &g2_to_evm_bytes(&p))?;
+    Ok(p)
+}
+
+pub fn verkey_to_evm_bytes(verkey: &Verkey) -> Vec<u8> {
+    verkey_group_to_evm_bytes(&verkey.value)
+}
+
+/// `sigma_1` followed by `sigma_2`
+pub fn signature_to_evm_bytes(sig: &Signature) -> Vec<u8> {
+    let mut bytes = signature_group_to_evm_bytes(&sig.sigma_1);
+    bytes.append(&mut verkey_group_to_evm_bytes(&sig.sigma_2));
+    bytes
+}
+
+/// Input of the pairing check precompile that succeeds if and only if `sig` is valid on `msg` for
+/// time period `t`, i.e. the 3 pairs of `e(sigma_1, -g2) * e(h, y) * e(sigma_1_1, sigma_2) == 1`
+/// with `sigma_1_1 = path factor of t + h_{l+1}^m`. Computing `sigma_1_1` requires the
+/// generators so it is done off chain.
+pub fn pairing_check_input(
+    msg: &[u8],
+    t: u128,
+    l: u8,
+    gens: &GeneratorSet,
+    sig: &Signature,
+    verkey: &Verkey,
+) -> Result<Vec<u8>, ForwardSecureSignatureError> {
+    if gens.1.len() < (l as usize + 2) {
+        return Err(ForwardSecureSignatureError::NotEnoughGenerators { n: l as usize + 2 });
+    }
+    let mut sigma_1_1 = calculate_path_factor_using_t_l(t, l, gens)?;
+    sigma_1_1 += &gens.1[l as usize + 1] * Signature::hash_message(msg);
+    let mut bytes = Vec::with_capacity(3 * EVM_PAIR_SIZE);
+    for (s, v) in [
+        (&sig.sigma_1, &gens.0.negation()),
+        (&gens.1[0], &verkey.value),
+        (&sigma_1_1, &sig.sigma_2),
+    ] {
+        bytes.append(&mut pair_to_evm_bytes(s, v));
+    }
+    Ok(bytes)
+}
+
+#[cfg(feature = "VerkeyG2")]
+fn verkey_group_to_evm_bytes(p: &VerkeyGroup) -> Vec<u8> {
+    g2_to_evm_bytes(p).to_vec()
+}
+
+#[cfg(feature = "VerkeyG1")]
+fn verkey_group_to_evm_bytes(p: &VerkeyGroup) -> Vec<u8> {
+    g1_to_evm_bytes(p).to_vec()
+}
+
+#[cfg(feature = "VerkeyG2")]
+fn signature_group_to_evm_bytes(p: &SignatureGroup) -> Vec<u8> {
+    g1_to_evm_bytes(p).to_vec()
+}
+
+#[cfg(feature = "VerkeyG1")]
+fn signature_group_to_evm_bytes(p: &SignatureGroup) -> Vec<u8> {
+    g2_to_evm_bytes(p).to_vec()
+}
+
+// The precompile takes the point in G1 first
+#[cfg(feature = "VerkeyG2")]
+fn pair_to_evm_bytes(s: &SignatureGroup, v: &VerkeyGroup) -> Vec<u8> {
+    let mut bytes = signature_group_to_evm_bytes(s);
+    bytes.append(&mut verkey_group_to_evm_bytes(v));
+    bytes
+}
+
+#[cfg(feature = "VerkeyG1")]
+fn pair_to_evm_bytes(s: &SignatureGroup, v: &VerkeyGroup) -> Vec<u8> {
+    let mut bytes = verkey_group_to_evm_bytes(v);
+    bytes.append(&mut signature_group_to_evm_bytes(s));
+    bytes
+}
+
+/// Copy every `MODBYTES` coordinate of `coordinates` into `out` as a padded field element
+fn pad_coordinates(coordinates: &[u8], out: &mut [u8]) {
+    for (c, o) in coordinates
+        .chunks(MODBYTES)
+        .zip(out.chunks_mut(EVM_FP_SIZE))
+    {
+        o[PADDING..].copy_from_slice(c);
+    }
+}
+
+fn unpad_coordinates(bytes: &[u8]) -> Result<Vec<u8>, ForwardSecureSignatureError> {
+    let mut coordinates = vec![];
+    for fp in bytes.chunks(EVM_FP_SIZE) {
+        if fp[..PADDING].iter().any(|b| *b != 0) {
+            return Err(invalid_evm_bytes("non zero padding of field element"));
+        }
+        coordinates.extend_from_slice(&fp[PADDING..]);
+    }
+    Ok(coordinates)
+}
+
+/// amcl reduces coordinates and maps points not on the curve to infinity so a point is valid if
+/// it encodes back to the input
+fn check_point<G: GroupElement>(
+    p: &G,
+    bytes: &[u8],
+    encoded: &[u8],
+) -> Result<(), ForwardSecureSignatureError> {
+    if p.is_identity() || encoded != bytes {
+        return Err(invalid_evm_bytes("not a point on the curve"));
+    }
+    if !p.has_correct_order() {
+        return Err(invalid_evm_bytes("point not in the prime order subgroup"));
+    }
+    Ok(())
+}
+
+fn invalid_evm_bytes(msg: &str) -> ForwardSecureSignatureError {
+    ForwardSecureSignatureError::InvalidWireBytes {
+        msg: String::from(msg),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{InMemorySigKeyDatabase, Keypair};
+    use crate::util::calculate_l;
+    use amcl_wrapper::extension_field_gt::GT;
+
+    fn from_hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_point_encodings() {
+        // Generator of G1 as given in EIP-2537
+        let g1 = g1_to_evm_bytes(&G1::generator());
+        let x = from_hex("17f1d3a73197d7942695638c4fa9ac0fc3688c4f9774b905a14e3a3f171bac586c55e83ff97a1aeffb3af00adb22c6bb");
+        let y = from_hex("08b3f481e3aaa0f1a09e30ed741d8ae4fcf5e095d5d00af600db18cb2c04b3edd03cc744a2888ae40caa232946c5e7e1");
+        assert_eq!(g1[..16].to_vec(), vec![0; 16]);
+        assert_eq!(g1[16..64].to_vec(), x);
+        assert_eq!(g1[80..].to_vec(), y);
+        // Coordinate c0 of x of the generator of G2
+        let g2 = g2_to_evm_bytes(&G2::generator());
+        let x_c0 = from_hex("024aa2b2f08f0a91260805272dc51051c6e47ad4fa403b02b4510b647ae3d1770bac0326a805bbefd48056c8c121bdb8");
+        assert_eq!(g2[16..64].to_vec(), x_c0);
+
+        for _ in 0..5 {
+            let p = G1::random();
+            assert_eq!(g1_from_evm_bytes(&g1_to_evm_bytes(&p)).unwrap(), p);
+            let q = G2::random();
+            assert_eq!(g2_from_evm_bytes(&g2_to_evm_bytes(&q)).unwrap(), q);
+        }
+        assert!(g1_from_evm_bytes(&[0; EVM_G1_SIZE]).unwrap().is_identity());
+        assert!(g2_from_evm_bytes(&[0; EVM_G2_SIZE]).unwrap().is_identity());
+        assert_eq!(
+            g1_to_evm_bytes(&G1::identity()).to_vec(),
+            vec![0; EVM_G1_SIZE]
+        );
+
+        let mut bad = g1.to_vec();
+        bad[0] = 1;
+        assert!(g1_from_evm_bytes(&bad).is_err());
+        let mut bad = g1.to_vec();
+        bad[EVM_G1_SIZE - 1] ^= 1;
+        assert!(g1_from_evm_bytes(&bad).is_err());
+        assert!(g1_from_evm_bytes(&g1[1..]).is_err());
+        let mut bad = g2.to_vec();
+        bad[EVM_G2_SIZE - 1] ^= 1;
+        assert!(g2_from_evm_bytes(&bad).is_err());
+    }
+
+    #[test]
+    fn test_pairing_check_input() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let l = calculate_l(T).unwrap();
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let mut db = InMemorySigKeyDatabase::new();
+        let (keypair, mut manager) = Keypair::new(T, &gens, &mut rng, &mut db).unwrap();
+        let msg = "Hello".as_bytes();
+        let sig = manager.sign(msg, &gens, &db, &mut rng).unwrap();
+
+        let vk_bytes = verkey_to_evm_bytes(&keypair.ver_key);
+        let sig_bytes = signature_to_evm_bytes(&sig);
+        assert_eq!(sig_bytes.len(), EVM_G1_SIZE + EVM_G2_SIZE);
+        #[cfg(feature = "VerkeyG2")]
+        assert_eq!(g2_from_evm_bytes(&vk_bytes).unwrap(), keypair.ver_key.value);
+        #[cfg(feature = "VerkeyG1")]
+        assert_eq!(g1_from_evm_bytes(&vk_bytes).unwrap(), keypair.ver_key.value);
+
+        // Evaluate the pairing check on the decoded input as the precompile would
+        let check = |input: &[u8]| {
+            let pairs = input
+                .chunks(EVM_PAIR_SIZE)
+                .map(|c| {
+                    (
+                        g1_from_evm_bytes(&c[..EVM_G1_SIZE]).unwrap(),
+                        g2_from_evm_bytes(&c[EVM_G1_SIZE..]).unwrap(),
+                    )
+                })
+                .collect::<Vec<_>>();
+            GT::ate_multi_pairing(pairs.iter().map(|(a, b)| (a, b)).collect()).is_one()
+        };
+        let input = pairing_check_input(msg, 1, l, &gens, &sig, &keypair.ver_key).unwrap();
+        assert_eq!(input.len(), 3 * EVM_PAIR_SIZE);
+        assert!(check(&input));
+        let input = pairing_check_input(b"Hi", 1, l, &gens, &sig, &keypair.ver_key).unwrap();
+        assert!(!check(&input));
+        let input = pairing_check_input(msg, 2, l, &gens, &sig, &keypair.ver_key).unwrap();
+        assert!(!check(&input));
+    }
+}
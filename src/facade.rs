@@ -0,0 +1,488 @@
+use rand::{CryptoRng, RngCore};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::Receiver;
+
+use crate::capability::{self, PeriodSigner, Updater};
+use crate::domain::{typed_message, SignableDomain};
+use crate::errors::ForwardSecureSignatureError;
+use crate::keys::{
+    Keypair, ProofOfPossession, SigKeyDb, SigManager, SigManagerEvent, SigningLimits, Verkey,
+    VerkeyFingerprint,
+};
+use crate::period_cache::PeriodCache;
+use crate::signature::Signature;
+use crate::util::{calculate_l, GeneratorSetRef};
+
+/// Bundles everything needed for signing: the generators, the verkey, the key manager for the
+/// current time period and the database of signing keys.
+pub struct Signer<D: SigKeyDb> {
+    gens: GeneratorSetRef,
+    verkey: Verkey,
+    sig_manager: SigManager,
+    db: D,
+}
+
+impl<D: SigKeyDb> Signer<D> {
+    /// Generate a new keypair supporting `T` time periods and store the initial signing key in
+    /// `db`. Returns the signer and the proof of possession of the signing key.
+    pub fn new<G: Into<GeneratorSetRef>, R: RngCore + CryptoRng>(
+        T: u128,
+        gens: G,
+        mut db: D,
+        rng: &mut R,
+    ) -> Result<(Self, ProofOfPossession), ForwardSecureSignatureError> {
+        let gens = gens.into();
+        let (keypair, sig_manager) = Keypair::new(T, &gens, rng, &mut db)?;
+        Ok((
+            Self {
+                gens,
+                verkey: keypair.ver_key,
+                sig_manager,
+                db,
+            },
+            keypair.pop,
+        ))
+    }
+
+    /// Create signer from already existing keys
+    pub fn from_parts<G: Into<GeneratorSetRef>>(
+        gens: G,
+        verkey: Verkey,
+        sig_manager: SigManager,
+        db: D,
+    ) -> Self {
+        Self {
+            gens: gens.into(),
+            verkey,
+            sig_manager,
+            db,
+        }
+    }
+
+    /// Inverse of `from_parts`
+    pub fn into_parts(self) -> (GeneratorSetRef, Verkey, SigManager, D) {
+        (self.gens, self.verkey, self.sig_manager, self.db)
+    }
+
+    /// Split into an `Updater` and a `PeriodSigner` sharing the keys
+    pub fn into_capabilities(self) -> (Updater<D>, PeriodSigner<D>) {
+        capability::split(self.gens, self.verkey, self.sig_manager, self.db)
+    }
+
+    /// Current time period
+    pub fn t(&self) -> u128 {
+        self.sig_manager.t()
+    }
+
+    pub fn verkey(&self) -> &Verkey {
+        &self.verkey
+    }
+
+    pub fn generators(&self) -> &GeneratorSetRef {
+        &self.gens
+    }
+
+    pub fn sig_manager(&self) -> &SigManager {
+        &self.sig_manager
+    }
+
+    /// Whether the key of the current time period belongs to the verkey. Meant to be called at
+    /// startup, see `Sigkey::matches_verkey`.
+    pub fn key_matches_verkey(&self) -> Result<bool, ForwardSecureSignatureError> {
+        let sk = self.sig_manager.get_current_key(&self.db)?;
+        sk.matches_verkey(&self.verkey, &self.gens, self.t(), self.sig_manager.l())
+    }
+
+    /// Receive the key update events of the key manager
+    pub fn subscribe(&mut self) -> Receiver<SigManagerEvent> {
+        self.sig_manager.subscribe()
+    }
+
+    pub fn db(&self) -> &D {
+        &self.db
+    }
+
+    /// Create in-deterministic signature for the current time period, see `SigManager::sign`.
+    /// Fails if the message was punctured or a signing limit is reached.
+    pub fn sign<R: RngCore + CryptoRng>(
+        &mut self,
+        msg: &[u8],
+        rng: &mut R,
+    ) -> Result<Signature, ForwardSecureSignatureError> {
+        self.sig_manager.sign(msg, &self.gens, &self.db, rng)
+    }
+
+    /// Create signature with hedged randomness for the current time period, recommended over
+    /// `sign` and `sign_deterministic`
+    pub fn sign_hedged<R: RngCore + CryptoRng>(
+        &mut self,
+        msg: &[u8],
+        rng: &mut R,
+    ) -> Result<Signature, ForwardSecureSignatureError> {
+        self.sig_manager.sign_hedged(msg, &self.gens, &self.db, rng)
+    }
+
+    /// Create deterministic signature for the current time period
+    pub fn sign_deterministic(
+        &mut self,
+        msg: &[u8],
+    ) -> Result<Signature, ForwardSecureSignatureError> {
+        self.sig_manager
+            .sign_deterministic(msg, &self.gens, &self.db)
+    }
+
+    /// Create in-deterministic signature on a typed message for the current time period
+    pub fn sign_typed<M: SignableDomain, R: RngCore + CryptoRng>(
+        &mut self,
+        msg: &M,
+        rng: &mut R,
+    ) -> Result<Signature, ForwardSecureSignatureError> {
+        self.sign(&typed_message(msg), rng)
+    }
+
+    /// Refuse to sign `msg` in the current time period, see `SigManager::puncture_message`
+    pub fn puncture_message(&mut self, msg: &[u8]) {
+        self.sig_manager.puncture_message(msg)
+    }
+
+    /// Same as `puncture_message` for the SHA3-256 hash of the message
+    pub fn puncture(&mut self, msg_digest: [u8; 32]) {
+        self.sig_manager.puncture(msg_digest)
+    }
+
+    /// See `SigManager::set_signing_limits`
+    pub fn set_signing_limits(&mut self, limits: SigningLimits) {
+        self.sig_manager.set_signing_limits(limits)
+    }
+
+    pub fn signing_limits(&self) -> SigningLimits {
+        self.sig_manager.signing_limits()
+    }
+
+    /// Update the signing key to time period `t`. Returns the time periods whose keys were removed.
+    pub fn update_to<R: RngCore + CryptoRng>(
+        &mut self,
+        t: u128,
+        rng: &mut R,
+    ) -> Result<Vec<u128>, ForwardSecureSignatureError> {
+        self.sig_manager
+            .fast_forward_update(t, &self.gens, rng, &mut self.db)
+    }
+
+    /// Returns a verifier for this signer's signatures. Shares the generators with the signer.
+    pub fn verifier(&self) -> Verifier {
+        Verifier {
+            gens: self.gens.clone(),
+            l: self.sig_manager.l(),
+            verkey: self.verkey.clone(),
+        }
+    }
+}
+
+/// Bundles the generators, tree depth and verkey needed to verify signatures
+pub struct Verifier {
+    gens: GeneratorSetRef,
+    l: u8,
+    verkey: Verkey,
+}
+
+impl Verifier {
+    pub fn new<G: Into<GeneratorSetRef>>(
+        T: u128,
+        gens: G,
+        verkey: Verkey,
+    ) -> Result<Self, ForwardSecureSignatureError> {
+        let l = calculate_l(T)?;
+        Ok(Self {
+            gens: gens.into(),
+            l,
+            verkey,
+        })
+    }
+
+    pub fn verkey(&self) -> &Verkey {
+        &self.verkey
+    }
+
+    pub fn generators(&self) -> &GeneratorSetRef {
+        &self.gens
+    }
+
+    pub fn l(&self) -> u8 {
+        self.l
+    }
+
+    /// Precomputations for this verifier that can be persisted, see `PeriodCache`
+    pub fn period_cache(&self) -> PeriodCache {
+        PeriodCache::new(self)
+    }
+
+    /// Verify signature on `msg` for time period `t`
+    pub fn verify(
+        &self,
+        msg: &[u8],
+        t: u128,
+        sig: &Signature,
+    ) -> Result<bool, ForwardSecureSignatureError> {
+        sig.verify(msg, t, self.l, &self.gens, &self.verkey)
+    }
+
+    /// Verify signature on typed message `msg` for time period `t`
+    pub fn verify_typed<M: SignableDomain>(
+        &self,
+        msg: &M,
+        t: u128,
+        sig: &Signature,
+    ) -> Result<bool, ForwardSecureSignatureError> {
+        self.verify(&typed_message(msg), t, sig)
+    }
+}
+
+/// Verification of signatures of any verkey. Code taking a `SignatureVerifier` rather than
+/// calling `Signature::verify` can be tested with `MockVerifier` instead of doing pairings.
+pub trait SignatureVerifier {
+    fn verify(
+        &self,
+        msg: &[u8],
+        t: u128,
+        sig: &Signature,
+        verkey: &Verkey,
+    ) -> Result<bool, ForwardSecureSignatureError>;
+
+    /// Verify a multi-signature by all of `verkeys`
+    fn verify_aggregated(
+        &self,
+        msg: &[u8],
+        t: u128,
+        sig: &Signature,
+        verkeys: &[&Verkey],
+    ) -> Result<bool, ForwardSecureSignatureError> {
+        self.verify(msg, t, sig, &Verkey::aggregate(verkeys.to_vec()))
+    }
+}
+
+/// `SignatureVerifier` doing the actual verification
+#[derive(Clone)]
+pub struct PairingVerifier {
+    gens: GeneratorSetRef,
+    l: u8,
+}
+
+impl PairingVerifier {
+    pub fn new<G: Into<GeneratorSetRef>>(
+        T: u128,
+        gens: G,
+    ) -> Result<Self, ForwardSecureSignatureError> {
+        Ok(Self {
+            gens: gens.into(),
+            l: calculate_l(T)?,
+        })
+    }
+}
+
+impl SignatureVerifier for PairingVerifier {
+    fn verify(
+        &self,
+        msg: &[u8],
+        t: u128,
+        sig: &Signature,
+        verkey: &Verkey,
+    ) -> Result<bool, ForwardSecureSignatureError> {
+        sig.verify(msg, t, self.l, &self.gens, verkey)
+    }
+}
+
+/// `SignatureVerifier` for tests that does no cryptography. Accepts or rejects every signature
+/// unless a rule rejects it because of its verkey, time period or message. Counts the calls.
+#[derive(Debug, Default)]
+pub struct MockVerifier {
+    accept: bool,
+    rejected_verkeys: HashSet<VerkeyFingerprint>,
+    rejected_periods: HashSet<u128>,
+    rejected_msgs: HashSet<Vec<u8>>,
+    calls: AtomicUsize,
+}
+
+impl MockVerifier {
+    pub fn accepting() -> Self {
+        Self {
+            accept: true,
+            ..Self::default()
+        }
+    }
+
+    pub fn rejecting() -> Self {
+        Self::default()
+    }
+
+    pub fn reject_verkey(mut self, verkey: &Verkey) -> Self {
+        self.rejected_verkeys.insert(verkey.fingerprint());
+        self
+    }
+
+    pub fn reject_period(mut self, t: u128) -> Self {
+        self.rejected_periods.insert(t);
+        self
+    }
+
+    pub fn reject_message(mut self, msg: &[u8]) -> Self {
+        self.rejected_msgs.insert(msg.to_vec());
+        self
+    }
+
+    /// Number of signatures verified so far, an aggregated signature counts once
+    pub fn calls(&self) -> usize {
+        self.calls.load(Ordering::Relaxed)
+    }
+
+    fn result(&self, msg: &[u8], t: u128, verkeys: &[&Verkey]) -> bool {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        self.accept
+            && !self.rejected_periods.contains(&t)
+            && !self.rejected_msgs.contains(msg)
+            && verkeys
+                .iter()
+                .all(|vk| !self.rejected_verkeys.contains(&vk.fingerprint()))
+    }
+}
+
+impl SignatureVerifier for MockVerifier {
+    fn verify(
+        &self,
+        msg: &[u8],
+        t: u128,
+        _sig: &Signature,
+        verkey: &Verkey,
+    ) -> Result<bool, ForwardSecureSignatureError> {
+        Ok(self.result(msg, t, &[verkey]))
+    }
+
+    fn verify_aggregated(
+        &self,
+        msg: &[u8],
+        t: u128,
+        _sig: &Signature,
+        verkeys: &[&Verkey],
+    ) -> Result<bool, ForwardSecureSignatureError> {
+        Ok(self.result(msg, t, verkeys))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::InMemorySigKeyDatabase;
+    use crate::util::GeneratorSet;
+
+    #[test]
+    fn test_signer_verifier() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let gens = GeneratorSetRef::new(GeneratorSet::new(T, "test_pixel").unwrap());
+        let (mut signer, pop) =
+            Signer::new(T, gens.clone(), InMemorySigKeyDatabase::new(), &mut rng).unwrap();
+        assert!(signer.generators().ptr_eq(&gens));
+        assert!(signer.key_matches_verkey().unwrap());
+        assert!(Keypair::verify_pop(&pop, signer.verkey(), &gens.0));
+        let verifier = Verifier::new(T, gens.clone(), signer.verkey().clone()).unwrap();
+
+        let msg = "Hello".as_bytes();
+        assert_eq!(signer.t(), 1);
+        let sig = signer.sign(msg, &mut rng).unwrap();
+        assert!(verifier.verify(msg, 1, &sig).unwrap());
+        assert!(!verifier.verify(msg, 2, &sig).unwrap());
+
+        signer.update_to(4, &mut rng).unwrap();
+        assert_eq!(signer.t(), 4);
+        let sig = signer.sign_deterministic(msg).unwrap();
+        assert_eq!(sig, signer.sign_deterministic(msg).unwrap());
+        assert!(verifier.verify(msg, 4, &sig).unwrap());
+        let hedged = signer.sign_hedged(msg, &mut rng).unwrap();
+        assert!(verifier.verify(msg, 4, &hedged).unwrap());
+        assert!(signer.verifier().verify(msg, 4, &sig).unwrap());
+        assert!(signer.verifier().generators().ptr_eq(&gens));
+        assert!(signer.update_to(3, &mut rng).is_err());
+    }
+
+    #[test]
+    fn test_signer_punctures_and_limits() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let (mut signer, _) =
+            Signer::new(T, gens, InMemorySigKeyDatabase::new(), &mut rng).unwrap();
+
+        signer.puncture_message(b"Hello");
+        match signer.sign(b"Hello", &mut rng) {
+            Err(ForwardSecureSignatureError::MessagePunctured { t }) => assert_eq!(t, 1),
+            _ => panic!("signed a punctured message"),
+        }
+        assert!(signer.sign_deterministic(b"Hello").is_err());
+        assert!(signer.sign(b"Hi", &mut rng).is_ok());
+        assert_eq!(signer.sig_manager().stats(1).signatures(), 1);
+
+        signer.set_signing_limits(SigningLimits {
+            per_period: Some(2),
+            total: None,
+        });
+        assert_eq!(signer.signing_limits().per_period, Some(2));
+        signer.sign_hedged(b"Hi", &mut rng).unwrap();
+        match signer.sign(b"Hi", &mut rng) {
+            Err(ForwardSecureSignatureError::SigningLimitExceeded { limit, t }) => {
+                assert_eq!((limit, t), (2, 1))
+            }
+            _ => panic!("signed beyond the limit"),
+        }
+
+        // Punctures and the per period limit are for the current time period only
+        signer.update_to(2, &mut rng).unwrap();
+        assert!(signer.sign(b"Hello", &mut rng).is_ok());
+    }
+
+    #[test]
+    fn test_signature_verifiers() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let gens = GeneratorSetRef::new(GeneratorSet::new(T, "test_pixel").unwrap());
+        let (mut signer, _) =
+            Signer::new(T, gens.clone(), InMemorySigKeyDatabase::new(), &mut rng).unwrap();
+        let (mut signer_1, _) =
+            Signer::new(T, gens.clone(), InMemorySigKeyDatabase::new(), &mut rng).unwrap();
+        let msg = "Hello".as_bytes();
+        let sig = signer.sign(msg, &mut rng).unwrap();
+        let sig_1 = signer_1.sign(msg, &mut rng).unwrap();
+        let asig = Signature::aggregate(vec![&sig, &sig_1]);
+
+        // Code under test only sees the trait
+        fn check(v: &dyn SignatureVerifier, msg: &[u8], sig: &Signature, vk: &Verkey) -> bool {
+            v.verify(msg, 1, sig, vk).unwrap()
+        }
+
+        let verifier = PairingVerifier::new(T, gens.clone()).unwrap();
+        assert!(check(&verifier, msg, &sig, signer.verkey()));
+        assert!(!check(&verifier, msg, &sig, signer_1.verkey()));
+        assert!(verifier
+            .verify_aggregated(msg, 1, &asig, &[signer.verkey(), signer_1.verkey()])
+            .unwrap());
+
+        let mock = MockVerifier::accepting()
+            .reject_verkey(signer_1.verkey())
+            .reject_period(2)
+            .reject_message(b"Hi");
+        assert!(check(&mock, msg, &sig_1, signer.verkey()));
+        assert!(!check(&mock, msg, &sig, signer_1.verkey()));
+        assert!(!check(&mock, b"Hi", &sig, signer.verkey()));
+        assert!(!mock.verify(msg, 2, &sig, signer.verkey()).unwrap());
+        assert!(!mock
+            .verify_aggregated(msg, 1, &asig, &[signer.verkey(), signer_1.verkey()])
+            .unwrap());
+        assert_eq!(mock.calls(), 5);
+        assert!(!check(
+            &MockVerifier::rejecting(),
+            msg,
+            &sig,
+            signer.verkey()
+        ));
+    }
+}
@@ -0,0 +1,223 @@
+//! API for deployments with a fixed maximum time period `T = 2^L - 1`, known at compile time.
+//! The depth `L` is a const generic so generators and node paths are fixed-size arrays, time
+//! periods are checked against `T` when created, at compile time for constants, and `l` is not
+//! passed around at runtime.
+//!
+//! Keys are still created and updated with `Keypair` and `SigManager` using the `GeneratorSet`
+//! returned by `FixedGeneratorSet::to_generator_set`.
+
+use amcl_wrapper::group_elem::GroupElement;
+use rand::{CryptoRng, RngCore};
+use std::convert::TryFrom;
+
+use crate::errors::ForwardSecureSignatureError;
+use crate::keys::{Sigkey, Verkey};
+use crate::signature::Signature;
+use crate::util::{node_num_to_path_into, random_field_element, GeneratorSet, MAX_PATH_LEN};
+use crate::{SignatureGroup, VerkeyGroup};
+
+/// A time period of a tree of depth `L`, i.e. `1 <= t <= 2^L - 1`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TimePeriod<const L: usize>(u128);
+
+impl<const L: usize> TimePeriod<L> {
+    /// Maximum time period of a tree of depth `L`
+    pub const T: u128 = {
+        assert!(
+            L >= 2 && L <= MAX_PATH_LEN,
+            "depth must be between 2 and 127"
+        );
+        (1u128 << L) - 1
+    };
+
+    /// Panics if `t` is out of range, which is a compile error when used in a constant, e.g.
+    /// `const PERIOD: TimePeriod<3> = TimePeriod::new(8);`
+    pub const fn new(t: u128) -> Self {
+        assert!(t >= 1 && t <= Self::T, "time period out of range");
+        Self(t)
+    }
+
+    pub fn try_new(t: u128) -> Result<Self, ForwardSecureSignatureError> {
+        if t == 0 || t > Self::T {
+            return Err(ForwardSecureSignatureError::InvalidNodeNum { t, l: L as u8 });
+        }
+        Ok(Self(t))
+    }
+
+    pub fn get(&self) -> u128 {
+        self.0
+    }
+
+    /// The following time period, `None` for `T`
+    pub fn next(&self) -> Option<Self> {
+        if self.0 == Self::T {
+            None
+        } else {
+            Some(Self(self.0 + 1))
+        }
+    }
+
+    /// Path of the node of this time period from the root
+    pub fn path(&self) -> NodePath<L> {
+        let mut nodes = [0; L];
+        let len = node_num_to_path_into(self.0, L as u8, &mut nodes)
+            .expect("time period is valid for depth L");
+        NodePath { nodes, len }
+    }
+}
+
+/// Path from the root to a node, 1 for left and 2 for right, at most `L - 1` nodes long
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NodePath<const L: usize> {
+    nodes: [u8; L],
+    len: usize,
+}
+
+impl<const L: usize> NodePath<L> {
+    pub fn as_slice(&self) -> &[u8] {
+        &self.nodes[..self.len]
+    }
+}
+
+/// Generators for depth `L`. Same generators as `GeneratorSet` for `T = 2^L - 1`, with `g2` the
+/// generator of the verkey group, `h`, `h_0` and `hs` the remaining `L` generators of the
+/// signature group, the last of which is used for the message.
+#[derive(Clone, Debug)]
+pub struct FixedGeneratorSet<const L: usize> {
+    pub g2: VerkeyGroup,
+    pub h: SignatureGroup,
+    pub h_0: SignatureGroup,
+    pub hs: [SignatureGroup; L],
+}
+
+impl<const L: usize> FixedGeneratorSet<L> {
+    pub fn new(prefix: &str) -> Result<Self, ForwardSecureSignatureError> {
+        Self::from_generator_set(GeneratorSet::new(TimePeriod::<L>::T, prefix)?)
+    }
+
+    /// Fails with `NotEnoughGenerators` unless `gens` has exactly the `L + 2` generators needed
+    pub fn from_generator_set(gens: GeneratorSet) -> Result<Self, ForwardSecureSignatureError> {
+        let GeneratorSet(g2, mut hs) = gens;
+        if hs.len() != L + 2 {
+            return Err(ForwardSecureSignatureError::NotEnoughGenerators { n: L + 2 });
+        }
+        let rest = hs.split_off(2);
+        let h_0 = hs.pop().unwrap();
+        let h = hs.pop().unwrap();
+        let hs = <[SignatureGroup; L]>::try_from(rest)
+            .map_err(|_| ForwardSecureSignatureError::NotEnoughGenerators { n: L + 2 })?;
+        Ok(Self { g2, h, h_0, hs })
+    }
+
+    pub fn to_generator_set(&self) -> GeneratorSet {
+        let mut hs = Vec::with_capacity(L + 2);
+        hs.push(self.h.clone());
+        hs.push(self.h_0.clone());
+        hs.extend_from_slice(&self.hs);
+        GeneratorSet(self.g2.clone(), hs)
+    }
+
+    /// h_0*h_1^path[0]*h_2^path[1]*... for the path of `t`
+    pub fn path_factor(&self, t: TimePeriod<L>) -> SignatureGroup {
+        let mut pf = self.h_0.clone();
+        for (p, h) in t.path().as_slice().iter().zip(self.hs.iter()) {
+            if *p == 1 {
+                pf += h
+            } else {
+                pf += h.double()
+            }
+        }
+        pf
+    }
+
+    /// Same as `Signature::new` with `sig_key` the key for `t`
+    pub fn sign<R: RngCore + CryptoRng>(
+        &self,
+        msg: &[u8],
+        t: TimePeriod<L>,
+        sig_key: &Sigkey,
+        rng: &mut R,
+    ) -> Result<Signature, ForwardSecureSignatureError> {
+        let r = random_field_element(rng)?;
+        Ok(Signature::gen_sig_with_generators(
+            Signature::hash_message(msg),
+            self.path_factor(t),
+            &self.g2,
+            &self.hs[L - 1],
+            sig_key,
+            r,
+        ))
+    }
+
+    /// Same as `Signature::verify`, cannot fail as the generators and time period are valid
+    pub fn verify(&self, sig: &Signature, msg: &[u8], t: TimePeriod<L>, verkey: &Verkey) -> bool {
+        if sig.is_identity() || verkey.is_identity() || !sig.has_correct_oder() {
+            return false;
+        }
+        Signature::verify_with_generators(
+            &sig.sigma_1,
+            &sig.sigma_2,
+            &verkey.value,
+            &Signature::hash_message(msg),
+            &self.path_factor(t),
+            (&self.g2, &self.h, &self.hs[L - 1]),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{InMemorySigKeyDatabase, Keypair};
+    use crate::util::{calculate_l, from_node_num_to_path};
+
+    const LAST: TimePeriod<3> = TimePeriod::new(7);
+
+    #[test]
+    fn test_time_periods() {
+        assert_eq!(TimePeriod::<3>::T, 7);
+        assert_eq!(LAST.get(), 7);
+        assert!(LAST.next().is_none());
+        assert_eq!(TimePeriod::<3>::new(1).next(), Some(TimePeriod::new(2)));
+        assert!(TimePeriod::<3>::try_new(0).is_err());
+        assert!(TimePeriod::<3>::try_new(8).is_err());
+        for t in 1..=TimePeriod::<4>::T {
+            assert_eq!(
+                TimePeriod::<4>::new(t).path().as_slice(),
+                from_node_num_to_path(t, 4).unwrap().as_slice()
+            );
+        }
+    }
+
+    #[test]
+    fn test_fixed_depth_sign_verify() {
+        let mut rng = rand::thread_rng();
+        let gens = FixedGeneratorSet::<3>::new("test_pixel").unwrap();
+        let gens_dyn = gens.to_generator_set();
+        assert_eq!(
+            gens_dyn.to_bytes(),
+            GeneratorSet::new(7, "test_pixel").unwrap().to_bytes()
+        );
+        assert!(FixedGeneratorSet::<4>::from_generator_set(gens_dyn.clone()).is_err());
+
+        let T = TimePeriod::<3>::T;
+        let l = calculate_l(T).unwrap();
+        let mut db = InMemorySigKeyDatabase::new();
+        let (keypair, mut manager) = Keypair::new(T, &gens_dyn, &mut rng, &mut db).unwrap();
+        manager
+            .fast_forward_update(5, &gens_dyn, &mut rng, &mut db)
+            .unwrap();
+        let t = TimePeriod::<3>::new(5);
+        let sk = manager.get_current_key(&db).unwrap();
+        let msg = "Hello".as_bytes();
+
+        let sig = gens.sign(msg, t, sk, &mut rng).unwrap();
+        assert!(gens.verify(&sig, msg, t, &keypair.ver_key));
+        assert!(sig.verify(msg, 5, l, &gens_dyn, &keypair.ver_key).unwrap());
+        assert!(!gens.verify(&sig, "Hellp".as_bytes(), t, &keypair.ver_key));
+        assert!(!gens.verify(&sig, msg, TimePeriod::new(6), &keypair.ver_key));
+
+        let sig = Signature::new(msg, 5, l, &gens_dyn, sk, &mut rng).unwrap();
+        assert!(gens.verify(&sig, msg, t, &keypair.ver_key));
+    }
+}
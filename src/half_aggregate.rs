@@ -0,0 +1,209 @@
+//! Half-aggregation: the `sigma_1` of several signatures are added up while every `sigma_2` is
+//! kept. Unlike a full aggregate the signatures may be on different messages and for different
+//! time periods, as verification pairs each `sigma_2` with the path factor and message of its
+//! own signature:
+//! `e(sum sigma_1, g2) == e(h, sum y_i) * prod e(pf(t_i) * h_m^m_i, sigma_2_i)`
+//! which is a single multi-pairing of `n + 2` pairs. The size is one point in the signature
+//! group plus one point in the verkey group per signature, between the full aggregate and the
+//! list of all signatures.
+//!
+//! Signatures can be added one at a time with `verify_and_add`, which checks the new signature
+//! alone first so an invalid one is attributed to its position and never enters the aggregate.
+//! Aggregating only `sigma_2` is not offered since adding up `sigma_2` needs the same message
+//! and time period for all signatures, for which the full aggregate is smaller.
+//!
+//! As for full aggregation, the verkeys must have verified proofs of possession.
+
+use amcl_wrapper::group_elem::GroupElement;
+
+use crate::errors::ForwardSecureSignatureError;
+use crate::keys::Verkey;
+use crate::signature::Signature;
+use crate::util::{calculate_path_factor_using_t_l, GeneratorSet};
+use crate::{ate_multi_pairing, SignatureGroup, VerkeyGroup};
+
+/// Message, time period and verkey of one signature of a half-aggregate
+pub type HalfAggregateEntry<'a> = (&'a [u8], u128, &'a Verkey);
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct HalfAggregateSignature {
+    pub sigma_1: SignatureGroup,
+    /// `sigma_2` of every signature in the order they were added
+    pub sigma_2: Vec<VerkeyGroup>,
+}
+
+impl HalfAggregateSignature {
+    pub fn new() -> Self {
+        Self {
+            sigma_1: SignatureGroup::identity(),
+            sigma_2: vec![],
+        }
+    }
+
+    pub fn aggregate(sigs: &[&Signature]) -> Self {
+        let mut aggr = Self::new();
+        for sig in sigs {
+            aggr.add(sig);
+        }
+        aggr
+    }
+
+    pub fn add(&mut self, sig: &Signature) {
+        self.sigma_1 += &sig.sigma_1;
+        self.sigma_2.push(sig.sigma_2.clone());
+    }
+
+    /// Verify `sig` alone and add it if valid. Fails with `InvalidShare` giving the position
+    /// the signature would have had otherwise.
+    pub fn verify_and_add(
+        &mut self,
+        sig: &Signature,
+        (msg, t, verkey): HalfAggregateEntry,
+        l: u8,
+        gens: &GeneratorSet,
+    ) -> Result<(), ForwardSecureSignatureError> {
+        if !sig.verify(msg, t, l, gens, verkey)? {
+            return Err(ForwardSecureSignatureError::InvalidShare {
+                index: self.sigma_2.len(),
+            });
+        }
+        self.add(sig);
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.sigma_2.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sigma_2.is_empty()
+    }
+
+    /// Verify against the message, time period and verkey of every signature, in the order
+    /// the signatures were added. Fails with `InvalidShare` for the position of the first
+    /// `sigma_2` that is the identity or not in the subgroup.
+    pub fn verify(
+        &self,
+        entries: &[HalfAggregateEntry],
+        l: u8,
+        gens: &GeneratorSet,
+    ) -> Result<bool, ForwardSecureSignatureError> {
+        if gens.1.len() < (l as usize + 2) {
+            return Err(ForwardSecureSignatureError::NotEnoughGenerators { n: l as usize + 2 });
+        }
+        if entries.len() != self.sigma_2.len() || self.is_empty() {
+            return Ok(false);
+        }
+        if self.sigma_1.is_identity() || !self.sigma_1.has_correct_order() {
+            return Ok(false);
+        }
+        for (index, sigma_2) in self.sigma_2.iter().enumerate() {
+            if sigma_2.is_identity() || !sigma_2.has_correct_order() {
+                return Err(ForwardSecureSignatureError::InvalidShare { index });
+            }
+        }
+
+        let mut bases = Vec::with_capacity(entries.len());
+        let mut verkeys = Vec::with_capacity(entries.len());
+        for (msg, t, verkey) in entries {
+            if verkey.is_identity() {
+                return Ok(false);
+            }
+            let mut base = calculate_path_factor_using_t_l(*t, l, gens)?;
+            base += &gens.1[l as usize + 1] * &Signature::hash_message(msg);
+            bases.push(base);
+            verkeys.push(*verkey);
+        }
+        let avk = Verkey::aggregate(verkeys);
+
+        // e(sigma_1, -g2) * e(h, sum y_i) * prod e(base_i, sigma_2_i) == 1
+        let neg_g2 = gens.0.negation();
+        let mut pairs = Vec::with_capacity(entries.len() + 2);
+        pairs.push((&self.sigma_1, &neg_g2));
+        pairs.push((&gens.1[0], &avk.value));
+        for (base, sigma_2) in bases.iter().zip(self.sigma_2.iter()) {
+            pairs.push((base, sigma_2));
+        }
+        Ok(ate_multi_pairing(pairs).is_one())
+    }
+}
+
+impl Default for HalfAggregateSignature {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{InMemorySigKeyDatabase, Keypair};
+    use crate::util::calculate_l;
+
+    #[test]
+    fn test_half_aggregation() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let l = calculate_l(T).unwrap();
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let msgs = ["a".as_bytes(), "b".as_bytes(), "c".as_bytes()];
+        let periods = [1u128, 3, 3];
+
+        let mut vks = vec![];
+        let mut sigs = vec![];
+        for (msg, t) in msgs.iter().zip(periods.iter()) {
+            let mut db = InMemorySigKeyDatabase::new();
+            let (keypair, mut manager) = Keypair::new(T, &gens, &mut rng, &mut db).unwrap();
+            if *t > 1 {
+                manager
+                    .fast_forward_update(*t, &gens, &mut rng, &mut db)
+                    .unwrap();
+            }
+            let sk = manager.get_current_key(&db).unwrap();
+            sigs.push(Signature::new(msg, *t, l, &gens, sk, &mut rng).unwrap());
+            vks.push(keypair.ver_key);
+        }
+        let entries = (0..3)
+            .map(|i| (msgs[i], periods[i], &vks[i]))
+            .collect::<Vec<HalfAggregateEntry>>();
+
+        let aggr = HalfAggregateSignature::aggregate(&sigs.iter().collect::<Vec<_>>());
+        assert_eq!(aggr.len(), 3);
+        assert!(aggr.verify(&entries, l, &gens).unwrap());
+        // Order, period and message of every entry matter
+        let swapped = [entries[1], entries[0], entries[2]];
+        assert!(!aggr.verify(&swapped, l, &gens).unwrap());
+        let mut wrong = entries.clone();
+        wrong[0].1 = 2;
+        assert!(!aggr.verify(&wrong, l, &gens).unwrap());
+        let mut wrong = entries.clone();
+        wrong[2].0 = "d".as_bytes();
+        assert!(!aggr.verify(&wrong, l, &gens).unwrap());
+        assert!(!aggr.verify(&entries[..2], l, &gens).unwrap());
+
+        // Built incrementally, an invalid signature is attributed and left out
+        let mut incremental = HalfAggregateSignature::new();
+        incremental
+            .verify_and_add(&sigs[0], entries[0], l, &gens)
+            .unwrap();
+        match incremental.verify_and_add(&sigs[2], entries[1], l, &gens) {
+            Err(ForwardSecureSignatureError::InvalidShare { index }) => assert_eq!(index, 1),
+            _ => panic!("invalid signature must be rejected"),
+        }
+        incremental
+            .verify_and_add(&sigs[1], entries[1], l, &gens)
+            .unwrap();
+        incremental
+            .verify_and_add(&sigs[2], entries[2], l, &gens)
+            .unwrap();
+        assert_eq!(incremental, aggr);
+
+        let mut bad = aggr.clone();
+        bad.sigma_2[1] = VerkeyGroup::identity();
+        match bad.verify(&entries, l, &gens) {
+            Err(ForwardSecureSignatureError::InvalidShare { index }) => assert_eq!(index, 1),
+            _ => panic!("identity sigma_2 must be rejected"),
+        }
+    }
+}
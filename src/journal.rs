@@ -0,0 +1,203 @@
+//! Journaled key updates, so a crash during an update leaves the database in a state it can be
+//! recovered from. `journaled_update` records the intent to update in the database metadata
+//! before updating and marks it complete afterwards. On startup, `recover` replays the journal:
+//! the key manager is moved to the time period of the last update and, if that update did not
+//! complete, the keys it did not derive are derived and those it did not remove are removed with
+//! `SigManager::repair_keys`.
+//!
+//! An update writes new keys before removing old ones, so until it completes the database holds
+//! an ancestor of every key it still has to derive. The journal does not make a single write of
+//! the database atomic, that is up to the database.
+
+use rand::{CryptoRng, RngCore};
+
+use crate::errors::ForwardSecureSignatureError;
+use crate::keys::{KeyRepair, SigKeyDb, SigManager};
+use crate::util::GeneratorSet;
+
+/// Name of the database metadata entry holding the journal
+pub const UPDATE_JOURNAL_METADATA: &str = "pixel/update-journal";
+
+const ENTRY_SIZE: usize = 1 + 2 * 16;
+
+/// The last update recorded in the journal
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UpdateJournalEntry {
+    pub from: u128,
+    pub to: u128,
+    pub complete: bool,
+}
+
+fn invalid(msg: &str) -> ForwardSecureSignatureError {
+    ForwardSecureSignatureError::InvalidUpdateJournal {
+        msg: msg.to_string(),
+    }
+}
+
+impl UpdateJournalEntry {
+    /// `complete as u8 || from || to` with integers big endian
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![self.complete as u8];
+        bytes.extend_from_slice(&self.from.to_be_bytes());
+        bytes.extend_from_slice(&self.to.to_be_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ForwardSecureSignatureError> {
+        if bytes.len() != ENTRY_SIZE || bytes[0] > 1 {
+            return Err(invalid("invalid entry"));
+        }
+        let mut from = [0; 16];
+        from.copy_from_slice(&bytes[1..17]);
+        let mut to = [0; 16];
+        to.copy_from_slice(&bytes[17..]);
+        Ok(Self {
+            from: u128::from_be_bytes(from),
+            to: u128::from_be_bytes(to),
+            complete: bytes[0] == 1,
+        })
+    }
+}
+
+/// The last update recorded in the journal of `db`, if any
+pub fn journal_entry(
+    db: &dyn SigKeyDb,
+) -> Result<Option<UpdateJournalEntry>, ForwardSecureSignatureError> {
+    db.get_metadata(UPDATE_JOURNAL_METADATA)
+        .map(UpdateJournalEntry::from_bytes)
+        .transpose()
+}
+
+/// Same as `SigManager::fast_forward_update` with the update recorded in the journal of `db`.
+/// Fails if the journal has an update that did not complete, which `recover` has to finish
+/// first, or if `db` does not support metadata.
+pub fn journaled_update<R: RngCore + CryptoRng>(
+    manager: &mut SigManager,
+    t: u128,
+    gens: &GeneratorSet,
+    rng: &mut R,
+    db: &mut dyn SigKeyDb,
+) -> Result<Vec<u128>, ForwardSecureSignatureError> {
+    if let Some(entry) = journal_entry(db)? {
+        if !entry.complete {
+            return Err(invalid(&format!(
+                "update from {} to {} did not complete",
+                entry.from, entry.to
+            )));
+        }
+    }
+    let mut entry = UpdateJournalEntry {
+        from: manager.t(),
+        to: t,
+        complete: false,
+    };
+    db.set_metadata(UPDATE_JOURNAL_METADATA, entry.to_bytes());
+    if journal_entry(db)? != Some(entry) {
+        return Err(invalid("database does not support metadata"));
+    }
+    let removed = manager.fast_forward_update(t, gens, rng, db)?;
+    entry.complete = true;
+    db.set_metadata(UPDATE_JOURNAL_METADATA, entry.to_bytes());
+    Ok(removed)
+}
+
+/// Replay the journal of `db` on a key manager loaded at startup. A manager behind the last
+/// update is reloaded at its time period, keeping the signing limits and loading the signing
+/// statistics from `db`, but dropping subscribers and punctured messages. Finishes an update
+/// that did not complete and returns the keys derived and removed to do so. Fails if the manager
+/// is ahead of an update that did not complete.
+pub fn recover<R: RngCore + CryptoRng>(
+    manager: &mut SigManager,
+    gens: &GeneratorSet,
+    rng: &mut R,
+    db: &mut dyn SigKeyDb,
+) -> Result<Option<KeyRepair>, ForwardSecureSignatureError> {
+    let mut entry = match journal_entry(db)? {
+        Some(entry) => entry,
+        None => return Ok(None),
+    };
+    if manager.t() < entry.to {
+        let limits = manager.signing_limits();
+        *manager = SigManager::load(manager.T(), manager.l(), entry.to)?;
+        manager.set_signing_limits(limits);
+        manager.load_stats(db)?;
+    } else if manager.t() > entry.to && !entry.complete {
+        return Err(invalid(&format!(
+            "key manager at {} is ahead of the update to {}",
+            manager.t(),
+            entry.to
+        )));
+    }
+    if entry.complete {
+        return Ok(None);
+    }
+    let repair = manager.repair_keys(gens, rng, db)?;
+    entry.complete = true;
+    db.set_metadata(UPDATE_JOURNAL_METADATA, entry.to_bytes());
+    Ok(Some(repair))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{InMemorySigKeyDatabase, Keypair};
+
+    #[test]
+    fn test_journaled_update_and_recovery() {
+        let mut rng = rand::thread_rng();
+        let T = 15;
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let mut db = InMemorySigKeyDatabase::new();
+        let (_, mut manager) = Keypair::new(T, &gens, &mut rng, &mut db).unwrap();
+        assert_eq!(journal_entry(&db).unwrap(), None);
+
+        journaled_update(&mut manager, 3, &gens, &mut rng, &mut db).unwrap();
+        let entry = journal_entry(&db).unwrap().unwrap();
+        assert_eq!(
+            entry,
+            UpdateJournalEntry {
+                from: 1,
+                to: 3,
+                complete: true
+            }
+        );
+        assert_eq!(
+            UpdateJournalEntry::from_bytes(&entry.to_bytes()).unwrap(),
+            entry
+        );
+
+        // Crash right after recording the intent, with the manager reloaded at its old time
+        // period
+        db.set_metadata(
+            UPDATE_JOURNAL_METADATA,
+            UpdateJournalEntry {
+                from: 3,
+                to: 6,
+                complete: false,
+            }
+            .to_bytes(),
+        );
+        assert!(journaled_update(&mut manager, 6, &gens, &mut rng, &mut db).is_err());
+        let mut restarted = SigManager::load(T, manager.l(), 3).unwrap();
+        let repair = recover(&mut restarted, &gens, &mut rng, &mut db)
+            .unwrap()
+            .unwrap();
+        assert_eq!(restarted.t(), 6);
+        assert_eq!(repair.removed, vec![3]);
+        assert!(journal_entry(&db).unwrap().unwrap().complete);
+        assert!(recover(&mut restarted, &gens, &mut rng, &mut db)
+            .unwrap()
+            .is_none());
+
+        // A manager reloaded at a stale time period after a complete update is moved forward
+        journaled_update(&mut restarted, 8, &gens, &mut rng, &mut db).unwrap();
+        let mut stale = SigManager::load(T, manager.l(), 3).unwrap();
+        assert!(recover(&mut stale, &gens, &mut rng, &mut db)
+            .unwrap()
+            .is_none());
+        assert_eq!(stale.t(), 8);
+
+        assert!(UpdateJournalEntry::from_bytes(&[2; ENTRY_SIZE]).is_err());
+        assert!(UpdateJournalEntry::from_bytes(&[0; 3]).is_err());
+    }
+}
@@ -0,0 +1,215 @@
+//! JWS compact serialization (RFC 7515) with this scheme as the signature algorithm, so web
+//! services can carry forward-secure signatures in standard JOSE tokens. A token is
+//! `BASE64URL(header) || '.' || BASE64URL(payload) || '.' || BASE64URL(signature)` with the
+//! protected header
+//!
+//! ```json
+//! {"alg":"PIXEL-BLS12381-G2","kid":"<verkey fingerprint>","crit":["pxt"],"pxt":<t>}
+//! ```
+//!
+//! where `pxt` is the time period of the signing key, marked critical so that JOSE libraries
+//! that do not know it reject the token instead of ignoring it. The algorithm name is
+//! `PIXEL-BLS12381-G1` with the `VerkeyG1` feature. The signature is the compressed encoding of
+//! a signature on `DST || signing input`, the signing input being the first two parts of the
+//! token.
+
+use rand::{CryptoRng, RngCore};
+
+use crate::errors::ForwardSecureSignatureError;
+use crate::facade::{Signer, Verifier};
+use crate::keys::SigKeyDb;
+use crate::signature::Signature;
+use crate::text_encoding::{base64url_decode, base64url_encode};
+
+#[cfg(feature = "VerkeyG2")]
+pub const JWS_ALGORITHM: &str = "PIXEL-BLS12381-G2";
+#[cfg(feature = "VerkeyG1")]
+pub const JWS_ALGORITHM: &str = "PIXEL-BLS12381-G1";
+
+/// Name of the header parameter holding the time period
+pub const TIME_PERIOD_HEADER: &str = "pxt";
+
+const JWS_DST: &[u8] = b"PIXEL-JWS-V1";
+
+fn invalid(msg: &str) -> ForwardSecureSignatureError {
+    ForwardSecureSignatureError::InvalidJws {
+        msg: msg.to_string(),
+    }
+}
+
+/// Protected header. Unknown parameters are ignored unless listed in `crit`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JwsHeader {
+    pub alg: String,
+    /// Fingerprint of the signer's verkey, as lowercase hex
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kid: Option<String>,
+    #[serde(default)]
+    pub crit: Vec<String>,
+    /// Time period of the signing key
+    pub pxt: u64,
+}
+
+/// Decoded token, check with `verify`
+#[derive(Clone, Debug)]
+pub struct Jws {
+    pub header: JwsHeader,
+    pub payload: Vec<u8>,
+    pub signature: Signature,
+    signing_input: String,
+}
+
+/// Sign `payload` with the signer's key for its current time period. Fails for time periods
+/// above `u64::MAX`.
+pub fn sign<D: SigKeyDb, R: RngCore + CryptoRng>(
+    payload: &[u8],
+    signer: &mut Signer<D>,
+    rng: &mut R,
+) -> Result<String, ForwardSecureSignatureError> {
+    if signer.t() > u64::MAX as u128 {
+        return Err(invalid("time period does not fit in the header"));
+    }
+    let header = JwsHeader {
+        alg: JWS_ALGORITHM.to_string(),
+        kid: Some(signer.verkey().fingerprint().to_string()),
+        crit: vec![TIME_PERIOD_HEADER.to_string()],
+        pxt: signer.t() as u64,
+    };
+    let header = serde_json::to_vec(&header).map_err(|e| invalid(&e.to_string()))?;
+    let signing_input = format!(
+        "{}.{}",
+        base64url_encode(&header),
+        base64url_encode(payload)
+    );
+    let signature = signer.sign(&message(&signing_input), rng)?;
+    Ok(format!(
+        "{}.{}",
+        signing_input,
+        base64url_encode(&signature.to_bytes())
+    ))
+}
+
+/// Parse a compact token. Fails if it is malformed, for another algorithm or has critical
+/// header parameters other than the time period, but does not check the signature.
+pub fn decode(token: &str) -> Result<Jws, ForwardSecureSignatureError> {
+    let parts = token.split('.').collect::<Vec<_>>();
+    if parts.len() != 3 {
+        return Err(invalid(&format!("{} parts instead of 3", parts.len())));
+    }
+    let header: JwsHeader = serde_json::from_slice(&base64url_decode(parts[0])?)
+        .map_err(|e| invalid(&format!("invalid header: {}", e)))?;
+    if header.alg != JWS_ALGORITHM {
+        return Err(invalid(&format!("unsupported algorithm {}", header.alg)));
+    }
+    if header.crit != [TIME_PERIOD_HEADER] {
+        return Err(invalid(
+            "critical header parameters must be exactly the time period",
+        ));
+    }
+    Ok(Jws {
+        header,
+        payload: base64url_decode(parts[1])?,
+        signature: Signature::from_bytes(&base64url_decode(parts[2])?)?,
+        signing_input: format!("{}.{}", parts[0], parts[1]),
+    })
+}
+
+impl Jws {
+    pub fn t(&self) -> u128 {
+        self.header.pxt as u128
+    }
+
+    /// Check the signature with the verifier of the signer. Returns false if the header names
+    /// another signer.
+    pub fn verify(&self, verifier: &Verifier) -> Result<bool, ForwardSecureSignatureError> {
+        if let Some(kid) = &self.header.kid {
+            if *kid != verifier.verkey().fingerprint().to_string() {
+                return Ok(false);
+            }
+        }
+        verifier.verify(&message(&self.signing_input), self.t(), &self.signature)
+    }
+}
+
+/// Decode the token and check its signature. Returns the payload and time period of a valid
+/// token.
+pub fn verify(
+    token: &str,
+    verifier: &Verifier,
+) -> Result<Option<(Vec<u8>, u128)>, ForwardSecureSignatureError> {
+    let jws = decode(token)?;
+    if !jws.verify(verifier)? {
+        return Ok(None);
+    }
+    let t = jws.t();
+    Ok(Some((jws.payload, t)))
+}
+
+fn message(signing_input: &str) -> Vec<u8> {
+    let mut bytes = JWS_DST.to_vec();
+    bytes.extend_from_slice(signing_input.as_bytes());
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::InMemorySigKeyDatabase;
+    use crate::util::GeneratorSet;
+
+    #[test]
+    fn test_jws() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let (mut signer, _) =
+            Signer::new(T, gens.clone(), InMemorySigKeyDatabase::new(), &mut rng).unwrap();
+        let (other, _) = Signer::new(T, gens, InMemorySigKeyDatabase::new(), &mut rng).unwrap();
+        let verifier = signer.verifier();
+        signer.update_to(3, &mut rng).unwrap();
+
+        let token = sign(b"{\"sub\":\"validator-1\"}", &mut signer, &mut rng).unwrap();
+        let jws = decode(&token).unwrap();
+        assert_eq!(jws.t(), 3);
+        assert_eq!(
+            jws.header.kid,
+            Some(signer.verkey().fingerprint().to_string())
+        );
+        assert_eq!(
+            verify(&token, &verifier).unwrap(),
+            Some((b"{\"sub\":\"validator-1\"}".to_vec(), 3))
+        );
+        assert_eq!(verify(&token, &other.verifier()).unwrap(), None);
+
+        // A changed payload or time period does not verify
+        let parts = token.split('.').collect::<Vec<_>>();
+        let tampered = [parts[0], &base64url_encode(b"{}"), parts[2]].join(".");
+        assert_eq!(verify(&tampered, &verifier).unwrap(), None);
+        let mut header = jws.header.clone();
+        header.pxt = 4;
+        let header = base64url_encode(&serde_json::to_vec(&header).unwrap());
+        let tampered = [&header, parts[1], parts[2]].join(".");
+        assert_eq!(verify(&tampered, &verifier).unwrap(), None);
+
+        // Other algorithms, unknown critical parameters and malformed tokens are refused
+        for header in &[
+            "{\"alg\":\"ES256\",\"crit\":[\"pxt\"],\"pxt\":3}".to_string(),
+            format!("{{\"alg\":\"{}\",\"pxt\":3}}", JWS_ALGORITHM),
+            format!(
+                "{{\"alg\":\"{}\",\"crit\":[\"pxt\",\"exp\"],\"pxt\":3}}",
+                JWS_ALGORITHM
+            ),
+        ] {
+            let token = [&base64url_encode(header.as_bytes()), parts[1], parts[2]].join(".");
+            assert!(decode(&token).is_err());
+        }
+        let header = format!(
+            "{{\"alg\":\"{}\",\"crit\":[\"pxt\"],\"pxt\":3}}",
+            JWS_ALGORITHM
+        );
+        let without_kid = [&base64url_encode(header.as_bytes()), parts[1], parts[2]].join(".");
+        assert!(decode(&without_kid).is_ok());
+        assert!(decode(&parts[..2].join(".")).is_err());
+        assert!(decode(&format!("{}=", token)).is_err());
+    }
+}
@@ -0,0 +1,182 @@
+//! A caching layer over a `SigKeyDb`, for databases where reading a key is slow such as
+//! encrypted or remote ones. `CachedSigKeyDb` keeps in memory the keys of the `capacity` earliest
+//! time periods in the database, which are the key of the current time period and those the key
+//! manager updates to next, and reads other keys from the database. When a cached key is removed,
+//! the cache is filled up with the keys of the next time periods.
+//!
+//! Writes go through: inserted keys are written to the database before being cached and removed
+//! keys are removed from both, so the database never lags behind the cache and no removed key
+//! stays in memory. Cached keys are cleared when evicted or removed as `Sigkey` is cleared on
+//! drop. Metadata is not cached.
+
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, HashSet};
+
+use crate::errors::ForwardSecureSignatureError;
+use crate::keys::{SigKeyDb, Sigkey};
+
+pub struct CachedSigKeyDb<D: SigKeyDb> {
+    inner: D,
+    cache: BTreeMap<u128, Sigkey>,
+    capacity: usize,
+}
+
+impl<D: SigKeyDb> CachedSigKeyDb<D> {
+    /// Cache at most `capacity` keys of `inner`. Keys already in `inner` are read from it until
+    /// `prefetch`ed or a cached key is removed.
+    pub fn new(inner: D, capacity: usize) -> Self {
+        Self {
+            inner,
+            cache: BTreeMap::new(),
+            capacity,
+        }
+    }
+
+    /// Read the key of time period `t` from the database into the cache
+    pub fn prefetch(&mut self, t: u128) -> Result<(), ForwardSecureSignatureError> {
+        if !self.cache.contains_key(&t) {
+            let sig_key = self.inner.get_key(t)?.clone();
+            self.cache_key(t, sig_key);
+        }
+        Ok(())
+    }
+
+    /// Time periods whose keys are cached, in increasing order
+    pub fn cached_periods(&self) -> Vec<u128> {
+        self.cache.keys().cloned().collect()
+    }
+
+    /// Drop all cached keys
+    pub fn clear(&mut self) {
+        self.cache.clear();
+    }
+
+    pub fn inner(&self) -> &D {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+
+    /// Read keys of the earliest time periods not cached until the cache is full. Keys added
+    /// to a full cache for later time periods are not cached, this catches up on them once keys
+    /// of earlier time periods are removed.
+    fn refill(&mut self) {
+        let mut indices = self.inner.get_key_indices().into_iter().collect::<Vec<_>>();
+        indices.sort_unstable();
+        for t in indices {
+            if self.cache.len() >= self.capacity {
+                break;
+            }
+            if let Entry::Vacant(entry) = self.cache.entry(t) {
+                if let Ok(sig_key) = self.inner.get_key(t) {
+                    entry.insert(sig_key.clone());
+                }
+            }
+        }
+    }
+
+    /// Cache the key unless the cache is full with keys of earlier time periods, evicting the
+    /// key of the latest time period if full
+    fn cache_key(&mut self, t: u128, sig_key: Sigkey) {
+        if self.cache.len() >= self.capacity {
+            match self.cache.keys().next_back() {
+                Some(&latest) if latest > t => {
+                    self.cache.remove(&latest);
+                }
+                _ => return,
+            }
+        }
+        self.cache.insert(t, sig_key);
+    }
+}
+
+impl<D: SigKeyDb> SigKeyDb for CachedSigKeyDb<D> {
+    fn insert_key(&mut self, t: u128, sig_key: Sigkey) {
+        self.inner.insert_key(t, sig_key.clone());
+        self.cache.remove(&t);
+        self.cache_key(t, sig_key);
+    }
+
+    fn remove_key(&mut self, t: u128) {
+        self.inner.remove_key(t);
+        if self.cache.remove(&t).is_some() {
+            self.refill();
+        }
+    }
+
+    fn has_key(&self, t: u128) -> bool {
+        self.cache.contains_key(&t) || self.inner.has_key(t)
+    }
+
+    fn get_key(&self, t: u128) -> Result<&Sigkey, ForwardSecureSignatureError> {
+        match self.cache.get(&t) {
+            Some(key) => Ok(key),
+            None => self.inner.get_key(t),
+        }
+    }
+
+    fn get_key_indices(&self) -> HashSet<u128> {
+        self.inner.get_key_indices()
+    }
+
+    fn get_metadata(&self, name: &str) -> Option<&[u8]> {
+        self.inner.get_metadata(name)
+    }
+
+    fn set_metadata(&mut self, name: &str, value: Vec<u8>) {
+        self.inner.set_metadata(name, value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::facade::Signer;
+    use crate::keys::InMemorySigKeyDatabase;
+    use crate::util::GeneratorSet;
+
+    #[test]
+    fn test_cached_sig_key_db() {
+        let mut rng = rand::thread_rng();
+        let T = 15;
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let db = CachedSigKeyDb::new(InMemorySigKeyDatabase::new(), 2);
+        let (mut signer, _) = Signer::new(T, gens, db, &mut rng).unwrap();
+        let verifier = signer.verifier();
+        let msg = "Hello".as_bytes();
+        assert_eq!(signer.db().cached_periods(), vec![1]);
+
+        // The earliest keys are kept, written through to the database
+        signer.update_to(4, &mut rng).unwrap();
+        let sig = signer.sign(msg, &mut rng).unwrap();
+        assert!(verifier.verify(msg, 4, &sig).unwrap());
+        let mut indices = signer
+            .db()
+            .get_key_indices()
+            .into_iter()
+            .collect::<Vec<_>>();
+        indices.sort();
+        assert_eq!(indices, vec![4, 5, 6, 9]);
+        assert_eq!(signer.db().cached_periods(), vec![4, 5]);
+        assert_eq!(
+            signer.db().inner().get_key(4).unwrap().0,
+            signer.db().get_key(4).unwrap().0
+        );
+
+        // Misses are read from the database, removed keys are gone from both
+        let (_, _, manager, mut db) = signer.into_parts();
+        db.clear();
+        assert!(db.has_key(6));
+        db.prefetch(6).unwrap();
+        assert_eq!(db.cached_periods(), vec![6]);
+        assert!(db.prefetch(3).is_err());
+        db.remove_key(6);
+        assert!(!db.has_key(6) && db.get_key(6).is_err());
+        assert!(manager.t() == 4 && db.get_key(9).is_ok());
+
+        db.set_metadata("name", vec![1]);
+        assert_eq!(db.into_inner().get_metadata("name"), Some(&[1][..]));
+    }
+}
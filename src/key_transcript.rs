@@ -0,0 +1,263 @@
+//! Transcript of the updates of a signer's key, for audits of the key's evolution. Every update
+//! is recorded with the nodes whose keys were derived, a commitment to the randomness of each
+//! derived key and the nodes whose keys were deleted. A third party replays the transcript
+//! against the signer's public params and checks that after each update exactly the keys for
+//! the new time period and its successors are left, i.e. no key that could sign for a past time
+//! period was kept.
+//!
+//! The commitment of a key is the hash of its node number and its first component `g2^r`, which
+//! is not revealed by the transcript. Should a key of a node leak or be handed to an auditor, it
+//! can be checked against the transcript with `KeyTranscript::commits_to`.
+//!
+//! The transcript holds the signer's claims only, it can't show that deleted keys were erased
+//! from storage. Signers should sign it, e.g. in a `SignedEnvelope`, before handing it out.
+
+use std::collections::BTreeSet;
+
+use amcl_wrapper::group_elem::GroupElement;
+use rand::{CryptoRng, RngCore};
+use sha3::{Digest, Sha3_256};
+
+use crate::errors::ForwardSecureSignatureError;
+use crate::keys::{SigKeyDb, SigManager, Sigkey, Verkey, VerkeyFingerprint};
+use crate::params::PublicParams;
+use crate::util::{node_successor_paths, path_to_node_num, GeneratorSet};
+
+const KEY_COMMITMENT_DST: &[u8] = b"PIXEL-KEY-COMMITMENT-V1";
+
+/// Commitment to the randomness of the key of a node
+pub type KeyCommitment = [u8; 32];
+
+pub fn key_commitment(node: u128, key: &Sigkey) -> KeyCommitment {
+    let mut hasher = Sha3_256::new();
+    hasher.input(KEY_COMMITMENT_DST);
+    hasher.input(node.to_be_bytes());
+    hasher.input(key.0.to_bytes());
+    let mut c = [0; 32];
+    c.copy_from_slice(&hasher.result());
+    c
+}
+
+/// One update of the key from time period `from` to `to`
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct UpdateRecord {
+    pub from: u128,
+    pub to: u128,
+    /// Nodes whose keys were derived, in increasing order, with the commitments to their keys
+    pub derived: Vec<(u128, KeyCommitment)>,
+    /// Nodes whose keys were deleted, in increasing order
+    pub deleted: Vec<u128>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct KeyTranscript {
+    pub verkey: VerkeyFingerprint,
+    /// Maximum time period
+    pub T: u128,
+    /// Time period when recording started
+    pub start: u128,
+    /// Nodes whose keys were present when recording started, with their commitments
+    pub initial: Vec<(u128, KeyCommitment)>,
+    pub updates: Vec<UpdateRecord>,
+}
+
+/// Nodes whose keys are needed from time period `t` on
+fn needed_nodes(t: u128, l: u8) -> Result<BTreeSet<u128>, ForwardSecureSignatureError> {
+    let mut nodes = BTreeSet::new();
+    nodes.insert(t);
+    for path in node_successor_paths(t, l)? {
+        nodes.insert(path_to_node_num(&path, l)?);
+    }
+    Ok(nodes)
+}
+
+fn commitments(
+    nodes: &BTreeSet<u128>,
+    db: &dyn SigKeyDb,
+) -> Result<Vec<(u128, KeyCommitment)>, ForwardSecureSignatureError> {
+    nodes
+        .iter()
+        .map(|n| Ok((*n, key_commitment(*n, db.get_key(*n)?))))
+        .collect()
+}
+
+fn invalid(position: usize, msg: String) -> ForwardSecureSignatureError {
+    ForwardSecureSignatureError::InvalidKeyTranscript { position, msg }
+}
+
+impl KeyTranscript {
+    /// Start recording the updates of `manager` from its current time period, e.g. right after
+    /// key generation
+    pub fn start(
+        verkey: &Verkey,
+        manager: &SigManager,
+        db: &dyn SigKeyDb,
+    ) -> Result<Self, ForwardSecureSignatureError> {
+        let nodes = db.get_key_indices().into_iter().collect::<BTreeSet<_>>();
+        Ok(Self {
+            verkey: verkey.fingerprint(),
+            T: manager.T(),
+            start: manager.t(),
+            initial: commitments(&nodes, db)?,
+            updates: vec![],
+        })
+    }
+
+    /// Time period after the last recorded update
+    pub fn current_t(&self) -> u128 {
+        self.updates.last().map_or(self.start, |u| u.to)
+    }
+
+    /// Same as `SigManager::fast_forward_update` and records the update. Any update of
+    /// `manager` not made through this method makes the transcript fail to replay.
+    pub fn update<R: RngCore + CryptoRng>(
+        &mut self,
+        manager: &mut SigManager,
+        t: u128,
+        gens: &GeneratorSet,
+        rng: &mut R,
+        db: &mut dyn SigKeyDb,
+    ) -> Result<Vec<u128>, ForwardSecureSignatureError> {
+        let from = manager.t();
+        let before = db.get_key_indices().into_iter().collect::<BTreeSet<_>>();
+        let removed = manager.fast_forward_update(t, gens, rng, db)?;
+        let after = db.get_key_indices().into_iter().collect::<BTreeSet<_>>();
+        let derived = after.difference(&before).cloned().collect();
+        self.updates.push(UpdateRecord {
+            from,
+            to: manager.t(),
+            derived: commitments(&derived, db)?,
+            deleted: before.difference(&after).cloned().collect(),
+        });
+        Ok(removed)
+    }
+
+    /// Replay the transcript against the signer's public params and return the time period the
+    /// key was updated to. Fails with `InvalidKeyTranscript` giving the first update whose
+    /// derived or deleted keys don't lead to exactly the keys needed for the time period
+    /// updated to, position 0 being the initial keys.
+    pub fn replay(&self, params: &PublicParams) -> Result<u128, ForwardSecureSignatureError> {
+        if self.verkey != params.verkey.fingerprint() || self.T != params.T {
+            return Err(invalid(0, String::from("created for other public params")));
+        }
+        let l = params.l()?;
+        if self.start == 0 || self.start > self.T {
+            return Err(invalid(0, format!("invalid time period {}", self.start)));
+        }
+        let mut keys = self
+            .initial
+            .iter()
+            .map(|(n, _)| *n)
+            .collect::<BTreeSet<_>>();
+        if keys.len() != self.initial.len() || keys != needed_nodes(self.start, l)? {
+            return Err(invalid(0, String::from("unexpected initial keys")));
+        }
+
+        let mut t = self.start;
+        for (i, update) in self.updates.iter().enumerate() {
+            let position = i + 1;
+            if update.from != t {
+                return Err(invalid(
+                    position,
+                    format!("starts at {} but the key is at {}", update.from, t),
+                ));
+            }
+            if update.to <= update.from || update.to > self.T {
+                return Err(invalid(
+                    position,
+                    format!("invalid update from {} to {}", update.from, update.to),
+                ));
+            }
+            for n in &update.deleted {
+                if !keys.remove(n) {
+                    return Err(invalid(position, format!("deletes absent key {}", n)));
+                }
+            }
+            for (n, _) in &update.derived {
+                if !keys.insert(*n) {
+                    return Err(invalid(position, format!("derives present key {}", n)));
+                }
+            }
+            if keys != needed_nodes(update.to, l)? {
+                return Err(invalid(
+                    position,
+                    format!("keys left are not those needed for {}", update.to),
+                ));
+            }
+            t = update.to;
+        }
+        Ok(t)
+    }
+
+    /// Whether `key` is the key of `node` recorded in the transcript
+    pub fn commits_to(&self, node: u128, key: &Sigkey) -> bool {
+        let commitment = key_commitment(node, key);
+        self.initial
+            .iter()
+            .chain(self.updates.iter().flat_map(|u| u.derived.iter()))
+            .any(|c| *c == (node, commitment))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{InMemorySigKeyDatabase, Keypair};
+
+    #[test]
+    fn test_key_transcript_replay() {
+        let mut rng = rand::thread_rng();
+        let T = 15;
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let mut db = InMemorySigKeyDatabase::new();
+        let (keypair, mut manager) = Keypair::new(T, &gens, &mut rng, &mut db).unwrap();
+        let params = PublicParams::new_with_label(T, "test_pixel", keypair.ver_key.clone(), 0);
+
+        let mut transcript = KeyTranscript::start(&keypair.ver_key, &manager, &db).unwrap();
+        assert!(transcript.commits_to(1, db.get_key(1).unwrap()));
+        for t in &[2, 3, 7, 8, 15] {
+            transcript
+                .update(&mut manager, *t, &gens, &mut rng, &mut db)
+                .unwrap();
+        }
+        assert_eq!(transcript.current_t(), 15);
+        assert_eq!(transcript.replay(&params).unwrap(), 15);
+        assert!(transcript.commits_to(15, db.get_key(15).unwrap()));
+        assert!(!transcript.commits_to(14, db.get_key(15).unwrap()));
+
+        // A key that was kept or not derived is detected
+        let mut kept = transcript.clone();
+        kept.updates[2].deleted.pop();
+        match kept.replay(&params) {
+            Err(ForwardSecureSignatureError::InvalidKeyTranscript { position, .. }) => {
+                assert_eq!(position, 3)
+            }
+            _ => panic!("kept key must be detected"),
+        }
+        let mut missing = transcript.clone();
+        missing.updates[0].derived.clear();
+        assert!(missing.replay(&params).is_err());
+        let mut skipped = transcript.clone();
+        skipped.updates.remove(1);
+        assert!(skipped.replay(&params).is_err());
+
+        let other = PublicParams::new_with_label(T, "test_pixel", Verkey::aggregate(vec![]), 0);
+        assert!(transcript.replay(&other).is_err());
+
+        // Updates not made through the transcript break it
+        let mut db = InMemorySigKeyDatabase::new();
+        let (keypair, mut manager) = Keypair::new(T, &gens, &mut rng, &mut db).unwrap();
+        let params = PublicParams::new_with_label(T, "test_pixel", keypair.ver_key.clone(), 0);
+        let mut transcript = KeyTranscript::start(&keypair.ver_key, &manager, &db).unwrap();
+        transcript
+            .update(&mut manager, 4, &gens, &mut rng, &mut db)
+            .unwrap();
+        manager.simple_update(&gens, &mut rng, &mut db).unwrap();
+        transcript
+            .update(&mut manager, 9, &gens, &mut rng, &mut db)
+            .unwrap();
+        assert!(transcript.replay(&params).is_err());
+    }
+}
@@ -0,0 +1,185 @@
+//! Wrapping of a signing key for a device, to move a migrated or delegated key over an untrusted
+//! channel.
+//!
+//! ECIES-style: the sender picks an ephemeral `e` and sends `E = G*e`. Both sides derive
+//! `k = SHA3-256(DST || E || Y || Y*e || t)` for the recipient's public key `Y = G*y`, as the
+//! recipient computes `Y*e = E*y`. The `CompressedSigkey` encoding of the key is XORed with the
+//! keystream `SHA3-256(DST || k || 1 || i)` for blocks `i = 0, 1, ..` and authenticated with
+//! `SHA3-256(DST || k || 2 || t || ciphertext)`. The time period of the key is bound into both
+//! so a wrapped key can't be passed off as the key of another time period.
+
+use amcl_wrapper::field_elem::FieldElement;
+use amcl_wrapper::group_elem::GroupElement;
+use rand::{CryptoRng, RngCore};
+use sha3::{Digest, Sha3_256};
+
+use crate::errors::ForwardSecureSignatureError;
+use crate::keys::Sigkey;
+use crate::util::random_field_element;
+use crate::wire::{decode_sigkey, CompressedSigkey};
+use crate::SignatureGroup;
+
+const KEY_WRAP_DST: &[u8] = b"PIXEL-KEY-WRAP-V1";
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct WrappingPublicKey(pub SignatureGroup);
+
+/// Keypair of the device receiving wrapped keys
+#[derive(Clone, Debug)]
+pub struct WrappingKeypair {
+    secret: FieldElement,
+    pub public: WrappingPublicKey,
+}
+
+impl WrappingKeypair {
+    pub fn new<R: RngCore + CryptoRng>(rng: &mut R) -> Result<Self, ForwardSecureSignatureError> {
+        let secret = random_field_element(rng)?;
+        let public = WrappingPublicKey(SignatureGroup::generator() * &secret);
+        Ok(Self { secret, public })
+    }
+}
+
+/// Signing key for time period `t` encrypted to a device
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct WrappedSigkey {
+    pub t: u128,
+    pub ephemeral: SignatureGroup,
+    pub ciphertext: Vec<u8>,
+    pub tag: [u8; 32],
+}
+
+fn wrapping_key(
+    ephemeral: &SignatureGroup,
+    recipient: &WrappingPublicKey,
+    shared: &SignatureGroup,
+    t: u128,
+) -> [u8; 32] {
+    let mut hasher = Sha3_256::new();
+    hasher.input(KEY_WRAP_DST);
+    hasher.input(ephemeral.to_bytes());
+    hasher.input(recipient.0.to_bytes());
+    hasher.input(shared.to_bytes());
+    hasher.input(t.to_be_bytes());
+    let mut k = [0; 32];
+    k.copy_from_slice(&hasher.result());
+    k
+}
+
+fn apply_keystream(k: &[u8; 32], data: &mut [u8]) {
+    for (i, block) in data.chunks_mut(32).enumerate() {
+        let mut hasher = Sha3_256::new();
+        hasher.input(KEY_WRAP_DST);
+        hasher.input(k);
+        hasher.input([1]);
+        hasher.input((i as u64).to_be_bytes());
+        for (b, s) in block.iter_mut().zip(hasher.result()) {
+            *b ^= s;
+        }
+    }
+}
+
+fn wrapping_tag(k: &[u8; 32], t: u128, ciphertext: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha3_256::new();
+    hasher.input(KEY_WRAP_DST);
+    hasher.input(k);
+    hasher.input([2]);
+    hasher.input(t.to_be_bytes());
+    hasher.input(ciphertext);
+    let mut tag = [0; 32];
+    tag.copy_from_slice(&hasher.result());
+    tag
+}
+
+impl Sigkey {
+    /// Encrypt this key for time period `t` so that only the holder of the secret key of
+    /// `recipient` can unwrap it
+    pub fn wrap_for<R: RngCore + CryptoRng>(
+        &self,
+        t: u128,
+        recipient: &WrappingPublicKey,
+        rng: &mut R,
+    ) -> Result<WrappedSigkey, ForwardSecureSignatureError> {
+        let e = random_field_element(rng)?;
+        let ephemeral = SignatureGroup::generator() * &e;
+        let k = wrapping_key(&ephemeral, recipient, &(&recipient.0 * &e), t);
+        let mut ciphertext = CompressedSigkey::from(self).into_bytes();
+        apply_keystream(&k, &mut ciphertext);
+        let tag = wrapping_tag(&k, t, &ciphertext);
+        Ok(WrappedSigkey {
+            t,
+            ephemeral,
+            ciphertext,
+            tag,
+        })
+    }
+}
+
+impl WrappedSigkey {
+    /// Decrypt with the device's keypair. Fails with `KeyUnwrapFailed` if the key was wrapped
+    /// for another device or the wrapped key was modified.
+    pub fn unwrap(&self, keypair: &WrappingKeypair) -> Result<Sigkey, ForwardSecureSignatureError> {
+        let failed = || ForwardSecureSignatureError::KeyUnwrapFailed { t: self.t };
+        if self.ephemeral.is_identity() {
+            return Err(failed());
+        }
+        let shared = &self.ephemeral * &keypair.secret;
+        let k = wrapping_key(&self.ephemeral, &keypair.public, &shared, self.t);
+        let expected = wrapping_tag(&k, self.t, &self.ciphertext);
+        // Compare without exiting early
+        let diff = expected
+            .iter()
+            .zip(self.tag.iter())
+            .fold(0, |acc, (a, b)| acc | (a ^ b));
+        if diff != 0 {
+            return Err(failed());
+        }
+        let mut plaintext = self.ciphertext.clone();
+        apply_keystream(&k, &mut plaintext);
+        decode_sigkey(&plaintext).map_err(|_| failed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{InMemorySigKeyDatabase, Keypair};
+    use crate::signature::Signature;
+    use crate::util::{calculate_l, GeneratorSet};
+
+    #[test]
+    fn test_key_wrapping() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let l = calculate_l(T).unwrap();
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let mut db = InMemorySigKeyDatabase::new();
+        let (keypair, mut manager) = Keypair::new(T, &gens, &mut rng, &mut db).unwrap();
+        manager.simple_update(&gens, &mut rng, &mut db).unwrap();
+        let sk = manager.get_current_key(&db).unwrap();
+
+        let device = WrappingKeypair::new(&mut rng).unwrap();
+        let other_device = WrappingKeypair::new(&mut rng).unwrap();
+        let wrapped = sk.wrap_for(2, &device.public, &mut rng).unwrap();
+        assert_ne!(wrapped.ciphertext, CompressedSigkey::from(sk).into_bytes());
+
+        let unwrapped = wrapped.unwrap(&device).unwrap();
+        assert_eq!(unwrapped.0, sk.0);
+        assert_eq!(unwrapped.1, sk.1);
+        let msg = "Hello".as_bytes();
+        let sig = Signature::new(msg, 2, l, &gens, &unwrapped, &mut rng).unwrap();
+        assert!(sig.verify(msg, 2, l, &gens, &keypair.ver_key).unwrap());
+
+        assert!(wrapped.unwrap(&other_device).is_err());
+        let mut tampered = wrapped.clone();
+        tampered.ciphertext[5] ^= 1;
+        assert!(tampered.unwrap(&device).is_err());
+        let mut relabeled = wrapped.clone();
+        relabeled.t = 3;
+        assert!(relabeled.unwrap(&device).is_err());
+        let mut no_ephemeral = wrapped;
+        no_ephemeral.ephemeral = SignatureGroup::identity();
+        assert!(no_ephemeral.unwrap(&device).is_err());
+    }
+}
@@ -1,3 +1,4 @@
+use rand::rngs::OsRng;
 use rand::{CryptoRng, RngCore};
 
 use amcl_wrapper::errors::SerzDeserzError;
@@ -5,17 +6,29 @@ use amcl_wrapper::field_elem::FieldElement;
 use amcl_wrapper::group_elem::GroupElement;
 
 use super::errors::ForwardSecureSignatureError;
+use crate::compression::{
+    compress_verkey_group, decompress_verkey_group, VERKEY_GROUP_COMPRESSED_SIZE,
+};
+use crate::signature::Signature;
+#[cfg(test)]
+use crate::util::GeneratorSetBuilder;
 use crate::util::{
     calculate_l, calculate_path_factor, from_node_num_to_path, node_successor_paths,
-    path_to_node_num, GeneratorSet,
+    path_to_node_num, random_field_element, GeneratorSet,
 };
 
 use crate::{ate_2_pairing, SignatureGroup, VerkeyGroup};
-use std::collections::{HashMap, HashSet};
+use sha3::{Digest, Sha3_256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::convert::TryFrom;
+use std::fmt;
 use std::mem;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-/// MasterSecret will be cleared on drop as FieldElement is cleared on drop
-#[derive(Clone, Debug, Serialize, Deserialize)]
+/// MasterSecret will be cleared on drop as FieldElement is cleared on drop. Serializable with
+/// feature `danger-serialize-secrets` only.
+#[derive(Clone, Debug)]
 pub struct MasterSecret {
     pub value: FieldElement,
 }
@@ -27,6 +40,15 @@ impl MasterSecret {
         }
     }
 
+    /// Same as `new` but fails with `RngFailure` instead of panicking if `rng` fails
+    pub fn try_new<R: RngCore + CryptoRng>(
+        rng: &mut R,
+    ) -> Result<Self, ForwardSecureSignatureError> {
+        Ok(Self {
+            value: random_field_element(rng)?,
+        })
+    }
+
     pub fn from_bytes(sk_bytes: &[u8]) -> Result<Self, SerzDeserzError> {
         FieldElement::from_bytes(sk_bytes).map(|x| Self { value: x })
     }
@@ -37,7 +59,8 @@ impl MasterSecret {
 }
 
 // The public key can be in group G1 or G2.
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Verkey {
     pub value: VerkeyGroup,
 }
@@ -57,7 +80,16 @@ impl Verkey {
         Self { value: avk }
     }
 
-    pub fn from_bytes(vk_bytes: &[u8]) -> Result<Verkey, SerzDeserzError> {
+    /// Deserialize verkey serialized with `to_bytes`, failing with `InvalidPoint` if the point
+    /// is the identity or not in the prime order subgroup. Use for verkeys from untrusted
+    /// sources.
+    pub fn from_bytes(vk_bytes: &[u8]) -> Result<Verkey, ForwardSecureSignatureError> {
+        Self::from_bytes_unchecked(vk_bytes)?.checked()
+    }
+
+    /// Deserialize verkey without checking that the point has the correct order. Only use for
+    /// verkeys that have been validated before, like ones loaded from own database.
+    pub fn from_bytes_unchecked(vk_bytes: &[u8]) -> Result<Verkey, SerzDeserzError> {
         VerkeyGroup::from_bytes(vk_bytes).map(|value| Verkey { value })
     }
 
@@ -65,6 +97,41 @@ impl Verkey {
         self.value.to_bytes()
     }
 
+    /// Serialize the compressed point in `VERKEY_GROUP_COMPRESSED_SIZE` bytes. See
+    /// `compression` for the encoding.
+    pub fn to_compressed_bytes(&self) -> Vec<u8> {
+        compress_verkey_group(&self.value)
+    }
+
+    /// Deserialize verkey serialized with `to_compressed_bytes`, with the checks of `from_bytes`
+    pub fn from_compressed_bytes(vk_bytes: &[u8]) -> Result<Verkey, ForwardSecureSignatureError> {
+        decompress_verkey_group(vk_bytes).map(|value| Verkey { value })
+    }
+
+    fn checked(self) -> Result<Self, ForwardSecureSignatureError> {
+        if self.value.is_identity() {
+            return Err(ForwardSecureSignatureError::InvalidPoint {
+                msg: String::from("verkey point at infinity"),
+            });
+        }
+        if !self.value.has_correct_order() {
+            return Err(ForwardSecureSignatureError::InvalidPoint {
+                msg: String::from("verkey point not in the prime order subgroup"),
+            });
+        }
+        Ok(self)
+    }
+
+    /// Short stable identifier of the verkey, a hash of its canonical encoding
+    pub fn fingerprint(&self) -> VerkeyFingerprint {
+        let mut hasher = Sha3_256::new();
+        hasher.input(VERKEY_FINGERPRINT_DST);
+        hasher.input(self.to_bytes());
+        let mut fp = [0; VERKEY_FINGERPRINT_SIZE];
+        fp.copy_from_slice(&hasher.result()[..VERKEY_FINGERPRINT_SIZE]);
+        VerkeyFingerprint(fp)
+    }
+
     pub fn is_identity(&self) -> bool {
         if self.value.is_identity() {
             println!("Verkey point at infinity");
@@ -74,17 +141,65 @@ impl Verkey {
     }
 }
 
+impl TryFrom<[u8; VERKEY_GROUP_COMPRESSED_SIZE]> for Verkey {
+    type Error = ForwardSecureSignatureError;
+
+    fn try_from(bytes: [u8; VERKEY_GROUP_COMPRESSED_SIZE]) -> Result<Self, Self::Error> {
+        Verkey::from_compressed_bytes(&bytes)
+    }
+}
+
+impl From<&Verkey> for [u8; VERKEY_GROUP_COMPRESSED_SIZE] {
+    fn from(vk: &Verkey) -> Self {
+        let mut bytes = [0; VERKEY_GROUP_COMPRESSED_SIZE];
+        bytes.copy_from_slice(&vk.to_compressed_bytes());
+        bytes
+    }
+}
+
+impl From<Verkey> for [u8; VERKEY_GROUP_COMPRESSED_SIZE] {
+    fn from(vk: Verkey) -> Self {
+        (&vk).into()
+    }
+}
+
+pub const VERKEY_FINGERPRINT_SIZE: usize = 20;
+
+const VERKEY_FINGERPRINT_DST: &[u8] = b"PIXEL-VERKEY-FINGERPRINT";
+
+/// Identifier of a verkey, displayed as lowercase hex. Created by `Verkey::fingerprint`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct VerkeyFingerprint(pub [u8; VERKEY_FINGERPRINT_SIZE]);
+
+impl VerkeyFingerprint {
+    pub fn as_bytes(&self) -> &[u8; VERKEY_FINGERPRINT_SIZE] {
+        &self.0
+    }
+}
+
+impl fmt::Display for VerkeyFingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for b in self.0.iter() {
+            write!(f, "{:02x}", b)?;
+        }
+        Ok(())
+    }
+}
+
 /// Proof of Possession of signing key. It is a signature on the verification key and can be
 /// group in G1 or G2. But it is in different group than Verkey.
 /// If Verkey is in G2 then proof of possession is in G1 and vice versa.
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ProofOfPossession {
     pub value: SignatureGroup,
 }
 
 /// Keypair consisting of a master secret, the corresponding verkey and the proof of possession
 /// Type GPrime denotes group for public key and type G denotes group for proof of possession.
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Keypair {
     pub ver_key: Verkey,
     pub pop: ProofOfPossession,
@@ -99,7 +214,7 @@ impl<'a> Keypair {
         rng: &mut R,
         db: &'a mut dyn SigKeyDb,
     ) -> Result<(Self, SigManager), ForwardSecureSignatureError> {
-        let master_secret = MasterSecret::new(rng);
+        let master_secret = MasterSecret::try_new(rng)?;
         let ver_key = Verkey::from_master_secret(&master_secret, &generators.0);
         let pop = Self::gen_pop(&ver_key, &master_secret);
         let sigkey_initial = Sigkey::initial_secret_key(
@@ -115,6 +230,15 @@ impl<'a> Keypair {
         Ok((kp, sigkeys))
     }
 
+    /// Same as `new` but takes randomness from the operating system
+    pub fn generate(
+        T: u128,
+        generators: &GeneratorSet,
+        db: &'a mut dyn SigKeyDb,
+    ) -> Result<(Self, SigManager), ForwardSecureSignatureError> {
+        Self::new(T, generators, &mut OsRng, db)
+    }
+
     /// Generate proof of possession
     pub fn gen_pop(vk: &Verkey, x: &MasterSecret) -> ProofOfPossession {
         ProofOfPossession {
@@ -137,11 +261,33 @@ impl<'a> Keypair {
 }
 
 /// Secret key sk can be seen as (sk', sk'') where sk'' is itself a vector with initial (and max) length l+1
-/// Sigkey will be cleared on drop as both G1 and G2 elements are cleared on drop
-#[derive(Clone, Debug, Serialize, Deserialize)]
+/// Sigkey will be cleared on drop as both G1 and G2 elements are cleared on drop. Serializable
+/// with feature `danger-serialize-secrets` only.
+#[derive(Clone, Debug)]
 pub struct Sigkey(pub VerkeyGroup, pub Vec<SignatureGroup>);
 
+const KEY_CHECK_MSG: &[u8] = b"PIXEL-SIGKEY-CHECK-V1";
+
 impl Sigkey {
+    /// Whether this is a key of `vk` for time period `t`, checked with a deterministic signature
+    /// on a fixed message. Catches corrupted or mismatched keys, e.g. on import or at startup,
+    /// for the cost of a signature and a verification.
+    pub fn matches_verkey(
+        &self,
+        vk: &Verkey,
+        gens: &GeneratorSet,
+        t: u128,
+        l: u8,
+    ) -> Result<bool, ForwardSecureSignatureError> {
+        // A key of the wrong length would make signing panic
+        let path = from_node_num_to_path(t, l)?;
+        if self.1.len() + path.len() != l as usize + 1 {
+            return Ok(false);
+        }
+        let sig = Signature::new_deterministic(KEY_CHECK_MSG, t, l, gens, self)?;
+        sig.verify(KEY_CHECK_MSG, t, l, gens, vk)
+    }
+
     /// Create secret key for the beginning, i.e. t=1
     pub fn initial_secret_key<R: RngCore + CryptoRng>(
         gen: &VerkeyGroup,
@@ -152,7 +298,7 @@ impl Sigkey {
         if gens.len() < 3 {
             return Err(ForwardSecureSignatureError::NotEnoughGenerators { n: 3 });
         }
-        let r = FieldElement::random_using_rng(rng);
+        let r = random_field_element(rng)?;
         // g^r
         let sk_prime = gen * &r;
         let mut sk_prime_prime = vec![];
@@ -176,6 +322,104 @@ pub struct SigManager {
     l: u8,
     T: u128,
     t: u128,
+    signing_cache: Option<SigningCache>,
+    punctured: PuncturedMessages,
+    stats: BTreeMap<u128, PeriodStats>,
+    limits: SigningLimits,
+    subscribers: Vec<Sender<SigManagerEvent>>,
+}
+
+/// Activity of a `SigManager` sent to the receivers returned by `SigManager::subscribe`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SigManagerEvent {
+    /// The key moved from time period `from` to `to`
+    KeyUpdated {
+        from: u128,
+        to: u128,
+    },
+    /// Keys of these nodes were removed from the database by an update
+    KeysDeleted {
+        nodes: Vec<u128>,
+    },
+    Signed {
+        t: u128,
+        deterministic: bool,
+    },
+    /// A signature was refused because the message was punctured or a signing limit was reached
+    PolicyRejected {
+        t: u128,
+        reason: String,
+    },
+    /// The key reached the last time period `t` and can't be updated anymore
+    Exhausted {
+        t: u128,
+    },
+    /// Missing keys were re-derived, see `SigManager::repair_keys`
+    KeysRepaired(KeyRepair),
+}
+
+/// Result of `SigManager::repair_keys`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct KeyRepair {
+    /// Nodes whose keys were missing or corrupted and have been re-derived
+    pub derived: Vec<u128>,
+    /// Nodes of `derived` whose stored keys failed with `SigkeyCorrupted` rather than missing
+    pub corrupted: Vec<u128>,
+    /// Nodes whose keys were removed since they are not needed from the current time period on,
+    /// including the ancestors the missing keys were derived from
+    pub removed: Vec<u128>,
+}
+
+impl KeyRepair {
+    pub fn is_empty(&self) -> bool {
+        self.derived.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Maximum number of signatures a `SigManager` creates, in each time period and in total.
+/// Counted like the signing statistics, so limits on total apply to the statistics loaded with
+/// `SigManager::load_stats` as well.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SigningLimits {
+    pub per_period: Option<u64>,
+    pub total: Option<u64>,
+}
+
+/// Name of the database metadata entry holding the signing statistics
+pub const SIGNING_STATS_METADATA: &str = "pixel/signing-stats";
+
+/// Number of signatures created with a `SigManager` in a time period
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PeriodStats {
+    pub randomized: u64,
+    pub deterministic: u64,
+    /// Unix time in seconds of the last signature
+    pub last_signed: Option<u64>,
+}
+
+impl PeriodStats {
+    pub fn signatures(&self) -> u64 {
+        self.randomized + self.deterministic
+    }
+}
+
+/// Signatures created in time period `t` keyed by the hash of the message and the caller's
+/// context. Entries of older time periods are dropped once the key has been updated.
+struct SigningCache {
+    t: u128,
+    sigs: HashMap<([u8; 32], Vec<u8>), Signature>,
+}
+
+/// Hashes of messages that must not be signed in time period `t`
+struct PuncturedMessages {
+    t: u128,
+    digests: HashSet<[u8; 32]>,
+}
+
+fn message_digest(msg: &[u8]) -> [u8; 32] {
+    let mut digest = [0; 32];
+    digest.copy_from_slice(&Sha3_256::digest(msg));
+    digest
 }
 
 impl SigManager {
@@ -187,11 +431,315 @@ impl SigManager {
     ) -> Result<Self, ForwardSecureSignatureError> {
         let t = 1;
         db.insert_key(t.clone(), sigkey);
-        Ok(Self { l, T, t })
+        Ok(Self {
+            l,
+            T,
+            t,
+            signing_cache: None,
+            punctured: PuncturedMessages {
+                t,
+                digests: HashSet::new(),
+            },
+            stats: BTreeMap::new(),
+            limits: SigningLimits::default(),
+            subscribers: vec![],
+        })
     }
 
     pub fn load(T: u128, l: u8, t: u128) -> Result<Self, ForwardSecureSignatureError> {
-        Ok(Self { l, T, t })
+        Ok(Self {
+            l,
+            T,
+            t,
+            signing_cache: None,
+            punctured: PuncturedMessages {
+                t,
+                digests: HashSet::new(),
+            },
+            stats: BTreeMap::new(),
+            limits: SigningLimits::default(),
+            subscribers: vec![],
+        })
+    }
+
+    /// Current time period
+    pub fn t(&self) -> u128 {
+        self.t
+    }
+
+    /// Maximum time period supported
+    pub fn T(&self) -> u128 {
+        self.T
+    }
+
+    pub fn l(&self) -> u8 {
+        self.l
+    }
+
+    /// Receive the events of this manager from now on. Events are queued until received, a
+    /// dropped receiver is unsubscribed on the next event.
+    pub fn subscribe(&mut self) -> Receiver<SigManagerEvent> {
+        let (sender, receiver) = channel();
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    fn emit(&mut self, event: SigManagerEvent) {
+        self.subscribers
+            .retain(|sender| sender.send(event.clone()).is_ok());
+    }
+
+    /// Emit `PolicyRejected` for `err` and return it
+    fn rejected(&mut self, err: ForwardSecureSignatureError) -> ForwardSecureSignatureError {
+        self.emit(SigManagerEvent::PolicyRejected {
+            t: self.t,
+            reason: err.to_string(),
+        });
+        err
+    }
+
+    /// Emit the events of an update from time period `from` that removed `nodes`
+    fn emit_update(&mut self, from: u128, nodes: Vec<u128>) {
+        self.emit(SigManagerEvent::KeyUpdated { from, to: self.t });
+        self.emit(SigManagerEvent::KeysDeleted { nodes });
+        if self.t == self.T {
+            self.emit(SigManagerEvent::Exhausted { t: self.t });
+        }
+    }
+
+    /// Cache signatures created with `sign_idempotent` so that repeating a request returns the
+    /// same signature rather than a new one.
+    pub fn enable_signing_cache(&mut self) {
+        if self.signing_cache.is_none() {
+            self.signing_cache = Some(SigningCache {
+                t: self.t,
+                sigs: HashMap::new(),
+            });
+        }
+    }
+
+    pub fn disable_signing_cache(&mut self) {
+        self.signing_cache = None;
+    }
+
+    /// Refuse to sign the message with hash `msg_digest` (SHA3-256) for the rest of the current
+    /// time period. Punctures are enforced by this manager and not by the key itself, so they
+    /// protect against the signer being asked twice, not against a leaked key. They are dropped
+    /// once the key is updated since the key of the next time period cannot sign for this one.
+    pub fn puncture(&mut self, msg_digest: [u8; 32]) {
+        if self.punctured.t != self.t {
+            self.punctured.digests.clear();
+            self.punctured.t = self.t;
+        }
+        self.punctured.digests.insert(msg_digest);
+    }
+
+    /// Same as `puncture` but hashes `msg`
+    pub fn puncture_message(&mut self, msg: &[u8]) {
+        self.puncture(message_digest(msg))
+    }
+
+    pub fn is_punctured(&self, msg: &[u8]) -> bool {
+        self.punctured.t == self.t && self.punctured.digests.contains(&message_digest(msg))
+    }
+
+    /// Sign `msg` with the key of the current time period unless the key was punctured on it
+    pub fn sign<R: RngCore + CryptoRng>(
+        &mut self,
+        msg: &[u8],
+        gens: &GeneratorSet,
+        db: &dyn SigKeyDb,
+        rng: &mut R,
+    ) -> Result<Signature, ForwardSecureSignatureError> {
+        if self.is_punctured(msg) {
+            let err = ForwardSecureSignatureError::MessagePunctured { t: self.t };
+            return Err(self.rejected(err));
+        }
+        self.check_limits().map_err(|e| self.rejected(e))?;
+        let sig = Signature::new(msg, self.t, self.l, gens, db.get_key(self.t)?, rng)?;
+        self.record_signature(false);
+        Ok(sig)
+    }
+
+    /// Same as `sign` but creates a signature with hedged randomness, see `Signature::new_hedged`
+    pub fn sign_hedged<R: RngCore + CryptoRng>(
+        &mut self,
+        msg: &[u8],
+        gens: &GeneratorSet,
+        db: &dyn SigKeyDb,
+        rng: &mut R,
+    ) -> Result<Signature, ForwardSecureSignatureError> {
+        if self.is_punctured(msg) {
+            let err = ForwardSecureSignatureError::MessagePunctured { t: self.t };
+            return Err(self.rejected(err));
+        }
+        self.check_limits().map_err(|e| self.rejected(e))?;
+        let sig = Signature::new_hedged(msg, self.t, self.l, gens, db.get_key(self.t)?, rng)?;
+        self.record_signature(false);
+        Ok(sig)
+    }
+
+    /// Same as `sign` but creates a deterministic signature
+    pub fn sign_deterministic(
+        &mut self,
+        msg: &[u8],
+        gens: &GeneratorSet,
+        db: &dyn SigKeyDb,
+    ) -> Result<Signature, ForwardSecureSignatureError> {
+        if self.is_punctured(msg) {
+            let err = ForwardSecureSignatureError::MessagePunctured { t: self.t };
+            return Err(self.rejected(err));
+        }
+        self.check_limits().map_err(|e| self.rejected(e))?;
+        let sig = Signature::new_deterministic(msg, self.t, self.l, gens, db.get_key(self.t)?)?;
+        self.record_signature(true);
+        Ok(sig)
+    }
+
+    /// Statistics of the signatures created with `sign`, `sign_deterministic` and
+    /// `sign_idempotent` in time period `t`. Signatures created directly with `Signature` are
+    /// not counted.
+    pub fn stats(&self, t: u128) -> PeriodStats {
+        self.stats.get(&t).copied().unwrap_or_default()
+    }
+
+    /// Statistics of every time period in which at least one signature was created
+    pub fn all_stats(&self) -> &BTreeMap<u128, PeriodStats> {
+        &self.stats
+    }
+
+    /// Write the statistics to the metadata of `db`. Also done on every key update.
+    pub fn save_stats(&self, db: &mut dyn SigKeyDb) {
+        let mut bytes = (self.stats.len() as u64).to_be_bytes().to_vec();
+        for (t, s) in &self.stats {
+            bytes.extend_from_slice(&t.to_be_bytes());
+            bytes.extend_from_slice(&s.randomized.to_be_bytes());
+            bytes.extend_from_slice(&s.deterministic.to_be_bytes());
+            bytes.extend_from_slice(&s.last_signed.unwrap_or(0).to_be_bytes());
+        }
+        db.set_metadata(SIGNING_STATS_METADATA, bytes);
+    }
+
+    /// Replace the statistics with the ones saved in the metadata of `db`, if any
+    pub fn load_stats(&mut self, db: &dyn SigKeyDb) -> Result<(), ForwardSecureSignatureError> {
+        let bytes = match db.get_metadata(SIGNING_STATS_METADATA) {
+            Some(bytes) => bytes,
+            None => return Ok(()),
+        };
+        let invalid = || ForwardSecureSignatureError::SerzDeserzError {
+            msg: String::from("invalid signing statistics"),
+        };
+        const ENTRY_SIZE: usize = 16 + 3 * 8;
+        let u64_at = |i: usize| {
+            let mut b = [0; 8];
+            b.copy_from_slice(&bytes[i..i + 8]);
+            u64::from_be_bytes(b)
+        };
+        if bytes.len() < 8 || (bytes.len() - 8) % ENTRY_SIZE != 0 {
+            return Err(invalid());
+        }
+        let n = (bytes.len() - 8) / ENTRY_SIZE;
+        if u64_at(0) != n as u64 {
+            return Err(invalid());
+        }
+        let mut stats = BTreeMap::new();
+        for i in 0..n {
+            let offset = 8 + i * ENTRY_SIZE;
+            let mut t = [0; 16];
+            t.copy_from_slice(&bytes[offset..offset + 16]);
+            let last_signed = u64_at(offset + 32);
+            stats.insert(
+                u128::from_be_bytes(t),
+                PeriodStats {
+                    randomized: u64_at(offset + 16),
+                    deterministic: u64_at(offset + 24),
+                    last_signed: if last_signed == 0 {
+                        None
+                    } else {
+                        Some(last_signed)
+                    },
+                },
+            );
+        }
+        self.stats = stats;
+        Ok(())
+    }
+
+    /// Refuse to sign once a limit is reached. Guards against a runaway or malicious caller
+    /// using up the signing capacity of the key, not against theft of the key.
+    pub fn set_signing_limits(&mut self, limits: SigningLimits) {
+        self.limits = limits;
+    }
+
+    pub fn signing_limits(&self) -> SigningLimits {
+        self.limits
+    }
+
+    fn check_limits(&self) -> Result<(), ForwardSecureSignatureError> {
+        if let Some(limit) = self.limits.per_period {
+            if self.stats(self.t).signatures() >= limit {
+                return Err(ForwardSecureSignatureError::SigningLimitExceeded { limit, t: self.t });
+            }
+        }
+        if let Some(limit) = self.limits.total {
+            if self.stats.values().map(|s| s.signatures()).sum::<u64>() >= limit {
+                return Err(ForwardSecureSignatureError::SigningLimitExceeded { limit, t: self.t });
+            }
+        }
+        Ok(())
+    }
+
+    fn record_signature(&mut self, deterministic: bool) {
+        let stats = self.stats.entry(self.t).or_default();
+        if deterministic {
+            stats.deterministic += 1;
+        } else {
+            stats.randomized += 1;
+        }
+        stats.last_signed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .ok()
+            .map(|d| d.as_secs());
+        self.emit(SigManagerEvent::Signed {
+            t: self.t,
+            deterministic,
+        });
+    }
+
+    /// Sign `msg` with the key of the current time period. If the signing cache is enabled and
+    /// the same message was signed with the same `context` in the current time period, the
+    /// earlier signature is returned. Thus a retried request does not produce a second distinct
+    /// signature for the same message.
+    pub fn sign_idempotent<R: RngCore + CryptoRng>(
+        &mut self,
+        msg: &[u8],
+        context: &[u8],
+        gens: &GeneratorSet,
+        db: &dyn SigKeyDb,
+        rng: &mut R,
+    ) -> Result<Signature, ForwardSecureSignatureError> {
+        if self.is_punctured(msg) {
+            let err = ForwardSecureSignatureError::MessagePunctured { t: self.t };
+            return Err(self.rejected(err));
+        }
+        let t = self.t;
+        let key = (message_digest(msg), context.to_vec());
+        if let Some(cache) = &mut self.signing_cache {
+            if cache.t != t {
+                cache.sigs.clear();
+                cache.t = t;
+            }
+            if let Some(sig) = cache.sigs.get(&key) {
+                return Ok(sig.clone());
+            }
+        }
+        self.check_limits().map_err(|e| self.rejected(e))?;
+        let sig = Signature::new(msg, t, self.l, gens, db.get_key(t)?, rng)?;
+        if let Some(cache) = &mut self.signing_cache {
+            cache.sigs.insert(key, sig.clone());
+        }
+        self.record_signature(false);
+        Ok(sig)
     }
 
     pub fn has_key(t: u128, db: &dyn SigKeyDb) -> bool {
@@ -205,6 +753,8 @@ impl SigManager {
         db.get_key(t)
     }
 
+    /// Key of the current time period as stored, fails with `SigkeyNotFound` or `SigkeyCorrupted`
+    /// without touching `db`. See `get_current_key_repaired` for re-deriving the key.
     pub fn get_current_key<'a>(
         &self,
         db: &'a dyn SigKeyDb,
@@ -212,6 +762,25 @@ impl SigManager {
         db.get_key(self.t)
     }
 
+    /// Same as `get_current_key` but if the key is missing or corrupted, repairs `db` with
+    /// `repair_keys` first. Returns the key and what was repaired.
+    pub fn get_current_key_repaired<'a, R: RngCore + CryptoRng>(
+        &mut self,
+        gens: &GeneratorSet,
+        rng: &mut R,
+        db: &'a mut dyn SigKeyDb,
+    ) -> Result<(&'a Sigkey, KeyRepair), ForwardSecureSignatureError> {
+        let repair = match db.get_key(self.t) {
+            Ok(_) => KeyRepair::default(),
+            Err(ForwardSecureSignatureError::SigkeyNotFound { .. })
+            | Err(ForwardSecureSignatureError::SigkeyCorrupted { .. }) => {
+                self.repair_keys(gens, rng, db)?
+            }
+            Err(e) => return Err(e),
+        };
+        Ok((db.get_key(self.t)?, repair))
+    }
+
     /// Update time by 1
     pub fn simple_update<R: RngCore + CryptoRng>(
         &mut self,
@@ -219,8 +788,10 @@ impl SigManager {
         rng: &mut R,
         db: &mut dyn SigKeyDb,
     ) -> Result<u128, ForwardSecureSignatureError> {
+        self.repair_keys(gens, rng, db)?;
         let path = from_node_num_to_path(self.t, self.l)?;
         let path_len = path.len();
+        self.save_stats(db);
         let sk = self.get_current_key(db)?;
         // sk.1.len() + path_len == l+1
         debug_assert_eq!(self.l as usize + 1, sk.1.len() + path_len);
@@ -245,13 +816,13 @@ impl SigManager {
             let path_right_len = path_right.len();
             let node_num_right = path_to_node_num(&path_right, self.l)?;
 
-            let r = FieldElement::random_using_rng(rng);
+            let r = random_field_element(rng)?;
             // d * e_j^2
             let mut sk_right_prime_prime = vec![&d + (sk.1[1].double())];
             // h_0 * h_1^path[0] * h_2^path[1] * ... h_k^path[-1]
             let path_factor = calculate_path_factor(path_right, &gens)?;
             // d * e_j^2 * (h_0 * h_1^path[0] * h_2^path[1] * ... h_k^path[-1])^r
-            sk_right_prime_prime[0] += (&path_factor * &r);
+            sk_right_prime_prime[0] += &path_factor * &r;
 
             for i in 2..sk.1.len() {
                 let e = &sk.1[i] + (&gens.1[path_right_len + i] * &r);
@@ -272,6 +843,7 @@ impl SigManager {
             self.t = self.t + 1;
         }
         db.remove_key(removed_key_idx);
+        self.emit_update(removed_key_idx, vec![removed_key_idx]);
         Ok(removed_key_idx)
     }
 
@@ -303,6 +875,8 @@ impl SigManager {
             return Ok(vec![removed]);
         }
 
+        self.repair_keys(gens, rng, db)?;
+        self.save_stats(db);
         // Find key for t and all of t's successors
         let t_path = from_node_num_to_path(t, self.l)?;
         let successor_paths = node_successor_paths(t, self.l)?;
@@ -374,10 +948,92 @@ impl SigManager {
             db.remove_key(*n);
             removed.push(n.clone())
         }
+        let from = self.t;
         self.t = t;
+        self.emit_update(from, removed.clone());
         Ok(removed)
     }
 
+    /// Re-derive the keys for the current time period and its successors that are missing from
+    /// `db` or fail to load with `SigkeyCorrupted`, e.g. after a crash during an update, from an
+    /// ancestor key still in `db`. Keys not needed from the current time period on, such as the
+    /// ancestors, are then removed since they could sign for past time periods. Fails with
+    /// `SigkeyNotFound` if a missing key has no ancestor in `db`. Done at the start of every
+    /// update and by `get_current_key_repaired`.
+    pub fn repair_keys<R: RngCore + CryptoRng>(
+        &mut self,
+        gens: &GeneratorSet,
+        rng: &mut R,
+        db: &mut dyn SigKeyDb,
+    ) -> Result<KeyRepair, ForwardSecureSignatureError> {
+        let mut needed_paths = node_successor_paths(self.t, self.l)?;
+        needed_paths.push(from_node_num_to_path(self.t, self.l)?);
+        let mut needed = HashSet::new();
+        let mut missing = vec![];
+        let mut corrupted = vec![];
+        for path in needed_paths {
+            let n = path_to_node_num(&path, self.l)?;
+            needed.insert(n);
+            match db.get_key(n) {
+                Ok(_) => (),
+                Err(ForwardSecureSignatureError::SigkeyNotFound { .. }) => missing.push((n, path)),
+                Err(ForwardSecureSignatureError::SigkeyCorrupted { .. }) => {
+                    corrupted.push(n);
+                    missing.push((n, path))
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        let mut to_remove = db
+            .get_key_indices()
+            .difference(&needed)
+            .cloned()
+            .collect::<Vec<_>>();
+        if missing.is_empty() && to_remove.is_empty() {
+            return Ok(KeyRepair::default());
+        }
+
+        let mut keys = vec![];
+        if !missing.is_empty() {
+            for (n, path) in &missing {
+                // Highest ancestor in the db
+                let mut pred = None;
+                for len in 0..path.len() {
+                    let pred_n = path_to_node_num(&path[..len], self.l)?;
+                    if db.get_key(pred_n).is_ok() {
+                        pred = Some((pred_n, len));
+                        break;
+                    }
+                }
+                let (pred_n, pred_len) =
+                    pred.ok_or(ForwardSecureSignatureError::SigkeyNotFound { t: *n })?;
+                let pred_sk = db.get_key(pred_n)?;
+                keys.push((
+                    *n,
+                    Self::derive_key(path, pred_sk, pred_len, self.l, gens, rng)?,
+                ));
+            }
+        }
+        let derived = keys.iter().map(|(n, _)| *n).collect::<Vec<_>>();
+        for n in &corrupted {
+            db.remove_key(*n);
+        }
+        for (n, k) in keys {
+            db.insert_key(n, k);
+        }
+        for n in &to_remove {
+            db.remove_key(*n);
+        }
+        to_remove.sort_unstable();
+        let repair = KeyRepair {
+            derived,
+            corrupted,
+            removed: to_remove,
+        };
+        self.emit(SigManagerEvent::KeysRepaired(repair.clone()));
+        Ok(repair)
+    }
+
     /// Derive signing key denoted by path `key_path` using its predecessor node's signing key `pred_sk`
     fn derive_key<R: RngCore + CryptoRng>(
         key_path: &[u8],
@@ -388,7 +1044,7 @@ impl SigManager {
         rng: &mut R,
     ) -> Result<Sigkey, ForwardSecureSignatureError> {
         let key_path_len = key_path.len();
-        let r = FieldElement::random_using_rng(rng);
+        let r = random_field_element(rng)?;
 
         let c = pred_sk.0.clone();
         let mut d = pred_sk.1[0].clone();
@@ -400,7 +1056,7 @@ impl SigManager {
             }
         }
         let path_factor = calculate_path_factor(key_path.to_vec(), &gens)?;
-        d += (&path_factor * &r);
+        d += &path_factor * &r;
 
         let sk_t_prime = c + (&gens.0 * &r);
         let mut sk_t_prime_prime = vec![];
@@ -434,11 +1090,20 @@ pub trait SigKeyDb {
 
     /// Returns indices (time periods) for all present keys
     fn get_key_indices(&self) -> HashSet<u128>;
+
+    /// Returns the metadata entry `name`. Databases not supporting metadata return `None`.
+    fn get_metadata(&self, _name: &str) -> Option<&[u8]> {
+        None
+    }
+
+    /// Sets the metadata entry `name`. Ignored by databases not supporting metadata.
+    fn set_metadata(&mut self, _name: &str, _value: Vec<u8>) {}
 }
 
 /// An in-memory database for storing signing keys. Uses hashmap. Should only be used for testing.
 pub struct InMemorySigKeyDatabase {
     keys: HashMap<u128, Sigkey>,
+    metadata: HashMap<String, Vec<u8>>,
 }
 
 impl SigKeyDb for InMemorySigKeyDatabase {
@@ -466,12 +1131,23 @@ impl SigKeyDb for InMemorySigKeyDatabase {
     fn get_key_indices(&self) -> HashSet<u128> {
         self.keys.keys().map(|k| *k).collect()
     }
+
+    fn get_metadata(&self, name: &str) -> Option<&[u8]> {
+        self.metadata.get(name).map(|v| v.as_slice())
+    }
+
+    fn set_metadata(&mut self, name: &str, value: Vec<u8>) {
+        self.metadata.insert(name.to_string(), value);
+    }
 }
 
 impl InMemorySigKeyDatabase {
     pub fn new() -> Self {
         let keys = HashMap::<u128, Sigkey>::new();
-        Self { keys }
+        Self {
+            keys,
+            metadata: HashMap::new(),
+        }
     }
 }
 
@@ -483,7 +1159,9 @@ pub fn setup<'a, R: RngCore + CryptoRng>(
     rng: &mut R,
     db: &'a mut dyn SigKeyDb,
 ) -> Result<(GeneratorSet, Verkey, SigManager, ProofOfPossession), ForwardSecureSignatureError> {
-    let generators = GeneratorSet::new(T, prefix)?;
+    let generators = GeneratorSetBuilder::new(prefix)
+        .max_time_period(T)
+        .build()?;
     let (keypair, sigkeys) = Keypair::new(T, &generators, rng, db)?;
     Ok((generators, keypair.ver_key, sigkeys, keypair.pop))
 }
@@ -491,6 +1169,7 @@ pub fn setup<'a, R: RngCore + CryptoRng>(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::compression::VERKEY_GROUP_COMPRESSED_SIZE;
     use rand::rngs::ThreadRng;
     // For benchmarking
     use std::time::Instant;
@@ -520,6 +1199,427 @@ mod tests {
         assert!(Keypair::verify_pop(&PoP, &verkey, &gens.0))
     }
 
+    #[test]
+    fn test_verkey_from_bytes_unchecked() {
+        let mut rng = rand::thread_rng();
+        let mut db = InMemorySigKeyDatabase::new();
+        let (_, verkey, _, _) = setup::<ThreadRng>(7, "test_pixel", &mut rng, &mut db).unwrap();
+        let bytes = verkey.to_bytes();
+        let vk = Verkey::from_bytes_unchecked(&bytes).unwrap();
+        assert_eq!(vk.value, verkey.value);
+        assert!(Verkey::from_bytes_unchecked(&bytes[1..]).is_err());
+    }
+
+    #[test]
+    fn test_verkey_checked_and_compressed_bytes() {
+        let mut rng = rand::thread_rng();
+        let mut db = InMemorySigKeyDatabase::new();
+        let (_, verkey, _, _) = setup::<ThreadRng>(7, "test_pixel", &mut rng, &mut db).unwrap();
+        assert_eq!(
+            Verkey::from_bytes(&verkey.to_bytes()).unwrap().value,
+            verkey.value
+        );
+        let bytes = verkey.to_compressed_bytes();
+        assert_eq!(bytes.len(), VERKEY_GROUP_COMPRESSED_SIZE);
+        assert_eq!(
+            Verkey::from_compressed_bytes(&bytes).unwrap().value,
+            verkey.value
+        );
+        assert!(Verkey::from_compressed_bytes(&bytes[1..]).is_err());
+
+        // The identity is rejected by the checked decoders only
+        let identity = Verkey {
+            value: VerkeyGroup::identity(),
+        };
+        assert!(Verkey::from_bytes_unchecked(&identity.to_bytes()).is_ok());
+        assert!(Verkey::from_bytes(&identity.to_bytes()).is_err());
+        assert!(Verkey::from_compressed_bytes(&identity.to_compressed_bytes()).is_err());
+        let mut array = [0; VERKEY_GROUP_COMPRESSED_SIZE];
+        array.copy_from_slice(&identity.to_compressed_bytes());
+        assert!(Verkey::try_from(array).is_err());
+    }
+
+    #[test]
+    fn test_verkey_byte_array_conversion() {
+        let mut rng = rand::thread_rng();
+        let mut db = InMemorySigKeyDatabase::new();
+        let (_, verkey, _, _) = setup::<ThreadRng>(7, "test_pixel", &mut rng, &mut db).unwrap();
+        let bytes: [u8; VERKEY_GROUP_COMPRESSED_SIZE] = (&verkey).into();
+        assert_eq!(bytes.to_vec(), verkey.to_compressed_bytes());
+        let vk = Verkey::try_from(bytes).unwrap();
+        assert_eq!(vk.value, verkey.value);
+        let bytes_1: [u8; VERKEY_GROUP_COMPRESSED_SIZE] = vk.into();
+        assert_eq!(bytes, bytes_1);
+    }
+
+    #[test]
+    fn test_verkey_fingerprint() {
+        let mut rng = rand::thread_rng();
+        let mut db = InMemorySigKeyDatabase::new();
+        let (_, verkey, _, _) = setup::<ThreadRng>(7, "test_pixel", &mut rng, &mut db).unwrap();
+        let fp = verkey.fingerprint();
+        let vk = Verkey::from_bytes(&verkey.to_bytes()).unwrap();
+        assert_eq!(fp, vk.fingerprint());
+        let s = fp.to_string();
+        assert_eq!(s.len(), 2 * VERKEY_FINGERPRINT_SIZE);
+        assert!(s
+            .chars()
+            .all(|c| c.is_ascii_hexdigit() && !c.is_ascii_uppercase()));
+
+        let (_, verkey_1, _, _) = setup::<ThreadRng>(7, "test_pixel", &mut rng, &mut db).unwrap();
+        assert_ne!(fp, verkey_1.fingerprint());
+    }
+
+    #[test]
+    fn test_signing_cache() {
+        let mut rng = rand::thread_rng();
+        let mut db = InMemorySigKeyDatabase::new();
+        let (gens, verkey, mut manager, _) =
+            setup::<ThreadRng>(7, "test_pixel", &mut rng, &mut db).unwrap();
+        let msg = "Hello".as_bytes();
+
+        // Without the cache each request creates a new signature
+        let sig_1 = manager
+            .sign_idempotent(msg, b"ctx", &gens, &db, &mut rng)
+            .unwrap();
+        let sig_2 = manager
+            .sign_idempotent(msg, b"ctx", &gens, &db, &mut rng)
+            .unwrap();
+        assert_ne!(sig_1, sig_2);
+
+        manager.enable_signing_cache();
+        let sig_1 = manager
+            .sign_idempotent(msg, b"ctx", &gens, &db, &mut rng)
+            .unwrap();
+        let sig_2 = manager
+            .sign_idempotent(msg, b"ctx", &gens, &db, &mut rng)
+            .unwrap();
+        assert_eq!(sig_1, sig_2);
+        let sig_3 = manager
+            .sign_idempotent(msg, b"other", &gens, &db, &mut rng)
+            .unwrap();
+        assert_ne!(sig_1, sig_3);
+        assert!(sig_3.verify(msg, 1, manager.l(), &gens, &verkey).unwrap());
+
+        // After the update, the cached signature of the old time period is not returned
+        manager.simple_update(&gens, &mut rng, &mut db).unwrap();
+        let sig_4 = manager
+            .sign_idempotent(msg, b"ctx", &gens, &db, &mut rng)
+            .unwrap();
+        assert_ne!(sig_1, sig_4);
+        assert!(sig_4.verify(msg, 2, manager.l(), &gens, &verkey).unwrap());
+    }
+
+    #[test]
+    fn test_puncture() {
+        let mut rng = rand::thread_rng();
+        let mut db = InMemorySigKeyDatabase::new();
+        let (gens, verkey, mut manager, _) =
+            setup::<ThreadRng>(7, "test_pixel", &mut rng, &mut db).unwrap();
+        let msg = "Hello".as_bytes();
+        let sig = manager.sign(msg, &gens, &db, &mut rng).unwrap();
+        assert!(sig.verify(msg, 1, manager.l(), &gens, &verkey).unwrap());
+
+        manager.puncture_message(msg);
+        assert!(manager.is_punctured(msg));
+        assert!(manager.sign(msg, &gens, &db, &mut rng).is_err());
+        manager.enable_signing_cache();
+        assert!(manager
+            .sign_idempotent(msg, b"ctx", &gens, &db, &mut rng)
+            .is_err());
+        // Other messages can still be signed
+        assert!(manager.sign(b"Hi", &gens, &db, &mut rng).is_ok());
+        let mut digest = [0; 32];
+        digest.copy_from_slice(&Sha3_256::digest(b"Hi"));
+        manager.puncture(digest);
+        assert!(manager.sign(b"Hi", &gens, &db, &mut rng).is_err());
+
+        // Punctures only apply to the time period they were made in
+        manager.simple_update(&gens, &mut rng, &mut db).unwrap();
+        assert!(!manager.is_punctured(msg));
+        let sig = manager.sign(msg, &gens, &db, &mut rng).unwrap();
+        assert!(sig.verify(msg, 2, manager.l(), &gens, &verkey).unwrap());
+    }
+
+    #[test]
+    fn test_signing_stats() {
+        let mut rng = rand::thread_rng();
+        let mut db = InMemorySigKeyDatabase::new();
+        let (gens, _, mut manager, _) =
+            setup::<ThreadRng>(7, "test_pixel", &mut rng, &mut db).unwrap();
+        assert_eq!(manager.stats(1), PeriodStats::default());
+        manager.sign(b"a", &gens, &db, &mut rng).unwrap();
+        manager.sign(b"b", &gens, &db, &mut rng).unwrap();
+        manager.sign_deterministic(b"c", &gens, &db).unwrap();
+        manager.enable_signing_cache();
+        manager
+            .sign_idempotent(b"d", b"ctx", &gens, &db, &mut rng)
+            .unwrap();
+        // Served from the cache so not counted
+        manager
+            .sign_idempotent(b"d", b"ctx", &gens, &db, &mut rng)
+            .unwrap();
+        let stats = manager.stats(1);
+        assert_eq!(stats.randomized, 3);
+        assert_eq!(stats.deterministic, 1);
+        assert_eq!(stats.signatures(), 4);
+        assert!(stats.last_signed.is_some());
+
+        // Statistics are saved on update
+        assert!(db.get_metadata(SIGNING_STATS_METADATA).is_none());
+        manager
+            .fast_forward_update(3, &gens, &mut rng, &mut db)
+            .unwrap();
+        manager.sign(b"a", &gens, &db, &mut rng).unwrap();
+        assert_eq!(manager.stats(2), PeriodStats::default());
+        assert_eq!(manager.stats(3).randomized, 1);
+        let mut loaded = SigManager::load(7, manager.l(), 3).unwrap();
+        loaded.load_stats(&db).unwrap();
+        assert_eq!(loaded.all_stats().len(), 1);
+        assert_eq!(loaded.stats(1), stats);
+        manager.save_stats(&mut db);
+        loaded.load_stats(&db).unwrap();
+        assert_eq!(loaded.all_stats(), manager.all_stats());
+
+        db.set_metadata(SIGNING_STATS_METADATA, vec![0, 1]);
+        assert!(loaded.load_stats(&db).is_err());
+    }
+
+    #[test]
+    fn test_signing_limits() {
+        let mut rng = rand::thread_rng();
+        let mut db = InMemorySigKeyDatabase::new();
+        let (gens, _, mut manager, _) =
+            setup::<ThreadRng>(7, "test_pixel", &mut rng, &mut db).unwrap();
+        manager.set_signing_limits(SigningLimits {
+            per_period: Some(2),
+            total: Some(3),
+        });
+        manager.enable_signing_cache();
+        manager.sign(b"a", &gens, &db, &mut rng).unwrap();
+        manager
+            .sign_idempotent(b"b", b"ctx", &gens, &db, &mut rng)
+            .unwrap();
+        assert!(manager.sign(b"c", &gens, &db, &mut rng).is_err());
+        assert!(manager.sign_hedged(b"c", &gens, &db, &mut rng).is_err());
+        assert!(manager.sign_deterministic(b"c", &gens, &db).is_err());
+        // A cached signature does not count against the limit
+        assert!(manager
+            .sign_idempotent(b"b", b"ctx", &gens, &db, &mut rng)
+            .is_ok());
+        assert!(manager
+            .sign_idempotent(b"c", b"ctx", &gens, &db, &mut rng)
+            .is_err());
+
+        // The per period limit resets on update but the total limit does not
+        manager.simple_update(&gens, &mut rng, &mut db).unwrap();
+        manager.sign(b"c", &gens, &db, &mut rng).unwrap();
+        assert!(manager.sign(b"d", &gens, &db, &mut rng).is_err());
+        manager.set_signing_limits(SigningLimits::default());
+        assert!(manager.sign(b"d", &gens, &db, &mut rng).is_ok());
+    }
+
+    #[test]
+    fn test_sigkey_matches_verkey() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let l = calculate_l(T).unwrap();
+        let mut db = InMemorySigKeyDatabase::new();
+        let (gens, verkey, mut manager, _) =
+            setup::<ThreadRng>(T, "test_pixel", &mut rng, &mut db).unwrap();
+        let mut other_db = InMemorySigKeyDatabase::new();
+        let (_, other_verkey, _, _) =
+            setup::<ThreadRng>(T, "test_pixel", &mut rng, &mut other_db).unwrap();
+
+        let sk = manager.get_current_key(&db).unwrap();
+        assert!(sk.matches_verkey(&verkey, &gens, 1, l).unwrap());
+        assert!(!sk.matches_verkey(&other_verkey, &gens, 1, l).unwrap());
+        // Key used for another time period
+        assert!(!sk.matches_verkey(&verkey, &gens, 2, l).unwrap());
+
+        manager.simple_update(&gens, &mut rng, &mut db).unwrap();
+        let sk = manager.get_current_key(&db).unwrap();
+        assert!(sk.matches_verkey(&verkey, &gens, 2, l).unwrap());
+        let mut corrupted = sk.clone();
+        corrupted.1[0] = corrupted.1[0].double();
+        assert!(!corrupted.matches_verkey(&verkey, &gens, 2, l).unwrap());
+        corrupted.1.pop();
+        assert!(!corrupted.matches_verkey(&verkey, &gens, 2, l).unwrap());
+    }
+
+    #[test]
+    fn test_repair_keys() {
+        let mut rng = rand::thread_rng();
+        let T = 15;
+        let l = calculate_l(T).unwrap();
+        let mut db = InMemorySigKeyDatabase::new();
+        let (gens, verkey, mut manager, _) =
+            setup::<ThreadRng>(T, "test_pixel", &mut rng, &mut db).unwrap();
+        let root = SigManager::get_key(1, &db).unwrap().clone();
+        assert!(manager
+            .repair_keys(&gens, &mut rng, &mut db)
+            .unwrap()
+            .is_empty());
+
+        manager.simple_update(&gens, &mut rng, &mut db).unwrap();
+        assert_eq!(db.get_key_indices(), HashSet::from([2, 9]));
+        // Key of the right child lost, the root is still around
+        db.remove_key(9);
+        assert!(manager.repair_keys(&gens, &mut rng, &mut db).is_err());
+        db.insert_key(1, root.clone());
+        let events = manager.subscribe();
+        let repair = manager.repair_keys(&gens, &mut rng, &mut db).unwrap();
+        assert_eq!(
+            repair,
+            KeyRepair {
+                derived: vec![9],
+                corrupted: vec![],
+                removed: vec![1]
+            }
+        );
+        assert_eq!(
+            events.try_recv().unwrap(),
+            SigManagerEvent::KeysRepaired(repair)
+        );
+        assert_eq!(db.get_key_indices(), HashSet::from([2, 9]));
+        let sk = SigManager::get_key(9, &db).unwrap();
+        assert!(sk.matches_verkey(&verkey, &gens, 9, l).unwrap());
+
+        // Repaired during the update
+        db.remove_key(9);
+        db.insert_key(1, root);
+        manager
+            .fast_forward_update(9, &gens, &mut rng, &mut db)
+            .unwrap();
+        assert_eq!(db.get_key_indices(), HashSet::from([9]));
+        let sk = manager.get_current_key(&db).unwrap();
+        assert!(sk.matches_verkey(&verkey, &gens, 9, l).unwrap());
+    }
+
+    /// Fails loading the keys in `corrupted` like a database checking `SealedSigkey`s would
+    struct CorruptingDb {
+        db: InMemorySigKeyDatabase,
+        corrupted: HashSet<u128>,
+    }
+
+    impl SigKeyDb for CorruptingDb {
+        fn insert_key(&mut self, t: u128, sig_key: Sigkey) {
+            self.corrupted.remove(&t);
+            self.db.insert_key(t, sig_key)
+        }
+
+        fn remove_key(&mut self, t: u128) {
+            self.corrupted.remove(&t);
+            self.db.remove_key(t)
+        }
+
+        fn has_key(&self, t: u128) -> bool {
+            self.db.has_key(t)
+        }
+
+        fn get_key(&self, t: u128) -> Result<&Sigkey, ForwardSecureSignatureError> {
+            if self.corrupted.contains(&t) {
+                return Err(ForwardSecureSignatureError::SigkeyCorrupted { t });
+            }
+            self.db.get_key(t)
+        }
+
+        fn get_key_indices(&self) -> HashSet<u128> {
+            self.db.get_key_indices()
+        }
+    }
+
+    #[test]
+    fn test_get_current_key_repaired() {
+        let mut rng = rand::thread_rng();
+        let T = 15;
+        let l = calculate_l(T).unwrap();
+        let mut db = CorruptingDb {
+            db: InMemorySigKeyDatabase::new(),
+            corrupted: HashSet::new(),
+        };
+        let (gens, verkey, mut manager, _) =
+            setup::<ThreadRng>(T, "test_pixel", &mut rng, &mut db).unwrap();
+        let root = SigManager::get_key(1, &db).unwrap().clone();
+        let (_, repair) = manager
+            .get_current_key_repaired(&gens, &mut rng, &mut db)
+            .unwrap();
+        assert!(repair.is_empty());
+
+        manager.simple_update(&gens, &mut rng, &mut db).unwrap();
+        db.corrupted.insert(2);
+        match manager.get_current_key(&db) {
+            Err(ForwardSecureSignatureError::SigkeyCorrupted { t }) => assert_eq!(t, 2),
+            _ => panic!("corrupted key returned"),
+        }
+        // Nothing to derive the key from
+        assert!(manager
+            .get_current_key_repaired(&gens, &mut rng, &mut db)
+            .is_err());
+
+        db.insert_key(1, root);
+        let (sk, repair) = manager
+            .get_current_key_repaired(&gens, &mut rng, &mut db)
+            .unwrap();
+        assert!(sk.matches_verkey(&verkey, &gens, 2, l).unwrap());
+        assert_eq!(
+            repair,
+            KeyRepair {
+                derived: vec![2],
+                corrupted: vec![2],
+                removed: vec![1]
+            }
+        );
+        assert_eq!(db.get_key_indices(), HashSet::from([2, 9]));
+    }
+
+    #[test]
+    fn test_sig_manager_events() {
+        let mut rng = rand::thread_rng();
+        let mut db = InMemorySigKeyDatabase::new();
+        let (gens, _, mut manager, _) =
+            setup::<ThreadRng>(7, "test_pixel", &mut rng, &mut db).unwrap();
+        let events = manager.subscribe();
+        let dropped = manager.subscribe();
+        drop(dropped);
+
+        manager.sign(b"a", &gens, &db, &mut rng).unwrap();
+        manager.sign_deterministic(b"a", &gens, &db).unwrap();
+        manager.puncture_message(b"b");
+        assert!(manager.sign(b"b", &gens, &db, &mut rng).is_err());
+        manager.simple_update(&gens, &mut rng, &mut db).unwrap();
+        let removed = manager
+            .fast_forward_update(7, &gens, &mut rng, &mut db)
+            .unwrap();
+        assert_eq!(manager.subscribers.len(), 1);
+
+        let received = events.try_iter().collect::<Vec<_>>();
+        assert_eq!(received.len(), 8);
+        assert_eq!(
+            received[0],
+            SigManagerEvent::Signed {
+                t: 1,
+                deterministic: false
+            }
+        );
+        assert_eq!(
+            received[1],
+            SigManagerEvent::Signed {
+                t: 1,
+                deterministic: true
+            }
+        );
+        assert!(matches!(
+            received[2],
+            SigManagerEvent::PolicyRejected { t: 1, .. }
+        ));
+        assert_eq!(received[3], SigManagerEvent::KeyUpdated { from: 1, to: 2 });
+        assert_eq!(received[4], SigManagerEvent::KeysDeleted { nodes: vec![1] });
+        assert_eq!(received[5], SigManagerEvent::KeyUpdated { from: 2, to: 7 });
+        assert_eq!(received[6], SigManagerEvent::KeysDeleted { nodes: removed });
+        assert_eq!(received[7], SigManagerEvent::Exhausted { t: 7 });
+    }
+
     #[test]
     fn test_setup_with_less_number_of_genertors() {
         let mut rng = rand::thread_rng();
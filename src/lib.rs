@@ -13,11 +13,15 @@ compile_error!("features `VerkeyG1` and `VerkeyG2` are mutually exclusive");
 #[cfg(feature = "VerkeyG2")]
 pub type SignatureGroup = amcl_wrapper::group_elem_g1::G1;
 #[cfg(feature = "VerkeyG2")]
-pub type SignatureGroupVec = amcl_wrapper::group_elem_g1::G1Vector;
+pub(crate) type SignatureGroupVec = amcl_wrapper::group_elem_g1::G1Vector;
 #[cfg(feature = "VerkeyG2")]
 pub type VerkeyGroup = amcl_wrapper::group_elem_g2::G2;
 #[cfg(feature = "VerkeyG2")]
-pub type VerkeyGroupVec = amcl_wrapper::group_elem_g2::G2Vector;
+pub(crate) type VerkeyGroupVec = amcl_wrapper::group_elem_g2::G2Vector;
+#[cfg(feature = "VerkeyG2")]
+pub const SIGNATURE_GROUP_SIZE: usize = amcl_wrapper::constants::GroupG1_SIZE;
+#[cfg(feature = "VerkeyG2")]
+pub const VERKEY_GROUP_SIZE: usize = amcl_wrapper::constants::GroupG2_SIZE;
 #[cfg(feature = "VerkeyG2")]
 pub fn ate_2_pairing(
     g1: &SignatureGroup,
@@ -31,16 +35,28 @@ pub fn ate_2_pairing(
 pub fn ate_multi_pairing(elems: Vec<(&SignatureGroup, &VerkeyGroup)>) -> GT {
     GT::ate_multi_pairing(elems)
 }
+/// Multi-pairing over slices of equal length, does not allocate. Panics if the lengths differ.
+#[cfg(feature = "VerkeyG2")]
+pub(crate) fn ate_multi_pairing_slices(
+    sig_elems: &[SignatureGroup],
+    vk_elems: &[VerkeyGroup],
+) -> GT {
+    GT::inner_product(sig_elems, vk_elems).unwrap()
+}
 
 // For feature VerkeyG1, verification key is in G1 and all but one element of signature are in G2
 #[cfg(feature = "VerkeyG1")]
 pub type SignatureGroup = amcl_wrapper::group_elem_g2::G2;
 #[cfg(feature = "VerkeyG1")]
-pub type SignatureGroupVec = amcl_wrapper::group_elem_g2::G2Vector;
+pub(crate) type SignatureGroupVec = amcl_wrapper::group_elem_g2::G2Vector;
 #[cfg(feature = "VerkeyG1")]
 pub type VerkeyGroup = amcl_wrapper::group_elem_g1::G1;
 #[cfg(feature = "VerkeyG1")]
-pub type VerkeyGroupVec = amcl_wrapper::group_elem_g1::G1Vector;
+pub(crate) type VerkeyGroupVec = amcl_wrapper::group_elem_g1::G1Vector;
+#[cfg(feature = "VerkeyG1")]
+pub const SIGNATURE_GROUP_SIZE: usize = amcl_wrapper::constants::GroupG2_SIZE;
+#[cfg(feature = "VerkeyG1")]
+pub const VERKEY_GROUP_SIZE: usize = amcl_wrapper::constants::GroupG1_SIZE;
 #[cfg(feature = "VerkeyG1")]
 pub fn ate_2_pairing(
     g1: &SignatureGroup,
@@ -59,19 +75,92 @@ pub fn ate_multi_pairing(elems: Vec<(&SignatureGroup, &VerkeyGroup)>) -> GT {
             .collect::<Vec<(&VerkeyGroup, &SignatureGroup)>>(),
     )
 }
+/// Multi-pairing over slices of equal length, does not allocate. Panics if the lengths differ.
+#[cfg(feature = "VerkeyG1")]
+pub(crate) fn ate_multi_pairing_slices(
+    sig_elems: &[SignatureGroup],
+    vk_elems: &[VerkeyGroup],
+) -> GT {
+    GT::inner_product(vk_elems, sig_elems).unwrap()
+}
 
 #[macro_use]
 extern crate failure;
 
+#[cfg(feature = "serde")]
 extern crate serde;
+#[cfg(feature = "serde")]
 #[macro_use]
 extern crate serde_derive;
 
+pub mod aggregator;
+pub mod beacon;
+pub mod bech32;
+pub mod bls;
+#[cfg(feature = "borsh")]
+mod borsh_impls;
+pub mod capability;
+pub mod cbor;
+pub mod ceremony;
+pub mod certificate;
+pub mod chunked;
+pub mod compression;
+#[cfg(feature = "cose")]
+pub mod cose;
+pub mod der;
+pub mod diagnostics;
+pub mod domain;
+pub mod drbg;
+pub mod entropy;
+pub mod envelope;
+pub mod epoch;
 pub mod errors;
+pub mod evm;
+pub mod facade;
+pub mod fixed_depth;
+pub mod half_aggregate;
+pub mod journal;
+#[cfg(feature = "jws")]
+pub mod jws;
+pub mod key_cache;
+pub mod key_transcript;
+pub mod key_wrap;
 pub mod keys;
+pub mod mapped_generators;
+pub mod merkle;
+pub mod message_hash;
+pub mod multibase;
+pub mod network;
+pub mod params;
+pub mod pem;
+pub mod period_cache;
+pub mod planner;
+pub mod pok_sig;
+pub mod prelude;
+pub mod primitives;
+#[cfg(feature = "proto")]
+pub mod proto;
+pub mod receipt;
+pub mod recovery;
+pub mod registry;
+pub mod resharing;
+pub mod result_cache;
+pub mod revocation;
+#[cfg(feature = "danger-serialize-secrets")]
+mod secret_serde;
+pub mod session;
 pub mod signature;
+pub mod ssz;
+pub mod staged;
+pub mod testing;
+pub mod text_encoding;
 pub mod threshold_sig;
+pub mod transition;
 pub mod util;
+pub mod verifiable_encryption;
+pub mod watch_only;
+pub mod watchdog;
+pub mod wire;
 
 // TODO: Add a high level object that orchestrates key update and signing. Like if the signing has to
 // be done for t=x and current time in SigkeyManager is y<x, it should update time to t=x.
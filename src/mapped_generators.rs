@@ -0,0 +1,142 @@
+use std::fs::File;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use amcl_wrapper::group_elem::GroupElement;
+use memmap2::Mmap;
+
+use crate::errors::ForwardSecureSignatureError;
+use crate::util::{GeneratorSet, GeneratorSource};
+use crate::{SignatureGroup, VerkeyGroup, SIGNATURE_GROUP_SIZE, VERKEY_GROUP_SIZE};
+
+/// A serialized `GeneratorSet` (as output by `GeneratorSet::to_bytes`) that is memory mapped
+/// rather than read. Generators are deserialized only when first accessed so processes mapping
+/// the same file share the page cache instead of each holding all the points. Implements
+/// `GeneratorSource` so signatures can be verified against it without a `GeneratorSet`.
+pub struct MappedGeneratorSet {
+    mmap: Mmap,
+    /// Where the serialized generators start in the file
+    offset: usize,
+    g: VerkeyGroup,
+    h: Vec<OnceLock<SignatureGroup>>,
+}
+
+impl MappedGeneratorSet {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, ForwardSecureSignatureError> {
+        let file = File::open(path)?;
+        // The file must not be modified while mapped. Generator files are written once and
+        // only read afterwards.
+        let mmap = unsafe { Mmap::map(&file)? };
+        Self::from_mmap(mmap, 0)
+    }
+
+    /// Generators serialized in `mmap` from `offset` on
+    pub(crate) fn from_mmap(
+        mmap: Mmap,
+        offset: usize,
+    ) -> Result<Self, ForwardSecureSignatureError> {
+        let n = GeneratorSet::num_generators_in_bytes(mmap.len() - offset)?;
+        let g = VerkeyGroup::from_bytes(&mmap[offset..offset + VERKEY_GROUP_SIZE])?;
+        Ok(Self {
+            mmap,
+            offset,
+            g,
+            h: (0..n).map(|_| OnceLock::new()).collect(),
+        })
+    }
+
+    /// Generator in the verkey group
+    pub fn g(&self) -> &VerkeyGroup {
+        &self.g
+    }
+
+    /// `i`th generator in the signature group, deserialized on first access
+    pub fn h(&self, i: usize) -> Result<&SignatureGroup, ForwardSecureSignatureError> {
+        if i >= self.h.len() {
+            return Err(ForwardSecureSignatureError::NotEnoughGenerators { n: i + 1 });
+        }
+        if let Some(h) = self.h[i].get() {
+            return Ok(h);
+        }
+        let start = self.offset + VERKEY_GROUP_SIZE + i * SIGNATURE_GROUP_SIZE;
+        let h = SignatureGroup::from_bytes(&self.mmap[start..start + SIGNATURE_GROUP_SIZE])?;
+        Ok(self.h[i].get_or_init(|| h))
+    }
+
+    /// Number of generators in the signature group
+    pub fn len(&self) -> usize {
+        self.h.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.h.is_empty()
+    }
+
+    /// Deserialize all generators to get a `GeneratorSet`
+    pub fn to_generator_set(&self) -> Result<GeneratorSet, ForwardSecureSignatureError> {
+        let mut h = Vec::with_capacity(self.len());
+        for i in 0..self.len() {
+            h.push(self.h(i)?.clone());
+        }
+        Ok(GeneratorSet(self.g.clone(), h))
+    }
+}
+
+impl GeneratorSource for MappedGeneratorSet {
+    fn g(&self) -> &VerkeyGroup {
+        &self.g
+    }
+
+    fn h(&self, i: usize) -> Result<&SignatureGroup, ForwardSecureSignatureError> {
+        MappedGeneratorSet::h(self, i)
+    }
+
+    fn num_h(&self) -> usize {
+        self.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::facade::Signer;
+    use crate::keys::InMemorySigKeyDatabase;
+    use crate::util::calculate_l;
+    use std::fs;
+
+    #[test]
+    fn test_mapped_generator_set() {
+        let gens = GeneratorSet::new(15, "test_pixel").unwrap();
+        let path = std::env::temp_dir().join(format!("fss_gens_{}", std::process::id()));
+        fs::write(&path, gens.to_bytes()).unwrap();
+
+        let mapped = MappedGeneratorSet::open(&path).unwrap();
+        assert_eq!(mapped.len(), gens.1.len());
+        assert_eq!(mapped.g(), &gens.0);
+        assert_eq!(mapped.h(3).unwrap(), &gens.1[3]);
+        // Cached on second access
+        assert_eq!(mapped.h(3).unwrap(), &gens.1[3]);
+        assert!(mapped.h(gens.1.len()).is_err());
+
+        let gens_1 = mapped.to_generator_set().unwrap();
+        assert_eq!(gens_1.1, gens.1);
+
+        // Verification reads only the generators it needs from the mapped file
+        let mut rng = rand::thread_rng();
+        let mapped = MappedGeneratorSet::open(&path).unwrap();
+        let (mut signer, _) =
+            Signer::new(15, gens.clone(), InMemorySigKeyDatabase::new(), &mut rng).unwrap();
+        let sig = signer.sign(b"Hello", &mut rng).unwrap();
+        let l = calculate_l(15).unwrap();
+        assert!(sig
+            .verify(b"Hello", 1, l, &mapped, signer.verkey())
+            .unwrap());
+        assert!(!sig.verify(b"Hi", 1, l, &mapped, signer.verkey()).unwrap());
+        assert!(mapped.h.iter().any(|h| h.get().is_none()));
+
+        // Truncated file
+        fs::write(&path, &gens.to_bytes()[1..]).unwrap();
+        assert!(MappedGeneratorSet::open(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
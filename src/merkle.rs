@@ -0,0 +1,506 @@
+//! Commitment to the verkeys of a committee as a Merkle root so that an aggregate signature can
+//! be verified with only the root, a bitmap of signers and membership proofs for the signers'
+//! verkeys, e.g. by an on-chain light client.
+//!
+//! Leaves are `H(0x00 || DST || position || verkey)` and inner nodes `H(0x01 || left || right)`
+//! with SHA3-256. A node without a sibling at the end of a level is moved up unchanged. Since
+//! verkeys are aggregated without checking proofs of possession, only verkeys whose proof of
+//! possession was verified must be committed to.
+//!
+//! The same construction commits to a batch of messages, with leaves
+//! `H(0x00 || DST || position || H(message))`, so that a single signature on the root covers
+//! every message of the batch. Each message then comes with a `BatchReceipt` holding its
+//! inclusion proof and the shared signature.
+
+use rand::{CryptoRng, RngCore};
+use sha3::{Digest, Sha3_256};
+
+use crate::errors::ForwardSecureSignatureError;
+use crate::keys::{SigKeyDb, SigManager, Verkey};
+use crate::signature::Signature;
+use crate::util::GeneratorSet;
+
+const LEAF_DST: &[u8] = b"PIXEL-COMMITTEE-MERKLE-V1";
+const BATCH_LEAF_DST: &[u8] = b"PIXEL-BATCH-MERKLE-V1";
+const BATCH_ROOT_DST: &[u8] = b"PIXEL-BATCH-ROOT-V1";
+
+pub type MerkleHash = [u8; 32];
+
+/// Root of the Merkle tree of a committee along with the committee size
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CommitteeRoot {
+    pub root: MerkleHash,
+    pub size: usize,
+}
+
+/// Merkle tree over the verkeys of a committee. Position `i` holds the `i`th verkey.
+pub struct CommitteeMerkleTree {
+    /// Hashes of every level, leaves first
+    levels: Vec<Vec<MerkleHash>>,
+}
+
+impl CommitteeMerkleTree {
+    pub fn new(verkeys: &[Verkey]) -> Result<Self, ForwardSecureSignatureError> {
+        if verkeys.is_empty() {
+            return Err(ForwardSecureSignatureError::InvalidQuorum {
+                quorum: 0,
+                committee: 0,
+            });
+        }
+        let leaves = verkeys
+            .iter()
+            .enumerate()
+            .map(|(i, vk)| leaf_hash(i, vk))
+            .collect();
+        Ok(Self {
+            levels: build_levels(leaves),
+        })
+    }
+
+    pub fn committee_root(&self) -> CommitteeRoot {
+        CommitteeRoot {
+            root: self.levels[self.levels.len() - 1][0],
+            size: self.levels[0].len(),
+        }
+    }
+
+    /// Membership proof for the verkey at `position`
+    pub fn proof(&self, position: usize) -> Result<MembershipProof, ForwardSecureSignatureError> {
+        if position >= self.levels[0].len() {
+            return Err(ForwardSecureSignatureError::UnknownCommitteeMember { index: position });
+        }
+        Ok(path(&self.levels, position))
+    }
+}
+
+/// Proof that a leaf, such as a verkey of a committee, is at `position` in a Merkle tree
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MembershipProof {
+    pub position: usize,
+    /// Hashes of the siblings on the path to the root, leaf level first
+    pub siblings: Vec<MerkleHash>,
+}
+
+impl MembershipProof {
+    pub fn verify(&self, verkey: &Verkey, committee: &CommitteeRoot) -> bool {
+        self.root(leaf_hash(self.position, verkey), committee.size) == Some(committee.root)
+    }
+
+    /// Root of a tree of `size` leaves with `leaf` at the proof's position. Returns `None` if
+    /// the number of siblings does not match.
+    fn root(&self, leaf: MerkleHash, size: usize) -> Option<MerkleHash> {
+        if self.position >= size {
+            return None;
+        }
+        let mut hash = leaf;
+        let mut siblings = self.siblings.iter();
+        let mut idx = self.position;
+        let mut width = size;
+        while width > 1 {
+            if idx % 2 == 1 {
+                hash = node_hash(siblings.next()?, &hash);
+            } else if idx + 1 < width {
+                hash = node_hash(&hash, siblings.next()?);
+            }
+            idx /= 2;
+            width = width.div_ceil(2);
+        }
+        match siblings.next() {
+            None => Some(hash),
+            Some(_) => None,
+        }
+    }
+}
+
+/// Bitmap with bit `i` (least significant bit first) set if the member at position `i` signed.
+/// Fails if a position is not below the committee size.
+pub fn signer_bitmap(
+    positions: &[usize],
+    size: usize,
+) -> Result<Vec<u8>, ForwardSecureSignatureError> {
+    let mut bitmap = vec![0; size.div_ceil(8)];
+    for p in positions {
+        if *p >= size {
+            return Err(ForwardSecureSignatureError::InvalidSignerBitmap {
+                msg: format!("position {} in a committee of {}", p, size),
+            });
+        }
+        bitmap[p / 8] |= 1 << (p % 8);
+    }
+    Ok(bitmap)
+}
+
+/// Aggregated signature as consumed by a light verifier holding only a `CommitteeRoot`
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MerkleAggregate {
+    pub signature: Signature,
+    pub bitmap: Vec<u8>,
+    /// Verkey and membership proof of every signer, in increasing order of position
+    pub signers: Vec<(Verkey, MembershipProof)>,
+}
+
+impl MerkleAggregate {
+    /// Create the aggregate of `sigs` of members at the given positions
+    pub fn new(
+        tree: &CommitteeMerkleTree,
+        sigs: Vec<(usize, Verkey, Signature)>,
+    ) -> Result<Self, ForwardSecureSignatureError> {
+        let mut sigs = sigs;
+        sigs.sort_by_key(|(p, _, _)| *p);
+        for w in sigs.windows(2) {
+            if w[0].0 == w[1].0 {
+                return Err(ForwardSecureSignatureError::DuplicateShare { index: w[0].0 });
+            }
+        }
+        let size = tree.committee_root().size;
+        let positions = sigs.iter().map(|(p, _, _)| *p).collect::<Vec<_>>();
+        let mut signers = vec![];
+        for (p, vk, _) in &sigs {
+            signers.push((vk.clone(), tree.proof(*p)?));
+        }
+        Ok(Self {
+            signature: Signature::aggregate(sigs.iter().map(|(_, _, s)| s).collect()),
+            bitmap: signer_bitmap(&positions, size)?,
+            signers,
+        })
+    }
+
+    /// Positions set in the bitmap. Fails if the bitmap has the wrong length or bits set
+    /// past the committee size.
+    pub fn positions(&self, size: usize) -> Result<Vec<usize>, ForwardSecureSignatureError> {
+        if self.bitmap.len() != size.div_ceil(8) {
+            return Err(ForwardSecureSignatureError::InvalidSignerBitmap {
+                msg: format!("expected {} bytes", size.div_ceil(8)),
+            });
+        }
+        let positions = (0..self.bitmap.len() * 8)
+            .filter(|i| self.bitmap[i / 8] & (1 << (i % 8)) != 0)
+            .collect::<Vec<_>>();
+        if positions.last().is_some_and(|p| *p >= size) {
+            return Err(ForwardSecureSignatureError::InvalidSignerBitmap {
+                msg: String::from("bit set past the committee size"),
+            });
+        }
+        Ok(positions)
+    }
+
+    /// Verify the aggregate given only the root of the committee. Fails if the bitmap is
+    /// malformed or does not match the signers.
+    pub fn verify(
+        &self,
+        msg: &[u8],
+        t: u128,
+        l: u8,
+        committee: &CommitteeRoot,
+        gens: &GeneratorSet,
+    ) -> Result<bool, ForwardSecureSignatureError> {
+        let positions = self.positions(committee.size)?;
+        if positions.is_empty() || positions.len() != self.signers.len() {
+            return Err(ForwardSecureSignatureError::InvalidSignerBitmap {
+                msg: format!(
+                    "{} bits set for {} signers",
+                    positions.len(),
+                    self.signers.len()
+                ),
+            });
+        }
+        for (p, (vk, proof)) in positions.iter().zip(self.signers.iter()) {
+            if proof.position != *p || !proof.verify(vk, committee) {
+                return Ok(false);
+            }
+        }
+        self.signature.verify_aggregated(
+            msg,
+            t,
+            l,
+            self.signers.iter().map(|(vk, _)| vk).collect(),
+            gens,
+        )
+    }
+}
+
+/// Signature on a batch of messages along with the inclusion proof of one message
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BatchReceipt {
+    /// Root of the tree over the messages of the batch
+    pub root: MerkleHash,
+    /// Number of messages in the batch
+    pub size: usize,
+    pub proof: MembershipProof,
+    /// Signature on the root, same for every message of the batch
+    pub signature: Signature,
+}
+
+impl BatchReceipt {
+    /// Sign the Merkle root of `msgs` once with the key of the current time period. Returns a
+    /// receipt for every message, in the order of `msgs`.
+    pub fn sign_batch<R: RngCore + CryptoRng>(
+        msgs: &[&[u8]],
+        manager: &mut SigManager,
+        gens: &GeneratorSet,
+        db: &dyn SigKeyDb,
+        rng: &mut R,
+    ) -> Result<Vec<Self>, ForwardSecureSignatureError> {
+        if msgs.is_empty() {
+            return Err(ForwardSecureSignatureError::EmptyBatch);
+        }
+        let leaves = msgs
+            .iter()
+            .enumerate()
+            .map(|(i, m)| batch_leaf_hash(i, m))
+            .collect();
+        let levels = build_levels(leaves);
+        let root = levels[levels.len() - 1][0];
+        let size = msgs.len();
+        let signature = manager.sign(&batch_statement(&root, size), gens, db, rng)?;
+        Ok((0..size)
+            .map(|i| Self {
+                root,
+                size,
+                proof: path(&levels, i),
+                signature: signature.clone(),
+            })
+            .collect())
+    }
+
+    /// Check that `msg` is in the batch and the signature on the batch's root
+    pub fn verify(
+        &self,
+        msg: &[u8],
+        t: u128,
+        l: u8,
+        gens: &GeneratorSet,
+        verkey: &Verkey,
+    ) -> Result<bool, ForwardSecureSignatureError> {
+        let leaf = batch_leaf_hash(self.proof.position, msg);
+        if self.proof.root(leaf, self.size) != Some(self.root) {
+            return Ok(false);
+        }
+        self.signature
+            .verify(&batch_statement(&self.root, self.size), t, l, gens, verkey)
+    }
+}
+
+/// Hashes of every level of the tree over `leaves`, leaves first
+fn build_levels(leaves: Vec<MerkleHash>) -> Vec<Vec<MerkleHash>> {
+    let mut levels = vec![leaves];
+    while levels[levels.len() - 1].len() > 1 {
+        let next = levels[levels.len() - 1]
+            .chunks(2)
+            .map(|c| {
+                if c.len() == 2 {
+                    node_hash(&c[0], &c[1])
+                } else {
+                    c[0]
+                }
+            })
+            .collect();
+        levels.push(next);
+    }
+    levels
+}
+
+fn path(levels: &[Vec<MerkleHash>], position: usize) -> MembershipProof {
+    let mut siblings = vec![];
+    let mut idx = position;
+    for level in &levels[..levels.len() - 1] {
+        let sibling = idx ^ 1;
+        if sibling < level.len() {
+            siblings.push(level[sibling]);
+        }
+        idx /= 2;
+    }
+    MembershipProof { position, siblings }
+}
+
+fn batch_leaf_hash(position: usize, msg: &[u8]) -> MerkleHash {
+    let mut hasher = Sha3_256::new();
+    hasher.input([0u8]);
+    hasher.input(BATCH_LEAF_DST);
+    hasher.input((position as u64).to_be_bytes());
+    hasher.input(Sha3_256::digest(msg));
+    let mut hash = [0; 32];
+    hash.copy_from_slice(&hasher.result());
+    hash
+}
+
+fn batch_statement(root: &MerkleHash, size: usize) -> Vec<u8> {
+    let mut bytes = BATCH_ROOT_DST.to_vec();
+    bytes.extend_from_slice(root);
+    bytes.extend_from_slice(&(size as u64).to_be_bytes());
+    bytes
+}
+
+fn leaf_hash(position: usize, verkey: &Verkey) -> MerkleHash {
+    let mut hasher = Sha3_256::new();
+    hasher.input([0u8]);
+    hasher.input(LEAF_DST);
+    hasher.input((position as u64).to_be_bytes());
+    hasher.input(verkey.to_bytes());
+    let mut hash = [0; 32];
+    hash.copy_from_slice(&hasher.result());
+    hash
+}
+
+fn node_hash(left: &MerkleHash, right: &MerkleHash) -> MerkleHash {
+    let mut hasher = Sha3_256::new();
+    hasher.input([1u8]);
+    hasher.input(left);
+    hasher.input(right);
+    let mut hash = [0; 32];
+    hash.copy_from_slice(&hasher.result());
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{InMemorySigKeyDatabase, Keypair, SigManager};
+    use crate::util::calculate_l;
+
+    #[test]
+    fn test_membership_proofs() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let mut verkeys = vec![];
+        for _ in 0..7 {
+            let mut db = InMemorySigKeyDatabase::new();
+            verkeys.push(Keypair::new(T, &gens, &mut rng, &mut db).unwrap().0.ver_key);
+        }
+        for n in 1..=verkeys.len() {
+            let tree = CommitteeMerkleTree::new(&verkeys[..n]).unwrap();
+            let root = tree.committee_root();
+            assert_eq!(root.size, n);
+            for (i, vk) in verkeys[..n].iter().enumerate() {
+                let proof = tree.proof(i).unwrap();
+                assert!(proof.verify(vk, &root));
+                // Wrong verkey or position
+                assert!(!proof.verify(&verkeys[(i + 1) % verkeys.len()], &root));
+                let mut moved = proof.clone();
+                moved.position = (i + 1) % n;
+                assert!(n == 1 || !moved.verify(vk, &root));
+                let mut extra = proof.clone();
+                extra.siblings.push([0; 32]);
+                assert!(!extra.verify(vk, &root));
+            }
+            assert!(tree.proof(n).is_err());
+        }
+        assert!(CommitteeMerkleTree::new(&[]).is_err());
+    }
+
+    #[test]
+    fn test_merkle_aggregate() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let l = calculate_l(T).unwrap();
+        let t = 1;
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let msg = "Hello".as_bytes();
+        let mut verkeys = vec![];
+        let mut sigs = vec![];
+        for _ in 0..10 {
+            let mut db = InMemorySigKeyDatabase::new();
+            let (keypair, _) = Keypair::new(T, &gens, &mut rng, &mut db).unwrap();
+            let sk = SigManager::get_key(t, &db).unwrap();
+            sigs.push(Signature::new(msg, t, l, &gens, sk, &mut rng).unwrap());
+            verkeys.push(keypair.ver_key);
+        }
+        let tree = CommitteeMerkleTree::new(&verkeys).unwrap();
+        let root = tree.committee_root();
+
+        let signed = [0, 3, 4, 9];
+        let agg = MerkleAggregate::new(
+            &tree,
+            signed
+                .iter()
+                .rev()
+                .map(|p| (*p, verkeys[*p].clone(), sigs[*p].clone()))
+                .collect(),
+        )
+        .unwrap();
+        assert_eq!(agg.bitmap, vec![0b0001_1001, 0b10]);
+        assert_eq!(signer_bitmap(&signed, root.size).unwrap(), agg.bitmap);
+        assert!(signer_bitmap(&[0, root.size], root.size).is_err());
+        assert!(signer_bitmap(&[16], root.size).is_err());
+        assert_eq!(agg.positions(root.size).unwrap(), signed.to_vec());
+        assert!(agg.verify(msg, t, l, &root, &gens).unwrap());
+        assert!(!agg.verify(b"Hi", t, l, &root, &gens).unwrap());
+
+        // Bitmap claiming another signer
+        let mut bad = agg.clone();
+        bad.bitmap[0] = 0b0001_0011;
+        assert!(!bad.verify(msg, t, l, &root, &gens).unwrap());
+        let mut bad = agg.clone();
+        bad.bitmap[1] = 0b110;
+        assert!(bad.verify(msg, t, l, &root, &gens).is_err());
+        let mut bad = agg.clone();
+        bad.bitmap.push(0);
+        assert!(bad.verify(msg, t, l, &root, &gens).is_err());
+        // Verkey outside the committee
+        let mut bad = agg.clone();
+        bad.signers[0].0 = verkeys[1].clone();
+        assert!(!bad.verify(msg, t, l, &root, &gens).unwrap());
+        // Another committee of the same size
+        let mut other_verkeys = verkeys.clone();
+        other_verkeys.reverse();
+        let other = CommitteeMerkleTree::new(&other_verkeys).unwrap();
+        assert!(!agg
+            .verify(msg, t, l, &other.committee_root(), &gens)
+            .unwrap());
+
+        assert!(MerkleAggregate::new(
+            &tree,
+            vec![
+                (1, verkeys[1].clone(), sigs[1].clone()),
+                (1, verkeys[1].clone(), sigs[1].clone())
+            ]
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_batch_signing() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let l = calculate_l(T).unwrap();
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let mut db = InMemorySigKeyDatabase::new();
+        let (keypair, mut manager) = Keypair::new(T, &gens, &mut rng, &mut db).unwrap();
+        let vk = keypair.ver_key;
+
+        let msgs = (0..11u8).map(|i| vec![i; 4]).collect::<Vec<_>>();
+        let refs = msgs.iter().map(|m| m.as_slice()).collect::<Vec<_>>();
+        let receipts = BatchReceipt::sign_batch(&refs, &mut manager, &gens, &db, &mut rng).unwrap();
+        assert_eq!(receipts.len(), 11);
+        // One signature for the whole batch
+        assert_eq!(manager.stats(1).signatures(), 1);
+        for (i, r) in receipts.iter().enumerate() {
+            assert_eq!(r.signature, receipts[0].signature);
+            assert!(r.verify(&msgs[i], 1, l, &gens, &vk).unwrap());
+            assert!(!r.verify(&msgs[(i + 1) % 11], 1, l, &gens, &vk).unwrap());
+            assert!(!r.verify(&msgs[i], 2, l, &gens, &vk).unwrap());
+        }
+
+        // Receipt moved to another position or batch size
+        let mut bad = receipts[3].clone();
+        bad.proof.position = 2;
+        assert!(!bad.verify(&msgs[3], 1, l, &gens, &vk).unwrap());
+        let mut bad = receipts[3].clone();
+        bad.size = 12;
+        assert!(!bad.verify(&msgs[3], 1, l, &gens, &vk).unwrap());
+        let mut bad = receipts[3].clone();
+        bad.proof.siblings.pop();
+        assert!(!bad.verify(&msgs[3], 1, l, &gens, &vk).unwrap());
+
+        let single =
+            BatchReceipt::sign_batch(&[b"only"], &mut manager, &gens, &db, &mut rng).unwrap();
+        assert!(single[0].proof.siblings.is_empty());
+        assert!(single[0].verify(b"only", 1, l, &gens, &vk).unwrap());
+        assert!(BatchReceipt::sign_batch(&[], &mut manager, &gens, &db, &mut rng).is_err());
+    }
+}
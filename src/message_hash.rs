@@ -0,0 +1,174 @@
+//! Hashing of the message to the field element that is signed. `Signature::new` and
+//! `Signature::verify` use `DefaultMessageHasher`. Deployments with a mandated hash function
+//! implement `MessageHasher` for it, sign and verify with `Signature::new_with_hasher` and
+//! `Signature::verify_with_hasher` and publish `PublicParams` created with
+//! `PublicParams::with_message_hasher` so verifiers using another hash are rejected.
+//!
+//! SHAKE-256, SHA3-256 and SHA-256 are provided. Other hashes such as BLAKE3 are plugged in by
+//! implementing `MessageHasher` over the respective crate, expanding the digest to
+//! `FieldElement_SIZE` bytes like `Sha256Hasher` does.
+
+use amcl_wrapper::constants::FieldElement_SIZE;
+use amcl_wrapper::field_elem::FieldElement;
+use sha2::Sha256;
+use sha3::{Digest, Sha3_256};
+
+use crate::params::CIPHERSUITE;
+
+pub trait MessageHasher {
+    /// Name of the hash function, part of the ciphersuite id
+    const ID: &'static str;
+
+    fn hash_to_field(msg: &[u8]) -> FieldElement;
+}
+
+/// SHAKE-256 with `FieldElement_SIZE` bytes of output reduced modulo the group order
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Shake256Hasher;
+
+impl MessageHasher for Shake256Hasher {
+    const ID: &'static str = "SHAKE256";
+
+    fn hash_to_field(msg: &[u8]) -> FieldElement {
+        FieldElement::from_msg_hash(msg)
+    }
+}
+
+pub type DefaultMessageHasher = Shake256Hasher;
+
+const SHA3_256_HASHER_DST: &[u8] = b"PIXEL-MSG-SHA3-256-V1";
+
+/// SHA3-256 expanded to `FieldElement_SIZE` bytes by hashing the message with a block counter,
+/// reduced modulo the group order
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sha3_256Hasher;
+
+impl MessageHasher for Sha3_256Hasher {
+    const ID: &'static str = "SHA3-256";
+
+    fn hash_to_field(msg: &[u8]) -> FieldElement {
+        let mut bytes = [0; FieldElement_SIZE];
+        for (i, block) in bytes.chunks_mut(32).enumerate() {
+            let mut hasher = Sha3_256::new();
+            hasher.input(SHA3_256_HASHER_DST);
+            hasher.input([i as u8]);
+            hasher.input(msg);
+            block.copy_from_slice(&hasher.result()[..block.len()]);
+        }
+        FieldElement::from(&bytes)
+    }
+}
+
+const SHA256_HASHER_DST: &[u8] = b"PIXEL-MSG-SHA-256-V1";
+
+/// SHA-256 expanded like `Sha3_256Hasher`, for deployments mandating SHA-2
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sha256Hasher;
+
+impl MessageHasher for Sha256Hasher {
+    const ID: &'static str = "SHA-256";
+
+    fn hash_to_field(msg: &[u8]) -> FieldElement {
+        let mut bytes = [0; FieldElement_SIZE];
+        for (i, block) in bytes.chunks_mut(32).enumerate() {
+            let mut hasher = Sha256::new();
+            hasher.input(SHA256_HASHER_DST);
+            hasher.input([i as u8]);
+            hasher.input(msg);
+            block.copy_from_slice(&hasher.result()[..block.len()]);
+        }
+        FieldElement::from(&bytes)
+    }
+}
+
+/// Ciphersuite id of signatures on messages hashed with `H`. `CIPHERSUITE` for the default
+/// hasher so existing public params stay valid, otherwise the id of `H` is appended.
+pub fn ciphersuite_id<H: MessageHasher>() -> String {
+    if H::ID == DefaultMessageHasher::ID {
+        CIPHERSUITE.to_string()
+    } else {
+        format!("{}+{}", CIPHERSUITE, H::ID)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{InMemorySigKeyDatabase, Keypair};
+    use crate::signature::Signature;
+    use crate::util::{calculate_l, GeneratorSet};
+
+    #[test]
+    fn test_message_hashers() {
+        let msg = "Hello".as_bytes();
+        assert_eq!(
+            Shake256Hasher::hash_to_field(msg),
+            Signature::hash_message(msg)
+        );
+        assert_eq!(
+            Sha3_256Hasher::hash_to_field(msg),
+            Sha3_256Hasher::hash_to_field(msg)
+        );
+        assert_ne!(
+            Sha3_256Hasher::hash_to_field(msg),
+            Shake256Hasher::hash_to_field(msg)
+        );
+        assert_ne!(
+            Sha3_256Hasher::hash_to_field(msg),
+            Sha3_256Hasher::hash_to_field("Hellp".as_bytes())
+        );
+        assert_ne!(
+            Sha256Hasher::hash_to_field(msg),
+            Sha3_256Hasher::hash_to_field(msg)
+        );
+        assert_ne!(
+            Sha256Hasher::hash_to_field(msg),
+            Sha256Hasher::hash_to_field("Hellp".as_bytes())
+        );
+
+        assert_eq!(ciphersuite_id::<Shake256Hasher>(), CIPHERSUITE);
+        assert_ne!(ciphersuite_id::<Sha3_256Hasher>(), CIPHERSUITE);
+        assert_ne!(
+            ciphersuite_id::<Sha256Hasher>(),
+            ciphersuite_id::<Sha3_256Hasher>()
+        );
+    }
+
+    #[test]
+    fn test_sign_verify_with_hasher() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let l = calculate_l(T).unwrap();
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let mut db = InMemorySigKeyDatabase::new();
+        let (keypair, manager) = Keypair::new(T, &gens, &mut rng, &mut db).unwrap();
+        let sk = manager.get_current_key(&db).unwrap();
+        let vk = &keypair.ver_key;
+        let msg = "Hello".as_bytes();
+
+        let sig = Signature::new_with_hasher::<Sha3_256Hasher, _>(msg, 1, l, &gens, sk, &mut rng)
+            .unwrap();
+        assert!(sig
+            .verify_with_hasher::<Sha3_256Hasher>(msg, 1, l, &gens, vk)
+            .unwrap());
+        // A signature under one hasher does not verify under another
+        assert!(!sig.verify(msg, 1, l, &gens, vk).unwrap());
+        assert!(!sig
+            .verify_with_hasher::<Sha256Hasher>(msg, 1, l, &gens, vk)
+            .unwrap());
+
+        let sig =
+            Signature::new_with_hasher::<Sha256Hasher, _>(msg, 1, l, &gens, sk, &mut rng).unwrap();
+        assert!(sig
+            .verify_with_hasher::<Sha256Hasher>(msg, 1, l, &gens, vk)
+            .unwrap());
+
+        let sig = Signature::new(msg, 1, l, &gens, sk, &mut rng).unwrap();
+        assert!(sig
+            .verify_with_hasher::<DefaultMessageHasher>(msg, 1, l, &gens, vk)
+            .unwrap());
+        assert!(!sig
+            .verify_with_hasher::<Sha3_256Hasher>(msg, 1, l, &gens, vk)
+            .unwrap());
+    }
+}
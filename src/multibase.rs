@@ -0,0 +1,219 @@
+//! Multicodec prefixed, multibase encoded verkeys and signatures, for DID documents and
+//! verifiable credentials. The bytes are `varint(codec) || to_bytes()` with the varint unsigned
+//! LEB128, encoded in base58btc (`z`, as for `did:key` and `Multikey`), lowercase base16 (`f`)
+//! or unpadded base64url (`u`).
+//!
+//! The registered `bls12_381-g1-pub` and `bls12_381-g2-pub` codecs are for compressed points
+//! in another encoding, so the codecs are from the private use range of the multicodec table:
+//!
+//! | codec    | identifies                            |
+//! |----------|---------------------------------------|
+//! | 0x300d01 | verkey of the scheme in G2            |
+//! | 0x300d02 | verkey of the scheme in G1            |
+//! | 0x300d03 | signature with the verkey in G2       |
+//! | 0x300d04 | signature with the verkey in G1       |
+//!
+//! Decoding checks the codec, the length and the points as `from_bytes` does.
+
+use crate::errors::ForwardSecureSignatureError;
+use crate::keys::Verkey;
+use crate::signature::Signature;
+use crate::text_encoding::{base64url_decode, base64url_encode, hex_decode, hex_encode};
+use crate::wire::write_varint;
+
+#[cfg(feature = "VerkeyG2")]
+pub const VERKEY_MULTICODEC: u128 = 0x300d01;
+#[cfg(feature = "VerkeyG1")]
+pub const VERKEY_MULTICODEC: u128 = 0x300d02;
+#[cfg(feature = "VerkeyG2")]
+pub const SIGNATURE_MULTICODEC: u128 = 0x300d03;
+#[cfg(feature = "VerkeyG1")]
+pub const SIGNATURE_MULTICODEC: u128 = 0x300d04;
+
+const BASE58BTC_CHARSET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Multibase {
+    Base58Btc,
+    Base16,
+    Base64Url,
+}
+
+impl Multibase {
+    pub fn prefix(&self) -> char {
+        match self {
+            Multibase::Base58Btc => 'z',
+            Multibase::Base16 => 'f',
+            Multibase::Base64Url => 'u',
+        }
+    }
+}
+
+fn invalid(msg: &str) -> ForwardSecureSignatureError {
+    ForwardSecureSignatureError::InvalidMultibase {
+        msg: msg.to_string(),
+    }
+}
+
+pub fn base58btc_encode(data: &[u8]) -> String {
+    let zeros = data.iter().take_while(|b| **b == 0).count();
+    // Digits in base 58, least significant first
+    let mut digits: Vec<u8> = vec![];
+    for b in &data[zeros..] {
+        let mut carry = *b as u32;
+        for d in digits.iter_mut() {
+            carry += (*d as u32) << 8;
+            *d = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    let mut s = "1".repeat(zeros);
+    s.extend(
+        digits
+            .iter()
+            .rev()
+            .map(|d| BASE58BTC_CHARSET[*d as usize] as char),
+    );
+    s
+}
+
+pub fn base58btc_decode(s: &str) -> Result<Vec<u8>, ForwardSecureSignatureError> {
+    let zeros = s.bytes().take_while(|c| *c == b'1').count();
+    // Bytes, least significant first
+    let mut bytes: Vec<u8> = vec![];
+    for c in s.bytes().skip(zeros) {
+        let mut carry = BASE58BTC_CHARSET
+            .iter()
+            .position(|b| *b == c)
+            .ok_or_else(|| invalid("invalid base58btc character"))? as u32;
+        for b in bytes.iter_mut() {
+            carry += *b as u32 * 58;
+            *b = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+    let mut data = vec![0; zeros];
+    data.extend(bytes.iter().rev());
+    Ok(data)
+}
+
+pub fn multibase_encode(base: Multibase, data: &[u8]) -> String {
+    let body = match base {
+        Multibase::Base58Btc => base58btc_encode(data),
+        Multibase::Base16 => hex_encode(data),
+        Multibase::Base64Url => base64url_encode(data),
+    };
+    format!("{}{}", base.prefix(), body)
+}
+
+/// Decode a string in any of the supported bases, returning the base
+pub fn multibase_decode(s: &str) -> Result<(Multibase, Vec<u8>), ForwardSecureSignatureError> {
+    let mut chars = s.chars();
+    let (base, data) = match chars.next() {
+        Some('z') => (Multibase::Base58Btc, base58btc_decode(chars.as_str())?),
+        Some('f') => (Multibase::Base16, hex_decode(chars.as_str())?),
+        Some('u') => (Multibase::Base64Url, base64url_decode(chars.as_str())?),
+        Some(c) => return Err(invalid(&format!("unsupported multibase prefix {}", c))),
+        None => return Err(invalid("empty string")),
+    };
+    Ok((base, data))
+}
+
+fn encode(codec: u128, bytes: &[u8], base: Multibase) -> String {
+    let mut data = vec![];
+    write_varint(codec, &mut data);
+    data.extend_from_slice(bytes);
+    multibase_encode(base, &data)
+}
+
+/// Bytes after the codec, which must be `codec`. Varints have a single encoding so comparing
+/// the prefix is enough.
+fn decode(codec: u128, s: &str) -> Result<Vec<u8>, ForwardSecureSignatureError> {
+    let (_, data) = multibase_decode(s)?;
+    let mut prefix = vec![];
+    write_varint(codec, &mut prefix);
+    if !data.starts_with(&prefix) {
+        return Err(invalid(&format!("multicodec is not {:#x}", codec)));
+    }
+    Ok(data[prefix.len()..].to_vec())
+}
+
+impl Verkey {
+    pub fn to_multibase(&self, base: Multibase) -> String {
+        encode(VERKEY_MULTICODEC, &self.to_bytes(), base)
+    }
+
+    pub fn from_multibase(s: &str) -> Result<Self, ForwardSecureSignatureError> {
+        Self::from_bytes(&decode(VERKEY_MULTICODEC, s)?)
+    }
+}
+
+impl Signature {
+    pub fn to_multibase(&self, base: Multibase) -> String {
+        encode(SIGNATURE_MULTICODEC, &self.to_bytes(), base)
+    }
+
+    pub fn from_multibase(s: &str) -> Result<Self, ForwardSecureSignatureError> {
+        Self::from_bytes(&decode(SIGNATURE_MULTICODEC, s)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::facade::Signer;
+    use crate::keys::InMemorySigKeyDatabase;
+    use crate::util::GeneratorSet;
+
+    #[test]
+    fn test_multibase() {
+        // Test vectors of the base58 draft
+        for (data, s) in &[
+            (&b""[..], ""),
+            (&b"Hello World!"[..], "2NEpo7TZRRrLZSi2U"),
+            (&[0, 0, 0x28, 0x7f, 0xb4, 0xcd][..], "11233QC4"),
+        ] {
+            assert_eq!(base58btc_encode(data), *s);
+            assert_eq!(base58btc_decode(s).unwrap(), *data);
+        }
+        assert!(base58btc_decode("0OIl").is_err());
+        assert_eq!(multibase_encode(Multibase::Base16, b"yes"), "f796573");
+        assert_eq!(
+            multibase_decode("ueWVz").unwrap(),
+            (Multibase::Base64Url, b"yes".to_vec())
+        );
+        assert!(multibase_decode("").is_err());
+        assert!(multibase_decode("Mc3Bhcms=").is_err());
+
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let (mut signer, _) =
+            Signer::new(T, gens, InMemorySigKeyDatabase::new(), &mut rng).unwrap();
+        let sig = signer.sign("Hello".as_bytes(), &mut rng).unwrap();
+        let vk = signer.verkey();
+        for base in &[
+            Multibase::Base58Btc,
+            Multibase::Base16,
+            Multibase::Base64Url,
+        ] {
+            let s = vk.to_multibase(*base);
+            assert!(s.starts_with(base.prefix()));
+            assert_eq!(Verkey::from_multibase(&s).unwrap().value, vk.value);
+            let s = sig.to_multibase(*base);
+            assert_eq!(Signature::from_multibase(&s).unwrap(), sig);
+            // The codec tells verkeys and signatures apart
+            assert!(Verkey::from_multibase(&s).is_err());
+        }
+        let s = vk.to_multibase(Multibase::Base58Btc);
+        assert!(Verkey::from_multibase(&s[..s.len() - 1]).is_err());
+    }
+}
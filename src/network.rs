@@ -0,0 +1,203 @@
+//! Parameters of several networks in one process, e.g. mainnet, testnet and devnet. A `Network`
+//! holds the generators and maximum time period of one network under its id, and `Networks`
+//! selects among them by id.
+//!
+//! Signatures made through a `Network` are on `DST || len(id) || id || msg`, with the length as
+//! 4 byte big endian, so a signature of one network never verifies on another even if both use
+//! the same generators. Signing and verifying also fail for signers and verifiers whose generators
+//! are another network's.
+
+use std::collections::BTreeMap;
+
+use rand::{CryptoRng, RngCore};
+
+use crate::errors::ForwardSecureSignatureError;
+use crate::facade::{Signer, Verifier};
+use crate::keys::{SigKeyDb, Verkey};
+use crate::params::generators_digest;
+use crate::signature::Signature;
+use crate::util::{calculate_l, GeneratorSet, GeneratorSetRef};
+
+const NETWORK_MESSAGE_DST: &[u8] = b"PIXEL-NETWORK-MESSAGE-V1";
+
+#[derive(Clone, Debug)]
+pub struct Network {
+    id: String,
+    T: u128,
+    gens: GeneratorSetRef,
+    gens_digest: [u8; 32],
+}
+
+impl Network {
+    /// Fails if there are fewer generators than needed for `T`
+    pub fn new<G: Into<GeneratorSetRef>>(
+        id: &str,
+        T: u128,
+        gens: G,
+    ) -> Result<Self, ForwardSecureSignatureError> {
+        let gens = gens.into();
+        let l = calculate_l(T)?;
+        if gens.1.len() < l as usize + 2 {
+            return Err(ForwardSecureSignatureError::NotEnoughGenerators { n: l as usize + 2 });
+        }
+        Ok(Self {
+            id: id.to_string(),
+            T,
+            gens_digest: generators_digest(&gens),
+            gens,
+        })
+    }
+
+    /// Network whose generators are created from `label`
+    pub fn from_label(id: &str, T: u128, label: &str) -> Result<Self, ForwardSecureSignatureError> {
+        Self::new(id, T, GeneratorSet::new(T, label)?)
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Maximum time period
+    pub fn T(&self) -> u128 {
+        self.T
+    }
+
+    pub fn generators(&self) -> &GeneratorSetRef {
+        &self.gens
+    }
+
+    /// The bytes signed for `msg` on this network
+    pub fn message(&self, msg: &[u8]) -> Vec<u8> {
+        let id = self.id.as_bytes();
+        let mut bytes = NETWORK_MESSAGE_DST.to_vec();
+        bytes.extend_from_slice(&(id.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(id);
+        bytes.extend_from_slice(msg);
+        bytes
+    }
+
+    pub fn verifier(&self, verkey: Verkey) -> Result<Verifier, ForwardSecureSignatureError> {
+        Verifier::new(self.T, self.gens.clone(), verkey)
+    }
+
+    /// Sign `msg` for this network with the signer's current key
+    pub fn sign<D: SigKeyDb, R: RngCore + CryptoRng>(
+        &self,
+        signer: &mut Signer<D>,
+        msg: &[u8],
+        rng: &mut R,
+    ) -> Result<Signature, ForwardSecureSignatureError> {
+        self.check_generators(signer.generators())?;
+        signer.sign(&self.message(msg), rng)
+    }
+
+    /// Verify a signature made with `sign` of this network
+    pub fn verify(
+        &self,
+        verifier: &Verifier,
+        msg: &[u8],
+        t: u128,
+        sig: &Signature,
+    ) -> Result<bool, ForwardSecureSignatureError> {
+        self.check_generators(verifier.generators())?;
+        verifier.verify(&self.message(msg), t, sig)
+    }
+
+    fn check_generators(&self, gens: &GeneratorSet) -> Result<(), ForwardSecureSignatureError> {
+        if generators_digest(gens) != self.gens_digest {
+            return Err(ForwardSecureSignatureError::NetworkMismatch {
+                msg: format!("generators are not those of network {}", self.id),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Networks by id
+#[derive(Clone, Debug, Default)]
+pub struct Networks {
+    networks: BTreeMap<String, Network>,
+}
+
+impl Networks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fails if a network with the same id was added before
+    pub fn add(&mut self, network: Network) -> Result<(), ForwardSecureSignatureError> {
+        if self.networks.contains_key(network.id()) {
+            return Err(ForwardSecureSignatureError::NetworkMismatch {
+                msg: format!("network {} already added", network.id()),
+            });
+        }
+        self.networks.insert(network.id.clone(), network);
+        Ok(())
+    }
+
+    /// The network with id `id`. Fails for unknown networks.
+    pub fn get(&self, id: &str) -> Result<&Network, ForwardSecureSignatureError> {
+        self.networks
+            .get(id)
+            .ok_or_else(|| ForwardSecureSignatureError::NetworkMismatch {
+                msg: format!("unknown network {}", id),
+            })
+    }
+
+    /// Ids of the networks in sorted order
+    pub fn ids(&self) -> Vec<&str> {
+        self.networks.keys().map(|id| id.as_str()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::InMemorySigKeyDatabase;
+
+    #[test]
+    fn test_networks() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let mut networks = Networks::new();
+        networks
+            .add(Network::from_label("mainnet", T, "test_pixel").unwrap())
+            .unwrap();
+        // Same generators, different network
+        networks
+            .add(Network::from_label("testnet", T, "test_pixel").unwrap())
+            .unwrap();
+        networks
+            .add(Network::from_label("devnet", T, "test_pixel_devnet").unwrap())
+            .unwrap();
+        assert!(networks
+            .add(Network::from_label("devnet", T, "test_pixel").unwrap())
+            .is_err());
+        assert_eq!(networks.ids(), vec!["devnet", "mainnet", "testnet"]);
+        assert!(networks.get("localnet").is_err());
+
+        let mainnet = networks.get("mainnet").unwrap();
+        let testnet = networks.get("testnet").unwrap();
+        let devnet = networks.get("devnet").unwrap();
+        let (mut signer, _) = Signer::new(
+            T,
+            mainnet.generators().clone(),
+            InMemorySigKeyDatabase::new(),
+            &mut rng,
+        )
+        .unwrap();
+        let msg = "Hello".as_bytes();
+        let sig = mainnet.sign(&mut signer, msg, &mut rng).unwrap();
+        let verifier = mainnet.verifier(signer.verkey().clone()).unwrap();
+        assert!(mainnet.verify(&verifier, msg, 1, &sig).unwrap());
+        assert!(!verifier.verify(msg, 1, &sig).unwrap());
+
+        // Artifacts of one network are refused by the others
+        let testnet_verifier = testnet.verifier(signer.verkey().clone()).unwrap();
+        assert!(!testnet.verify(&testnet_verifier, msg, 1, &sig).unwrap());
+        assert!(devnet.verify(&verifier, msg, 1, &sig).is_err());
+        assert!(devnet.sign(&mut signer, msg, &mut rng).is_err());
+
+        assert!(Network::new("mainnet", 15, mainnet.generators().clone()).is_err());
+    }
+}
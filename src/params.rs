@@ -0,0 +1,379 @@
+use crate::errors::ForwardSecureSignatureError;
+use crate::facade::Verifier;
+use crate::keys::Verkey;
+use crate::message_hash::{ciphersuite_id, DefaultMessageHasher, MessageHasher};
+use crate::util::{calculate_l, GeneratorSet, GeneratorSetBuilder};
+use sha3::{Digest, Sha3_256};
+
+/// Identifies the curve, the group of the verkey and the version of the scheme. Public params
+/// created by a build with a different ciphersuite are rejected.
+#[cfg(feature = "VerkeyG2")]
+pub const CIPHERSUITE: &str = "PIXEL-BLS12381-VKG2-V1";
+#[cfg(feature = "VerkeyG1")]
+pub const CIPHERSUITE: &str = "PIXEL-BLS12381-VKG1-V1";
+
+/// How the verifier gets the generators
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum GeneratorParams {
+    /// Generators are recreated from the label for the depth given by `T`
+    Label(String),
+    /// Generators serialized with `GeneratorSet::to_bytes`
+    Explicit(Vec<u8>),
+    /// Generators recreated from the label and checked against a digest
+    Compact(GeneratorSetParams),
+}
+
+/// Parameters from which a `GeneratorSet` is recreated, with the digest of the generators so
+/// that the recreated set can be checked. Serialized in a few dozen bytes instead of the
+/// hundreds of group elements of `GeneratorSet::to_bytes`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GeneratorSetParams {
+    pub ciphersuite: String,
+    pub label: String,
+    /// Depth `l` of the tree
+    pub depth: u8,
+    /// SHA3-256 of `GeneratorSet::to_bytes`
+    pub digest: [u8; 32],
+}
+
+pub(crate) fn generators_digest(gens: &GeneratorSet) -> [u8; 32] {
+    bytes_digest(&gens.to_bytes())
+}
+
+/// Digest of generators serialized with `GeneratorSet::to_bytes`
+pub(crate) fn bytes_digest(bytes: &[u8]) -> [u8; 32] {
+    let mut digest = [0; 32];
+    digest.copy_from_slice(&Sha3_256::digest(bytes));
+    digest
+}
+
+impl GeneratorSetParams {
+    /// Creates the generators for `label` and `depth` to compute their digest
+    pub fn new(label: &str, depth: u8) -> Result<Self, ForwardSecureSignatureError> {
+        let gens = GeneratorSetBuilder::new(label).depth(depth).build()?;
+        Ok(Self::from_generators(label, depth, &gens))
+    }
+
+    /// Params for generators already created from `label` and `depth`. `derive` fails for the
+    /// params if `gens` were created otherwise.
+    pub fn from_generators(label: &str, depth: u8, gens: &GeneratorSet) -> Self {
+        Self {
+            ciphersuite: CIPHERSUITE.to_string(),
+            label: label.to_string(),
+            depth,
+            digest: generators_digest(gens),
+        }
+    }
+
+    /// Recreate the generators and check them against the digest
+    pub fn derive(&self) -> Result<GeneratorSet, ForwardSecureSignatureError> {
+        if self.ciphersuite != CIPHERSUITE {
+            return Err(ForwardSecureSignatureError::InconsistentPublicParams {
+                msg: format!(
+                    "ciphersuite {} does not match {}",
+                    self.ciphersuite, CIPHERSUITE
+                ),
+            });
+        }
+        let gens = GeneratorSetBuilder::new(&self.label)
+            .depth(self.depth)
+            .build()?;
+        if generators_digest(&gens) != self.digest {
+            return Err(ForwardSecureSignatureError::InconsistentPublicParams {
+                msg: String::from("recreated generators do not match the digest"),
+            });
+        }
+        Ok(gens)
+    }
+
+    /// 1 byte ciphersuite length, ciphersuite, 2 byte big endian label length, label, 1 byte
+    /// depth and the digest
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![self.ciphersuite.len() as u8];
+        bytes.extend_from_slice(self.ciphersuite.as_bytes());
+        bytes.extend_from_slice(&(self.label.len() as u16).to_be_bytes());
+        bytes.extend_from_slice(self.label.as_bytes());
+        bytes.push(self.depth);
+        bytes.extend_from_slice(&self.digest);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ForwardSecureSignatureError> {
+        let invalid = |msg: &str| ForwardSecureSignatureError::InvalidWireBytes {
+            msg: format!("generator set params: {}", msg),
+        };
+        let take = |bytes: &mut &[u8], n: usize| -> Result<Vec<u8>, ForwardSecureSignatureError> {
+            if bytes.len() < n {
+                return Err(invalid("truncated"));
+            }
+            let (head, rest) = bytes.split_at(n);
+            *bytes = rest;
+            Ok(head.to_vec())
+        };
+        let mut rest = bytes;
+        let n = take(&mut rest, 1)?[0] as usize;
+        let ciphersuite = String::from_utf8(take(&mut rest, n)?)
+            .map_err(|_| invalid("ciphersuite is not UTF-8"))?;
+        let len = take(&mut rest, 2)?;
+        let n = u16::from_be_bytes([len[0], len[1]]) as usize;
+        let label =
+            String::from_utf8(take(&mut rest, n)?).map_err(|_| invalid("label is not UTF-8"))?;
+        let depth = take(&mut rest, 1)?[0];
+        let mut digest = [0; 32];
+        digest.copy_from_slice(&take(&mut rest, 32)?);
+        if !rest.is_empty() {
+            return Err(invalid("trailing bytes"));
+        }
+        Ok(Self {
+            ciphersuite,
+            label,
+            depth,
+            digest,
+        })
+    }
+}
+
+/// Everything a verifier needs to verify signatures of a signer, meant to be distributed as a
+/// single file.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PublicParams {
+    pub ciphersuite: String,
+    /// Maximum time period
+    pub T: u128,
+    pub generators: GeneratorParams,
+    pub verkey: Verkey,
+    /// Unix time in seconds at which time period 1 begins
+    pub genesis_time: u64,
+}
+
+impl PublicParams {
+    /// Public params whose generators are recreated by the verifier from `label`
+    pub fn new_with_label(T: u128, label: &str, verkey: Verkey, genesis_time: u64) -> Self {
+        Self {
+            ciphersuite: CIPHERSUITE.to_string(),
+            T,
+            generators: GeneratorParams::Label(label.to_string()),
+            verkey,
+            genesis_time,
+        }
+    }
+
+    /// Public params containing the generators themselves
+    pub fn new_with_generators(
+        T: u128,
+        gens: &GeneratorSet,
+        verkey: Verkey,
+        genesis_time: u64,
+    ) -> Self {
+        Self {
+            ciphersuite: CIPHERSUITE.to_string(),
+            T,
+            generators: GeneratorParams::Explicit(gens.to_bytes()),
+            verkey,
+            genesis_time,
+        }
+    }
+
+    /// Public params whose generators are recreated by the verifier from `label` and checked
+    /// against their digest
+    pub fn new_with_compact_generators(
+        T: u128,
+        gens: &GeneratorSet,
+        label: &str,
+        verkey: Verkey,
+        genesis_time: u64,
+    ) -> Result<Self, ForwardSecureSignatureError> {
+        let compact = GeneratorSetParams::from_generators(label, calculate_l(T)?, gens);
+        Ok(Self {
+            ciphersuite: CIPHERSUITE.to_string(),
+            T,
+            generators: GeneratorParams::Compact(compact),
+            verkey,
+            genesis_time,
+        })
+    }
+
+    pub fn l(&self) -> Result<u8, ForwardSecureSignatureError> {
+        calculate_l(self.T)
+    }
+
+    /// Returns the generators, creating them if only the label is present
+    pub fn generator_set(&self) -> Result<GeneratorSet, ForwardSecureSignatureError> {
+        match &self.generators {
+            GeneratorParams::Label(label) => GeneratorSet::new(self.T, label),
+            GeneratorParams::Explicit(bytes) => GeneratorSet::from_bytes(bytes),
+            GeneratorParams::Compact(compact) => compact.derive(),
+        }
+    }
+
+    /// Bind the params to messages hashed with `H` by setting the ciphersuite to the id for `H`
+    pub fn with_message_hasher<H: MessageHasher>(mut self) -> Self {
+        self.ciphersuite = ciphersuite_id::<H>();
+        self
+    }
+
+    /// Check that the params were created for this ciphersuite, that `T` is valid, that there
+    /// are as many generators as needed for `T` and that the verkey is not the identity.
+    pub fn verify_consistency(&self) -> Result<(), ForwardSecureSignatureError> {
+        self.verify_consistency_with_hasher::<DefaultMessageHasher>()
+    }
+
+    /// Same as `verify_consistency` but for params created `with_message_hasher` for `H`
+    pub fn verify_consistency_with_hasher<H: MessageHasher>(
+        &self,
+    ) -> Result<(), ForwardSecureSignatureError> {
+        let ciphersuite = ciphersuite_id::<H>();
+        if self.ciphersuite != ciphersuite {
+            return Err(ForwardSecureSignatureError::InconsistentPublicParams {
+                msg: format!(
+                    "ciphersuite {} does not match {}",
+                    self.ciphersuite, ciphersuite
+                ),
+            });
+        }
+        let l = self.l()?;
+        match &self.generators {
+            GeneratorParams::Explicit(bytes) => {
+                let n = GeneratorSet::num_generators_in_bytes(bytes.len())?;
+                if n != l as usize + 2 {
+                    return Err(ForwardSecureSignatureError::InconsistentPublicParams {
+                        msg: format!("{} generators given but {} needed for l={}", n, l + 2, l),
+                    });
+                }
+            }
+            GeneratorParams::Compact(compact) => {
+                if compact.depth != l || compact.ciphersuite != CIPHERSUITE {
+                    return Err(ForwardSecureSignatureError::InconsistentPublicParams {
+                        msg: format!(
+                            "generators for depth {} of {} given but l={}",
+                            compact.depth, compact.ciphersuite, l
+                        ),
+                    });
+                }
+            }
+            GeneratorParams::Label(_) => (),
+        }
+        if self.verkey.is_identity() {
+            return Err(ForwardSecureSignatureError::InconsistentPublicParams {
+                msg: String::from("verkey is the identity"),
+            });
+        }
+        Ok(())
+    }
+
+    /// Check consistency and create a verifier
+    pub fn verifier(&self) -> Result<Verifier, ForwardSecureSignatureError> {
+        self.verify_consistency()?;
+        Verifier::new(self.T, self.generator_set()?, self.verkey.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::facade::Signer;
+    use crate::keys::InMemorySigKeyDatabase;
+    use crate::message_hash::Sha3_256Hasher;
+
+    #[test]
+    fn test_public_params() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let (mut signer, _) =
+            Signer::new(T, gens.clone(), InMemorySigKeyDatabase::new(), &mut rng).unwrap();
+        let msg = "Hello".as_bytes();
+        let sig = signer.sign(msg, &mut rng).unwrap();
+
+        let with_label =
+            PublicParams::new_with_label(T, "test_pixel", signer.verkey().clone(), 1000);
+        let with_gens = PublicParams::new_with_generators(T, &gens, signer.verkey().clone(), 1000);
+        let compact = PublicParams::new_with_compact_generators(
+            T,
+            &gens,
+            "test_pixel",
+            signer.verkey().clone(),
+            1000,
+        )
+        .unwrap();
+        for params in [with_label, with_gens, compact] {
+            params.verify_consistency().unwrap();
+            assert_eq!(params.generator_set().unwrap().to_bytes(), gens.to_bytes());
+            assert!(params.verifier().unwrap().verify(msg, 1, &sig).unwrap());
+
+            let mut p = params.clone();
+            p.ciphersuite = String::from("PIXEL-OTHER");
+            assert!(p.verify_consistency().is_err());
+
+            let mut p = params.clone();
+            p.T = 8;
+            assert!(p.verify_consistency().is_err());
+        }
+
+        // Generators for a different depth
+        let gens_15 = GeneratorSet::new(15, "test_pixel").unwrap();
+        let params = PublicParams::new_with_generators(T, &gens_15, signer.verkey().clone(), 0);
+        assert!(params.verify_consistency().is_err());
+
+        // Params bound to another message hasher are rejected by the default verifier
+        let params = PublicParams::new_with_label(T, "test_pixel", signer.verkey().clone(), 0)
+            .with_message_hasher::<Sha3_256Hasher>();
+        params
+            .verify_consistency_with_hasher::<Sha3_256Hasher>()
+            .unwrap();
+        assert!(params.verify_consistency().is_err());
+        assert!(params.verifier().is_err());
+    }
+
+    #[test]
+    fn test_generator_set_params() {
+        let gens = GeneratorSet::new(7, "test_pixel").unwrap();
+        let params = GeneratorSetParams::new("test_pixel", 3).unwrap();
+        assert_eq!(
+            params,
+            GeneratorSetParams::from_generators("test_pixel", 3, &gens)
+        );
+        assert_eq!(params.derive().unwrap().to_bytes(), gens.to_bytes());
+
+        let bytes = params.to_bytes();
+        assert!(bytes.len() < 100);
+        assert_eq!(GeneratorSetParams::from_bytes(&bytes).unwrap(), params);
+        assert!(GeneratorSetParams::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        let mut longer = bytes.clone();
+        longer.push(0);
+        assert!(GeneratorSetParams::from_bytes(&longer).is_err());
+
+        // Generators not created from the label
+        let other = GeneratorSet::new(7, "other").unwrap();
+        assert!(GeneratorSetParams::from_generators("test_pixel", 3, &other)
+            .derive()
+            .is_err());
+        let mut p = params.clone();
+        p.depth = 4;
+        assert!(p.derive().is_err());
+        let mut p = params;
+        p.ciphersuite = String::from("PIXEL-OTHER");
+        assert!(p.derive().is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_public_params_serialization() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let (signer, _) = Signer::new(T, gens, InMemorySigKeyDatabase::new(), &mut rng).unwrap();
+        let params = PublicParams::new_with_label(T, "test_pixel", signer.verkey().clone(), 1000);
+
+        let json = serde_json::to_string(&params).unwrap();
+        let params_1: PublicParams = serde_json::from_str(&json).unwrap();
+        params_1.verify_consistency().unwrap();
+        assert_eq!(params_1.T, params.T);
+        assert_eq!(params_1.generators, params.generators);
+        assert_eq!(params_1.genesis_time, params.genesis_time);
+        assert_eq!(params_1.verkey.value, params.verkey.value);
+    }
+}
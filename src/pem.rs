@@ -0,0 +1,167 @@
+//! PEM armored keys (RFC 7468) for key management tooling and the usual file conventions.
+//!
+//! A verkey is a `FORWARD SECURE PUBLIC KEY` holding a `SubjectPublicKeyInfo` like structure
+//! `SEQUENCE { SEQUENCE { OID }, BIT STRING }` with the `to_bytes` encoding of the verkey in the
+//! bit string. An exported signing key is a `FORWARD SECURE PRIVATE KEY` holding a PKCS#8 like
+//! `SEQUENCE { INTEGER 0, SEQUENCE { OID }, OCTET STRING }` with the `Sigkey::to_versioned_bytes`
+//! encoding of the key and its node number in the octet string. The OID is
+//! `der::KEY_ALGORITHM_OID`, which differs between the `VerkeyG2` and `VerkeyG1` features.
+//!
+//! The private key PEM holds the key in the clear, it is up to the caller to protect it.
+
+use crate::der::{
+    self, key_algorithm_identifier, tlv, DerReader, TAG_BIT_STRING, TAG_INTEGER, TAG_OCTET_STRING,
+    TAG_SEQUENCE,
+};
+use crate::errors::ForwardSecureSignatureError;
+use crate::keys::{Sigkey, Verkey};
+use crate::text_encoding::{base64_decode, base64_encode};
+
+pub const VERKEY_PEM_LABEL: &str = "FORWARD SECURE PUBLIC KEY";
+pub const SIGKEY_PEM_LABEL: &str = "FORWARD SECURE PRIVATE KEY";
+
+const PEM_LINE_LENGTH: usize = 64;
+const PKCS8_VERSION: &[u8] = &[0];
+
+fn invalid(msg: &str) -> ForwardSecureSignatureError {
+    ForwardSecureSignatureError::InvalidPem {
+        msg: msg.to_string(),
+    }
+}
+
+/// Armor `der` with `label`, in lines of 64 characters
+pub fn pem_encode(label: &str, der: &[u8]) -> String {
+    let body = base64_encode(der);
+    let mut pem = format!("-----BEGIN {}-----\n", label);
+    for line in body.as_bytes().chunks(PEM_LINE_LENGTH) {
+        pem.push_str(std::str::from_utf8(line).unwrap());
+        pem.push('\n');
+    }
+    pem.push_str(&format!("-----END {}-----\n", label));
+    pem
+}
+
+/// Contents of a PEM with label `label`. Lines may end with `\r\n` and surrounding whitespace
+/// is ignored.
+pub fn pem_decode(label: &str, pem: &str) -> Result<Vec<u8>, ForwardSecureSignatureError> {
+    let lines = pem.trim().lines().map(str::trim).collect::<Vec<_>>();
+    if lines.len() < 2 || lines[0] != format!("-----BEGIN {}-----", label) {
+        return Err(invalid(&format!("expected BEGIN {}", label)));
+    }
+    if lines[lines.len() - 1] != format!("-----END {}-----", label) {
+        return Err(invalid(&format!("expected END {}", label)));
+    }
+    base64_decode(&lines[1..lines.len() - 1].concat())
+}
+
+impl Verkey {
+    /// `SubjectPublicKeyInfo` like DER encoding
+    pub fn to_public_key_der(&self) -> Vec<u8> {
+        let mut bits = vec![0];
+        bits.extend_from_slice(&self.to_bytes());
+        let mut content = key_algorithm_identifier();
+        content.extend_from_slice(&tlv(TAG_BIT_STRING, &bits));
+        tlv(TAG_SEQUENCE, &content)
+    }
+
+    pub fn from_public_key_der(der: &[u8]) -> Result<Self, ForwardSecureSignatureError> {
+        let mut outer = DerReader::new(der);
+        let mut info = DerReader::new(outer.read(TAG_SEQUENCE)?);
+        outer.finish()?;
+        info.read_key_algorithm()?;
+        let bits = info.read(TAG_BIT_STRING)?;
+        info.finish()?;
+        match bits.split_first() {
+            Some((0, vk)) => Self::from_bytes(vk),
+            _ => Err(der::invalid("bit string with unused bits")),
+        }
+    }
+
+    pub fn to_pem(&self) -> String {
+        pem_encode(VERKEY_PEM_LABEL, &self.to_public_key_der())
+    }
+
+    pub fn from_pem(pem: &str) -> Result<Self, ForwardSecureSignatureError> {
+        Self::from_public_key_der(&pem_decode(VERKEY_PEM_LABEL, pem)?)
+    }
+}
+
+impl Sigkey {
+    /// PKCS#8 like DER encoding of the key of node `t`
+    pub fn to_pkcs8_der(&self, t: u128) -> Vec<u8> {
+        let mut content = tlv(TAG_INTEGER, PKCS8_VERSION);
+        content.extend_from_slice(&key_algorithm_identifier());
+        content.extend_from_slice(&tlv(TAG_OCTET_STRING, &self.to_versioned_bytes(t)));
+        tlv(TAG_SEQUENCE, &content)
+    }
+
+    /// Decode a key encoded with `to_pkcs8_der`, returning its node number
+    pub fn from_pkcs8_der(der: &[u8]) -> Result<(u128, Self), ForwardSecureSignatureError> {
+        let mut outer = DerReader::new(der);
+        let mut info = DerReader::new(outer.read(TAG_SEQUENCE)?);
+        outer.finish()?;
+        if info.read(TAG_INTEGER)? != PKCS8_VERSION {
+            return Err(der::invalid("unsupported version"));
+        }
+        info.read_key_algorithm()?;
+        let key = info.read(TAG_OCTET_STRING)?;
+        info.finish()?;
+        Self::from_versioned_bytes(key)
+    }
+
+    pub fn to_pem(&self, t: u128) -> String {
+        pem_encode(SIGKEY_PEM_LABEL, &self.to_pkcs8_der(t))
+    }
+
+    pub fn from_pem(pem: &str) -> Result<(u128, Self), ForwardSecureSignatureError> {
+        Self::from_pkcs8_der(&pem_decode(SIGKEY_PEM_LABEL, pem)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{InMemorySigKeyDatabase, Keypair, SigKeyDb};
+    use crate::util::GeneratorSet;
+
+    #[test]
+    fn test_pem() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let mut db = InMemorySigKeyDatabase::new();
+        let (keypair, _) = Keypair::new(T, &gens, &mut rng, &mut db).unwrap();
+        let vk = keypair.ver_key;
+
+        let pem = vk.to_pem();
+        assert!(pem.starts_with("-----BEGIN FORWARD SECURE PUBLIC KEY-----\n"));
+        assert!(pem.lines().all(|line| line.len() <= PEM_LINE_LENGTH));
+        assert_eq!(Verkey::from_pem(&pem).unwrap().value, vk.value);
+        let crlf = format!("  {}  ", pem.replace('\n', "\r\n"));
+        assert_eq!(Verkey::from_pem(&crlf).unwrap().value, vk.value);
+
+        let sig_key = db.get_key(1).unwrap();
+        let pem = sig_key.to_pem(1);
+        let (t, decoded) = Sigkey::from_pem(&pem).unwrap();
+        assert_eq!(t, 1);
+        assert_eq!(decoded.to_pem(1), pem);
+
+        // Labels are not interchangeable, bodies are strict
+        assert!(Verkey::from_pem(&pem).is_err());
+        assert!(Sigkey::from_pem(&vk.to_pem()).is_err());
+        assert!(Verkey::from_pem(&vk.to_pem().replacen("-----\n", "-----\n!", 1)).is_err());
+        let der = vk.to_public_key_der();
+        let mut trailing = der.clone();
+        trailing.push(0);
+        assert!(Verkey::from_public_key_der(&trailing).is_err());
+        let mut bad_bits = der.clone();
+        let position = der.len() - vk.to_bytes().len() - 1;
+        bad_bits[position] = 1;
+        assert!(Verkey::from_public_key_der(&bad_bits).is_err());
+        assert!(pem_decode(
+            VERKEY_PEM_LABEL,
+            "-----BEGIN FORWARD SECURE PUBLIC KEY-----"
+        )
+        .is_err());
+    }
+}
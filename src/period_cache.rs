@@ -0,0 +1,266 @@
+//! Precomputations of a `Verifier` that can be saved to disk and loaded again, so short-lived
+//! verifier processes don't redo them on every start. Holds the pairing `e(h, y)` of the
+//! verkey, which turns each verification into a 2-pairing, and the path factors of the time
+//! periods verified or prepared so far.
+//!
+//! The file is bound to the generators, depth and verkey it was computed for and carries a
+//! checksum, so loading it for another verifier or after corruption fails with
+//! `InvalidPeriodCache`. The checksum is no protection against deliberate modification, a
+//! modified cache can make invalid signatures verify. Store it with the same protection as the
+//! verkey and public params.
+//!
+//! Encoded as `DST || binding || checksum || e(h, y) || number of periods || (t || path factor)..`
+//! with the number of periods and `t` as 8 and 16 byte big endian integers.
+
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+use amcl_wrapper::constants::GroupGT_SIZE;
+use amcl_wrapper::extension_field_gt::GT;
+use amcl_wrapper::group_elem::GroupElement;
+use sha3::{Digest, Sha3_256};
+
+use crate::errors::ForwardSecureSignatureError;
+use crate::facade::Verifier;
+use crate::params::generators_digest;
+use crate::signature::Signature;
+use crate::staged::DEFAULT_PATH_FACTOR_CACHE_SIZE;
+use crate::util::calculate_path_factor_using_t_l;
+use crate::{ate_2_pairing, ate_multi_pairing, SignatureGroup, SIGNATURE_GROUP_SIZE};
+
+const PERIOD_CACHE_DST: &[u8] = b"PIXEL-PERIOD-CACHE-V1";
+
+pub struct PeriodCache {
+    /// Hash of the generators, depth and verkey
+    binding: [u8; 32],
+    /// e(h, y)
+    prepared: GT,
+    path_factors: BTreeMap<u128, SignatureGroup>,
+}
+
+fn binding(verifier: &Verifier) -> [u8; 32] {
+    let mut hasher = Sha3_256::new();
+    hasher.input(PERIOD_CACHE_DST);
+    hasher.input(generators_digest(verifier.generators()));
+    hasher.input([verifier.l()]);
+    hasher.input(verifier.verkey().to_bytes());
+    let mut b = [0; 32];
+    b.copy_from_slice(&hasher.result());
+    b
+}
+
+fn checksum(binding: &[u8; 32], body: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha3_256::new();
+    hasher.input(PERIOD_CACHE_DST);
+    hasher.input(binding);
+    hasher.input(body);
+    let mut c = [0; 32];
+    c.copy_from_slice(&hasher.result());
+    c
+}
+
+fn invalid(msg: &str) -> ForwardSecureSignatureError {
+    ForwardSecureSignatureError::InvalidPeriodCache {
+        msg: msg.to_string(),
+    }
+}
+
+impl PeriodCache {
+    /// Computes `e(h, y)` for the verifier's verkey, path factors are added as periods are
+    /// verified or prepared
+    pub fn new(verifier: &Verifier) -> Self {
+        let gens = verifier.generators();
+        Self {
+            binding: binding(verifier),
+            prepared: ate_multi_pairing(vec![(&gens.1[0], &verifier.verkey().value)]),
+            path_factors: BTreeMap::new(),
+        }
+    }
+
+    /// Compute the path factors of all `periods`, e.g. of the periods the process will verify
+    pub fn prepare(
+        &mut self,
+        verifier: &Verifier,
+        periods: RangeInclusive<u128>,
+    ) -> Result<(), ForwardSecureSignatureError> {
+        self.check_binding(verifier)?;
+        for t in periods {
+            if let Entry::Vacant(e) = self.path_factors.entry(t) {
+                e.insert(calculate_path_factor_using_t_l(
+                    t,
+                    verifier.l(),
+                    verifier.generators(),
+                )?);
+            }
+        }
+        Ok(())
+    }
+
+    /// Same as `Verifier::verify` using the precomputations. The path factor of `t` is kept if
+    /// it was not cached yet, up to `DEFAULT_PATH_FACTOR_CACHE_SIZE` periods beyond those
+    /// prepared.
+    pub fn verify(
+        &mut self,
+        verifier: &Verifier,
+        msg: &[u8],
+        t: u128,
+        sig: &Signature,
+    ) -> Result<bool, ForwardSecureSignatureError> {
+        self.check_binding(verifier)?;
+        let gens = verifier.generators();
+        let l = verifier.l();
+        if gens.1.len() < (l as usize + 2) {
+            return Err(ForwardSecureSignatureError::NotEnoughGenerators { n: l as usize + 2 });
+        }
+        if sig.is_identity() || verifier.verkey().is_identity() || !sig.has_correct_oder() {
+            return Ok(false);
+        }
+        let mut base = match self.path_factors.get(&t) {
+            Some(pf) => pf.clone(),
+            None => {
+                let pf = calculate_path_factor_using_t_l(t, l, gens)?;
+                if self.path_factors.len() < DEFAULT_PATH_FACTOR_CACHE_SIZE {
+                    self.path_factors.insert(t, pf.clone());
+                }
+                pf
+            }
+        };
+        base += &gens.1[l as usize + 1] * &Signature::hash_message(msg);
+
+        // e(sigma_1, -g2) * e(base, sigma_2) * e(h, y) == 1
+        let e = ate_2_pairing(&sig.sigma_1, &gens.0.negation(), &base, &sig.sigma_2);
+        Ok(GT::product(&e, &self.prepared).is_one())
+    }
+
+    /// Number of periods whose path factor is cached
+    pub fn num_periods(&self) -> usize {
+        self.path_factors.len()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut body = self.prepared.to_bytes();
+        body.extend_from_slice(&(self.path_factors.len() as u64).to_be_bytes());
+        for (t, pf) in &self.path_factors {
+            body.extend_from_slice(&t.to_be_bytes());
+            body.append(&mut pf.to_bytes());
+        }
+        let mut bytes = PERIOD_CACHE_DST.to_vec();
+        bytes.extend_from_slice(&self.binding);
+        bytes.extend_from_slice(&checksum(&self.binding, &body));
+        bytes.append(&mut body);
+        bytes
+    }
+
+    /// Decode a cache created for `verifier`
+    pub fn from_bytes(
+        bytes: &[u8],
+        verifier: &Verifier,
+    ) -> Result<Self, ForwardSecureSignatureError> {
+        let header = PERIOD_CACHE_DST.len() + 64;
+        if bytes.len() < header + GroupGT_SIZE + 8 || !bytes.starts_with(PERIOD_CACHE_DST) {
+            return Err(invalid("not a period cache"));
+        }
+        let expected = binding(verifier);
+        if bytes[PERIOD_CACHE_DST.len()..header - 32] != expected {
+            return Err(invalid("created for another verifier"));
+        }
+        let body = &bytes[header..];
+        if bytes[header - 32..header] != checksum(&expected, body) {
+            return Err(invalid("checksum mismatch"));
+        }
+
+        let prepared = GT::from_bytes(&body[..GroupGT_SIZE])?;
+        let (n, mut rest) = body[GroupGT_SIZE..].split_at(8);
+        let n = u64::from_be_bytes(n.try_into().unwrap());
+        let entry_size = 16 + SIGNATURE_GROUP_SIZE;
+        if rest.len() as u64 != n.saturating_mul(entry_size as u64) {
+            return Err(invalid("wrong number of periods"));
+        }
+        let mut path_factors = BTreeMap::new();
+        while !rest.is_empty() {
+            let (entry, tail) = rest.split_at(entry_size);
+            let t = u128::from_be_bytes(entry[..16].try_into().unwrap());
+            path_factors.insert(t, SignatureGroup::from_bytes(&entry[16..])?);
+            rest = tail;
+        }
+        Ok(Self {
+            binding: expected,
+            prepared,
+            path_factors,
+        })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ForwardSecureSignatureError> {
+        Ok(fs::write(path, self.to_bytes())?)
+    }
+
+    pub fn load<P: AsRef<Path>>(
+        path: P,
+        verifier: &Verifier,
+    ) -> Result<Self, ForwardSecureSignatureError> {
+        Self::from_bytes(&fs::read(path)?, verifier)
+    }
+
+    fn check_binding(&self, verifier: &Verifier) -> Result<(), ForwardSecureSignatureError> {
+        if self.binding != binding(verifier) {
+            return Err(invalid("created for another verifier"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::facade::Signer;
+    use crate::keys::InMemorySigKeyDatabase;
+    use crate::util::GeneratorSet;
+    use std::env;
+
+    #[test]
+    fn test_period_cache() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let (mut signer, _) =
+            Signer::new(T, gens.clone(), InMemorySigKeyDatabase::new(), &mut rng).unwrap();
+        let (other, _) = Signer::new(T, gens, InMemorySigKeyDatabase::new(), &mut rng).unwrap();
+        let verifier = signer.verifier();
+        let msg = "Hello".as_bytes();
+
+        let mut cache = verifier.period_cache();
+        cache.prepare(&verifier, 1..=2).unwrap();
+        assert_eq!(cache.num_periods(), 2);
+        signer.update_to(3, &mut rng).unwrap();
+        let sig = signer.sign(msg, &mut rng).unwrap();
+        assert!(cache.verify(&verifier, msg, 3, &sig).unwrap());
+        assert_eq!(cache.num_periods(), 3);
+        assert!(!cache.verify(&verifier, msg, 2, &sig).unwrap());
+        assert!(!cache
+            .verify(&verifier, "Hellp".as_bytes(), 3, &sig)
+            .unwrap());
+        assert!(cache.verify(&other.verifier(), msg, 3, &sig).is_err());
+
+        let path = env::temp_dir().join("test_period_cache.bin");
+        cache.save(&path).unwrap();
+        let mut loaded = PeriodCache::load(&path, &verifier).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.num_periods(), 3);
+        assert!(loaded.verify(&verifier, msg, 3, &sig).unwrap());
+        assert_eq!(loaded.to_bytes(), cache.to_bytes());
+
+        // Bound to the verifier and checked for corruption
+        let bytes = cache.to_bytes();
+        assert!(PeriodCache::from_bytes(&bytes, &other.verifier()).is_err());
+        let mut corrupted = bytes.clone();
+        let last = corrupted.len() - 1;
+        corrupted[last] ^= 1;
+        assert!(PeriodCache::from_bytes(&corrupted, &verifier).is_err());
+        assert!(PeriodCache::from_bytes(&bytes[..bytes.len() - 1], &verifier).is_err());
+        assert!(PeriodCache::from_bytes(&bytes[..10], &verifier).is_err());
+    }
+}
@@ -0,0 +1,220 @@
+//! Planning of key updates from an `EpochConfig`: when each update is due, when the key runs out
+//! of time periods and which update brings a key back to the wall clock after downtime.
+
+use crate::epoch::EpochConfig;
+use crate::errors::ForwardSecureSignatureError;
+
+/// Update of the key to time period `t`, due at Unix time `due_at`, the start of `t`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ScheduledUpdate {
+    pub t: u128,
+    pub due_at: u64,
+}
+
+/// What to do with a key given the wall clock
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum UpdateAction {
+    UpToDate,
+    /// The key is one time period behind, `SigManager::simple_update`
+    Simple,
+    /// The key is several time periods behind, `SigManager::fast_forward_update` to `to`
+    FastForward {
+        to: u128,
+    },
+    /// The key is ahead of the wall clock and can't sign until time period `t` begins
+    Ahead {
+        t: u128,
+    },
+    /// The last time period has ended, a new key is needed
+    Exhausted,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ExhaustionStatus {
+    Ok {
+        remaining: u128,
+    },
+    /// At most the warning threshold of time periods are left after the current one
+    Warning {
+        remaining: u128,
+        exhausted_at: u64,
+    },
+    Exhausted,
+}
+
+pub struct RotationPlanner {
+    epochs: EpochConfig,
+    /// Number of time periods left after the current one at which exhaustion is flagged
+    warn_remaining: u128,
+}
+
+impl RotationPlanner {
+    /// By default exhaustion is flagged once 10% of the time periods, at least 1, are left
+    pub fn new(epochs: EpochConfig) -> Self {
+        let warn_remaining = std::cmp::max(1, epochs.T / 10);
+        Self {
+            epochs,
+            warn_remaining,
+        }
+    }
+
+    pub fn with_warning_threshold(mut self, warn_remaining: u128) -> Self {
+        self.warn_remaining = warn_remaining;
+        self
+    }
+
+    pub fn epochs(&self) -> &EpochConfig {
+        &self.epochs
+    }
+
+    /// Unix time at which the last time period ends
+    pub fn exhausted_at(&self) -> Result<u64, ForwardSecureSignatureError> {
+        let last = self.epochs.period_start(self.epochs.T)?;
+        last.checked_add(self.epochs.period_duration).ok_or(
+            ForwardSecureSignatureError::KeyExpired {
+                t: self.epochs.T + 1,
+                T: self.epochs.T,
+            },
+        )
+    }
+
+    /// Updates after time period `current_t`, in order, up to the last time period. Lazy since
+    /// there can be very many time periods.
+    pub fn updates_after(&self, current_t: u128) -> impl Iterator<Item = ScheduledUpdate> + '_ {
+        (current_t.saturating_add(1)..=self.epochs.T).map_while(move |t| {
+            self.epochs
+                .period_start(t)
+                .ok()
+                .map(|due_at| ScheduledUpdate { t, due_at })
+        })
+    }
+
+    /// Next update after time period `current_t`, `None` if `current_t` is the last one
+    pub fn next_update(&self, current_t: u128) -> Option<ScheduledUpdate> {
+        self.updates_after(current_t).next()
+    }
+
+    pub fn exhaustion_status(
+        &self,
+        now: u64,
+    ) -> Result<ExhaustionStatus, ForwardSecureSignatureError> {
+        let t = match self.epochs.period_at(now) {
+            Ok(t) => t,
+            Err(ForwardSecureSignatureError::KeyExpired { .. }) => {
+                return Ok(ExhaustionStatus::Exhausted)
+            }
+            Err(ForwardSecureSignatureError::BeforeGenesis { .. }) => 1,
+            Err(e) => return Err(e),
+        };
+        let remaining = self.epochs.T - t;
+        if remaining <= self.warn_remaining {
+            Ok(ExhaustionStatus::Warning {
+                remaining,
+                exhausted_at: self.exhausted_at()?,
+            })
+        } else {
+            Ok(ExhaustionStatus::Ok { remaining })
+        }
+    }
+
+    /// Update bringing a key at time period `key_t` to the time period of `now`. Before genesis
+    /// time period 1 is expected.
+    pub fn catch_up(
+        &self,
+        key_t: u128,
+        now: u64,
+    ) -> Result<UpdateAction, ForwardSecureSignatureError> {
+        let expected = match self.epochs.period_at(now) {
+            Ok(t) => t,
+            Err(ForwardSecureSignatureError::KeyExpired { .. }) => {
+                return Ok(UpdateAction::Exhausted)
+            }
+            Err(ForwardSecureSignatureError::BeforeGenesis { .. }) => 1,
+            Err(e) => return Err(e),
+        };
+        Ok(if key_t == expected {
+            UpdateAction::UpToDate
+        } else if key_t > expected {
+            UpdateAction::Ahead { t: key_t }
+        } else if key_t + 1 == expected {
+            UpdateAction::Simple
+        } else {
+            UpdateAction::FastForward { to: expected }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_rotation_planner() {
+        let epochs = EpochConfig::new(1000, Duration::from_secs(60), 15).unwrap();
+        let planner = RotationPlanner::new(epochs).with_warning_threshold(2);
+        assert_eq!(planner.exhausted_at().unwrap(), 1000 + 15 * 60);
+
+        let updates = planner.updates_after(1).collect::<Vec<_>>();
+        assert_eq!(updates.len(), 14);
+        assert_eq!(updates[0], ScheduledUpdate { t: 2, due_at: 1060 });
+        assert_eq!(
+            updates[13],
+            ScheduledUpdate {
+                t: 15,
+                due_at: 1840
+            }
+        );
+        assert_eq!(
+            planner.next_update(14),
+            Some(ScheduledUpdate {
+                t: 15,
+                due_at: 1840
+            })
+        );
+        assert_eq!(planner.next_update(15), None);
+
+        assert_eq!(
+            planner.exhaustion_status(500).unwrap(),
+            ExhaustionStatus::Ok { remaining: 14 }
+        );
+        assert_eq!(
+            planner.exhaustion_status(1000 + 12 * 60).unwrap(),
+            ExhaustionStatus::Warning {
+                remaining: 2,
+                exhausted_at: 1900
+            }
+        );
+        assert_eq!(
+            planner.exhaustion_status(1900).unwrap(),
+            ExhaustionStatus::Exhausted
+        );
+
+        assert_eq!(planner.catch_up(1, 999).unwrap(), UpdateAction::UpToDate);
+        assert_eq!(planner.catch_up(3, 1130).unwrap(), UpdateAction::UpToDate);
+        assert_eq!(planner.catch_up(2, 1130).unwrap(), UpdateAction::Simple);
+        // Down for several periods
+        assert_eq!(
+            planner.catch_up(2, 1500).unwrap(),
+            UpdateAction::FastForward { to: 9 }
+        );
+        assert_eq!(
+            planner.catch_up(4, 1130).unwrap(),
+            UpdateAction::Ahead { t: 4 }
+        );
+        assert_eq!(planner.catch_up(15, 2000).unwrap(), UpdateAction::Exhausted);
+
+        let planner =
+            RotationPlanner::new(EpochConfig::new(0, Duration::from_secs(1), 1023).unwrap());
+        assert_eq!(
+            planner.exhaustion_status(1023 - 103).unwrap(),
+            ExhaustionStatus::Warning {
+                remaining: 102,
+                exhausted_at: 1023
+            }
+        );
+    }
+}
@@ -0,0 +1,278 @@
+//! Proof of knowledge of a signature for time period `t` on a message that may be kept hidden.
+//!
+//! A signature `(sigma_1, sigma_2)` satisfies `e(sigma_1, g2) = e(h, y) * e(pf + h_{l+1}*m, sigma_2)`
+//! where `pf` is the path factor of `t` and `m` the hashed message. The prover re-randomizes the
+//! signature with `r` to `sigma_2' = sigma_2 + g2*r`, `sigma_1' = sigma_1 + (pf + h_{l+1}*m)*r`
+//! and blinds `sigma_1'` to `S = sigma_1' + h*rho`. Both `S` and `sigma_2'` are uniformly random.
+//! Then `e(S, g2) / (e(h, y) * e(pf, sigma_2')) = e(h_{l+1}, sigma_2')^m * e(h, g2)^rho` and the
+//! prover proves knowledge of `m` (unless disclosed) and `rho` with a Schnorr proof in GT made
+//! non-interactive with Fiat-Shamir over a verifier chosen nonce.
+
+use amcl_wrapper::extension_field_gt::GT;
+use amcl_wrapper::field_elem::FieldElement;
+use amcl_wrapper::group_elem::GroupElement;
+use rand::{CryptoRng, RngCore};
+
+use crate::errors::ForwardSecureSignatureError;
+use crate::keys::Verkey;
+use crate::signature::Signature;
+use crate::util::{calculate_path_factor_using_t_l, random_field_element, GeneratorSet};
+use crate::{ate_multi_pairing, SignatureGroup, VerkeyGroup};
+
+const POK_DST: &[u8] = b"PIXEL-POK-SIG-V1";
+
+/// Message of the signature, either kept hidden from the verifier or disclosed
+#[derive(Clone, Copy, Debug)]
+pub enum ProvedMessage<'a> {
+    Hidden(&'a [u8]),
+    Disclosed(&'a [u8]),
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SignatureKnowledgeProof {
+    /// Blinded and re-randomized `sigma_1`
+    pub blinded_sigma_1: SignatureGroup,
+    /// Re-randomized `sigma_2`
+    pub sigma_2: VerkeyGroup,
+    pub challenge: FieldElement,
+    /// Response for the message, `None` when the message is disclosed
+    pub resp_msg: Option<FieldElement>,
+    pub resp_blinding: FieldElement,
+}
+
+impl SignatureKnowledgeProof {
+    /// Prove knowledge of `sig` on `msg` for time period `t`. `nonce` is chosen by the verifier
+    /// to prevent replay.
+    pub fn new<R: RngCore + CryptoRng>(
+        sig: &Signature,
+        msg: ProvedMessage,
+        t: u128,
+        l: u8,
+        gens: &GeneratorSet,
+        nonce: &[u8],
+        rng: &mut R,
+    ) -> Result<Self, ForwardSecureSignatureError> {
+        check_gens(l, gens)?;
+        let (msg, disclosed) = match msg {
+            ProvedMessage::Hidden(msg) => (msg, None),
+            ProvedMessage::Disclosed(msg) => (msg, Some(msg)),
+        };
+        let m = Signature::hash_message(msg);
+        let pf = calculate_path_factor_using_t_l(t, l, gens)?;
+        let h = &gens.1[0];
+        let h_m = &gens.1[l as usize + 1];
+
+        let r = random_field_element(rng)?;
+        let rho = random_field_element(rng)?;
+        let sigma_2 = &sig.sigma_2 + (&gens.0 * &r);
+        let mut blinded_sigma_1 = &sig.sigma_1 + (&pf + (h_m * &m)) * &r;
+        blinded_sigma_1 += h * &rho;
+
+        let (base_msg, base_blinding) = bases(&sigma_2, l, gens);
+        let k_rho = random_field_element(rng)?;
+        let mut commitment = base_blinding.pow(&k_rho);
+        let k_m = if disclosed.is_none() {
+            let k_m = random_field_element(rng)?;
+            commitment = commitment * base_msg.pow(&k_m);
+            Some(k_m)
+        } else {
+            None
+        };
+        let challenge = challenge(&blinded_sigma_1, &sigma_2, &commitment, t, disclosed, nonce);
+        Ok(Self {
+            blinded_sigma_1,
+            sigma_2,
+            resp_msg: k_m.map(|k| k - &challenge * &m),
+            resp_blinding: k_rho - &challenge * &rho,
+            challenge,
+        })
+    }
+
+    /// Verify the proof. `disclosed_msg` must be given if and only if the message was not hidden.
+    pub fn verify(
+        &self,
+        disclosed_msg: Option<&[u8]>,
+        t: u128,
+        l: u8,
+        gens: &GeneratorSet,
+        verkey: &Verkey,
+        nonce: &[u8],
+    ) -> Result<bool, ForwardSecureSignatureError> {
+        check_gens(l, gens)?;
+        if disclosed_msg.is_some() == self.resp_msg.is_some()
+            || self.blinded_sigma_1.is_identity()
+            || self.sigma_2.is_identity()
+            || verkey.is_identity()
+            || !self.blinded_sigma_1.has_correct_order()
+            || !self.sigma_2.has_correct_order()
+        {
+            return Ok(false);
+        }
+        let pf = calculate_path_factor_using_t_l(t, l, gens)?;
+        let h = &gens.1[0];
+        let (base_msg, base_blinding) = bases(&self.sigma_2, l, gens);
+
+        // z = e(S, g2) / (e(h, y) * e(pf, sigma_2'))
+        let mut z = ate_multi_pairing(vec![
+            (&self.blinded_sigma_1, &gens.0),
+            (&h.negation(), &verkey.value),
+            (&pf.negation(), &self.sigma_2),
+        ]);
+        if let Some(msg) = disclosed_msg {
+            let m = Signature::hash_message(msg);
+            z = z * base_msg.pow(&m).inverse();
+        }
+        let mut commitment = base_blinding.pow(&self.resp_blinding) * z.pow(&self.challenge);
+        if let Some(s_m) = &self.resp_msg {
+            commitment = commitment * base_msg.pow(s_m);
+        }
+        let c = challenge(
+            &self.blinded_sigma_1,
+            &self.sigma_2,
+            &commitment,
+            t,
+            disclosed_msg,
+            nonce,
+        );
+        Ok(c == self.challenge)
+    }
+}
+
+fn check_gens(l: u8, gens: &GeneratorSet) -> Result<(), ForwardSecureSignatureError> {
+    if gens.1.len() < (l as usize + 2) {
+        return Err(ForwardSecureSignatureError::NotEnoughGenerators { n: l as usize + 2 });
+    }
+    Ok(())
+}
+
+/// Returns `e(h_{l+1}, sigma_2)` and `e(h, g2)`
+fn bases(sigma_2: &VerkeyGroup, l: u8, gens: &GeneratorSet) -> (GT, GT) {
+    (
+        ate_multi_pairing(vec![(&gens.1[l as usize + 1], sigma_2)]),
+        ate_multi_pairing(vec![(&gens.1[0], &gens.0)]),
+    )
+}
+
+fn challenge(
+    blinded_sigma_1: &SignatureGroup,
+    sigma_2: &VerkeyGroup,
+    commitment: &GT,
+    t: u128,
+    disclosed_msg: Option<&[u8]>,
+    nonce: &[u8],
+) -> FieldElement {
+    let mut bytes = POK_DST.to_vec();
+    bytes.append(&mut blinded_sigma_1.to_bytes());
+    bytes.append(&mut sigma_2.to_bytes());
+    bytes.append(&mut commitment.to_bytes());
+    bytes.extend_from_slice(&t.to_be_bytes());
+    match disclosed_msg {
+        Some(msg) => {
+            bytes.push(1);
+            bytes.extend_from_slice(&(msg.len() as u64).to_be_bytes());
+            bytes.extend_from_slice(msg);
+        }
+        None => bytes.push(0),
+    }
+    bytes.extend_from_slice(nonce);
+    FieldElement::from_msg_hash(&bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{InMemorySigKeyDatabase, Keypair, SigManager};
+    use crate::util::calculate_l;
+
+    #[test]
+    fn test_signature_knowledge_proof() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let l = calculate_l(T).unwrap();
+        let t = 1u128;
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let mut db = InMemorySigKeyDatabase::new();
+        let (keypair, _) = Keypair::new(T, &gens, &mut rng, &mut db).unwrap();
+        let vk = &keypair.ver_key;
+        let sk = SigManager::get_key(t, &db).unwrap();
+        let msg = "Hello".as_bytes();
+        let sig = Signature::new(msg, t, l, &gens, sk, &mut rng).unwrap();
+        let nonce = b"nonce";
+
+        // Hidden message
+        let proof = SignatureKnowledgeProof::new(
+            &sig,
+            ProvedMessage::Hidden(msg),
+            t,
+            l,
+            &gens,
+            nonce,
+            &mut rng,
+        )
+        .unwrap();
+        assert_ne!(proof.sigma_2, sig.sigma_2);
+        assert!(proof.verify(None, t, l, &gens, vk, nonce).unwrap());
+        assert!(!proof.verify(Some(msg), t, l, &gens, vk, nonce).unwrap());
+        assert!(!proof.verify(None, t + 1, l, &gens, vk, nonce).unwrap());
+        assert!(!proof.verify(None, t, l, &gens, vk, b"other").unwrap());
+
+        // Disclosed message
+        let proof = SignatureKnowledgeProof::new(
+            &sig,
+            ProvedMessage::Disclosed(msg),
+            t,
+            l,
+            &gens,
+            nonce,
+            &mut rng,
+        )
+        .unwrap();
+        assert!(proof.verify(Some(msg), t, l, &gens, vk, nonce).unwrap());
+        assert!(!proof.verify(Some(b"Hi"), t, l, &gens, vk, nonce).unwrap());
+        assert!(!proof.verify(None, t, l, &gens, vk, nonce).unwrap());
+
+        // Proof for a signature on another message or for another verkey fails
+        let proof = SignatureKnowledgeProof::new(
+            &sig,
+            ProvedMessage::Hidden(b"Hi"),
+            t,
+            l,
+            &gens,
+            nonce,
+            &mut rng,
+        )
+        .unwrap();
+        assert!(!proof.verify(None, t, l, &gens, vk, nonce).unwrap());
+        let mut db_1 = InMemorySigKeyDatabase::new();
+        let (keypair_1, _) = Keypair::new(T, &gens, &mut rng, &mut db_1).unwrap();
+        let proof = SignatureKnowledgeProof::new(
+            &sig,
+            ProvedMessage::Hidden(msg),
+            t,
+            l,
+            &gens,
+            nonce,
+            &mut rng,
+        )
+        .unwrap();
+        assert!(!proof
+            .verify(None, t, l, &gens, &keypair_1.ver_key, nonce)
+            .unwrap());
+
+        // Points outside the prime order subgroup are rejected before the pairing
+        assert!(proof.verify(None, t, l, &gens, vk, nonce).unwrap());
+        #[cfg(feature = "VerkeyG2")]
+        {
+            let mut bad = proof.clone();
+            bad.blinded_sigma_1 = &bad.blinded_sigma_1 + &crate::compression::g1_outside_subgroup();
+            assert!(!bad.verify(None, t, l, &gens, vk, nonce).unwrap());
+        }
+        #[cfg(feature = "VerkeyG1")]
+        {
+            let mut bad = proof.clone();
+            bad.sigma_2 = &bad.sigma_2 + &crate::compression::g1_outside_subgroup();
+            assert!(!bad.verify(None, t, l, &gens, vk, nonce).unwrap());
+        }
+    }
+}
@@ -0,0 +1,17 @@
+//! Re-exports of the types needed by most users of this crate
+
+pub use crate::aggregator::Aggregator;
+pub use crate::domain::SignableDomain;
+pub use crate::epoch::EpochConfig;
+pub use crate::errors::ForwardSecureSignatureError;
+pub use crate::facade::{SignatureVerifier, Signer, Verifier};
+pub use crate::keys::{
+    InMemorySigKeyDatabase, Keypair, ProofOfPossession, SigKeyDb, SigManager, SigManagerEvent,
+    Sigkey, Verkey, VerkeyFingerprint,
+};
+pub use crate::params::PublicParams;
+pub use crate::registry::{CommitteeId, InMemoryVerkeyRegistry, PopStatus, VerkeyRegistry};
+pub use crate::session::{AccountableAggregate, AggregateTree, MultisigSession};
+pub use crate::signature::Signature;
+pub use crate::util::{calculate_l, GeneratorSet, GeneratorSetBuilder, GeneratorSetRef};
+pub use crate::{SignatureGroup, VerkeyGroup};
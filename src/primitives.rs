@@ -0,0 +1,149 @@
+//! The verification equation of a signature in its parts, for protocols composing their own
+//! batched or recursive verification. A signature `(sigma_1, sigma_2)` on message `m` for time
+//! period `t` under verkey `y` is valid iff
+//! `e(sigma_1, -g2) * e(h, y) * e(base, sigma_2) == 1` where `base` is the period base of `t`
+//! and `m`, see `period_base`.
+//!
+//! `verification_pairs` returns the 3 pairs of this product after the subgroup checks
+//! `Signature::verify` does, callers must not skip them by building the pairs themselves. Pairs
+//! of several signatures can be scaled by random scalars and put into one multi-pairing.
+
+use amcl_wrapper::field_elem::FieldElement;
+use amcl_wrapper::group_elem::GroupElement;
+
+use crate::errors::ForwardSecureSignatureError;
+use crate::signature::Signature;
+use crate::util::{calculate_path_factor_using_t_l, GeneratorSet};
+use crate::{ate_multi_pairing, SignatureGroup, VerkeyGroup};
+
+/// The message as the scalar that is signed
+pub fn message_scalar(msg: &[u8]) -> FieldElement {
+    Signature::hash_message(msg)
+}
+
+/// `h_0 * h_1^path[0] * ... * h_{l+1}^m` for the path of time period `t` in a tree of depth `l`
+/// and message scalar `m`
+pub fn period_base(
+    m: &FieldElement,
+    t: u128,
+    l: u8,
+    gens: &GeneratorSet,
+) -> Result<SignatureGroup, ForwardSecureSignatureError> {
+    if gens.1.len() < (l as usize + 2) {
+        return Err(ForwardSecureSignatureError::NotEnoughGenerators { n: l as usize + 2 });
+    }
+    let mut base = calculate_path_factor_using_t_l(t, l, gens)?;
+    base += &gens.1[l as usize + 1] * m;
+    Ok(base)
+}
+
+/// The pairs whose pairing product is 1 iff the signature is valid. Returns None if a point of
+/// the signature or the verkey is the identity or not in the prime order subgroup.
+pub fn verification_pairs(
+    sigma_1: &SignatureGroup,
+    sigma_2: &VerkeyGroup,
+    verkey: &VerkeyGroup,
+    base: &SignatureGroup,
+    gens: &GeneratorSet,
+) -> Option<[(SignatureGroup, VerkeyGroup); 3]> {
+    if sigma_1.is_identity()
+        || sigma_2.is_identity()
+        || verkey.is_identity()
+        || !sigma_1.has_correct_order()
+        || !sigma_2.has_correct_order()
+        || !verkey.has_correct_order()
+    {
+        return None;
+    }
+    Some([
+        (sigma_1.clone(), gens.0.negation()),
+        (gens.1[0].clone(), verkey.clone()),
+        (base.clone(), sigma_2.clone()),
+    ])
+}
+
+/// Verify a signature given as points, same as `Signature::verify` with the period base
+/// computed by the caller
+pub fn verify_raw(
+    sigma_1: &SignatureGroup,
+    sigma_2: &VerkeyGroup,
+    verkey: &VerkeyGroup,
+    base: &SignatureGroup,
+    gens: &GeneratorSet,
+) -> bool {
+    match verification_pairs(sigma_1, sigma_2, verkey, base, gens) {
+        Some(pairs) => ate_multi_pairing(pairs.iter().map(|(s, v)| (s, v)).collect()).is_one(),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::facade::Signer;
+    use crate::keys::InMemorySigKeyDatabase;
+
+    #[test]
+    fn test_verification_primitives() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let (mut signer, _) =
+            Signer::new(T, gens.clone(), InMemorySigKeyDatabase::new(), &mut rng).unwrap();
+        let verifier = signer.verifier();
+        let l = verifier.l();
+        let y = &signer.verkey().value.clone();
+
+        let msg_1 = "Hello".as_bytes();
+        let sig_1 = signer.sign(msg_1, &mut rng).unwrap();
+        signer.update_to(3, &mut rng).unwrap();
+        let msg_2 = "World".as_bytes();
+        let sig_2 = signer.sign(msg_2, &mut rng).unwrap();
+
+        let base_1 = period_base(&message_scalar(msg_1), 1, l, &gens).unwrap();
+        let base_2 = period_base(&message_scalar(msg_2), 3, l, &gens).unwrap();
+        assert!(verify_raw(
+            &sig_1.sigma_1,
+            &sig_1.sigma_2,
+            y,
+            &base_1,
+            &gens
+        ));
+        assert!(verify_raw(
+            &sig_2.sigma_1,
+            &sig_2.sigma_2,
+            y,
+            &base_2,
+            &gens
+        ));
+        assert!(!verify_raw(
+            &sig_1.sigma_1,
+            &sig_1.sigma_2,
+            y,
+            &base_2,
+            &gens
+        ));
+
+        // Both signatures in one randomized multi-pairing
+        let mut batch = vec![];
+        for (sig, base) in &[(&sig_1, &base_1), (&sig_2, &base_2)] {
+            let r = FieldElement::random();
+            for (s, v) in verification_pairs(&sig.sigma_1, &sig.sigma_2, y, base, &gens).unwrap() {
+                batch.push((&s * &r, v));
+            }
+        }
+        assert!(ate_multi_pairing(batch.iter().map(|(s, v)| (s, v)).collect()).is_one());
+        batch[2].0 = &batch[2].0 + &batch[2].0;
+        assert!(!ate_multi_pairing(batch.iter().map(|(s, v)| (s, v)).collect()).is_one());
+
+        assert!(verification_pairs(
+            &SignatureGroup::identity(),
+            &sig_1.sigma_2,
+            y,
+            &base_1,
+            &gens
+        )
+        .is_none());
+        assert!(period_base(&message_scalar(msg_1), 1, l + 1, &gens).is_err());
+    }
+}
@@ -0,0 +1,296 @@
+//! Protobuf messages of `proto/pixel.proto` for gRPC based validator infrastructure, with
+//! conversions from and to the types of this crate. The messages are defined with `prost` derives
+//! as `prost-build` generates them, so no `protoc` is needed to build; they must be kept in sync
+//! with the `.proto` file. Encode and decode with `prost::Message`.
+//!
+//! Converting from a message checks signatures and verkeys as their `from_bytes` do and fails
+//! with `InvalidProtoMessage` on missing fields or values out of range. Time periods are encoded
+//! as `uint64` so converting a transition message with a larger time period fails as well.
+
+use std::convert::{TryFrom, TryInto};
+
+use crate::errors::ForwardSecureSignatureError;
+use crate::keys;
+use crate::session;
+use crate::signature;
+use crate::transition;
+
+fn invalid(msg: &str) -> ForwardSecureSignatureError {
+    ForwardSecureSignatureError::InvalidProtoMessage {
+        msg: msg.to_string(),
+    }
+}
+
+fn to_u64<T: TryInto<u64>>(value: T, field: &str) -> Result<u64, ForwardSecureSignatureError> {
+    value
+        .try_into()
+        .map_err(|_| invalid(&format!("{} does not fit in uint64", field)))
+}
+
+fn to_usize(value: u64, field: &str) -> Result<usize, ForwardSecureSignatureError> {
+    usize::try_from(value).map_err(|_| invalid(&format!("{} does not fit in usize", field)))
+}
+
+fn required<T>(field: Option<T>, name: &str) -> Result<T, ForwardSecureSignatureError> {
+    field.ok_or_else(|| invalid(&format!("missing {}", name)))
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Signature {
+    #[prost(bytes = "vec", tag = "1")]
+    pub value: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Verkey {
+    #[prost(bytes = "vec", tag = "1")]
+    pub value: Vec<u8>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum TransitionMessageKind {
+    Unspecified = 0,
+    Announce = 1,
+    ConfirmDeletion = 2,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct TransitionMessage {
+    #[prost(enumeration = "TransitionMessageKind", tag = "1")]
+    pub kind: i32,
+    #[prost(uint64, tag = "2")]
+    pub member: u64,
+    #[prost(uint64, tag = "3")]
+    pub from: u64,
+    #[prost(uint64, tag = "4")]
+    pub to: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SignedTransitionMessage {
+    #[prost(message, optional, tag = "1")]
+    pub message: Option<TransitionMessage>,
+    #[prost(message, optional, tag = "2")]
+    pub signature: Option<Signature>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SignatureShare {
+    #[prost(uint64, tag = "1")]
+    pub signer: u64,
+    #[prost(message, optional, tag = "2")]
+    pub signature: Option<Signature>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct AccountableAggregate {
+    #[prost(message, optional, tag = "1")]
+    pub signature: Option<Signature>,
+    #[prost(uint64, repeated, tag = "2")]
+    pub signers: Vec<u64>,
+}
+
+impl From<&signature::Signature> for Signature {
+    fn from(sig: &signature::Signature) -> Self {
+        Self {
+            value: sig.to_bytes(),
+        }
+    }
+}
+
+impl TryFrom<Signature> for signature::Signature {
+    type Error = ForwardSecureSignatureError;
+    fn try_from(sig: Signature) -> Result<Self, Self::Error> {
+        Self::from_bytes(&sig.value)
+    }
+}
+
+impl From<&keys::Verkey> for Verkey {
+    fn from(vk: &keys::Verkey) -> Self {
+        Self {
+            value: vk.to_bytes(),
+        }
+    }
+}
+
+impl TryFrom<Verkey> for keys::Verkey {
+    type Error = ForwardSecureSignatureError;
+    fn try_from(vk: Verkey) -> Result<Self, Self::Error> {
+        Self::from_bytes(&vk.value)
+    }
+}
+
+impl From<transition::TransitionMessageKind> for TransitionMessageKind {
+    fn from(kind: transition::TransitionMessageKind) -> Self {
+        match kind {
+            transition::TransitionMessageKind::Announce => TransitionMessageKind::Announce,
+            transition::TransitionMessageKind::ConfirmDeletion => {
+                TransitionMessageKind::ConfirmDeletion
+            }
+        }
+    }
+}
+
+impl TryFrom<&transition::TransitionMessage> for TransitionMessage {
+    type Error = ForwardSecureSignatureError;
+    fn try_from(msg: &transition::TransitionMessage) -> Result<Self, Self::Error> {
+        Ok(Self {
+            kind: TransitionMessageKind::from(msg.kind) as i32,
+            member: to_u64(msg.member, "member")?,
+            from: to_u64(msg.from, "from")?,
+            to: to_u64(msg.to, "to")?,
+        })
+    }
+}
+
+impl TryFrom<TransitionMessage> for transition::TransitionMessage {
+    type Error = ForwardSecureSignatureError;
+    fn try_from(msg: TransitionMessage) -> Result<Self, Self::Error> {
+        let kind = match TransitionMessageKind::try_from(msg.kind) {
+            Ok(TransitionMessageKind::Announce) => transition::TransitionMessageKind::Announce,
+            Ok(TransitionMessageKind::ConfirmDeletion) => {
+                transition::TransitionMessageKind::ConfirmDeletion
+            }
+            _ => return Err(invalid(&format!("invalid kind {}", msg.kind))),
+        };
+        Ok(Self {
+            kind,
+            member: to_usize(msg.member, "member")?,
+            from: msg.from as u128,
+            to: msg.to as u128,
+        })
+    }
+}
+
+impl TryFrom<&transition::SignedTransitionMessage> for SignedTransitionMessage {
+    type Error = ForwardSecureSignatureError;
+    fn try_from(msg: &transition::SignedTransitionMessage) -> Result<Self, Self::Error> {
+        Ok(Self {
+            message: Some(TransitionMessage::try_from(&msg.message)?),
+            signature: Some(Signature::from(&msg.signature)),
+        })
+    }
+}
+
+impl TryFrom<SignedTransitionMessage> for transition::SignedTransitionMessage {
+    type Error = ForwardSecureSignatureError;
+    fn try_from(msg: SignedTransitionMessage) -> Result<Self, Self::Error> {
+        Ok(Self {
+            message: required(msg.message, "message")?.try_into()?,
+            signature: required(msg.signature, "signature")?.try_into()?,
+        })
+    }
+}
+
+impl SignatureShare {
+    pub fn new(signer: usize, sig: &signature::Signature) -> Self {
+        Self {
+            signer: signer as u64,
+            signature: Some(sig.into()),
+        }
+    }
+
+    /// Index of the signer and its signature share, e.g. for `MultisigSession::add_share`
+    pub fn into_share(self) -> Result<(usize, signature::Signature), ForwardSecureSignatureError> {
+        Ok((
+            to_usize(self.signer, "signer")?,
+            required(self.signature, "signature")?.try_into()?,
+        ))
+    }
+}
+
+impl From<&session::AccountableAggregate> for AccountableAggregate {
+    fn from(aggr: &session::AccountableAggregate) -> Self {
+        Self {
+            signature: Some((&aggr.signature).into()),
+            signers: aggr.signers.iter().map(|i| *i as u64).collect(),
+        }
+    }
+}
+
+impl TryFrom<AccountableAggregate> for session::AccountableAggregate {
+    type Error = ForwardSecureSignatureError;
+    fn try_from(aggr: AccountableAggregate) -> Result<Self, Self::Error> {
+        Ok(Self {
+            signature: required(aggr.signature, "signature")?.try_into()?,
+            signers: aggr
+                .signers
+                .into_iter()
+                .map(|i| to_usize(i, "signer"))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::facade::Signer;
+    use crate::keys::InMemorySigKeyDatabase;
+    use crate::util::GeneratorSet;
+    use prost::Message;
+
+    #[test]
+    fn test_proto_conversions() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let (mut signer, _) =
+            Signer::new(T, gens, InMemorySigKeyDatabase::new(), &mut rng).unwrap();
+        let sig = signer.sign("Hello".as_bytes(), &mut rng).unwrap();
+
+        let bytes = Signature::from(&sig).encode_to_vec();
+        let decoded = signature::Signature::try_from(Signature::decode(&bytes[..]).unwrap());
+        assert_eq!(decoded.unwrap(), sig);
+        let vk = signer.verkey();
+        let bytes = Verkey::from(vk).encode_to_vec();
+        let decoded = keys::Verkey::try_from(Verkey::decode(&bytes[..]).unwrap()).unwrap();
+        assert_eq!(decoded.value, vk.value);
+        assert!(keys::Verkey::try_from(Verkey {
+            value: sig.to_bytes()
+        })
+        .is_err());
+
+        let msg = transition::SignedTransitionMessage {
+            message: transition::TransitionMessage {
+                kind: transition::TransitionMessageKind::Announce,
+                member: 3,
+                from: 1,
+                to: 4,
+            },
+            signature: sig.clone(),
+        };
+        let bytes = SignedTransitionMessage::try_from(&msg)
+            .unwrap()
+            .encode_to_vec();
+        let decoded = SignedTransitionMessage::decode(&bytes[..]).unwrap();
+        let decoded = transition::SignedTransitionMessage::try_from(decoded).unwrap();
+        assert_eq!(decoded.message, msg.message);
+        assert_eq!(decoded.signature, sig);
+        let mut too_large = msg.message;
+        too_large.to = 1 << 64;
+        assert!(TransitionMessage::try_from(&too_large).is_err());
+        let unspecified = TransitionMessage {
+            kind: TransitionMessageKind::Unspecified as i32,
+            member: 3,
+            from: 1,
+            to: 4,
+        };
+        assert!(transition::TransitionMessage::try_from(unspecified).is_err());
+
+        let share = SignatureShare::decode(&SignatureShare::new(2, &sig).encode_to_vec()[..]);
+        assert_eq!(share.unwrap().into_share().unwrap(), (2, sig.clone()));
+        assert!(SignatureShare::default().into_share().is_err());
+
+        let aggr = session::AccountableAggregate {
+            signature: sig.clone(),
+            signers: vec![1, 2, 5],
+        };
+        let bytes = AccountableAggregate::from(&aggr).encode_to_vec();
+        let decoded = AccountableAggregate::decode(&bytes[..]).unwrap();
+        let decoded = session::AccountableAggregate::try_from(decoded).unwrap();
+        assert_eq!(decoded.signers, aggr.signers);
+        assert_eq!(decoded.signature, sig);
+    }
+}
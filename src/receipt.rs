@@ -0,0 +1,285 @@
+//! Receipts of a verification gateway. The gateway verifies a batch of signatures of a committee
+//! for a time period and signs a receipt stating that the batch of `N` signatures verified
+//! against the committee, so components behind the gateway check one signature of the gateway
+//! instead of the whole batch.
+//!
+//! A receipt names the committee by its `CommitteeId` and the fingerprint of the aggregate
+//! verkey the batch was verified with. It is only as trustworthy as the gateway: the gateway
+//! vouches that the aggregate verkey belongs to the committee, which the receipt can't show.
+//! The batch is referenced by its digest, which depends on the order of the messages and
+//! signatures, so holders of the batch can check with `covers` that the receipt is about it.
+
+use rand::{CryptoRng, RngCore};
+use sha3::{Digest, Sha3_256};
+
+use crate::envelope::PayloadDigest;
+use crate::errors::ForwardSecureSignatureError;
+use crate::facade::{Signer, Verifier};
+use crate::keys::{SigKeyDb, VerkeyFingerprint};
+use crate::registry::CommitteeId;
+use crate::signature::Signature;
+
+const RECEIPT_DST: &[u8] = b"PIXEL-RECEIPT-V1";
+const RECEIPT_BATCH_DST: &[u8] = b"PIXEL-RECEIPT-BATCH-V1";
+
+/// Digest of the messages and signatures of a batch, in order
+pub fn batch_digest(batch: &[(&[u8], &Signature)]) -> PayloadDigest {
+    let mut hasher = Sha3_256::new();
+    hasher.input(RECEIPT_BATCH_DST);
+    hasher.input((batch.len() as u64).to_be_bytes());
+    for (msg, sig) in batch {
+        hasher.input((msg.len() as u64).to_be_bytes());
+        hasher.input(msg);
+        hasher.input(sig.to_bytes());
+    }
+    let mut digest = [0; 32];
+    digest.copy_from_slice(&hasher.result());
+    digest
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct VerificationReceipt {
+    pub committee: CommitteeId,
+    /// Fingerprint of the aggregate verkey of the committee
+    pub committee_verkey: VerkeyFingerprint,
+    /// Time period of the verified signatures
+    pub t: u128,
+    /// Number of signatures in the batch
+    pub count: u64,
+    pub batch: PayloadDigest,
+    pub gateway: VerkeyFingerprint,
+    /// Time period of the gateway's signing key
+    pub gateway_t: u128,
+    pub signature: Signature,
+}
+
+impl VerificationReceipt {
+    /// Verify all signatures of `batch` for time period `t` with `committee_verifier`, the
+    /// verifier of the aggregate verkey of `committee`, and sign a receipt with the gateway's
+    /// key for its current time period. Fails with `BatchVerificationFailed` for the first
+    /// invalid signature, no receipt is issued for a batch with an invalid signature.
+    pub fn issue<D: SigKeyDb, R: RngCore + CryptoRng>(
+        batch: &[(&[u8], &Signature)],
+        t: u128,
+        committee: CommitteeId,
+        committee_verifier: &Verifier,
+        gateway: &mut Signer<D>,
+        rng: &mut R,
+    ) -> Result<Self, ForwardSecureSignatureError> {
+        if batch.is_empty() {
+            return Err(ForwardSecureSignatureError::EmptyBatch);
+        }
+        let vk = committee_verifier.verkey();
+        if !Signature::verify_batch(
+            batch,
+            t,
+            committee_verifier.l(),
+            committee_verifier.generators(),
+            vk,
+        )? {
+            // Only a failing batch is verified signature by signature, to report the position
+            let mut position = 0;
+            for (i, (msg, sig)) in batch.iter().enumerate() {
+                if !committee_verifier.verify(msg, t, sig)? {
+                    position = i;
+                    break;
+                }
+            }
+            return Err(ForwardSecureSignatureError::BatchVerificationFailed { position, t });
+        }
+
+        let committee_verkey = vk.fingerprint();
+        let count = batch.len() as u64;
+        let digest = batch_digest(batch);
+        let fingerprint = gateway.verkey().fingerprint();
+        let gateway_t = gateway.t();
+        let msg = statement(
+            &committee,
+            &committee_verkey,
+            t,
+            count,
+            &digest,
+            &fingerprint,
+            gateway_t,
+        );
+        let signature = gateway.sign(&msg, rng)?;
+        Ok(Self {
+            committee,
+            committee_verkey,
+            t,
+            count,
+            batch: digest,
+            gateway: fingerprint,
+            gateway_t,
+            signature,
+        })
+    }
+
+    /// Check the gateway's signature with the verifier of the gateway. Returns false if the
+    /// receipt names another gateway.
+    pub fn verify(&self, gateway: &Verifier) -> Result<bool, ForwardSecureSignatureError> {
+        if gateway.verkey().fingerprint() != self.gateway {
+            return Ok(false);
+        }
+        gateway.verify(&self.statement(), self.gateway_t, &self.signature)
+    }
+
+    /// Whether the receipt is about `batch`
+    pub fn covers(&self, batch: &[(&[u8], &Signature)]) -> bool {
+        batch.len() as u64 == self.count && batch_digest(batch) == self.batch
+    }
+
+    /// Check that the receipt is about `batch` and the gateway's signature
+    pub fn verify_batch(
+        &self,
+        batch: &[(&[u8], &Signature)],
+        gateway: &Verifier,
+    ) -> Result<bool, ForwardSecureSignatureError> {
+        if !self.covers(batch) {
+            return Ok(false);
+        }
+        self.verify(gateway)
+    }
+
+    fn statement(&self) -> Vec<u8> {
+        statement(
+            &self.committee,
+            &self.committee_verkey,
+            self.t,
+            self.count,
+            &self.batch,
+            &self.gateway,
+            self.gateway_t,
+        )
+    }
+}
+
+/// The message signed by the gateway
+fn statement(
+    committee: &CommitteeId,
+    committee_verkey: &VerkeyFingerprint,
+    t: u128,
+    count: u64,
+    batch: &PayloadDigest,
+    gateway: &VerkeyFingerprint,
+    gateway_t: u128,
+) -> Vec<u8> {
+    let mut msg = RECEIPT_DST.to_vec();
+    msg.extend_from_slice(committee.as_bytes());
+    msg.extend_from_slice(committee_verkey.as_bytes());
+    msg.extend_from_slice(&t.to_be_bytes());
+    msg.extend_from_slice(&count.to_be_bytes());
+    msg.extend_from_slice(batch);
+    msg.extend_from_slice(gateway.as_bytes());
+    msg.extend_from_slice(&gateway_t.to_be_bytes());
+    msg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{InMemorySigKeyDatabase, Keypair, Verkey};
+    use crate::util::{calculate_l, GeneratorSet};
+
+    #[test]
+    fn test_verification_receipt() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let l = calculate_l(T).unwrap();
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+
+        let mut vks = vec![];
+        let mut sks = vec![];
+        for _ in 0..2 {
+            let mut db = InMemorySigKeyDatabase::new();
+            let (keypair, manager) = Keypair::new(T, &gens, &mut rng, &mut db).unwrap();
+            vks.push(keypair.ver_key);
+            sks.push(manager.get_current_key(&db).unwrap().clone());
+        }
+        let committee = CommitteeId::new(&[(1, &vks[0], 1), (2, &vks[1], 1)], 2).unwrap();
+        let avk = Verkey::aggregate(vks.iter().collect());
+        let committee_verifier = Verifier::new(T, gens.clone(), avk).unwrap();
+
+        let msgs = ["a".as_bytes(), "b".as_bytes(), "c".as_bytes()];
+        let sigs = msgs
+            .iter()
+            .map(|msg| {
+                let shares = sks
+                    .iter()
+                    .map(|sk| Signature::new(msg, 1, l, &gens, sk, &mut rng).unwrap())
+                    .collect::<Vec<_>>();
+                Signature::aggregate(shares.iter().collect())
+            })
+            .collect::<Vec<_>>();
+        let batch = msgs
+            .iter()
+            .zip(sigs.iter())
+            .map(|(m, s)| (*m, s))
+            .collect::<Vec<_>>();
+
+        let (mut gateway, _) =
+            Signer::new(T, gens.clone(), InMemorySigKeyDatabase::new(), &mut rng).unwrap();
+        let (other, _) =
+            Signer::new(T, gens.clone(), InMemorySigKeyDatabase::new(), &mut rng).unwrap();
+        let receipt = VerificationReceipt::issue(
+            &batch,
+            1,
+            committee,
+            &committee_verifier,
+            &mut gateway,
+            &mut rng,
+        )
+        .unwrap();
+        assert_eq!(receipt.count, 3);
+        assert!(receipt.verify(&gateway.verifier()).unwrap());
+        assert!(receipt.verify_batch(&batch, &gateway.verifier()).unwrap());
+        assert!(!receipt.verify(&other.verifier()).unwrap());
+
+        // The receipt is about this batch only, in this order
+        assert!(!receipt.covers(&batch[..2]));
+        let swapped = [batch[1], batch[0], batch[2]];
+        assert!(!receipt.verify_batch(&swapped, &gateway.verifier()).unwrap());
+
+        let mut tampered = receipt.clone();
+        tampered.t = 2;
+        assert!(!tampered.verify(&gateway.verifier()).unwrap());
+        let mut tampered = receipt;
+        tampered.count = 2;
+        assert!(!tampered.verify(&gateway.verifier()).unwrap());
+
+        // No receipt for a batch with an invalid signature
+        let bad = [batch[0], ("d".as_bytes(), &sigs[1]), batch[2]];
+        match VerificationReceipt::issue(
+            &bad,
+            1,
+            committee,
+            &committee_verifier,
+            &mut gateway,
+            &mut rng,
+        ) {
+            Err(ForwardSecureSignatureError::BatchVerificationFailed { position, t }) => {
+                assert_eq!((position, t), (1, 1))
+            }
+            _ => panic!("batch with an invalid signature must be rejected"),
+        }
+        assert!(VerificationReceipt::issue(
+            &batch,
+            2,
+            committee,
+            &committee_verifier,
+            &mut gateway,
+            &mut rng
+        )
+        .is_err());
+        assert!(VerificationReceipt::issue(
+            &[],
+            1,
+            committee,
+            &committee_verifier,
+            &mut gateway,
+            &mut rng
+        )
+        .is_err());
+    }
+}
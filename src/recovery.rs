@@ -0,0 +1,175 @@
+use rand::{CryptoRng, RngCore};
+
+use crate::epoch::EpochConfig;
+use crate::errors::ForwardSecureSignatureError;
+use crate::keys::{SigKeyDb, SigManager, Sigkey, Verkey};
+use crate::util::{calculate_l, GeneratorSet};
+
+/// Snapshot of all signing keys held by a signer at time period `t`. Serializable with feature
+/// `danger-serialize-secrets` only.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "danger-serialize-secrets", derive(Serialize, Deserialize))]
+pub struct KeyBackup {
+    pub T: u128,
+    pub t: u128,
+    pub verkey: Verkey,
+    /// Signing keys along with their time periods
+    pub keys: Vec<(u128, Sigkey)>,
+}
+
+impl KeyBackup {
+    pub fn create(
+        manager: &SigManager,
+        verkey: &Verkey,
+        db: &dyn SigKeyDb,
+    ) -> Result<Self, ForwardSecureSignatureError> {
+        let mut indices = db.get_key_indices().into_iter().collect::<Vec<_>>();
+        indices.sort_unstable();
+        let mut keys = Vec::with_capacity(indices.len());
+        for t in indices {
+            keys.push((t, db.get_key(t)?.clone()));
+        }
+        Ok(Self {
+            T: manager.T(),
+            t: manager.t(),
+            verkey: verkey.clone(),
+            keys,
+        })
+    }
+}
+
+/// Restore signing keys from `backup` into `db` and update them to the time period of
+/// `current_wall_time`. Keys already in `db` are replaced. Before returning, a signature with the
+/// restored key is verified against the backed up verkey.
+/// Refuses to restore if the backup or `db` is already past the current time period since
+/// recovery must never move a signer back in time.
+pub fn recover<R: RngCore + CryptoRng>(
+    backup: &KeyBackup,
+    current_wall_time: u64,
+    epochs: &EpochConfig,
+    gens: &GeneratorSet,
+    db: &mut dyn SigKeyDb,
+    rng: &mut R,
+) -> Result<SigManager, ForwardSecureSignatureError> {
+    if backup.T != epochs.T {
+        return Err(ForwardSecureSignatureError::InvalidBackup {
+            msg: format!(
+                "backup is for T={} but epochs are for T={}",
+                backup.T, epochs.T
+            ),
+        });
+    }
+    if !backup.keys.iter().any(|(t, _)| *t == backup.t) {
+        return Err(ForwardSecureSignatureError::InvalidBackup {
+            msg: format!("backup has no key for its time period {}", backup.t),
+        });
+    }
+    let l = calculate_l(backup.T)?;
+    let t = epochs.period_at(current_wall_time)?;
+    if t < backup.t {
+        return Err(ForwardSecureSignatureError::SigkeyUpdateBackward {
+            old_t: t,
+            current_t: backup.t,
+        });
+    }
+    // The current key of the signer has the smallest time period of all its keys
+    let existing = db.get_key_indices();
+    if let Some(db_t) = existing.iter().min() {
+        if *db_t > t {
+            return Err(ForwardSecureSignatureError::SigkeyUpdateBackward {
+                old_t: t,
+                current_t: *db_t,
+            });
+        }
+    }
+
+    for i in existing {
+        db.remove_key(i);
+    }
+    for (i, key) in &backup.keys {
+        db.insert_key(*i, key.clone());
+    }
+    let mut manager = SigManager::load(backup.T, l, backup.t)?;
+    if t > backup.t {
+        manager.fast_forward_update(t, gens, rng, db)?;
+    }
+
+    if !manager
+        .get_current_key(db)?
+        .matches_verkey(&backup.verkey, gens, t, l)?
+    {
+        return Err(ForwardSecureSignatureError::RecoveryVerificationFailed { t });
+    }
+    Ok(manager)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{InMemorySigKeyDatabase, Keypair};
+    use std::time::Duration;
+
+    #[test]
+    fn test_recover_from_backup() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let epochs = EpochConfig::new(1000, Duration::from_secs(60), T).unwrap();
+        let mut db = InMemorySigKeyDatabase::new();
+        let (keypair, mut manager) = Keypair::new(T, &gens, &mut rng, &mut db).unwrap();
+        manager
+            .fast_forward_update(2, &gens, &mut rng, &mut db)
+            .unwrap();
+        let backup = KeyBackup::create(&manager, &keypair.ver_key, &db).unwrap();
+        assert_eq!(backup.t, 2);
+
+        // Restore into an empty database in time period 5
+        let mut new_db = InMemorySigKeyDatabase::new();
+        let now = epochs.period_start(5).unwrap() + 10;
+        let restored = recover(&backup, now, &epochs, &gens, &mut new_db, &mut rng).unwrap();
+        assert_eq!(restored.t(), 5);
+        assert!(!new_db.has_key(2));
+        assert!(new_db.has_key(5));
+
+        // The database is already at time period 5, restoring for time period 3 goes back
+        let now = epochs.period_start(3).unwrap();
+        assert!(recover(&backup, now, &epochs, &gens, &mut new_db, &mut rng).is_err());
+        // Backup is at time period 2, time period 1 is then too early
+        assert!(recover(
+            &backup,
+            1000,
+            &epochs,
+            &gens,
+            &mut InMemorySigKeyDatabase::new(),
+            &mut rng
+        )
+        .is_err());
+        // Restoring for the backup's own time period needs no update
+        let restored = recover(
+            &backup,
+            epochs.period_start(2).unwrap(),
+            &epochs,
+            &gens,
+            &mut InMemorySigKeyDatabase::new(),
+            &mut rng,
+        )
+        .unwrap();
+        assert_eq!(restored.t(), 2);
+
+        // Backup with a different verkey fails the test signature
+        let mut db_1 = InMemorySigKeyDatabase::new();
+        let (keypair_1, _) = Keypair::new(T, &gens, &mut rng, &mut db_1).unwrap();
+        let mut bad_backup = backup.clone();
+        bad_backup.verkey = keypair_1.ver_key;
+        let now = epochs.period_start(4).unwrap();
+        assert!(recover(
+            &bad_backup,
+            now,
+            &epochs,
+            &gens,
+            &mut InMemorySigKeyDatabase::new(),
+            &mut rng
+        )
+        .is_err());
+    }
+}
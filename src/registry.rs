@@ -0,0 +1,340 @@
+use sha3::{Digest, Sha3_256};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use crate::errors::ForwardSecureSignatureError;
+use crate::keys::{Keypair, ProofOfPossession, Verkey, VerkeyFingerprint};
+use crate::VerkeyGroup;
+
+const COMMITTEE_ID_DST: &[u8] = b"PIXEL-COMMITTEE-ID-V1";
+
+/// Whether the proof of possession of a committee member's signing key has been checked
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum PopStatus {
+    Unverified,
+    Verified,
+    Invalid,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CommitteeMember {
+    pub index: usize,
+    pub verkey: Verkey,
+    pub fingerprint: VerkeyFingerprint,
+    pub pop_status: PopStatus,
+}
+
+/// Canonical identifier of a committee, a hash over the threshold and the index, weight and
+/// verkey of every member in increasing order of index. Components agree on a committee by
+/// comparing identifiers instead of exchanging the verkeys. Displayed as lowercase hex.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CommitteeId(pub [u8; 32]);
+
+impl CommitteeId {
+    /// Identifier of the committee of `members`, given as `(index, verkey, weight)` in any
+    /// order, which needs a total weight of `threshold` to sign. Fails if an index is repeated.
+    pub fn new(
+        members: &[(usize, &Verkey, u64)],
+        threshold: u64,
+    ) -> Result<Self, ForwardSecureSignatureError> {
+        let mut sorted = BTreeMap::new();
+        for (index, verkey, weight) in members {
+            if sorted.insert(*index, (*verkey, *weight)).is_some() {
+                return Err(ForwardSecureSignatureError::DuplicateCommitteeMember {
+                    index: *index,
+                });
+            }
+        }
+        let mut hasher = Sha3_256::new();
+        hasher.input(COMMITTEE_ID_DST);
+        hasher.input(threshold.to_be_bytes());
+        hasher.input((sorted.len() as u64).to_be_bytes());
+        for (index, (verkey, weight)) in sorted {
+            hasher.input((index as u64).to_be_bytes());
+            hasher.input(weight.to_be_bytes());
+            hasher.input(verkey.to_bytes());
+        }
+        let mut id = [0; 32];
+        id.copy_from_slice(&hasher.result());
+        Ok(CommitteeId(id))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl fmt::Display for CommitteeId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for b in self.0.iter() {
+            write!(f, "{:02x}", b)?;
+        }
+        Ok(())
+    }
+}
+
+/// Interface for storing the verkeys of a committee. Members are identified by their index, the
+/// same one used as signer id during aggregation, and by the fingerprint of their verkey.
+pub trait VerkeyRegistry {
+    /// Adds a member with an unverified proof of possession. Fails if the index or the verkey is
+    /// already registered.
+    fn add_member(
+        &mut self,
+        index: usize,
+        verkey: Verkey,
+    ) -> Result<(), ForwardSecureSignatureError>;
+
+    fn get_by_index(&self, index: usize) -> Result<&CommitteeMember, ForwardSecureSignatureError>;
+
+    fn get_by_fingerprint(
+        &self,
+        fingerprint: &VerkeyFingerprint,
+    ) -> Result<&CommitteeMember, ForwardSecureSignatureError>;
+
+    /// Returns indices of all members in increasing order
+    fn member_indices(&self) -> Vec<usize>;
+
+    fn set_pop_status(
+        &mut self,
+        index: usize,
+        status: PopStatus,
+    ) -> Result<(), ForwardSecureSignatureError>;
+
+    /// Verify the proof of possession of member `index` and record the outcome
+    fn verify_pop(
+        &mut self,
+        index: usize,
+        pop: &ProofOfPossession,
+        gen: &VerkeyGroup,
+    ) -> Result<bool, ForwardSecureSignatureError> {
+        let valid = Keypair::verify_pop(pop, &self.get_by_index(index)?.verkey, gen);
+        let status = if valid {
+            PopStatus::Verified
+        } else {
+            PopStatus::Invalid
+        };
+        self.set_pop_status(index, status)?;
+        Ok(valid)
+    }
+
+    /// Aggregate verkeys of the given members. Every member must have a verified proof of
+    /// possession, otherwise aggregation is open to rogue key attacks.
+    fn aggregate_verkey(&self, indices: &[usize]) -> Result<Verkey, ForwardSecureSignatureError> {
+        let mut vks = Vec::with_capacity(indices.len());
+        for index in indices {
+            let member = self.get_by_index(*index)?;
+            if member.pop_status != PopStatus::Verified {
+                return Err(ForwardSecureSignatureError::PopNotVerified { index: *index });
+            }
+            vks.push(&member.verkey);
+        }
+        Ok(Verkey::aggregate(vks))
+    }
+
+    /// Identifier of the committee of the given members, each of weight 1, with `quorum` of
+    /// them needed to sign
+    fn committee_id(
+        &self,
+        indices: &[usize],
+        quorum: usize,
+    ) -> Result<CommitteeId, ForwardSecureSignatureError> {
+        let mut members = Vec::with_capacity(indices.len());
+        for index in indices {
+            members.push((*index, &self.get_by_index(*index)?.verkey, 1));
+        }
+        CommitteeId::new(&members, quorum as u64)
+    }
+}
+
+/// A registry of committee members kept in memory
+pub struct InMemoryVerkeyRegistry {
+    members: BTreeMap<usize, CommitteeMember>,
+    by_fingerprint: HashMap<VerkeyFingerprint, usize>,
+}
+
+impl VerkeyRegistry for InMemoryVerkeyRegistry {
+    fn add_member(
+        &mut self,
+        index: usize,
+        verkey: Verkey,
+    ) -> Result<(), ForwardSecureSignatureError> {
+        let fingerprint = verkey.fingerprint();
+        if self.members.contains_key(&index) || self.by_fingerprint.contains_key(&fingerprint) {
+            return Err(ForwardSecureSignatureError::DuplicateCommitteeMember { index });
+        }
+        self.by_fingerprint.insert(fingerprint, index);
+        self.members.insert(
+            index,
+            CommitteeMember {
+                index,
+                verkey,
+                fingerprint,
+                pop_status: PopStatus::Unverified,
+            },
+        );
+        Ok(())
+    }
+
+    fn get_by_index(&self, index: usize) -> Result<&CommitteeMember, ForwardSecureSignatureError> {
+        self.members
+            .get(&index)
+            .ok_or(ForwardSecureSignatureError::UnknownCommitteeMember { index })
+    }
+
+    fn get_by_fingerprint(
+        &self,
+        fingerprint: &VerkeyFingerprint,
+    ) -> Result<&CommitteeMember, ForwardSecureSignatureError> {
+        match self.by_fingerprint.get(fingerprint) {
+            Some(index) => self.get_by_index(*index),
+            None => Err(ForwardSecureSignatureError::UnknownVerkeyFingerprint {
+                fingerprint: fingerprint.to_string(),
+            }),
+        }
+    }
+
+    fn member_indices(&self) -> Vec<usize> {
+        self.members.keys().copied().collect()
+    }
+
+    fn set_pop_status(
+        &mut self,
+        index: usize,
+        status: PopStatus,
+    ) -> Result<(), ForwardSecureSignatureError> {
+        match self.members.get_mut(&index) {
+            Some(member) => {
+                member.pop_status = status;
+                Ok(())
+            }
+            None => Err(ForwardSecureSignatureError::UnknownCommitteeMember { index }),
+        }
+    }
+}
+
+impl InMemoryVerkeyRegistry {
+    pub fn new() -> Self {
+        Self {
+            members: BTreeMap::new(),
+            by_fingerprint: HashMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+}
+
+impl Default for InMemoryVerkeyRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{InMemorySigKeyDatabase, SigManager};
+    use crate::signature::Signature;
+    use crate::util::{calculate_l, GeneratorSet};
+
+    #[test]
+    fn test_registry_aggregated_verification() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let l = calculate_l(T).unwrap();
+        let t = 1u128;
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let msg = "Hello".as_bytes();
+
+        let mut registry = InMemoryVerkeyRegistry::new();
+        let mut sigs = vec![];
+        let mut pops = vec![];
+        for i in 1..=3 {
+            let mut db = InMemorySigKeyDatabase::new();
+            let (keypair, _) = Keypair::new(T, &gens, &mut rng, &mut db).unwrap();
+            let sk = SigManager::get_key(t, &db).unwrap();
+            sigs.push(Signature::new(msg, t, l, &gens, sk, &mut rng).unwrap());
+            pops.push(keypair.pop);
+            registry.add_member(i, keypair.ver_key.clone()).unwrap();
+            // Same index or same verkey cannot be registered twice
+            assert!(registry.add_member(i, keypair.ver_key.clone()).is_err());
+            assert!(registry.add_member(i + 10, keypair.ver_key).is_err());
+        }
+        assert_eq!(registry.len(), 3);
+        assert_eq!(registry.member_indices(), vec![1, 2, 3]);
+
+        let member = registry.get_by_index(2).unwrap();
+        let fp = member.fingerprint;
+        assert_eq!(registry.get_by_fingerprint(&fp).unwrap().index, 2);
+        assert!(registry.get_by_index(4).is_err());
+
+        let asig = Signature::aggregate(sigs.iter().collect());
+        // Members without verified PoP cannot be aggregated
+        assert!(asig
+            .verify_aggregated_with_registry(msg, t, l, &[1, 2, 3], &registry, &gens)
+            .is_err());
+
+        // PoP of member 2 does not verify for member 1
+        assert!(!registry.verify_pop(1, &pops[1], &gens.0).unwrap());
+        assert_eq!(
+            registry.get_by_index(1).unwrap().pop_status,
+            PopStatus::Invalid
+        );
+        for i in 1..=3 {
+            assert!(registry.verify_pop(i, &pops[i - 1], &gens.0).unwrap());
+        }
+        assert!(asig
+            .verify_aggregated_with_registry(msg, t, l, &[1, 2, 3], &registry, &gens)
+            .unwrap());
+        assert!(!asig
+            .verify_aggregated_with_registry(msg, t, l, &[1, 2], &registry, &gens)
+            .unwrap());
+    }
+
+    #[test]
+    fn test_committee_id() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let mut registry = InMemoryVerkeyRegistry::new();
+        let mut vks = vec![];
+        for i in 1..=3 {
+            let mut db = InMemorySigKeyDatabase::new();
+            let (keypair, _) = Keypair::new(T, &gens, &mut rng, &mut db).unwrap();
+            registry.add_member(i, keypair.ver_key.clone()).unwrap();
+            vks.push(keypair.ver_key);
+        }
+
+        // Independent of the order of members
+        let id = registry.committee_id(&[1, 2, 3], 2).unwrap();
+        assert_eq!(id, registry.committee_id(&[3, 1, 2], 2).unwrap());
+        assert_eq!(
+            id,
+            CommitteeId::new(&[(2, &vks[1], 1), (1, &vks[0], 1), (3, &vks[2], 1)], 2).unwrap()
+        );
+        assert_eq!(id.to_string().len(), 64);
+
+        // Changes with the members, their verkeys, weights and the threshold
+        assert_ne!(id, registry.committee_id(&[1, 2], 2).unwrap());
+        assert_ne!(id, registry.committee_id(&[1, 2, 3], 3).unwrap());
+        assert_ne!(
+            id,
+            CommitteeId::new(&[(1, &vks[0], 1), (2, &vks[1], 2), (3, &vks[2], 1)], 2).unwrap()
+        );
+        assert_ne!(
+            id,
+            CommitteeId::new(&[(1, &vks[1], 1), (2, &vks[0], 1), (3, &vks[2], 1)], 2).unwrap()
+        );
+
+        assert!(registry.committee_id(&[1, 2, 1], 2).is_err());
+        assert!(registry.committee_id(&[1, 4], 2).is_err());
+    }
+}
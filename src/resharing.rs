@@ -0,0 +1,298 @@
+//! Handing the shared key of a threshold committee to a new committee, possibly with different
+//! members and threshold, without changing the threshold verkey.
+//!
+//! At least `old_threshold` members of the old committee act as dealers. Every dealer shares its
+//! own master secret share with a random polynomial of degree `new_threshold - 1` and broadcasts
+//! commitments to the coefficients of the polynomial in the verkey group. The constant term
+//! commitment equals the dealer's verkey so anyone can check that the dealer reshared its actual
+//! share. New member `j` receives `f_i(j)` from every dealer `i`, checks it against the
+//! commitments and combines the shares with the Lagrange coefficients of the dealers. The
+//! combined shares are a sharing of the same master secret.
+//! As with `trusted_party_SSS_keygen`, new members create their signing key for time period 1
+//! and must fast forward it to the current time period immediately.
+
+use std::collections::{HashMap, HashSet};
+
+use amcl_wrapper::field_elem::FieldElement;
+use amcl_wrapper::group_elem::GroupElement;
+use rand::{CryptoRng, RngCore};
+use secret_sharing::polynomial::Polynomial;
+
+use crate::errors::ForwardSecureSignatureError;
+use crate::keys::{MasterSecret, Verkey};
+use crate::threshold_sig::ThresholdScheme;
+use crate::util::random_field_element;
+use crate::VerkeyGroup;
+
+/// Broadcast by a dealer. Commitments `gen^a_k` to the coefficients `a_k` of its polynomial,
+/// lowest degree first.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ResharingCommitment {
+    pub dealer: usize,
+    pub coefficients: Vec<VerkeyGroup>,
+}
+
+/// Sent privately by a dealer to new member `receiver`
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ResharingShare {
+    pub dealer: usize,
+    pub receiver: usize,
+    pub value: FieldElement,
+}
+
+impl ResharingCommitment {
+    /// Whether the dealer reshared the master secret share behind `old_verkey`
+    pub fn matches_verkey(&self, old_verkey: &Verkey) -> bool {
+        !self.coefficients.is_empty() && self.coefficients[0] == old_verkey.value
+    }
+
+    /// Commitment to the share of new member `id`, i.e. `gen^f(id)`
+    pub fn evaluate(&self, id: usize) -> VerkeyGroup {
+        let x = FieldElement::from(id as u64);
+        let mut res = VerkeyGroup::identity();
+        for c in self.coefficients.iter().rev() {
+            res = &(&res * &x) + c;
+        }
+        res
+    }
+}
+
+impl ResharingShare {
+    pub fn verify(&self, commitment: &ResharingCommitment, gen: &VerkeyGroup) -> bool {
+        self.dealer == commitment.dealer && gen * &self.value == commitment.evaluate(self.receiver)
+    }
+}
+
+/// Called by old member `dealer` holding `share` of the master secret. Returns the commitment
+/// to broadcast and one share for each of `new_ids`.
+pub fn deal_reshare<R: RngCore + CryptoRng>(
+    dealer: usize,
+    share: &MasterSecret,
+    new_threshold: usize,
+    new_ids: &[usize],
+    gen: &VerkeyGroup,
+    rng: &mut R,
+) -> Result<(ResharingCommitment, Vec<ResharingShare>), ForwardSecureSignatureError> {
+    check_threshold(new_threshold, new_ids)?;
+    let mut coeffs = vec![share.value.clone()];
+    for _ in 1..new_threshold {
+        coeffs.push(random_field_element(rng)?);
+    }
+    let shares = new_ids
+        .iter()
+        .map(|id| {
+            let x = FieldElement::from(*id as u64);
+            let mut value = FieldElement::zero();
+            for c in coeffs.iter().rev() {
+                value = &(&value * &x) + c;
+            }
+            ResharingShare {
+                dealer,
+                receiver: *id,
+                value,
+            }
+        })
+        .collect();
+    let commitment = ResharingCommitment {
+        dealer,
+        coefficients: coeffs.iter().map(|c| gen * c).collect(),
+    };
+    Ok((commitment, shares))
+}
+
+/// Publicly verify the handoff. Checks that there are at least `old_threshold` dealers, that
+/// every dealer reshared its share of `old_verkeys` with a polynomial of the right degree and
+/// that the new members' verkeys, which are returned, aggregate to `threshold_vk`.
+pub fn verify_handoff(
+    old_threshold: usize,
+    old_verkeys: &HashMap<usize, Verkey>,
+    threshold_vk: &Verkey,
+    commitments: &[ResharingCommitment],
+    new_threshold: usize,
+    new_ids: &[usize],
+) -> Result<HashMap<usize, Verkey>, ForwardSecureSignatureError> {
+    check_threshold(new_threshold, new_ids)?;
+    let dealers = check_dealers(old_threshold, commitments)?;
+    for c in commitments {
+        let valid = match old_verkeys.get(&c.dealer) {
+            Some(vk) => c.matches_verkey(vk) && c.coefficients.len() == new_threshold,
+            None => false,
+        };
+        if !valid {
+            return Err(ForwardSecureSignatureError::InvalidReshare { dealer: c.dealer });
+        }
+    }
+
+    let mut new_verkeys = HashMap::new();
+    for id in new_ids {
+        let mut value = VerkeyGroup::identity();
+        for c in commitments {
+            let l = Polynomial::lagrange_basis_at_0(dealers.clone(), c.dealer);
+            value += c.evaluate(*id) * &l;
+        }
+        new_verkeys.insert(*id, Verkey { value });
+    }
+    let new_vk = ThresholdScheme::aggregate_vk(
+        new_threshold,
+        new_ids.iter().map(|id| (*id, &new_verkeys[id])).collect(),
+    );
+    if new_vk.value != threshold_vk.value {
+        return Err(ForwardSecureSignatureError::InconsistentPublicParams {
+            msg: String::from("reshared verkey differs from threshold verkey"),
+        });
+    }
+    Ok(new_verkeys)
+}
+
+/// Called by new member `receiver` to combine the shares it received from the dealers into its
+/// master secret share. Every share is checked against the dealer's commitment.
+pub fn combine_reshares(
+    receiver: usize,
+    old_threshold: usize,
+    commitments: &[ResharingCommitment],
+    shares: &[ResharingShare],
+    gen: &VerkeyGroup,
+) -> Result<MasterSecret, ForwardSecureSignatureError> {
+    let dealers = check_dealers(old_threshold, commitments)?;
+    if shares.len() != commitments.len() {
+        return Err(ForwardSecureSignatureError::QuorumNotReached {
+            received: shares.len(),
+            quorum: commitments.len(),
+        });
+    }
+    let mut value = FieldElement::zero();
+    for c in commitments {
+        let share = match shares.iter().find(|s| s.dealer == c.dealer) {
+            Some(s) if s.receiver == receiver && s.verify(c, gen) => s,
+            _ => return Err(ForwardSecureSignatureError::InvalidReshare { dealer: c.dealer }),
+        };
+        let l = Polynomial::lagrange_basis_at_0(dealers.clone(), c.dealer);
+        value += &share.value * &l;
+    }
+    Ok(MasterSecret { value })
+}
+
+fn check_threshold(threshold: usize, ids: &[usize]) -> Result<(), ForwardSecureSignatureError> {
+    let unique = ids.iter().collect::<HashSet<_>>();
+    if threshold == 0 || threshold > ids.len() || unique.len() != ids.len() || unique.contains(&0) {
+        return Err(ForwardSecureSignatureError::InvalidQuorum {
+            quorum: threshold,
+            committee: ids.len(),
+        });
+    }
+    Ok(())
+}
+
+fn check_dealers(
+    old_threshold: usize,
+    commitments: &[ResharingCommitment],
+) -> Result<HashSet<usize>, ForwardSecureSignatureError> {
+    let dealers = commitments.iter().map(|c| c.dealer).collect::<HashSet<_>>();
+    if dealers.len() != commitments.len() {
+        return Err(ForwardSecureSignatureError::InvalidQuorum {
+            quorum: old_threshold,
+            committee: commitments.len(),
+        });
+    }
+    if dealers.len() < old_threshold {
+        return Err(ForwardSecureSignatureError::QuorumNotReached {
+            received: dealers.len(),
+            quorum: old_threshold,
+        });
+    }
+    Ok(dealers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{InMemorySigKeyDatabase, SigManager, Sigkey};
+    use crate::signature::Signature;
+    use crate::util::{calculate_l, GeneratorSet};
+    use secret_sharing::shamir_secret_sharing::get_shared_secret;
+
+    #[test]
+    fn test_resharing() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let l = calculate_l(T).unwrap();
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let gen = &gens.0;
+
+        // Old committee of 5 with threshold 3
+        let (secret_x, x_shares) = get_shared_secret(3, 5);
+        let threshold_vk = Verkey {
+            value: gen * &secret_x,
+        };
+        let old_verkeys = x_shares
+            .iter()
+            .map(|(i, x)| (*i, Verkey { value: gen * x }))
+            .collect::<HashMap<_, _>>();
+
+        // Members 1, 3 and 4 reshare to a new committee of 4 with threshold 2
+        let new_ids = vec![10, 11, 12, 13];
+        let mut commitments = vec![];
+        let mut shares = vec![];
+        for i in [1, 3, 4].iter() {
+            let share = MasterSecret {
+                value: x_shares[i].clone(),
+            };
+            let (c, s) = deal_reshare(*i, &share, 2, &new_ids, gen, &mut rng).unwrap();
+            commitments.push(c);
+            shares.push(s);
+        }
+
+        let new_verkeys =
+            verify_handoff(3, &old_verkeys, &threshold_vk, &commitments, 2, &new_ids).unwrap();
+        // Not enough dealers
+        assert!(verify_handoff(
+            3,
+            &old_verkeys,
+            &threshold_vk,
+            &commitments[..2],
+            2,
+            &new_ids
+        )
+        .is_err());
+        // Dealer claiming to be another old member
+        let mut bad = commitments.clone();
+        bad[0].dealer = 2;
+        assert!(verify_handoff(3, &old_verkeys, &threshold_vk, &bad, 2, &new_ids).is_err());
+
+        let mut new_secrets = vec![];
+        for (j, id) in new_ids.iter().enumerate() {
+            let received = shares.iter().map(|s| s[j].clone()).collect::<Vec<_>>();
+            let secret = combine_reshares(*id, 3, &commitments, &received, gen).unwrap();
+            assert_eq!(gen * &secret.value, new_verkeys[id].value);
+            new_secrets.push(secret);
+        }
+        // Tampered share is rejected
+        let mut received = shares.iter().map(|s| s[0].clone()).collect::<Vec<_>>();
+        received[1].value = FieldElement::random();
+        assert!(combine_reshares(10, 3, &commitments, &received, gen).is_err());
+
+        // Any 2 new members create a signature verifying with the old threshold verkey
+        let t = 3;
+        let msg = "Hello".as_bytes();
+        let mut sigs = vec![];
+        for j in [1, 3].iter() {
+            let sk = Sigkey::initial_secret_key(gen, &gens.1, &new_secrets[*j], &mut rng).unwrap();
+            let mut db = InMemorySigKeyDatabase::new();
+            let mut manager = SigManager::new(T, l, sk, &mut db).unwrap();
+            manager
+                .fast_forward_update(t, &gens, &mut rng, &mut db)
+                .unwrap();
+            let sk = manager.get_current_key(&db).unwrap();
+            sigs.push((
+                new_ids[*j],
+                Signature::new(msg, t, l, &gens, sk, &mut rng).unwrap(),
+            ));
+        }
+        let threshold_sig = ThresholdScheme::aggregate_sigs(2, sigs);
+        assert!(threshold_sig
+            .verify(msg, t, l, &gens, &threshold_vk)
+            .unwrap());
+    }
+}
@@ -0,0 +1,168 @@
+//! Cache of verification results, for gossip layers receiving the same signature from many
+//! peers. Results are keyed by the hash of the message, the time period, the fingerprint of the
+//! verkey and the encoded signature, so a repeated verification of the same artifacts returns
+//! the earlier result without a pairing. Invalid results are cached as well, so a peer resending
+//! an invalid signature costs one lookup.
+//!
+//! The cache is bounded in the number of results and in their age. Results older than the TTL
+//! are not returned and, when the cache is full, the oldest result is evicted. Times are Unix
+//! time in seconds. All verifiers used with one cache must use the same generators.
+
+use std::collections::{HashMap, VecDeque};
+
+use sha3::{Digest, Sha3_256};
+
+use crate::errors::ForwardSecureSignatureError;
+use crate::facade::Verifier;
+use crate::keys::VerkeyFingerprint;
+use crate::signature::Signature;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct ResultKey {
+    msg: [u8; 32],
+    t: u128,
+    verkey: VerkeyFingerprint,
+    sig: Vec<u8>,
+}
+
+#[derive(Clone, Debug)]
+pub struct VerificationResultCache {
+    results: HashMap<ResultKey, (bool, u64)>,
+    /// Keys in order of insertion with their insertion time
+    order: VecDeque<(ResultKey, u64)>,
+    max_results: usize,
+    ttl: u64,
+    hits: u64,
+}
+
+impl VerificationResultCache {
+    /// Keeps at most `max_results` results for at most `ttl` seconds each
+    pub fn new(max_results: usize, ttl: u64) -> Self {
+        Self {
+            results: HashMap::new(),
+            order: VecDeque::new(),
+            max_results,
+            ttl,
+            hits: 0,
+        }
+    }
+
+    /// Same as `Verifier::verify`, returning the cached result if the same signature on the
+    /// same message for the same time period and verkey was verified within the TTL before
+    /// `now`. Errors are not cached.
+    pub fn verify(
+        &mut self,
+        verifier: &Verifier,
+        msg: &[u8],
+        t: u128,
+        sig: &Signature,
+        now: u64,
+    ) -> Result<bool, ForwardSecureSignatureError> {
+        self.expire(now);
+        let mut digest = [0; 32];
+        digest.copy_from_slice(&Sha3_256::digest(msg));
+        let key = ResultKey {
+            msg: digest,
+            t,
+            verkey: verifier.verkey().fingerprint(),
+            sig: sig.to_bytes(),
+        };
+        if let Some((valid, _)) = self.results.get(&key) {
+            self.hits += 1;
+            return Ok(*valid);
+        }
+
+        let valid = verifier.verify(msg, t, sig)?;
+        if self.max_results > 0 {
+            if self.results.len() >= self.max_results {
+                if let Some((oldest, _)) = self.order.pop_front() {
+                    self.results.remove(&oldest);
+                }
+            }
+            self.results.insert(key.clone(), (valid, now));
+            self.order.push_back((key, now));
+        }
+        Ok(valid)
+    }
+
+    /// Number of results cached
+    pub fn len(&self) -> usize {
+        self.results.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.results.is_empty()
+    }
+
+    /// Number of verifications answered from the cache
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    pub fn clear(&mut self) {
+        self.results.clear();
+        self.order.clear();
+    }
+
+    /// Drop results older than the TTL at `now`
+    fn expire(&mut self, now: u64) {
+        while let Some((key, inserted)) = self.order.front() {
+            if now.saturating_sub(*inserted) <= self.ttl {
+                break;
+            }
+            self.results.remove(key);
+            self.order.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::facade::Signer;
+    use crate::keys::InMemorySigKeyDatabase;
+    use crate::util::GeneratorSet;
+
+    #[test]
+    fn test_verification_result_cache() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let (mut signer, _) =
+            Signer::new(T, gens.clone(), InMemorySigKeyDatabase::new(), &mut rng).unwrap();
+        let (other, _) = Signer::new(T, gens, InMemorySigKeyDatabase::new(), &mut rng).unwrap();
+        let verifier = signer.verifier();
+        let msg = "Hello".as_bytes();
+        let sig = signer.sign(msg, &mut rng).unwrap();
+
+        let mut cache = VerificationResultCache::new(2, 60);
+        assert!(cache.verify(&verifier, msg, 1, &sig, 1000).unwrap());
+        assert!(cache.verify(&verifier, msg, 1, &sig, 1010).unwrap());
+        assert_eq!((cache.len(), cache.hits()), (1, 1));
+
+        // Every part of the key matters, invalid results are cached too
+        assert!(!cache.verify(&verifier, msg, 2, &sig, 1020).unwrap());
+        assert!(!cache.verify(&verifier, msg, 2, &sig, 1020).unwrap());
+        assert_eq!((cache.len(), cache.hits()), (2, 2));
+        assert!(!cache.verify(&other.verifier(), msg, 1, &sig, 1020).unwrap());
+        assert!(!cache
+            .verify(&verifier, "Hellp".as_bytes(), 1, &sig, 1020)
+            .unwrap());
+        assert_eq!((cache.len(), cache.hits()), (2, 2));
+
+        // Bounded in size and age
+        let sig2 = signer.sign(msg, &mut rng).unwrap();
+        assert!(cache.verify(&verifier, msg, 1, &sig2, 1030).unwrap());
+        assert!(cache.verify(&verifier, msg, 1, &sig2, 1085).unwrap());
+        assert_eq!(cache.hits(), 3);
+        assert_eq!(cache.len(), 1);
+        assert!(cache.verify(&verifier, msg, 1, &sig2, 1100).unwrap());
+        assert_eq!(cache.hits(), 3);
+
+        cache.clear();
+        assert!(cache.is_empty());
+        let mut disabled = VerificationResultCache::new(0, 60);
+        assert!(disabled.verify(&verifier, msg, 1, &sig, 1000).unwrap());
+        assert!(disabled.is_empty());
+    }
+}
@@ -0,0 +1,551 @@
+//! Signed statements that a verkey must not be trusted from a time period on, e.g. because its
+//! signing key was compromised. A statement is signed either by an authority or, as a last
+//! resort when no authority is available, by the revoked key itself. A self-signed statement
+//! can be created by whoever holds the key, including an attacker who stole the key of some
+//! time period, so registries refuse self-signed statements revoking the key before the time
+//! period they were signed in. Otherwise the attacker could invalidate signatures made before
+//! the compromise. Only authorities can revoke a key retroactively.
+//!
+//! The canonical encoding of a signed statement is
+//! `DST || revoked fingerprint || as_of || reason || signer fingerprint || signed_at || signature`
+//! with time periods as 16 byte big endian and the signature as in `Signature::to_bytes`. The
+//! signature is on the same bytes without the signature.
+
+use rand::{CryptoRng, RngCore};
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+
+use crate::errors::ForwardSecureSignatureError;
+use crate::facade::SignatureVerifier;
+use crate::keys::{SigKeyDb, SigManager, Verkey, VerkeyFingerprint, VERKEY_FINGERPRINT_SIZE};
+use crate::signature::{Signature, SIGNATURE_SIZE};
+use crate::util::GeneratorSet;
+
+const REVOCATION_DST: &[u8] = b"PIXEL-REVOCATION-V1";
+
+/// Size of the encoding of a `SignedRevocation`
+pub const SIGNED_REVOCATION_SIZE: usize =
+    REVOCATION_DST.len() + 2 * VERKEY_FINGERPRINT_SIZE + 2 * 16 + 1 + SIGNATURE_SIZE;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum RevocationReason {
+    Unspecified = 0,
+    KeyCompromise = 1,
+    Superseded = 2,
+    CessationOfOperation = 3,
+}
+
+impl TryFrom<u8> for RevocationReason {
+    type Error = ForwardSecureSignatureError;
+
+    fn try_from(b: u8) -> Result<Self, Self::Error> {
+        match b {
+            0 => Ok(RevocationReason::Unspecified),
+            1 => Ok(RevocationReason::KeyCompromise),
+            2 => Ok(RevocationReason::Superseded),
+            3 => Ok(RevocationReason::CessationOfOperation),
+            _ => Err(ForwardSecureSignatureError::InvalidWireBytes {
+                msg: format!("unknown revocation reason {}", b),
+            }),
+        }
+    }
+}
+
+/// Verkey with fingerprint `revoked` must not be trusted for time periods `as_of` and later
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Revocation {
+    pub revoked: VerkeyFingerprint,
+    pub as_of: u128,
+    pub reason: RevocationReason,
+}
+
+impl Revocation {
+    pub fn new(revoked: &Verkey, as_of: u128, reason: RevocationReason) -> Self {
+        Self {
+            revoked: revoked.fingerprint(),
+            as_of,
+            reason,
+        }
+    }
+
+    /// Whether a signature for time period `t` by `verkey` is affected
+    pub fn applies_to(&self, verkey: &VerkeyFingerprint, t: u128) -> bool {
+        self.revoked == *verkey && t >= self.as_of
+    }
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SignedRevocation {
+    pub revocation: Revocation,
+    pub signer: VerkeyFingerprint,
+    /// Time period of the signer's key that signed the statement
+    pub signed_at: u128,
+    pub signature: Signature,
+}
+
+impl SignedRevocation {
+    /// Sign `revocation` with the signer's key of its current time period. To create a
+    /// self-signed statement, pass the revoked key as signer.
+    pub fn sign<R: RngCore + CryptoRng>(
+        revocation: Revocation,
+        signer: &Verkey,
+        manager: &mut SigManager,
+        gens: &GeneratorSet,
+        db: &dyn SigKeyDb,
+        rng: &mut R,
+    ) -> Result<Self, ForwardSecureSignatureError> {
+        let signer = signer.fingerprint();
+        let signed_at = manager.t();
+        let msg = statement(&revocation, &signer, signed_at);
+        let signature = manager.sign(&msg, gens, db, rng)?;
+        Ok(Self {
+            revocation,
+            signer,
+            signed_at,
+            signature,
+        })
+    }
+
+    pub fn is_self_signed(&self) -> bool {
+        self.signer == self.revocation.revoked
+    }
+
+    /// Check the signature against the signer's verkey. Whether the signer is allowed to revoke
+    /// the key is up to the caller.
+    pub fn verify(
+        &self,
+        signer: &Verkey,
+        l: u8,
+        gens: &GeneratorSet,
+    ) -> Result<bool, ForwardSecureSignatureError> {
+        if signer.fingerprint() != self.signer {
+            return Ok(false);
+        }
+        let msg = statement(&self.revocation, &self.signer, self.signed_at);
+        self.signature.verify(&msg, self.signed_at, l, gens, signer)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = statement(&self.revocation, &self.signer, self.signed_at);
+        bytes.append(&mut self.signature.to_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ForwardSecureSignatureError> {
+        if bytes.len() != SIGNED_REVOCATION_SIZE || !bytes.starts_with(REVOCATION_DST) {
+            return Err(ForwardSecureSignatureError::InvalidWireBytes {
+                msg: String::from("invalid revocation statement"),
+            });
+        }
+        let mut offset = REVOCATION_DST.len();
+        let mut take = |n: usize| {
+            let b = &bytes[offset..offset + n];
+            offset += n;
+            b
+        };
+        let revoked = fingerprint(take(VERKEY_FINGERPRINT_SIZE));
+        let as_of = period(take(16));
+        let reason = RevocationReason::try_from(take(1)[0])?;
+        let signer = fingerprint(take(VERKEY_FINGERPRINT_SIZE));
+        let signed_at = period(take(16));
+        let signature = Signature::from_bytes_unchecked(take(SIGNATURE_SIZE))?;
+        Ok(Self {
+            revocation: Revocation {
+                revoked,
+                as_of,
+                reason,
+            },
+            signer,
+            signed_at,
+            signature,
+        })
+    }
+}
+
+/// Source of revocations consulted during verification
+pub trait RevocationRegistry {
+    /// Time period from which the verkey with fingerprint `verkey` is revoked, if it is. For a
+    /// self-signed statement this must not be before its `signed_at`, see
+    /// `InMemoryRevocationRegistry::add`.
+    fn revoked_as_of(&self, verkey: &VerkeyFingerprint) -> Option<u128>;
+}
+
+/// Keeps verified revocation statements in memory. Accepts statements signed by one of the
+/// given authorities and self-signed statements with `as_of` not before `signed_at`. If a key is
+/// revoked more than once, the earliest time period wins.
+pub struct InMemoryRevocationRegistry {
+    authorities: HashSet<VerkeyFingerprint>,
+    revocations: HashMap<VerkeyFingerprint, SignedRevocation>,
+}
+
+impl InMemoryRevocationRegistry {
+    pub fn new(authorities: &[Verkey]) -> Self {
+        Self {
+            authorities: authorities.iter().map(|vk| vk.fingerprint()).collect(),
+            revocations: HashMap::new(),
+        }
+    }
+
+    /// Verify the statement with the signer's verkey and record it. Fails if the signer is
+    /// neither an authority nor the revoked key, if a self-signed statement is backdated or if
+    /// the signature does not verify.
+    pub fn add(
+        &mut self,
+        statement: SignedRevocation,
+        signer: &Verkey,
+        l: u8,
+        gens: &GeneratorSet,
+    ) -> Result<(), ForwardSecureSignatureError> {
+        if !statement.is_self_signed() && !self.authorities.contains(&statement.signer) {
+            return Err(ForwardSecureSignatureError::InvalidRevocation {
+                msg: format!("{} is not allowed to revoke keys", statement.signer),
+            });
+        }
+        if statement.is_self_signed() && statement.revocation.as_of < statement.signed_at {
+            return Err(ForwardSecureSignatureError::InvalidRevocation {
+                msg: format!(
+                    "self-signed revocation as of {} signed in time period {}",
+                    statement.revocation.as_of, statement.signed_at
+                ),
+            });
+        }
+        if !statement.verify(signer, l, gens)? {
+            return Err(ForwardSecureSignatureError::InvalidRevocation {
+                msg: String::from("signature does not verify"),
+            });
+        }
+        let revoked = statement.revocation.revoked;
+        let earlier = match self.revocations.get(&revoked) {
+            Some(existing) => existing.revocation.as_of <= statement.revocation.as_of,
+            None => false,
+        };
+        if !earlier {
+            self.revocations.insert(revoked, statement);
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, verkey: &VerkeyFingerprint) -> Option<&SignedRevocation> {
+        self.revocations.get(verkey)
+    }
+}
+
+impl RevocationRegistry for InMemoryRevocationRegistry {
+    fn revoked_as_of(&self, verkey: &VerkeyFingerprint) -> Option<u128> {
+        self.revocations.get(verkey).map(|s| s.revocation.as_of)
+    }
+}
+
+/// Wraps a `SignatureVerifier` to fail for signatures of revoked verkeys for time periods from
+/// their revocation on, before doing the actual verification
+pub struct RevocationAwareVerifier<'a, V: SignatureVerifier, R: RevocationRegistry> {
+    inner: V,
+    registry: &'a R,
+}
+
+impl<'a, V: SignatureVerifier, R: RevocationRegistry> RevocationAwareVerifier<'a, V, R> {
+    pub fn new(inner: V, registry: &'a R) -> Self {
+        Self { inner, registry }
+    }
+
+    fn check(&self, verkey: &Verkey, t: u128) -> Result<(), ForwardSecureSignatureError> {
+        let fingerprint = verkey.fingerprint();
+        match self.registry.revoked_as_of(&fingerprint) {
+            Some(as_of) if t >= as_of => Err(ForwardSecureSignatureError::VerkeyRevoked {
+                fingerprint: fingerprint.to_string(),
+                as_of,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<'a, V: SignatureVerifier, R: RevocationRegistry> SignatureVerifier
+    for RevocationAwareVerifier<'a, V, R>
+{
+    fn verify(
+        &self,
+        msg: &[u8],
+        t: u128,
+        sig: &Signature,
+        verkey: &Verkey,
+    ) -> Result<bool, ForwardSecureSignatureError> {
+        self.check(verkey, t)?;
+        self.inner.verify(msg, t, sig, verkey)
+    }
+
+    fn verify_aggregated(
+        &self,
+        msg: &[u8],
+        t: u128,
+        sig: &Signature,
+        verkeys: &[&Verkey],
+    ) -> Result<bool, ForwardSecureSignatureError> {
+        for vk in verkeys {
+            self.check(vk, t)?;
+        }
+        self.inner.verify_aggregated(msg, t, sig, verkeys)
+    }
+}
+
+fn statement(revocation: &Revocation, signer: &VerkeyFingerprint, signed_at: u128) -> Vec<u8> {
+    let mut bytes = REVOCATION_DST.to_vec();
+    bytes.extend_from_slice(revocation.revoked.as_bytes());
+    bytes.extend_from_slice(&revocation.as_of.to_be_bytes());
+    bytes.push(revocation.reason as u8);
+    bytes.extend_from_slice(signer.as_bytes());
+    bytes.extend_from_slice(&signed_at.to_be_bytes());
+    bytes
+}
+
+fn fingerprint(bytes: &[u8]) -> VerkeyFingerprint {
+    let mut fp = [0; VERKEY_FINGERPRINT_SIZE];
+    fp.copy_from_slice(bytes);
+    VerkeyFingerprint(fp)
+}
+
+fn period(bytes: &[u8]) -> u128 {
+    let mut t = [0; 16];
+    t.copy_from_slice(bytes);
+    u128::from_be_bytes(t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::facade::PairingVerifier;
+    use crate::keys::{InMemorySigKeyDatabase, Keypair};
+    use crate::util::calculate_l;
+
+    #[test]
+    fn test_revocation_statements() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let l = calculate_l(T).unwrap();
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let mut db = InMemorySigKeyDatabase::new();
+        let (keypair, mut manager) = Keypair::new(T, &gens, &mut rng, &mut db).unwrap();
+        let mut auth_db = InMemorySigKeyDatabase::new();
+        let (authority, mut auth_manager) = Keypair::new(T, &gens, &mut rng, &mut auth_db).unwrap();
+
+        let revocation = Revocation::new(&keypair.ver_key, 3, RevocationReason::KeyCompromise);
+        assert!(!revocation.applies_to(&keypair.ver_key.fingerprint(), 2));
+        assert!(revocation.applies_to(&keypair.ver_key.fingerprint(), 3));
+        assert!(!revocation.applies_to(&authority.ver_key.fingerprint(), 3));
+
+        // By an authority
+        let signed = SignedRevocation::sign(
+            revocation,
+            &authority.ver_key,
+            &mut auth_manager,
+            &gens,
+            &auth_db,
+            &mut rng,
+        )
+        .unwrap();
+        assert!(!signed.is_self_signed());
+        assert!(signed.verify(&authority.ver_key, l, &gens).unwrap());
+        assert!(!signed.verify(&keypair.ver_key, l, &gens).unwrap());
+
+        let bytes = signed.to_bytes();
+        assert_eq!(bytes.len(), SIGNED_REVOCATION_SIZE);
+        let decoded = SignedRevocation::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.revocation, revocation);
+        assert_eq!(decoded.to_bytes(), bytes);
+        assert!(decoded.verify(&authority.ver_key, l, &gens).unwrap());
+        assert!(SignedRevocation::from_bytes(&bytes[1..]).is_err());
+        let mut bad_reason = bytes.clone();
+        bad_reason[REVOCATION_DST.len() + VERKEY_FINGERPRINT_SIZE + 16] = 9;
+        assert!(SignedRevocation::from_bytes(&bad_reason).is_err());
+        let mut tampered = bytes.clone();
+        tampered[REVOCATION_DST.len() + VERKEY_FINGERPRINT_SIZE + 15] = 2;
+        let tampered = SignedRevocation::from_bytes(&tampered).unwrap();
+        assert!(!tampered.verify(&authority.ver_key, l, &gens).unwrap());
+
+        // Self-signed from a later time period
+        manager
+            .fast_forward_update(5, &gens, &mut rng, &mut db)
+            .unwrap();
+        let signed = SignedRevocation::sign(
+            revocation,
+            &keypair.ver_key,
+            &mut manager,
+            &gens,
+            &db,
+            &mut rng,
+        )
+        .unwrap();
+        assert!(signed.is_self_signed());
+        assert_eq!(signed.signed_at, 5);
+        assert!(signed.verify(&keypair.ver_key, l, &gens).unwrap());
+    }
+
+    #[test]
+    fn test_revocation_aware_verification() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let l = calculate_l(T).unwrap();
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let mut db = InMemorySigKeyDatabase::new();
+        let (keypair, mut manager) = Keypair::new(T, &gens, &mut rng, &mut db).unwrap();
+        let mut db_1 = InMemorySigKeyDatabase::new();
+        let (keypair_1, mut manager_1) = Keypair::new(T, &gens, &mut rng, &mut db_1).unwrap();
+        let mut auth_db = InMemorySigKeyDatabase::new();
+        let (authority, mut auth_manager) = Keypair::new(T, &gens, &mut rng, &mut auth_db).unwrap();
+
+        let msg = "Hello".as_bytes();
+        let early_sig = manager.sign(msg, &gens, &db, &mut rng).unwrap();
+        let mut registry =
+            InMemoryRevocationRegistry::new(std::slice::from_ref(&authority.ver_key));
+        let revocation = Revocation::new(&keypair.ver_key, 3, RevocationReason::KeyCompromise);
+        // Only authorities or the key itself may revoke
+        let by_other = SignedRevocation::sign(
+            revocation,
+            &keypair_1.ver_key,
+            &mut manager_1,
+            &gens,
+            &db_1,
+            &mut rng,
+        )
+        .unwrap();
+        assert!(registry
+            .add(by_other, &keypair_1.ver_key, l, &gens)
+            .is_err());
+        let signed = SignedRevocation::sign(
+            revocation,
+            &authority.ver_key,
+            &mut auth_manager,
+            &gens,
+            &auth_db,
+            &mut rng,
+        )
+        .unwrap();
+        assert!(registry
+            .add(signed.clone(), &keypair.ver_key, l, &gens)
+            .is_err());
+        registry.add(signed, &authority.ver_key, l, &gens).unwrap();
+        assert_eq!(
+            registry.revoked_as_of(&keypair.ver_key.fingerprint()),
+            Some(3)
+        );
+
+        // A self-signed revocation cannot reach back before the time period it was signed in
+        let mut other_registry = InMemoryRevocationRegistry::new(&[]);
+        manager
+            .fast_forward_update(2, &gens, &mut rng, &mut db)
+            .unwrap();
+        let backdated = SignedRevocation::sign(
+            Revocation::new(&keypair.ver_key, 1, RevocationReason::KeyCompromise),
+            &keypair.ver_key,
+            &mut manager,
+            &gens,
+            &db,
+            &mut rng,
+        )
+        .unwrap();
+        assert!(backdated.verify(&keypair.ver_key, l, &gens).unwrap());
+        assert!(other_registry
+            .add(backdated.clone(), &keypair.ver_key, l, &gens)
+            .is_err());
+        assert!(registry.add(backdated, &keypair.ver_key, l, &gens).is_err());
+        assert_eq!(
+            other_registry.revoked_as_of(&keypair.ver_key.fingerprint()),
+            None
+        );
+        assert_eq!(
+            registry.revoked_as_of(&keypair.ver_key.fingerprint()),
+            Some(3)
+        );
+
+        // A later self-signed revocation does not move the revocation period
+        manager
+            .fast_forward_update(4, &gens, &mut rng, &mut db)
+            .unwrap();
+        let later = SignedRevocation::sign(
+            Revocation::new(&keypair.ver_key, 4, RevocationReason::KeyCompromise),
+            &keypair.ver_key,
+            &mut manager,
+            &gens,
+            &db,
+            &mut rng,
+        )
+        .unwrap();
+        registry.add(later, &keypair.ver_key, l, &gens).unwrap();
+        assert_eq!(
+            registry.revoked_as_of(&keypair.ver_key.fingerprint()),
+            Some(3)
+        );
+
+        let verifier =
+            RevocationAwareVerifier::new(PairingVerifier::new(T, gens.clone()).unwrap(), &registry);
+        let sig = manager.sign(msg, &gens, &db, &mut rng).unwrap();
+        assert!(verifier.verify(msg, 4, &sig, &keypair.ver_key).is_err());
+        // Signatures from before the revocation period still verify
+        assert!(verifier
+            .verify(msg, 1, &early_sig, &keypair.ver_key)
+            .unwrap());
+        let sig_1 = manager_1.sign(msg, &gens, &db_1, &mut rng).unwrap();
+        assert!(verifier.verify(msg, 1, &sig_1, &keypair_1.ver_key).unwrap());
+        let asig = Signature::aggregate(vec![&sig, &sig_1]);
+        assert!(verifier
+            .verify_aggregated(msg, 4, &asig, &[&keypair.ver_key, &keypair_1.ver_key])
+            .is_err());
+    }
+
+    #[test]
+    fn test_stolen_key_cannot_revoke_earlier_signatures() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let l = calculate_l(T).unwrap();
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let mut db = InMemorySigKeyDatabase::new();
+        let (keypair, mut manager) = Keypair::new(T, &gens, &mut rng, &mut db).unwrap();
+        let msg = "Hello".as_bytes();
+        let early_sig = manager.sign(msg, &gens, &db, &mut rng).unwrap();
+
+        // The key of time period 4 is stolen and used to revoke the key from time period 1 on
+        manager
+            .fast_forward_update(4, &gens, &mut rng, &mut db)
+            .unwrap();
+        let mut registry = InMemoryRevocationRegistry::new(&[]);
+        for as_of in 1..4 {
+            let backdated = SignedRevocation::sign(
+                Revocation::new(&keypair.ver_key, as_of, RevocationReason::KeyCompromise),
+                &keypair.ver_key,
+                &mut manager,
+                &gens,
+                &db,
+                &mut rng,
+            )
+            .unwrap();
+            assert!(registry.add(backdated, &keypair.ver_key, l, &gens).is_err());
+        }
+        let verifier =
+            RevocationAwareVerifier::new(PairingVerifier::new(T, gens.clone()).unwrap(), &registry);
+        assert!(verifier
+            .verify(msg, 1, &early_sig, &keypair.ver_key)
+            .unwrap());
+
+        // Revoking from the time period of the stolen key on is accepted
+        let signed = SignedRevocation::sign(
+            Revocation::new(&keypair.ver_key, 4, RevocationReason::KeyCompromise),
+            &keypair.ver_key,
+            &mut manager,
+            &gens,
+            &db,
+            &mut rng,
+        )
+        .unwrap();
+        registry.add(signed, &keypair.ver_key, l, &gens).unwrap();
+        let verifier =
+            RevocationAwareVerifier::new(PairingVerifier::new(T, gens.clone()).unwrap(), &registry);
+        assert!(verifier
+            .verify(msg, 1, &early_sig, &keypair.ver_key)
+            .unwrap());
+        let sig = manager.sign(msg, &gens, &db, &mut rng).unwrap();
+        assert!(verifier.verify(msg, 4, &sig, &keypair.ver_key).is_err());
+    }
+}
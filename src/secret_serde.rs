@@ -0,0 +1,87 @@
+//! Serde support for `MasterSecret`, `Sigkey` and `KeyBackup`, only compiled with feature
+//! `danger-serialize-secrets`.
+//!
+//! Threat model: the serialized form is the secret key in the clear. Anyone reading it, from a
+//! file, a log or a swapped out page, can sign for every time period the key covers, which
+//! defeats forward security for those periods. Serialize only into storage that is encrypted or
+//! otherwise protected at least as well as the key itself, and delete stored keys on update like
+//! `SigKeyDb::remove_key` does.
+//!
+//! Keys are serialized as bytes, `Sigkey` in the `CompressedSigkey` encoding and `MasterSecret` as
+//! its field element. The buffers holding the bytes on the way in and out are zeroed once used.
+//! Copies made by the serializer or deserializer, e.g. the text of a JSON document, are out of
+//! reach of this crate and must be cleared by the caller.
+
+use amcl_wrapper::field_elem::FieldElement;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use zeroize::Zeroizing;
+
+use crate::keys::{MasterSecret, Sigkey};
+use crate::wire::{decode_sigkey, CompressedSigkey};
+
+impl Serialize for MasterSecret {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let bytes = Zeroizing::new(self.value.to_bytes());
+        serializer.serialize_bytes(&bytes)
+    }
+}
+
+impl<'de> Deserialize<'de> for MasterSecret {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = Zeroizing::new(Vec::<u8>::deserialize(deserializer)?);
+        let value = FieldElement::from_bytes(&bytes)
+            .map_err(|e| D::Error::custom(format!("invalid master secret: {:?}", e)))?;
+        Ok(Self { value })
+    }
+}
+
+impl Serialize for Sigkey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let bytes = Zeroizing::new(CompressedSigkey::from(self).into_bytes());
+        serializer.serialize_bytes(&bytes)
+    }
+}
+
+impl<'de> Deserialize<'de> for Sigkey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = Zeroizing::new(Vec::<u8>::deserialize(deserializer)?);
+        decode_sigkey(&bytes).map_err(|e| D::Error::custom(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{InMemorySigKeyDatabase, Keypair, SigKeyDb};
+    use crate::recovery::KeyBackup;
+    use crate::util::GeneratorSet;
+
+    #[test]
+    fn test_secret_serialization() {
+        let mut rng = rand::thread_rng();
+        let secret = MasterSecret::new(&mut rng);
+        let json = serde_json::to_string(&secret).unwrap();
+        let secret_1: MasterSecret = serde_json::from_str(&json).unwrap();
+        assert_eq!(secret_1.value, secret.value);
+        assert!(serde_json::from_str::<MasterSecret>("[1,2,3]").is_err());
+
+        let T = 7;
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let mut db = InMemorySigKeyDatabase::new();
+        let (keypair, mut manager) = Keypair::new(T, &gens, &mut rng, &mut db).unwrap();
+        manager
+            .fast_forward_update(3, &gens, &mut rng, &mut db)
+            .unwrap();
+        let backup = KeyBackup::create(&manager, &keypair.ver_key, &db).unwrap();
+        let json = serde_json::to_string(&backup).unwrap();
+        let backup_1: KeyBackup = serde_json::from_str(&json).unwrap();
+        assert_eq!(backup_1.t, 3);
+        for ((t, sk), (t_1, sk_1)) in backup.keys.iter().zip(backup_1.keys.iter()) {
+            assert_eq!(t, t_1);
+            assert_eq!(sk.0, sk_1.0);
+            assert_eq!(sk.1, sk_1.1);
+        }
+        assert!(db.has_key(3));
+    }
+}
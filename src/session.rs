@@ -0,0 +1,365 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::aggregator::Aggregator;
+use crate::errors::ForwardSecureSignatureError;
+use crate::keys::Verkey;
+use crate::registry::{CommitteeId, VerkeyRegistry};
+use crate::signature::Signature;
+use crate::util::{GeneratorSet, GeneratorSetRef};
+
+/// Aggregated signature along with the committee members who contributed to it
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AccountableAggregate {
+    pub signature: Signature,
+    /// Indices of signers, in increasing order
+    pub signers: Vec<usize>,
+}
+
+impl AccountableAggregate {
+    /// Verify the aggregate against the verkeys of its signers
+    pub fn verify<V: VerkeyRegistry + ?Sized>(
+        &self,
+        msg: &[u8],
+        t: u128,
+        l: u8,
+        registry: &V,
+        gens: &GeneratorSet,
+    ) -> Result<bool, ForwardSecureSignatureError> {
+        self.signature
+            .verify_aggregated_with_registry(msg, t, l, &self.signers, registry, gens)
+    }
+}
+
+/// Aggregate of the aggregates of sub-committees, e.g. of the shards of a large validator set.
+/// Leaves are the aggregates of sub-committees and every inner node aggregates its children,
+/// whose signer sets must be disjoint. The whole tree is verified with a single pairing check
+/// on the root's signature after checking that every node is consistent with its children.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AggregateTree {
+    pub aggregate: AccountableAggregate,
+    /// Empty for the aggregate of a sub-committee
+    pub children: Vec<AggregateTree>,
+}
+
+impl AggregateTree {
+    pub fn leaf(aggregate: AccountableAggregate) -> Self {
+        Self {
+            aggregate,
+            children: vec![],
+        }
+    }
+
+    /// Aggregate the given trees. Fails if a signer is in more than one of them since its
+    /// signature would be counted twice.
+    pub fn combine(children: Vec<AggregateTree>) -> Result<Self, ForwardSecureSignatureError> {
+        if children.is_empty() {
+            return Err(ForwardSecureSignatureError::QuorumNotReached {
+                received: 0,
+                quorum: 1,
+            });
+        }
+        let signers = merge_signers(&children)?;
+        let signature =
+            Signature::aggregate(children.iter().map(|c| &c.aggregate.signature).collect());
+        Ok(Self {
+            aggregate: AccountableAggregate { signature, signers },
+            children,
+        })
+    }
+
+    /// Number of levels, 1 for a leaf
+    pub fn depth(&self) -> usize {
+        1 + self.children.iter().map(|c| c.depth()).max().unwrap_or(0)
+    }
+
+    /// Check that every inner node aggregates exactly its children and verify the root's
+    /// aggregate against the verkeys of all signers
+    pub fn verify<V: VerkeyRegistry + ?Sized>(
+        &self,
+        msg: &[u8],
+        t: u128,
+        l: u8,
+        registry: &V,
+        gens: &GeneratorSet,
+    ) -> Result<bool, ForwardSecureSignatureError> {
+        if !self.is_consistent()? {
+            return Ok(false);
+        }
+        self.aggregate.verify(msg, t, l, registry, gens)
+    }
+
+    fn is_consistent(&self) -> Result<bool, ForwardSecureSignatureError> {
+        if self.children.is_empty() {
+            return Ok(self.aggregate.signers.windows(2).all(|w| w[0] < w[1]));
+        }
+        for c in &self.children {
+            if !c.is_consistent()? {
+                return Ok(false);
+            }
+        }
+        let signature = Signature::aggregate(
+            self.children
+                .iter()
+                .map(|c| &c.aggregate.signature)
+                .collect(),
+        );
+        Ok(merge_signers(&self.children)? == self.aggregate.signers
+            && signature == self.aggregate.signature)
+    }
+}
+
+/// Sorted signers of all `trees`. Fails on a signer in more than one tree.
+fn merge_signers(trees: &[AggregateTree]) -> Result<Vec<usize>, ForwardSecureSignatureError> {
+    let mut signers = BTreeSet::new();
+    for tree in trees {
+        for s in &tree.aggregate.signers {
+            if !signers.insert(*s) {
+                return Err(ForwardSecureSignatureError::DuplicateShare { index: *s });
+            }
+        }
+    }
+    Ok(signers.into_iter().collect())
+}
+
+/// Collects signature shares of a committee on a message for a time period. Each share is
+/// verified against its signer's verkey before being aggregated so an invalid share cannot
+/// spoil the aggregate. Once `quorum` shares have been received the session can be finalized.
+pub struct MultisigSession {
+    msg: Vec<u8>,
+    t: u128,
+    l: u8,
+    gens: GeneratorSetRef,
+    quorum: usize,
+    committee_id: CommitteeId,
+    /// Verkeys of committee members who have not sent a valid share yet
+    pending: BTreeMap<usize, Verkey>,
+    received: BTreeSet<usize>,
+    aggregator: Aggregator,
+}
+
+impl MultisigSession {
+    /// Start a session for `committee`. Verkeys are taken from `registry` so every member must
+    /// have a verified proof of possession.
+    pub fn new<G: Into<GeneratorSetRef>, V: VerkeyRegistry + ?Sized>(
+        msg: &[u8],
+        t: u128,
+        l: u8,
+        gens: G,
+        committee: &[usize],
+        registry: &V,
+        quorum: usize,
+    ) -> Result<Self, ForwardSecureSignatureError> {
+        let mut pending = BTreeMap::new();
+        for index in committee {
+            pending.insert(*index, registry.aggregate_verkey(&[*index])?);
+        }
+        if quorum == 0 || quorum > pending.len() {
+            return Err(ForwardSecureSignatureError::InvalidQuorum {
+                quorum,
+                committee: pending.len(),
+            });
+        }
+        let members = pending.keys().copied().collect::<Vec<usize>>();
+        let committee_id = registry.committee_id(&members, quorum)?;
+        Ok(Self {
+            msg: msg.to_vec(),
+            t,
+            l,
+            gens: gens.into(),
+            quorum,
+            committee_id,
+            pending,
+            received: BTreeSet::new(),
+            aggregator: Aggregator::new(),
+        })
+    }
+
+    pub fn msg(&self) -> &[u8] {
+        &self.msg
+    }
+
+    pub fn t(&self) -> u128 {
+        self.t
+    }
+
+    pub fn quorum(&self) -> usize {
+        self.quorum
+    }
+
+    /// Identifier of the committee and quorum of this session
+    pub fn committee_id(&self) -> &CommitteeId {
+        &self.committee_id
+    }
+
+    /// Number of valid shares received so far
+    pub fn num_received(&self) -> usize {
+        self.received.len()
+    }
+
+    pub fn has_quorum(&self) -> bool {
+        self.received.len() >= self.quorum
+    }
+
+    /// Committee members who have not sent a valid share yet
+    pub fn missing(&self) -> Vec<usize> {
+        self.pending.keys().copied().collect()
+    }
+
+    /// Verify and aggregate the share of committee member `index`. Returns whether the quorum
+    /// has been reached.
+    pub fn add_share(
+        &mut self,
+        index: usize,
+        sig: &Signature,
+    ) -> Result<bool, ForwardSecureSignatureError> {
+        if self.received.contains(&index) {
+            return Err(ForwardSecureSignatureError::DuplicateShare { index });
+        }
+        let verkey = match self.pending.get(&index) {
+            Some(vk) => vk,
+            None => return Err(ForwardSecureSignatureError::UnknownCommitteeMember { index }),
+        };
+        if !sig.verify(&self.msg, self.t, self.l, &self.gens, verkey)? {
+            return Err(ForwardSecureSignatureError::InvalidShare { index });
+        }
+        self.pending.remove(&index);
+        self.received.insert(index);
+        self.aggregator.add(index, sig)?;
+        Ok(self.has_quorum())
+    }
+
+    /// Returns the aggregate of all received shares, fails if the quorum has not been reached
+    pub fn finalize(self) -> Result<AccountableAggregate, ForwardSecureSignatureError> {
+        if !self.has_quorum() {
+            return Err(ForwardSecureSignatureError::QuorumNotReached {
+                received: self.received.len(),
+                quorum: self.quorum,
+            });
+        }
+        let (signature, _) = self.aggregator.finalize();
+        Ok(AccountableAggregate {
+            signature,
+            signers: self.received.into_iter().collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{InMemorySigKeyDatabase, Keypair, SigManager};
+    use crate::registry::InMemoryVerkeyRegistry;
+    use crate::util::calculate_l;
+
+    #[test]
+    fn test_multisig_session() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let l = calculate_l(T).unwrap();
+        let t = 2u128;
+        let gens = GeneratorSetRef::new(GeneratorSet::new(T, "test_pixel").unwrap());
+        let msg = "Hello".as_bytes();
+
+        let mut registry = InMemoryVerkeyRegistry::new();
+        let mut sigs = vec![];
+        for i in 1..=4 {
+            let mut db = InMemorySigKeyDatabase::new();
+            let (keypair, mut manager) = Keypair::new(T, &gens, &mut rng, &mut db).unwrap();
+            manager.simple_update(&gens, &mut rng, &mut db).unwrap();
+            let sk = SigManager::get_key(t, &db).unwrap();
+            sigs.push(Signature::new(msg, t, l, &gens, sk, &mut rng).unwrap());
+            registry.add_member(i, keypair.ver_key).unwrap();
+            registry.verify_pop(i, &keypair.pop, &gens.0).unwrap();
+        }
+
+        let committee = [1, 2, 3, 4];
+        assert!(MultisigSession::new(msg, t, l, gens.clone(), &committee, &registry, 5).is_err());
+        assert!(MultisigSession::new(msg, t, l, gens.clone(), &[1, 5], &registry, 1).is_err());
+        let mut session =
+            MultisigSession::new(msg, t, l, gens.clone(), &committee, &registry, 3).unwrap();
+        assert_eq!(
+            session.committee_id(),
+            &registry.committee_id(&committee, 3).unwrap()
+        );
+
+        assert!(!session.add_share(1, &sigs[0]).unwrap());
+        // Duplicate, wrong signer and non member shares are rejected
+        assert!(session.add_share(1, &sigs[0]).is_err());
+        assert!(session.add_share(2, &sigs[2]).is_err());
+        assert!(session.add_share(5, &sigs[1]).is_err());
+        assert_eq!(session.num_received(), 1);
+        assert_eq!(session.missing(), vec![2, 3, 4]);
+
+        assert!(!session.add_share(4, &sigs[3]).unwrap());
+        let mut session_1 =
+            MultisigSession::new(msg, t, l, gens.clone(), &committee, &registry, 3).unwrap();
+        session_1.add_share(1, &sigs[0]).unwrap();
+        assert!(session_1.finalize().is_err());
+
+        assert!(session.add_share(2, &sigs[1]).unwrap());
+        assert_eq!(session.missing(), vec![3]);
+
+        let aggr = session.finalize().unwrap();
+        assert_eq!(aggr.signers, vec![1, 2, 4]);
+        assert!(aggr.verify(msg, t, l, &registry, &gens).unwrap());
+        assert!(!aggr.verify(msg, t - 1, l, &registry, &gens).unwrap());
+    }
+
+    #[test]
+    fn test_aggregate_tree() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let l = calculate_l(T).unwrap();
+        let gens = GeneratorSetRef::new(GeneratorSet::new(T, "test_pixel").unwrap());
+        let msg = "Hello".as_bytes();
+
+        let mut registry = InMemoryVerkeyRegistry::new();
+        let mut sigs = BTreeMap::new();
+        for i in 1..=6 {
+            let mut db = InMemorySigKeyDatabase::new();
+            let (keypair, manager) = Keypair::new(T, &gens, &mut rng, &mut db).unwrap();
+            let sk = manager.get_current_key(&db).unwrap();
+            sigs.insert(i, Signature::new(msg, 1, l, &gens, sk, &mut rng).unwrap());
+            registry.add_member(i, keypair.ver_key).unwrap();
+            registry.verify_pop(i, &keypair.pop, &gens.0).unwrap();
+        }
+        // Three sub-committees, two signing members each
+        let subs = [[1, 2], [3, 4], [5, 6]]
+            .iter()
+            .map(|members| {
+                let mut session =
+                    MultisigSession::new(msg, 1, l, gens.clone(), members, &registry, 2).unwrap();
+                for m in members {
+                    session.add_share(*m, &sigs[m]).unwrap();
+                }
+                AggregateTree::leaf(session.finalize().unwrap())
+            })
+            .collect::<Vec<_>>();
+
+        let left = AggregateTree::combine(vec![subs[1].clone(), subs[0].clone()]).unwrap();
+        let root = AggregateTree::combine(vec![left, subs[2].clone()]).unwrap();
+        assert_eq!(root.depth(), 3);
+        assert_eq!(root.aggregate.signers, vec![1, 2, 3, 4, 5, 6]);
+        assert!(root.verify(msg, 1, l, &registry, &gens).unwrap());
+        assert!(!root.verify(msg, 2, l, &registry, &gens).unwrap());
+        assert!(AggregateTree::combine(vec![root.clone(), subs[0].clone()]).is_err());
+        assert!(AggregateTree::combine(vec![]).is_err());
+
+        // Nodes inconsistent with their children
+        let mut bad = root.clone();
+        bad.children[0].aggregate.signers = vec![1, 2, 3];
+        assert!(!bad.verify(msg, 1, l, &registry, &gens).unwrap());
+        // Overlapping children
+        let mut bad = root.clone();
+        bad.children[1].aggregate.signers = vec![4, 5];
+        assert!(bad.verify(msg, 1, l, &registry, &gens).is_err());
+        let mut bad = root.clone();
+        bad.children[0].children[0].aggregate.signature = sigs[&1].clone();
+        assert!(!bad.verify(msg, 1, l, &registry, &gens).unwrap());
+        let mut bad = root.clone();
+        bad.aggregate.signers.pop();
+        assert!(!bad.verify(msg, 1, l, &registry, &gens).unwrap());
+    }
+}
@@ -1,20 +1,164 @@
+use rand::rngs::OsRng;
 use rand::{CryptoRng, RngCore};
 
 use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
 use amcl_wrapper::group_elem::{GroupElement, GroupElementVector};
-
+use rayon::prelude::*;
+use std::convert::TryFrom;
+
+use crate::compression::{
+    compress_signature_group, compress_verkey_group, decompress_signature_group,
+    decompress_signature_group_unchecked, decompress_verkey_group,
+    decompress_verkey_group_unchecked, SIGNATURE_GROUP_COMPRESSED_SIZE,
+    VERKEY_GROUP_COMPRESSED_SIZE,
+};
 use crate::errors::ForwardSecureSignatureError;
 use crate::keys::{Sigkey, Verkey};
-use crate::util::{calculate_path_factor_using_t_l, from_node_num_to_path, GeneratorSet};
-use crate::{ate_multi_pairing, SignatureGroup, SignatureGroupVec, VerkeyGroup};
-
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+use crate::message_hash::{DefaultMessageHasher, MessageHasher};
+use crate::registry::VerkeyRegistry;
+use crate::util::{
+    calculate_path_factor_from_slice, calculate_path_factor_using_t_l, from_node_num_to_path,
+    node_num_to_path_into, random_field_element, GeneratorSet, GeneratorSource, MAX_PATH_LEN,
+};
+use crate::{
+    ate_multi_pairing, ate_multi_pairing_slices, SignatureGroup, SignatureGroupVec, VerkeyGroup,
+};
+
+const HEDGED_NONCE_DST: &[u8] = b"PIXEL-HEDGED-NONCE-V1";
+
+/// Size of a serialized signature, compressed `sigma_1` followed by compressed `sigma_2`
+pub const SIGNATURE_SIZE: usize = SIGNATURE_GROUP_COMPRESSED_SIZE + VERKEY_GROUP_COMPRESSED_SIZE;
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Signature {
     pub sigma_1: SignatureGroup,
     pub sigma_2: VerkeyGroup,
 }
 
+/// Caller-provided scratch space for `Signature::verify_no_alloc`. Holds the node path and the
+/// inputs of the 3-pairing in fixed-size arrays. Can be reused across verifications.
+pub struct VerificationScratch {
+    path: [u8; MAX_PATH_LEN],
+    sig_elems: [SignatureGroup; 3],
+    vk_elems: [VerkeyGroup; 3],
+}
+
+impl VerificationScratch {
+    pub fn new() -> Self {
+        Self {
+            path: [0; MAX_PATH_LEN],
+            sig_elems: [
+                SignatureGroup::identity(),
+                SignatureGroup::identity(),
+                SignatureGroup::identity(),
+            ],
+            vk_elems: [
+                VerkeyGroup::identity(),
+                VerkeyGroup::identity(),
+                VerkeyGroup::identity(),
+            ],
+        }
+    }
+}
+
+impl Default for VerificationScratch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Message independent part of a signature for time period `t`, created ahead of time with
+/// `Signature::precompute`. Contains the signing randomness so it is consumed by the signature
+/// it completes and must never be reused.
+pub struct PrecomputedSignature {
+    t: u128,
+    /// d + pf*r
+    sigma_1_base: SignatureGroup,
+    /// e_l + gens.1[l+1]*r, gets multiplied by the message
+    msg_base: SignatureGroup,
+    sigma_2: VerkeyGroup,
+}
+
+impl PrecomputedSignature {
+    /// Time period for which the signature will be valid
+    pub fn t(&self) -> u128 {
+        self.t
+    }
+}
+
+fn split_signature_bytes(sig_bytes: &[u8]) -> Result<(&[u8], &[u8]), ForwardSecureSignatureError> {
+    if sig_bytes.len() != SIGNATURE_SIZE {
+        return Err(ForwardSecureSignatureError::InvalidPoint {
+            msg: format!("{} bytes instead of {}", sig_bytes.len(), SIGNATURE_SIZE),
+        });
+    }
+    Ok(sig_bytes.split_at(SIGNATURE_GROUP_COMPRESSED_SIZE))
+}
+
+impl TryFrom<[u8; SIGNATURE_SIZE]> for Signature {
+    type Error = ForwardSecureSignatureError;
+
+    fn try_from(bytes: [u8; SIGNATURE_SIZE]) -> Result<Self, Self::Error> {
+        Signature::from_bytes(&bytes)
+    }
+}
+
+impl From<&Signature> for [u8; SIGNATURE_SIZE] {
+    fn from(sig: &Signature) -> Self {
+        let mut bytes = [0; SIGNATURE_SIZE];
+        bytes.copy_from_slice(&sig.to_bytes());
+        bytes
+    }
+}
+
+impl From<Signature> for [u8; SIGNATURE_SIZE] {
+    fn from(sig: Signature) -> Self {
+        (&sig).into()
+    }
+}
+
 impl Signature {
+    /// Does the message independent work of signing for time period `t`. The signature can then
+    /// be completed cheaply with `Signature::new_from_precomputed` once the message is known.
+    pub fn precompute<R: RngCore + CryptoRng>(
+        t: u128,
+        l: u8,
+        gens: &GeneratorSet,
+        sig_key: &Sigkey,
+        rng: &mut R,
+    ) -> Result<PrecomputedSignature, ForwardSecureSignatureError> {
+        if gens.1.len() < (l as usize + 2) {
+            return Err(ForwardSecureSignatureError::NotEnoughGenerators { n: l as usize + 2 });
+        }
+
+        let r = random_field_element(rng)?;
+        let pf = calculate_path_factor_using_t_l(t, l, gens)?;
+        let c = &sig_key.0;
+        let d = &sig_key.1[0];
+        // e_l
+        let e_l = &sig_key.1[sig_key.1.len() - 1];
+
+        // sigma_1 = d + (e_l * &m) + (pf + (gens.1[l as usize + 1] * m))*r
+        //         = (d + pf*r) + (e_l + gens.1[l as usize + 1]*r)*m
+        Ok(PrecomputedSignature {
+            t,
+            sigma_1_base: d + (&pf * &r),
+            msg_base: e_l + (&gens.1[l as usize + 1] * &r),
+            sigma_2: c + (&gens.0 * &r),
+        })
+    }
+
+    /// Completes a signature on `msg` using the precomputation. Only needs a single scalar
+    /// multiplication. The precomputation is consumed.
+    pub fn new_from_precomputed(msg: &[u8], precomputed: PrecomputedSignature) -> Self {
+        let m = Self::hash_message(msg);
+        Self {
+            sigma_1: precomputed.sigma_1_base + (&precomputed.msg_base * &m),
+            sigma_2: precomputed.sigma_2,
+        }
+    }
+
     /// Creates new in-deterministic signature
     pub fn new<R: RngCore + CryptoRng>(
         msg: &[u8],
@@ -28,8 +172,38 @@ impl Signature {
             return Err(ForwardSecureSignatureError::NotEnoughGenerators { n: l as usize + 2 });
         }
 
-        let r = FieldElement::random_using_rng(rng);
-        Self::gen_sig(msg, t, l, gens, sig_key, r)
+        Self::new_with_hasher::<DefaultMessageHasher, R>(msg, t, l, gens, sig_key, rng)
+    }
+
+    /// Same as `new` but hashes the message with `H` instead of the default hasher. Verify with
+    /// `verify_with_hasher` for the same `H`.
+    pub fn new_with_hasher<H: MessageHasher, R: RngCore + CryptoRng>(
+        msg: &[u8],
+        t: u128,
+        l: u8,
+        gens: &GeneratorSet,
+        sig_key: &Sigkey,
+        rng: &mut R,
+    ) -> Result<Self, ForwardSecureSignatureError> {
+        if gens.1.len() < (l as usize + 2) {
+            return Err(ForwardSecureSignatureError::NotEnoughGenerators { n: l as usize + 2 });
+        }
+
+        let r = random_field_element(rng)?;
+        let m = H::hash_to_field(msg);
+        let pf = calculate_path_factor_using_t_l(t, l, gens)?;
+        Ok(Self::gen_sig_with_hashed_msg(m, pf, l, gens, sig_key, r))
+    }
+
+    /// Same as `new` but takes randomness from the operating system
+    pub fn sign(
+        msg: &[u8],
+        t: u128,
+        l: u8,
+        gens: &GeneratorSet,
+        sig_key: &Sigkey,
+    ) -> Result<Self, ForwardSecureSignatureError> {
+        Self::new(msg, t, l, gens, sig_key, &mut OsRng)
     }
 
     /// Creates new deterministic signature. Signature for same message and secret key will be equal
@@ -47,6 +221,63 @@ impl Signature {
         Self::gen_sig(msg, t, l, gens, sig_key, r)
     }
 
+    /// Creates a signature whose randomness is derived from fresh randomness of `rng` and from
+    /// the message, time period and signing key as for `new_deterministic`. Thus a weak `rng`
+    /// does not break security and neither does a fault while signing deterministically.
+    /// Recommended in production.
+    pub fn new_hedged<R: RngCore + CryptoRng>(
+        msg: &[u8],
+        t: u128,
+        l: u8,
+        gens: &GeneratorSet,
+        sig_key: &Sigkey,
+        rng: &mut R,
+    ) -> Result<Self, ForwardSecureSignatureError> {
+        if gens.1.len() < (l as usize + 2) {
+            return Err(ForwardSecureSignatureError::NotEnoughGenerators { n: l as usize + 2 });
+        }
+        let mut fresh = [0; 32];
+        rng.try_fill_bytes(&mut fresh)
+            .map_err(|e| ForwardSecureSignatureError::RngFailure { msg: e.to_string() })?;
+        let mut bytes = HEDGED_NONCE_DST.to_vec();
+        bytes.extend_from_slice(&fresh);
+        bytes.extend_from_slice(&Self::gen_sig_rand(msg, t, sig_key).to_bytes());
+        let r = FieldElement::from_msg_hash(&bytes);
+        Self::gen_sig(msg, t, l, gens, sig_key, r)
+    }
+
+    /// Creates in-deterministic signatures on several messages for the same time period. Messages
+    /// are hashed in parallel and the path factor for `t` is computed only once.
+    pub fn new_batch<R: RngCore + CryptoRng>(
+        msgs: &[&[u8]],
+        t: u128,
+        l: u8,
+        gens: &GeneratorSet,
+        sig_key: &Sigkey,
+        rng: &mut R,
+    ) -> Result<Vec<Self>, ForwardSecureSignatureError> {
+        if gens.1.len() < (l as usize + 2) {
+            return Err(ForwardSecureSignatureError::NotEnoughGenerators { n: l as usize + 2 });
+        }
+
+        let hashes = Self::hash_messages(msgs);
+        let pf = calculate_path_factor_using_t_l(t, l, gens)?;
+        hashes
+            .into_iter()
+            .map(|m| {
+                let r = random_field_element(rng)?;
+                Ok(Self::gen_sig_with_hashed_msg(
+                    m,
+                    pf.clone(),
+                    l,
+                    gens,
+                    sig_key,
+                    r,
+                ))
+            })
+            .collect()
+    }
+
     pub fn aggregate(sigs: Vec<&Self>) -> Self {
         let mut asig_1 = SignatureGroup::identity();
         let mut asig_2 = VerkeyGroup::identity();
@@ -60,7 +291,35 @@ impl Signature {
         }
     }
 
-    pub fn verify(
+    pub fn verify<G: GeneratorSource + ?Sized>(
+        &self,
+        msg: &[u8],
+        t: u128,
+        l: u8,
+        gens: &G,
+        verkey: &Verkey,
+    ) -> Result<bool, ForwardSecureSignatureError> {
+        if gens.num_h() < (l as usize + 2) {
+            return Err(ForwardSecureSignatureError::NotEnoughGenerators { n: l as usize + 2 });
+        }
+
+        if self.is_identity() || verkey.is_identity() || !self.has_correct_oder() {
+            return Ok(false);
+        }
+        let m = Self::hash_message(msg);
+        let pf = calculate_path_factor_using_t_l(t, l, gens)?;
+        Ok(Self::verify_with_generators(
+            &self.sigma_1,
+            &self.sigma_2,
+            &verkey.value,
+            &m,
+            &pf,
+            (gens.g(), gens.h(0)?, gens.h(l as usize + 1)?),
+        ))
+    }
+
+    /// Same as `verify` but for a signature created with `new_with_hasher` for `H`
+    pub fn verify_with_hasher<H: MessageHasher>(
         &self,
         msg: &[u8],
         t: u128,
@@ -75,7 +334,95 @@ impl Signature {
         if self.is_identity() || verkey.is_identity() || !self.has_correct_oder() {
             return Ok(false);
         }
-        Self::verify_naked(&self.sigma_1, &self.sigma_2, &verkey.value, msg, t, l, gens)
+        let m = H::hash_to_field(msg);
+        let pf = calculate_path_factor_using_t_l(t, l, gens)?;
+        Ok(Self::verify_naked_with_hashed_msg(
+            &self.sigma_1,
+            &self.sigma_2,
+            &verkey.value,
+            &m,
+            &pf,
+            l,
+            gens,
+        ))
+    }
+
+    /// Same as `verify` but does not allocate on the heap. The node path and pairing inputs are
+    /// written to `scratch`.
+    pub fn verify_no_alloc(
+        &self,
+        msg: &[u8],
+        t: u128,
+        l: u8,
+        gens: &GeneratorSet,
+        verkey: &Verkey,
+        scratch: &mut VerificationScratch,
+    ) -> Result<bool, ForwardSecureSignatureError> {
+        if gens.1.len() < (l as usize + 2) {
+            return Err(ForwardSecureSignatureError::NotEnoughGenerators { n: l as usize + 2 });
+        }
+
+        if self.is_identity() || verkey.is_identity() || !self.has_correct_oder() {
+            return Ok(false);
+        }
+
+        let path_len = node_num_to_path_into(t, l, &mut scratch.path)?;
+        let mut sigma_1_1 = calculate_path_factor_from_slice(&scratch.path[..path_len], gens)?;
+        sigma_1_1 += &gens.1[l as usize + 1] * Self::hash_message(msg);
+
+        // Same equation as in `verify_naked`, e(sigma_1, -g2) * e(h, y) * e(sigma_1_1, sigma_2) == 1
+        scratch.sig_elems = [self.sigma_1.clone(), gens.1[0].clone(), sigma_1_1];
+        scratch.vk_elems = [
+            gens.0.negation(),
+            verkey.value.clone(),
+            self.sigma_2.clone(),
+        ];
+        Ok(ate_multi_pairing_slices(&scratch.sig_elems, &scratch.vk_elems).is_one())
+    }
+
+    /// Verify several signatures by the same verkey for the same time period. Messages are hashed
+    /// in parallel and the path factor for `t` is computed only once. Returns true only if all
+    /// signatures are valid.
+    pub fn verify_batch(
+        msgs_and_sigs: &[(&[u8], &Self)],
+        t: u128,
+        l: u8,
+        gens: &GeneratorSet,
+        verkey: &Verkey,
+    ) -> Result<bool, ForwardSecureSignatureError> {
+        if gens.1.len() < (l as usize + 2) {
+            return Err(ForwardSecureSignatureError::NotEnoughGenerators { n: l as usize + 2 });
+        }
+
+        if verkey.is_identity() {
+            return Ok(false);
+        }
+        for (_, sig) in msgs_and_sigs {
+            if sig.is_identity() || !sig.has_correct_oder() {
+                return Ok(false);
+            }
+        }
+
+        let msgs = msgs_and_sigs
+            .iter()
+            .map(|(m, _)| *m)
+            .collect::<Vec<&[u8]>>();
+        let hashes = Self::hash_messages(&msgs);
+        let pf = calculate_path_factor_using_t_l(t, l, gens)?;
+        Ok(msgs_and_sigs
+            .par_iter()
+            .zip(hashes.par_iter())
+            .all(|((_, sig), m)| {
+                Self::verify_naked_with_hashed_msg(
+                    &sig.sigma_1,
+                    &sig.sigma_2,
+                    &verkey.value,
+                    m,
+                    &pf,
+                    l,
+                    gens,
+                )
+            }))
     }
 
     pub fn verify_aggregated(
@@ -90,10 +437,60 @@ impl Signature {
         self.verify(msg, t, l, gens, &avk)
     }
 
+    /// Verify an aggregated signature of the given committee members. Fails if any member is
+    /// unknown or its proof of possession has not been verified.
+    pub fn verify_aggregated_with_registry<V: VerkeyRegistry + ?Sized>(
+        &self,
+        msg: &[u8],
+        t: u128,
+        l: u8,
+        indices: &[usize],
+        registry: &V,
+        gens: &GeneratorSet,
+    ) -> Result<bool, ForwardSecureSignatureError> {
+        let avk = registry.aggregate_verkey(indices)?;
+        self.verify(msg, t, l, gens, &avk)
+    }
+
+    /// Serialize as compressed `sigma_1` followed by compressed `sigma_2`, in `SIGNATURE_SIZE`
+    /// bytes. See `compression` for the encoding.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = compress_signature_group(&self.sigma_1);
+        bytes.append(&mut compress_verkey_group(&self.sigma_2));
+        bytes
+    }
+
+    /// Deserialize signature without checking that the points have the correct order and are
+    /// not the identity. Only use for signatures that have been validated before, like ones
+    /// loaded from own database.
+    pub fn from_bytes_unchecked(sig_bytes: &[u8]) -> Result<Self, ForwardSecureSignatureError> {
+        let (sigma_1_bytes, sigma_2_bytes) = split_signature_bytes(sig_bytes)?;
+        Ok(Self {
+            sigma_1: decompress_signature_group_unchecked(sigma_1_bytes)?,
+            sigma_2: decompress_verkey_group_unchecked(sigma_2_bytes)?,
+        })
+    }
+
+    /// Deserialize signature serialized with `to_bytes`, failing with `InvalidPoint` if a point
+    /// is the identity or not in the prime order subgroup. Use for signatures from untrusted
+    /// sources.
+    pub fn from_bytes(sig_bytes: &[u8]) -> Result<Self, ForwardSecureSignatureError> {
+        let (sigma_1_bytes, sigma_2_bytes) = split_signature_bytes(sig_bytes)?;
+        Ok(Self {
+            sigma_1: decompress_signature_group(sigma_1_bytes)?,
+            sigma_2: decompress_verkey_group(sigma_2_bytes)?,
+        })
+    }
+
     /// Hash message in the field before signing or verification
-    fn hash_message(message: &[u8]) -> FieldElement {
+    pub(crate) fn hash_message(message: &[u8]) -> FieldElement {
         // Fixme: This is not accurate and might affect the security proof but should work in practice
-        FieldElement::from_msg_hash(message)
+        DefaultMessageHasher::hash_to_field(message)
+    }
+
+    /// Hash several messages in parallel
+    fn hash_messages(messages: &[&[u8]]) -> Vec<FieldElement> {
+        messages.par_iter().map(|m| Self::hash_message(m)).collect()
     }
 
     /// Generate random number for signature using message time period and signing key for that time period.
@@ -115,17 +512,41 @@ impl Signature {
         sig_key: &Sigkey,
         r: FieldElement,
     ) -> Result<Self, ForwardSecureSignatureError> {
-        let c = sig_key.0.clone();
-        let d = sig_key.1[0].clone();
-
         // Hash(msg) -> FieldElement
         let m = Self::hash_message(msg);
+        let pf = calculate_path_factor_using_t_l(t, l, gens)?;
+        Ok(Self::gen_sig_with_hashed_msg(m, pf, l, gens, sig_key, r))
+    }
 
-        let sigma_2 = &c + (&gens.0 * &r);
+    /// Generate signature for an already hashed message `m` and path factor `pf` of the time period
+    fn gen_sig_with_hashed_msg(
+        m: FieldElement,
+        pf: SignatureGroup,
+        l: u8,
+        gens: &GeneratorSet,
+        sig_key: &Sigkey,
+        r: FieldElement,
+    ) -> Self {
+        Self::gen_sig_with_generators(m, pf, &gens.0, &gens.1[l as usize + 1], sig_key, r)
+    }
+
+    /// Same as `gen_sig_with_hashed_msg` but takes the generator `g2` of the verkey group and
+    /// the generator `h_m` for the message rather than the generator set
+    pub(crate) fn gen_sig_with_generators(
+        m: FieldElement,
+        pf: SignatureGroup,
+        g2: &VerkeyGroup,
+        h_m: &SignatureGroup,
+        sig_key: &Sigkey,
+        r: FieldElement,
+    ) -> Self {
+        let c = sig_key.0.clone();
+        let d = sig_key.1[0].clone();
+
+        let sigma_2 = &c + (g2 * &r);
 
         // e_l
         let e_l = sig_key.1[sig_key.1.len() - 1].clone();
-        let pf = calculate_path_factor_using_t_l(t, l, gens)?;
 
         // sigma_1 = d + (e_l * &m) + (pf + (gens.1[l as usize + 1] * m))*r
         let mut sigma_1 = d;
@@ -137,7 +558,7 @@ impl Signature {
         scalars.push(m.clone());
 
         // gens.1[l as usize + 1] * (m * r)
-        points.push(gens.1[l as usize + 1].clone());
+        points.push(h_m.clone());
         scalars.push(m * &r);
 
         // pf * r
@@ -148,27 +569,42 @@ impl Signature {
             .multi_scalar_mul_const_time(scalars.as_ref())
             .unwrap();
 
-        Ok(Self {
-            sigma_1: sigma_1.clone(),
-            sigma_2: sigma_2.clone(),
-        })
+        Self { sigma_1, sigma_2 }
     }
 
-    fn verify_naked(
+    /// Verify for an already hashed message `m` and path factor `pf` of the time period
+    pub(crate) fn verify_naked_with_hashed_msg(
         sigma_1: &SignatureGroup,
         sigma_2: &VerkeyGroup,
         verkey: &VerkeyGroup,
-        msg: &[u8],
-        t: u128,
+        m: &FieldElement,
+        pf: &SignatureGroup,
         l: u8,
         gens: &GeneratorSet,
-    ) -> Result<bool, ForwardSecureSignatureError> {
-        let h = &gens.1[0];
-        let g2 = &gens.0;
+    ) -> bool {
+        Self::verify_with_generators(
+            sigma_1,
+            sigma_2,
+            verkey,
+            m,
+            pf,
+            (&gens.0, &gens.1[0], &gens.1[l as usize + 1]),
+        )
+    }
+
+    /// Same as `verify_naked_with_hashed_msg` but takes the generators `(g2, h, h_m)`, `g2` of
+    /// the verkey group and `h_m` for the message, rather than the generator set
+    pub(crate) fn verify_with_generators(
+        sigma_1: &SignatureGroup,
+        sigma_2: &VerkeyGroup,
+        verkey: &VerkeyGroup,
+        m: &FieldElement,
+        pf: &SignatureGroup,
+        (g2, h, h_m): (&VerkeyGroup, &SignatureGroup, &SignatureGroup),
+    ) -> bool {
         let y = verkey;
-        let m = Self::hash_message(msg);
-        let mut sigma_1_1 = calculate_path_factor_using_t_l(t, l, gens)?;
-        sigma_1_1 += &gens.1[l as usize + 1] * m;
+        let mut sigma_1_1 = pf.clone();
+        sigma_1_1 += h_m * m;
 
         // Check that e(sigma_1, g2) == e(h, y) * e(sigma_1_1, sigma_2)
         // This is equivalent to checking e(h, y) * e(sigma_1_1, sigma_2) * e(sigma_1, g2)^-1 == 1
@@ -180,10 +616,10 @@ impl Signature {
             (h, y),
             (&sigma_1_1, sigma_2),
         ]);
-        Ok(e.is_one())
+        e.is_one()
     }
 
-    fn is_identity(&self) -> bool {
+    pub(crate) fn is_identity(&self) -> bool {
         if self.sigma_1.is_identity() {
             println!("Signature point in G1 at infinity");
             return true;
@@ -195,7 +631,7 @@ impl Signature {
         return false;
     }
 
-    fn has_correct_oder(&self) -> bool {
+    pub(crate) fn has_correct_oder(&self) -> bool {
         if !self.sigma_1.has_correct_order() {
             println!("Signature point in G1 has incorrect order");
             return false;
@@ -256,6 +692,218 @@ mod tests {
         create_sig_and_verify::<ThreadRng>(&set, t, &vk, l, &gens, &mut rng, &db);
     }
 
+    #[test]
+    fn test_sign_with_os_rng() {
+        let T = 7;
+        let l = calculate_l(T).unwrap();
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let mut db = InMemorySigKeyDatabase::new();
+        let (keypair, manager) = Keypair::generate(T, &gens, &mut db).unwrap();
+        let msg = "Hello".as_bytes();
+        let sk = manager.get_current_key(&db).unwrap();
+        let sig = Signature::sign(msg, 1, l, &gens, sk).unwrap();
+        assert!(sig.verify(msg, 1, l, &gens, &keypair.ver_key).unwrap());
+        assert_ne!(sig, Signature::sign(msg, 1, l, &gens, sk).unwrap());
+    }
+
+    #[test]
+    fn test_hedged_signature() {
+        use crate::drbg::SeededDrbg;
+        use rand::SeedableRng;
+
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let l = calculate_l(T).unwrap();
+        let mut db = InMemorySigKeyDatabase::new();
+        let (gens, vk, manager, _) =
+            setup::<ThreadRng>(T, "test_pixel", &mut rng, &mut db).unwrap();
+        let sk = manager.get_current_key(&db).unwrap();
+        let msg = "Hello".as_bytes();
+
+        let sig = Signature::new_hedged(msg, 1, l, &gens, sk, &mut rng).unwrap();
+        assert!(sig.verify(msg, 1, l, &gens, &vk).unwrap());
+        assert_ne!(
+            sig,
+            Signature::new_hedged(msg, 1, l, &gens, sk, &mut rng).unwrap()
+        );
+        let deterministic = Signature::new_deterministic(msg, 1, l, &gens, sk).unwrap();
+        assert_ne!(sig, deterministic);
+
+        // With an rng repeating its output, distinct messages still get distinct randomness
+        let hedged = |msg: &[u8]| {
+            let mut stuck = SeededDrbg::from_seed([0; 32]);
+            Signature::new_hedged(msg, 1, l, &gens, sk, &mut stuck).unwrap()
+        };
+        assert_eq!(hedged(msg), hedged(msg));
+        let other = hedged(b"Hi");
+        assert!(other.verify(b"Hi", 1, l, &gens, &vk).unwrap());
+        assert_ne!(hedged(msg).sigma_2, other.sigma_2);
+    }
+
+    #[test]
+    fn test_sig_verify_no_alloc() {
+        let mut rng = rand::thread_rng();
+        let T = 15;
+        let l = calculate_l(T).unwrap();
+        let mut db = InMemorySigKeyDatabase::new();
+        let (gens, vk, mut set, _) =
+            setup::<ThreadRng>(T, "test_pixel", &mut rng, &mut db).unwrap();
+        let msg = "Hello".as_bytes();
+        let mut scratch = VerificationScratch::new();
+
+        for t in [1u128, 4, 9, 15] {
+            if t > 1 {
+                set.fast_forward_update(t, &gens, &mut rng, &mut db)
+                    .unwrap();
+            }
+            let sk = SigManager::get_key(t, &db).unwrap();
+            let sig = Signature::new(msg, t, l, &gens, sk, &mut rng).unwrap();
+            assert!(sig.verify(msg, t, l, &gens, &vk).unwrap());
+            assert!(sig
+                .verify_no_alloc(msg, t, l, &gens, &vk, &mut scratch)
+                .unwrap());
+            assert!(!sig
+                .verify_no_alloc("World".as_bytes(), t, l, &gens, &vk, &mut scratch)
+                .unwrap());
+        }
+    }
+
+    #[test]
+    fn test_sig_from_bytes_unchecked() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let l = calculate_l(T).unwrap();
+        let mut db = InMemorySigKeyDatabase::new();
+        let (gens, vk, _, _) = setup::<ThreadRng>(T, "test_pixel", &mut rng, &mut db).unwrap();
+        let msg = "Hello".as_bytes();
+        let sk = SigManager::get_key(1, &db).unwrap();
+        let sig = Signature::new(msg, 1, l, &gens, sk, &mut rng).unwrap();
+
+        let bytes = sig.to_bytes();
+        assert_eq!(bytes.len(), SIGNATURE_SIZE);
+        let sig_1 = Signature::from_bytes_unchecked(&bytes).unwrap();
+        assert_eq!(sig, sig_1);
+        assert!(sig_1.verify(msg, 1, l, &gens, &vk).unwrap());
+
+        assert!(Signature::from_bytes_unchecked(&bytes[..SIGNATURE_SIZE - 1]).is_err());
+        assert!(Signature::from_bytes_unchecked(&bytes[..10]).is_err());
+    }
+
+    #[test]
+    fn test_sig_checked_and_compressed_bytes() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let l = calculate_l(T).unwrap();
+        let mut db = InMemorySigKeyDatabase::new();
+        let (gens, vk, _, _) = setup::<ThreadRng>(T, "test_pixel", &mut rng, &mut db).unwrap();
+        let msg = "Hello".as_bytes();
+        let sk = SigManager::get_key(1, &db).unwrap();
+        let sig = Signature::new(msg, 1, l, &gens, sk, &mut rng).unwrap();
+
+        let bytes = sig.to_bytes();
+        assert_eq!(bytes.len(), SIGNATURE_SIZE);
+        let sig_1 = Signature::from_bytes(&bytes).unwrap();
+        assert_eq!(sig_1, sig);
+        assert!(sig_1.verify(msg, 1, l, &gens, &vk).unwrap());
+        assert!(Signature::from_bytes(&bytes[1..]).is_err());
+
+        // Identity points are rejected by the checked decoders only
+        let identity = Signature {
+            sigma_1: SignatureGroup::identity(),
+            sigma_2: sig.sigma_2.clone(),
+        };
+        assert!(Signature::from_bytes_unchecked(&identity.to_bytes()).is_ok());
+        assert!(Signature::from_bytes(&identity.to_bytes()).is_err());
+        let mut array = [0; SIGNATURE_SIZE];
+        array.copy_from_slice(&identity.to_bytes());
+        assert!(Signature::try_from(array).is_err());
+    }
+
+    #[test]
+    fn test_sig_byte_array_conversion() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let l = calculate_l(T).unwrap();
+        let mut db = InMemorySigKeyDatabase::new();
+        let (gens, vk, _, _) = setup::<ThreadRng>(T, "test_pixel", &mut rng, &mut db).unwrap();
+        let msg = "Hello".as_bytes();
+        let sk = SigManager::get_key(1, &db).unwrap();
+        let sig = Signature::new(msg, 1, l, &gens, sk, &mut rng).unwrap();
+
+        let bytes: [u8; SIGNATURE_SIZE] = (&sig).into();
+        assert_eq!(bytes.to_vec(), sig.to_bytes());
+        let sig_1 = Signature::try_from(bytes).unwrap();
+        assert!(sig_1.verify(msg, 1, l, &gens, &vk).unwrap());
+        let bytes_1: [u8; SIGNATURE_SIZE] = sig_1.into();
+        assert_eq!(bytes, bytes_1);
+        let mut bad = bytes;
+        bad[10] ^= 1;
+        assert!(Signature::try_from(bad).is_err());
+    }
+
+    #[test]
+    fn test_batch_sign_verify() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let l = calculate_l(T).unwrap();
+        let mut db = InMemorySigKeyDatabase::new();
+        let (gens, vk, mut set, _) =
+            setup::<ThreadRng>(T, "test_pixel", &mut rng, &mut db).unwrap();
+        set.fast_forward_update(3, &gens, &mut rng, &mut db)
+            .unwrap();
+        let t = 3u128;
+        let sk = SigManager::get_key(t, &db).unwrap();
+
+        let msgs = (0..10)
+            .map(|_| FieldElement::random().to_bytes())
+            .collect::<Vec<Vec<u8>>>();
+        let msg_refs = msgs.iter().map(|m| m.as_slice()).collect::<Vec<&[u8]>>();
+        let sigs = Signature::new_batch(&msg_refs, t, l, &gens, sk, &mut rng).unwrap();
+        assert_eq!(sigs.len(), msgs.len());
+        for (m, sig) in msg_refs.iter().zip(sigs.iter()) {
+            assert!(sig.verify(m, t, l, &gens, &vk).unwrap());
+        }
+
+        let mut items = msg_refs
+            .iter()
+            .cloned()
+            .zip(sigs.iter())
+            .collect::<Vec<(&[u8], &Signature)>>();
+        assert!(Signature::verify_batch(&items, t, l, &gens, &vk).unwrap());
+        // Wrong time period
+        assert!(!Signature::verify_batch(&items, 4, l, &gens, &vk).unwrap());
+
+        // A single mismatched message fails the batch
+        items[5].0 = "Hello".as_bytes();
+        assert!(!Signature::verify_batch(&items, t, l, &gens, &vk).unwrap());
+    }
+
+    #[test]
+    fn test_sig_precomputed() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let l = calculate_l(T).unwrap();
+        let mut db = InMemorySigKeyDatabase::new();
+        let (gens, vk, mut set, _) =
+            setup::<ThreadRng>(T, "test_pixel", &mut rng, &mut db).unwrap();
+        set.fast_forward_update(5, &gens, &mut rng, &mut db)
+            .unwrap();
+        let sk = SigManager::get_key(5, &db).unwrap();
+
+        let pre_1 = Signature::precompute(5, l, &gens, sk, &mut rng).unwrap();
+        let pre_2 = Signature::precompute(5, l, &gens, sk, &mut rng).unwrap();
+        assert_eq!(pre_1.t(), 5);
+
+        let msg = "Hello".as_bytes();
+        let sig_1 = Signature::new_from_precomputed(msg, pre_1);
+        let sig_2 = Signature::new_from_precomputed(msg, pre_2);
+        assert!(sig_1.verify(msg, 5, l, &gens, &vk).unwrap());
+        assert!(sig_2.verify(msg, 5, l, &gens, &vk).unwrap());
+        assert_ne!(sig_1, sig_2);
+        assert!(!sig_1.verify("World".as_bytes(), 5, l, &gens, &vk).unwrap());
+        assert!(!sig_1.verify(msg, 6, l, &gens, &vk).unwrap());
+    }
+
     #[test]
     fn test_sig_deterministic() {
         let mut rng = rand::thread_rng();
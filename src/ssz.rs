@@ -0,0 +1,257 @@
+//! SSZ (SimpleSerialize) encodings and hash tree roots, as in Ethereum consensus clients, of
+//! signatures, verkeys and accountable aggregates. The SSZ types are
+//!
+//! ```text
+//! Verkey = ByteVector[VERKEY_GROUP_SIZE]
+//! Signature = Container { sigma_1: ByteVector[SIGNATURE_GROUP_COMPRESSED_SIZE],
+//!                         sigma_2: ByteVector[VERKEY_GROUP_COMPRESSED_SIZE] }
+//! AccountableAggregate = Container { signature: Signature,
+//!                                    signers: List[uint64, MAX_SSZ_SIGNERS] }
+//! ```
+//!
+//! with the points of signatures compressed as in `compression` and verkeys in their `to_bytes`
+//! encoding, so the SSZ encodings of signatures and verkeys are their `to_bytes` encodings. Hash
+//! tree roots use SHA-256. Decoding has the checks of `from_bytes` for points and rejects offsets
+//! other than the size of the fixed part.
+
+use std::convert::TryFrom;
+
+use sha2::{Digest, Sha256};
+
+use crate::compression::SIGNATURE_GROUP_COMPRESSED_SIZE;
+use crate::errors::ForwardSecureSignatureError;
+use crate::keys::Verkey;
+use crate::session::AccountableAggregate;
+use crate::signature::{Signature, SIGNATURE_SIZE};
+use crate::VERKEY_GROUP_SIZE;
+
+/// Limit of the signers list of an aggregate
+pub const MAX_SSZ_SIGNERS: usize = 1 << 20;
+
+const BYTES_PER_CHUNK: usize = 32;
+const OFFSET_SIZE: usize = 4;
+
+pub type Root = [u8; 32];
+
+fn invalid(msg: &str) -> ForwardSecureSignatureError {
+    ForwardSecureSignatureError::InvalidSsz {
+        msg: msg.to_string(),
+    }
+}
+
+fn hash(a: &[u8], b: &[u8]) -> Root {
+    let mut hasher = Sha256::new();
+    hasher.input(a);
+    hasher.input(b);
+    let mut root = [0; 32];
+    root.copy_from_slice(&hasher.result());
+    root
+}
+
+/// Root of `chunks` padded with zero chunks to the next power of 2 of `limit` chunks
+fn merkleize(mut chunks: Vec<Root>, limit: usize) -> Root {
+    let depth = limit.next_power_of_two().trailing_zeros();
+    let mut zero = [0; 32];
+    for _ in 0..depth {
+        if chunks.len() % 2 == 1 {
+            chunks.push(zero);
+        }
+        chunks = chunks
+            .chunks(2)
+            .map(|pair| hash(&pair[0], &pair[1]))
+            .collect();
+        zero = hash(&zero, &zero);
+    }
+    chunks.first().copied().unwrap_or(zero)
+}
+
+/// Bytes split into chunks, the last one padded with zeros
+fn pack(bytes: &[u8]) -> Vec<Root> {
+    bytes
+        .chunks(BYTES_PER_CHUNK)
+        .map(|c| {
+            let mut chunk = [0; 32];
+            chunk[..c.len()].copy_from_slice(c);
+            chunk
+        })
+        .collect()
+}
+
+fn byte_vector_root(bytes: &[u8]) -> Root {
+    let chunks = pack(bytes);
+    let limit = chunks.len();
+    merkleize(chunks, limit)
+}
+
+impl Verkey {
+    pub fn to_ssz_bytes(&self) -> Vec<u8> {
+        self.to_bytes()
+    }
+
+    pub fn from_ssz_bytes(bytes: &[u8]) -> Result<Self, ForwardSecureSignatureError> {
+        if bytes.len() != VERKEY_GROUP_SIZE {
+            return Err(invalid(&format!(
+                "{} bytes instead of {}",
+                bytes.len(),
+                VERKEY_GROUP_SIZE
+            )));
+        }
+        Self::from_bytes(bytes)
+    }
+
+    pub fn hash_tree_root(&self) -> Root {
+        byte_vector_root(&self.to_bytes())
+    }
+}
+
+impl Signature {
+    pub fn to_ssz_bytes(&self) -> Vec<u8> {
+        self.to_bytes()
+    }
+
+    pub fn from_ssz_bytes(bytes: &[u8]) -> Result<Self, ForwardSecureSignatureError> {
+        if bytes.len() != SIGNATURE_SIZE {
+            return Err(invalid(&format!(
+                "{} bytes instead of {}",
+                bytes.len(),
+                SIGNATURE_SIZE
+            )));
+        }
+        Self::from_bytes(bytes)
+    }
+
+    pub fn hash_tree_root(&self) -> Root {
+        let bytes = self.to_bytes();
+        let fields = vec![
+            byte_vector_root(&bytes[..SIGNATURE_GROUP_COMPRESSED_SIZE]),
+            byte_vector_root(&bytes[SIGNATURE_GROUP_COMPRESSED_SIZE..]),
+        ];
+        merkleize(fields, 2)
+    }
+}
+
+impl AccountableAggregate {
+    /// Fails if there are more than `MAX_SSZ_SIGNERS` signers
+    pub fn to_ssz_bytes(&self) -> Result<Vec<u8>, ForwardSecureSignatureError> {
+        if self.signers.len() > MAX_SSZ_SIGNERS {
+            return Err(invalid("too many signers"));
+        }
+        let mut bytes = self.signature.to_ssz_bytes();
+        bytes.extend_from_slice(&((SIGNATURE_SIZE + OFFSET_SIZE) as u32).to_le_bytes());
+        for i in &self.signers {
+            bytes.extend_from_slice(&(*i as u64).to_le_bytes());
+        }
+        Ok(bytes)
+    }
+
+    pub fn from_ssz_bytes(bytes: &[u8]) -> Result<Self, ForwardSecureSignatureError> {
+        let fixed_size = SIGNATURE_SIZE + OFFSET_SIZE;
+        if bytes.len() < fixed_size {
+            return Err(invalid("shorter than the fixed part"));
+        }
+        let mut offset = [0; OFFSET_SIZE];
+        offset.copy_from_slice(&bytes[SIGNATURE_SIZE..fixed_size]);
+        if u32::from_le_bytes(offset) as usize != fixed_size {
+            return Err(invalid(
+                "offset of signers is not the size of the fixed part",
+            ));
+        }
+        let signers = &bytes[fixed_size..];
+        if signers.len() % 8 != 0 || signers.len() / 8 > MAX_SSZ_SIGNERS {
+            return Err(invalid("invalid length of signers"));
+        }
+        let signers = signers
+            .chunks(8)
+            .map(|c| {
+                let mut i = [0; 8];
+                i.copy_from_slice(c);
+                usize::try_from(u64::from_le_bytes(i))
+                    .map_err(|_| invalid("signer index does not fit in usize"))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            signature: Signature::from_ssz_bytes(&bytes[..SIGNATURE_SIZE])?,
+            signers,
+        })
+    }
+
+    /// Fails if there are more than `MAX_SSZ_SIGNERS` signers
+    pub fn hash_tree_root(&self) -> Result<Root, ForwardSecureSignatureError> {
+        if self.signers.len() > MAX_SSZ_SIGNERS {
+            return Err(invalid("too many signers"));
+        }
+        let mut packed = Vec::with_capacity(8 * self.signers.len());
+        for i in &self.signers {
+            packed.extend_from_slice(&(*i as u64).to_le_bytes());
+        }
+        let limit = MAX_SSZ_SIGNERS * 8 / BYTES_PER_CHUNK;
+        let mut length = [0; 32];
+        length[..8].copy_from_slice(&(self.signers.len() as u64).to_le_bytes());
+        let signers_root = hash(&merkleize(pack(&packed), limit), &length);
+        Ok(merkleize(
+            vec![self.signature.hash_tree_root(), signers_root],
+            2,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::facade::Signer;
+    use crate::keys::InMemorySigKeyDatabase;
+    use crate::util::GeneratorSet;
+
+    #[test]
+    fn test_ssz() {
+        // Roots of zero chunks, as in the consensus specs
+        let zero_1 = hash(&[0; 32], &[0; 32]);
+        assert_eq!(merkleize(vec![], 4), hash(&zero_1, &zero_1));
+        assert_eq!(merkleize(vec![[1; 32]], 1), [1; 32]);
+        assert_eq!(
+            merkleize(vec![[1; 32], [2; 32], [3; 32]], 4),
+            hash(&hash(&[1; 32], &[2; 32]), &hash(&[3; 32], &[0; 32]))
+        );
+        assert_eq!(pack(&[1; 33]).len(), 2);
+
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let (mut signer, _) =
+            Signer::new(T, gens, InMemorySigKeyDatabase::new(), &mut rng).unwrap();
+        let sig = signer.sign("Hello".as_bytes(), &mut rng).unwrap();
+        let vk = signer.verkey();
+
+        assert_eq!(Signature::from_ssz_bytes(&sig.to_ssz_bytes()).unwrap(), sig);
+        let vk_bytes = vk.to_ssz_bytes();
+        assert_eq!(Verkey::from_ssz_bytes(&vk_bytes).unwrap().value, vk.value);
+        assert_eq!(vk.hash_tree_root(), byte_vector_root(&vk_bytes));
+        assert!(Verkey::from_ssz_bytes(&sig.to_ssz_bytes()).is_err());
+        let other = signer.sign("Hi".as_bytes(), &mut rng).unwrap();
+        assert_ne!(sig.hash_tree_root(), other.hash_tree_root());
+
+        let aggr = AccountableAggregate {
+            signature: sig.clone(),
+            signers: vec![1, 4, 9],
+        };
+        let bytes = aggr.to_ssz_bytes().unwrap();
+        assert_eq!(bytes.len(), SIGNATURE_SIZE + OFFSET_SIZE + 3 * 8);
+        let decoded = AccountableAggregate::from_ssz_bytes(&bytes).unwrap();
+        assert_eq!(decoded.signers, aggr.signers);
+        assert_eq!(decoded.signature, sig);
+        let root = aggr.hash_tree_root().unwrap();
+        assert_eq!(decoded.hash_tree_root().unwrap(), root);
+        let fewer = AccountableAggregate {
+            signature: sig.clone(),
+            signers: vec![1, 4],
+        };
+        assert_ne!(fewer.hash_tree_root().unwrap(), root);
+
+        // Truncated signers, wrong offset
+        assert!(AccountableAggregate::from_ssz_bytes(&bytes[..bytes.len() - 1]).is_err());
+        let mut bad_offset = bytes.clone();
+        bad_offset[SIGNATURE_SIZE] += 1;
+        assert!(AccountableAggregate::from_ssz_bytes(&bad_offset).is_err());
+        assert!(AccountableAggregate::from_ssz_bytes(&bytes[..SIGNATURE_SIZE]).is_err());
+    }
+}
@@ -0,0 +1,251 @@
+//! Verification of untrusted signatures in stages of increasing cost, so garbage is rejected
+//! before any pairing is computed. The stages run in the order of `VerificationStage`:
+//! decoding, range of the time period, identity checks, subgroup checks and finally the
+//! pairing. `VerificationCache` remembers verkeys that passed the subgroup check and the path
+//! factors of recent time periods, so repeated traffic for the same signer and period skips
+//! that work.
+
+use amcl_wrapper::group_elem::GroupElement;
+use std::collections::{HashMap, HashSet};
+
+use crate::errors::ForwardSecureSignatureError;
+use crate::keys::{Verkey, VerkeyFingerprint};
+use crate::signature::{Signature, SIGNATURE_SIZE};
+use crate::util::{calculate_path_factor_using_t_l, GeneratorSet, MAX_PATH_LEN};
+use crate::SignatureGroup;
+
+/// Default number of path factors kept by `VerificationCache`
+pub const DEFAULT_PATH_FACTOR_CACHE_SIZE: usize = 1024;
+
+/// Stages of verification, cheapest first
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum VerificationStage {
+    /// Length and encoding of the signature
+    Encoding,
+    /// The time period exists for the depth
+    TimePeriod,
+    /// Neither the signature nor the verkey has a point at infinity
+    Identity,
+    /// The signature and verkey are in the prime order subgroups
+    SubgroupCheck,
+    Pairing,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VerificationOutcome {
+    Valid,
+    /// Rejected at the given stage, no later stage was run
+    Rejected(VerificationStage),
+}
+
+impl VerificationOutcome {
+    pub fn is_valid(&self) -> bool {
+        *self == VerificationOutcome::Valid
+    }
+}
+
+/// Verifies encoded signatures in stages, caching subgroup checks of verkeys and path factors.
+/// At most `max_path_factors` path factors are kept so attacker chosen time periods can't grow
+/// the cache without bound.
+#[derive(Clone, Debug)]
+pub struct VerificationCache {
+    checked_verkeys: HashSet<VerkeyFingerprint>,
+    path_factors: HashMap<(u128, u8), SignatureGroup>,
+    max_path_factors: usize,
+}
+
+impl VerificationCache {
+    pub fn new(max_path_factors: usize) -> Self {
+        Self {
+            checked_verkeys: HashSet::new(),
+            path_factors: HashMap::new(),
+            max_path_factors,
+        }
+    }
+
+    /// Verify the encoded signature `sig_bytes` on `msg` for time period `t`. Invalid input is
+    /// reported as `Rejected` with the stage that rejected it. Fails only if `gens` has too few
+    /// generators for `l`.
+    pub fn verify(
+        &mut self,
+        sig_bytes: &[u8],
+        msg: &[u8],
+        t: u128,
+        l: u8,
+        gens: &GeneratorSet,
+        verkey: &Verkey,
+    ) -> Result<VerificationOutcome, ForwardSecureSignatureError> {
+        use self::VerificationOutcome::Rejected;
+
+        if gens.1.len() < (l as usize + 2) {
+            return Err(ForwardSecureSignatureError::NotEnoughGenerators { n: l as usize + 2 });
+        }
+
+        if sig_bytes.len() != SIGNATURE_SIZE {
+            return Ok(Rejected(VerificationStage::Encoding));
+        }
+        let sig = match Signature::from_bytes_unchecked(sig_bytes) {
+            Ok(sig) => sig,
+            Err(_) => return Ok(Rejected(VerificationStage::Encoding)),
+        };
+
+        if l == 0 || l as usize > MAX_PATH_LEN || t == 0 || t > (1u128 << l) - 1 {
+            return Ok(Rejected(VerificationStage::TimePeriod));
+        }
+
+        if sig.is_identity() || verkey.is_identity() {
+            return Ok(Rejected(VerificationStage::Identity));
+        }
+
+        if !sig.has_correct_oder() || !self.check_verkey(verkey) {
+            return Ok(Rejected(VerificationStage::SubgroupCheck));
+        }
+
+        let pf = self.path_factor(t, l, gens)?;
+        let valid = Signature::verify_naked_with_hashed_msg(
+            &sig.sigma_1,
+            &sig.sigma_2,
+            &verkey.value,
+            &Signature::hash_message(msg),
+            &pf,
+            l,
+            gens,
+        );
+        Ok(if valid {
+            VerificationOutcome::Valid
+        } else {
+            Rejected(VerificationStage::Pairing)
+        })
+    }
+
+    /// Whether `verkey` passed the subgroup check before
+    pub fn is_verkey_checked(&self, verkey: &Verkey) -> bool {
+        self.checked_verkeys.contains(&verkey.fingerprint())
+    }
+
+    pub fn num_path_factors(&self) -> usize {
+        self.path_factors.len()
+    }
+
+    fn check_verkey(&mut self, verkey: &Verkey) -> bool {
+        let fingerprint = verkey.fingerprint();
+        if self.checked_verkeys.contains(&fingerprint) {
+            return true;
+        }
+        if !verkey.value.has_correct_order() {
+            return false;
+        }
+        self.checked_verkeys.insert(fingerprint);
+        true
+    }
+
+    fn path_factor(
+        &mut self,
+        t: u128,
+        l: u8,
+        gens: &GeneratorSet,
+    ) -> Result<SignatureGroup, ForwardSecureSignatureError> {
+        if let Some(pf) = self.path_factors.get(&(t, l)) {
+            return Ok(pf.clone());
+        }
+        let pf = calculate_path_factor_using_t_l(t, l, gens)?;
+        if self.path_factors.len() < self.max_path_factors {
+            self.path_factors.insert((t, l), pf.clone());
+        }
+        Ok(pf)
+    }
+}
+
+impl Default for VerificationCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_PATH_FACTOR_CACHE_SIZE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{InMemorySigKeyDatabase, Keypair};
+    use crate::util::calculate_l;
+    use crate::VerkeyGroup;
+
+    #[test]
+    fn test_staged_verification_order() {
+        use self::VerificationOutcome::Rejected;
+        use self::VerificationStage::*;
+
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let l = calculate_l(T).unwrap();
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let mut db = InMemorySigKeyDatabase::new();
+        let (keypair, manager) = Keypair::new(T, &gens, &mut rng, &mut db).unwrap();
+        let vk = &keypair.ver_key;
+        let msg = "Hello".as_bytes();
+        let sig = Signature::new(
+            msg,
+            1,
+            l,
+            &gens,
+            manager.get_current_key(&db).unwrap(),
+            &mut rng,
+        )
+        .unwrap();
+        let bytes = sig.to_bytes();
+        let identity = Signature {
+            sigma_1: SignatureGroup::identity(),
+            sigma_2: VerkeyGroup::identity(),
+        }
+        .to_bytes();
+        let identity_vk = Verkey {
+            value: VerkeyGroup::identity(),
+        };
+
+        let mut cache = VerificationCache::new(2);
+        assert!(cache
+            .verify(&bytes, msg, 1, l, &gens, vk)
+            .unwrap()
+            .is_valid());
+        assert!(cache.is_verkey_checked(vk));
+        assert_eq!(cache.num_path_factors(), 1);
+
+        // Each input fails every stage from the reported one on, the earliest is reported
+        let outcome = |cache: &mut VerificationCache, bytes: &[u8], t, vk| {
+            cache.verify(bytes, msg, t, l, &gens, vk).unwrap()
+        };
+        assert_eq!(
+            outcome(&mut cache, &bytes[1..], 0, &identity_vk),
+            Rejected(Encoding)
+        );
+        assert_eq!(
+            outcome(&mut cache, &identity, 0, &identity_vk),
+            Rejected(TimePeriod)
+        );
+        assert_eq!(
+            outcome(&mut cache, &identity, T + 1, vk),
+            Rejected(TimePeriod)
+        );
+        assert_eq!(
+            outcome(&mut cache, &identity, 1, &identity_vk),
+            Rejected(Identity)
+        );
+        assert_eq!(
+            outcome(&mut cache, &bytes, 1, &identity_vk),
+            Rejected(Identity)
+        );
+        assert_eq!(outcome(&mut cache, &bytes, 2, vk), Rejected(Pairing));
+        assert_eq!(
+            cache
+                .verify(&bytes, "Hellp".as_bytes(), 1, l, &gens, vk)
+                .unwrap(),
+            Rejected(Pairing)
+        );
+
+        // The cache of path factors is bounded
+        assert_eq!(outcome(&mut cache, &bytes, 3, vk), Rejected(Pairing));
+        assert_eq!(cache.num_path_factors(), 2);
+
+        assert!(VerificationStage::Encoding < VerificationStage::Pairing);
+        assert!(cache.verify(&bytes, msg, 1, l + 1, &gens, vk).is_err());
+    }
+}
@@ -0,0 +1,4 @@
+//! Utilities for testing protocols built on this crate. Like `InMemorySigKeyDatabase`, nothing
+//! here should be used in production.
+
+pub mod simulation;
@@ -0,0 +1,331 @@
+//! Simulation of a committee of signers running for a number of time periods. In every time
+//! period all signers update their keys, sign random messages and send their shares to a
+//! `MultisigSession`. Faults are injected at random: a signer may not send its share, send a
+//! share on another message or lag one time period behind and send a share for the previous
+//! time period. After each session the invariants below are checked and violations recorded in
+//! the report.
+//! - shares of honest signers are accepted and faulty shares rejected
+//! - the session reaches quorum if and only if enough signers were honest, and then the aggregate
+//!   verifies and names exactly the honest signers
+//! - keys of past time periods are gone from the signers' databases
+
+use rand::{CryptoRng, Rng, RngCore};
+
+use crate::errors::ForwardSecureSignatureError;
+use crate::keys::{InMemorySigKeyDatabase, Keypair, SigKeyDb, SigManager};
+use crate::registry::{InMemoryVerkeyRegistry, VerkeyRegistry};
+use crate::session::MultisigSession;
+use crate::util::{calculate_l, GeneratorSet, GeneratorSetRef};
+
+/// Probability of each fault, per signer and message except for `stale_period` which is per
+/// signer and time period
+#[derive(Clone, Debug, Default)]
+pub struct FaultConfig {
+    pub missing_share: f64,
+    pub bad_share: f64,
+    pub stale_period: f64,
+}
+
+#[derive(Clone, Debug)]
+pub struct SimulationConfig {
+    pub num_signers: usize,
+    pub T: u128,
+    /// Number of time periods to run, starting from 1
+    pub periods: u128,
+    pub messages_per_period: usize,
+    pub quorum: usize,
+    pub faults: FaultConfig,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SimulationReport {
+    pub sessions: usize,
+    pub finalized: usize,
+    pub honest_shares: usize,
+    pub missing_shares: usize,
+    pub bad_shares: usize,
+    pub stale_shares: usize,
+    /// Description of every invariant that did not hold
+    pub violations: Vec<String>,
+}
+
+impl SimulationReport {
+    /// Panics listing the violations if any invariant did not hold
+    pub fn assert_invariants(&self) {
+        assert!(
+            self.violations.is_empty(),
+            "invariants violated: {:?}",
+            self.violations
+        );
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Behavior {
+    Honest,
+    Missing,
+    Bad,
+    Stale,
+}
+
+struct SimulatedSigner {
+    index: usize,
+    manager: SigManager,
+    db: InMemorySigKeyDatabase,
+}
+
+pub struct Simulation {
+    config: SimulationConfig,
+    l: u8,
+    gens: GeneratorSetRef,
+    registry: InMemoryVerkeyRegistry,
+    signers: Vec<SimulatedSigner>,
+}
+
+impl Simulation {
+    /// Create the signers, with indices 1 to `num_signers`, and register their verkeys
+    pub fn new<R: RngCore + CryptoRng>(
+        config: SimulationConfig,
+        rng: &mut R,
+    ) -> Result<Self, ForwardSecureSignatureError> {
+        let l = calculate_l(config.T)?;
+        if config.periods > config.T {
+            return Err(ForwardSecureSignatureError::KeyExpired {
+                t: config.periods,
+                T: config.T,
+            });
+        }
+        let gens = GeneratorSetRef::new(GeneratorSet::new(config.T, "simulation")?);
+        let mut registry = InMemoryVerkeyRegistry::new();
+        let mut signers = vec![];
+        for index in 1..=config.num_signers {
+            let mut db = InMemorySigKeyDatabase::new();
+            let (keypair, manager) = Keypair::new(config.T, &gens, rng, &mut db)?;
+            registry.add_member(index, keypair.ver_key)?;
+            registry.verify_pop(index, &keypair.pop, &gens.0)?;
+            signers.push(SimulatedSigner { index, manager, db });
+        }
+        if config.quorum == 0 || config.quorum > config.num_signers {
+            return Err(ForwardSecureSignatureError::InvalidQuorum {
+                quorum: config.quorum,
+                committee: config.num_signers,
+            });
+        }
+        Ok(Self {
+            config,
+            l,
+            gens,
+            registry,
+            signers,
+        })
+    }
+
+    pub fn registry(&self) -> &InMemoryVerkeyRegistry {
+        &self.registry
+    }
+
+    pub fn gens(&self) -> &GeneratorSetRef {
+        &self.gens
+    }
+
+    pub fn run<R: RngCore + CryptoRng>(
+        &mut self,
+        rng: &mut R,
+    ) -> Result<SimulationReport, ForwardSecureSignatureError> {
+        let mut report = SimulationReport::default();
+        let committee = (1..=self.config.num_signers).collect::<Vec<_>>();
+        for t in 1..=self.config.periods {
+            // Stale signers stay one time period behind, at most until the next time period
+            let mut stale = vec![];
+            for signer in self.signers.iter_mut() {
+                let lag = t > 1 && rng.gen_bool(self.config.faults.stale_period);
+                let target = if lag { t - 1 } else { t };
+                if signer.manager.t() < target {
+                    signer
+                        .manager
+                        .fast_forward_update(target, &self.gens, rng, &mut signer.db)?;
+                }
+                for past in 1..signer.manager.t() {
+                    if signer.db.has_key(past) {
+                        report.violations.push(format!(
+                            "signer {} still has key for time period {} at {}",
+                            signer.index,
+                            past,
+                            signer.manager.t()
+                        ));
+                    }
+                }
+                stale.push(signer.manager.t() < t);
+            }
+
+            for _ in 0..self.config.messages_per_period {
+                let mut msg = vec![0; 32];
+                rng.fill_bytes(&mut msg);
+                let mut session = MultisigSession::new(
+                    &msg,
+                    t,
+                    self.l,
+                    self.gens.clone(),
+                    &committee,
+                    &self.registry,
+                    self.config.quorum,
+                )?;
+                report.sessions += 1;
+                let mut honest = vec![];
+                for (signer, is_stale) in self.signers.iter_mut().zip(stale.iter()) {
+                    let behavior = if *is_stale {
+                        Behavior::Stale
+                    } else if rng.gen_bool(self.config.faults.missing_share) {
+                        Behavior::Missing
+                    } else if rng.gen_bool(self.config.faults.bad_share) {
+                        Behavior::Bad
+                    } else {
+                        Behavior::Honest
+                    };
+                    let sig = match behavior {
+                        Behavior::Missing => {
+                            report.missing_shares += 1;
+                            continue;
+                        }
+                        Behavior::Bad => {
+                            report.bad_shares += 1;
+                            signer
+                                .manager
+                                .sign(b"not the message", &self.gens, &signer.db, rng)?
+                        }
+                        Behavior::Stale => {
+                            report.stale_shares += 1;
+                            signer.manager.sign(&msg, &self.gens, &signer.db, rng)?
+                        }
+                        Behavior::Honest => {
+                            report.honest_shares += 1;
+                            honest.push(signer.index);
+                            signer.manager.sign(&msg, &self.gens, &signer.db, rng)?
+                        }
+                    };
+                    let accepted = session.add_share(signer.index, &sig).is_ok();
+                    if accepted != (behavior == Behavior::Honest) {
+                        report.violations.push(format!(
+                            "share of signer {} for time period {} accepted: {}",
+                            signer.index, t, accepted
+                        ));
+                    }
+                }
+                self.check_session(session, &msg, t, honest, &mut report)?;
+            }
+        }
+        Ok(report)
+    }
+
+    fn check_session(
+        &self,
+        session: MultisigSession,
+        msg: &[u8],
+        t: u128,
+        honest: Vec<usize>,
+        report: &mut SimulationReport,
+    ) -> Result<(), ForwardSecureSignatureError> {
+        let expect_quorum = honest.len() >= self.config.quorum;
+        if session.has_quorum() != expect_quorum {
+            report.violations.push(format!(
+                "session for time period {} with {} honest signers has quorum: {}",
+                t,
+                honest.len(),
+                session.has_quorum()
+            ));
+        }
+        match session.finalize() {
+            Ok(aggr) => {
+                report.finalized += 1;
+                if aggr.signers != honest {
+                    report.violations.push(format!(
+                        "aggregate for time period {} names signers {:?} instead of {:?}",
+                        t, aggr.signers, honest
+                    ));
+                }
+                if !aggr.verify(msg, t, self.l, &self.registry, &self.gens)? {
+                    report
+                        .violations
+                        .push(format!("aggregate for time period {} does not verify", t));
+                }
+                let avk = self.registry.aggregate_verkey(&aggr.signers)?;
+                if aggr
+                    .signature
+                    .verify(b"not the message", t, self.l, &self.gens, &avk)?
+                {
+                    report.violations.push(format!(
+                        "aggregate for time period {} verifies for another message",
+                        t
+                    ));
+                }
+            }
+            Err(_) if !expect_quorum => (),
+            Err(e) => report
+                .violations
+                .push(format!("finalizing for time period {} failed: {}", t, e)),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(faults: FaultConfig) -> SimulationConfig {
+        SimulationConfig {
+            num_signers: 4,
+            T: 7,
+            periods: 4,
+            messages_per_period: 2,
+            quorum: 3,
+            faults,
+        }
+    }
+
+    #[test]
+    fn test_simulation_without_faults() {
+        let mut rng = rand::thread_rng();
+        let mut sim = Simulation::new(config(FaultConfig::default()), &mut rng).unwrap();
+        let report = sim.run(&mut rng).unwrap();
+        report.assert_invariants();
+        assert_eq!(report.sessions, 8);
+        assert_eq!(report.finalized, 8);
+        assert_eq!(report.honest_shares, 32);
+    }
+
+    #[test]
+    fn test_simulation_with_faults() {
+        let mut rng = rand::thread_rng();
+        let faults = FaultConfig {
+            missing_share: 0.2,
+            bad_share: 0.2,
+            stale_period: 0.2,
+        };
+        let mut sim = Simulation::new(config(faults), &mut rng).unwrap();
+        let report = sim.run(&mut rng).unwrap();
+        report.assert_invariants();
+        assert_eq!(report.sessions, 8);
+        assert_eq!(
+            report.honest_shares + report.missing_shares + report.bad_shares + report.stale_shares,
+            32
+        );
+
+        let mut all_faulty = config(FaultConfig {
+            missing_share: 0.0,
+            bad_share: 1.0,
+            stale_period: 0.0,
+        });
+        all_faulty.periods = 1;
+        let report = Simulation::new(all_faulty, &mut rng)
+            .unwrap()
+            .run(&mut rng)
+            .unwrap();
+        report.assert_invariants();
+        assert_eq!(report.finalized, 0);
+
+        let mut too_long = config(FaultConfig::default());
+        too_long.periods = 8;
+        assert!(Simulation::new(too_long, &mut rng).is_err());
+    }
+}
@@ -0,0 +1,256 @@
+//! Hex and Base64 encodings of signatures, verkeys and generators, for config files and JSON
+//! APIs. Both encode the `to_bytes` encoding of the type. Hex is lowercase and Base64 uses the
+//! standard alphabet with padding (RFC 4648). The URL safe alphabet without padding is provided
+//! for JOSE. Decoding is strict so every value has exactly one string: hex must be lowercase,
+//! Base64 must be padded unless URL safe and have no bits set past the data, and the decoded
+//! bytes must have the length of the type. Signatures and verkeys are decoded with the checks of
+//! `from_bytes`.
+
+use crate::errors::ForwardSecureSignatureError;
+use crate::keys::Verkey;
+use crate::signature::{Signature, SIGNATURE_SIZE};
+use crate::util::GeneratorSet;
+use crate::VERKEY_GROUP_SIZE;
+
+const HEX_CHARSET: &[u8; 16] = b"0123456789abcdef";
+const BASE64_CHARSET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const BASE64URL_CHARSET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+fn invalid(msg: &str) -> ForwardSecureSignatureError {
+    ForwardSecureSignatureError::InvalidTextEncoding {
+        msg: msg.to_string(),
+    }
+}
+
+pub fn hex_encode(data: &[u8]) -> String {
+    let mut s = String::with_capacity(2 * data.len());
+    for b in data {
+        s.push(HEX_CHARSET[(b >> 4) as usize] as char);
+        s.push(HEX_CHARSET[(b & 0xf) as usize] as char);
+    }
+    s
+}
+
+pub fn hex_decode(s: &str) -> Result<Vec<u8>, ForwardSecureSignatureError> {
+    let digit = |c: u8| match c {
+        b'0'..=b'9' => Ok(c - b'0'),
+        b'a'..=b'f' => Ok(c - b'a' + 10),
+        _ => Err(invalid("invalid hex character")),
+    };
+    let s = s.as_bytes();
+    if s.len() % 2 != 0 {
+        return Err(invalid("odd number of hex characters"));
+    }
+    s.chunks(2)
+        .map(|pair| Ok(digit(pair[0])? << 4 | digit(pair[1])?))
+        .collect()
+}
+
+pub fn base64_encode(data: &[u8]) -> String {
+    encode_base64(data, BASE64_CHARSET, true)
+}
+
+pub fn base64_decode(s: &str) -> Result<Vec<u8>, ForwardSecureSignatureError> {
+    decode_base64(s, BASE64_CHARSET, true)
+}
+
+/// URL safe Base64 without padding, as used by JOSE (RFC 7515)
+pub fn base64url_encode(data: &[u8]) -> String {
+    encode_base64(data, BASE64URL_CHARSET, false)
+}
+
+pub fn base64url_decode(s: &str) -> Result<Vec<u8>, ForwardSecureSignatureError> {
+    decode_base64(s, BASE64URL_CHARSET, false)
+}
+
+fn encode_base64(data: &[u8], charset: &[u8; 64], pad: bool) -> String {
+    let mut s = String::with_capacity(4 * data.len().div_ceil(3));
+    for chunk in data.chunks(3) {
+        let mut block = [0; 3];
+        block[..chunk.len()].copy_from_slice(chunk);
+        let n = u32::from(block[0]) << 16 | u32::from(block[1]) << 8 | u32::from(block[2]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                s.push(charset[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else if pad {
+                s.push('=');
+            }
+        }
+    }
+    s
+}
+
+fn decode_base64(
+    s: &str,
+    charset: &[u8; 64],
+    pad: bool,
+) -> Result<Vec<u8>, ForwardSecureSignatureError> {
+    let mut s = s.as_bytes();
+    if pad {
+        if s.len() % 4 != 0 {
+            return Err(invalid("Base64 length not a multiple of 4"));
+        }
+        let padding = s.iter().rev().take_while(|c| **c == b'=').count();
+        if padding > 2 {
+            return Err(invalid("invalid Base64 padding"));
+        }
+        s = &s[..s.len() - padding];
+    }
+    if s.len() % 4 == 1 {
+        return Err(invalid("invalid Base64 length"));
+    }
+    let mut data = Vec::with_capacity(s.len() / 4 * 3 + 2);
+    for block in s.chunks(4) {
+        let missing = 4 - block.len();
+        let mut n = 0u32;
+        for c in block {
+            let v = charset
+                .iter()
+                .position(|b| b == c)
+                .ok_or_else(|| invalid("invalid Base64 character"))?;
+            n = n << 6 | v as u32;
+        }
+        n <<= 6 * missing;
+        if n & ((1 << (8 * missing)) - 1) != 0 {
+            return Err(invalid("non-zero bits after Base64 data"));
+        }
+        data.extend_from_slice(&n.to_be_bytes()[1..4 - missing]);
+    }
+    Ok(data)
+}
+
+fn check_len(data: Vec<u8>, len: usize) -> Result<Vec<u8>, ForwardSecureSignatureError> {
+    if data.len() != len {
+        return Err(invalid(&format!("{} bytes instead of {}", data.len(), len)));
+    }
+    Ok(data)
+}
+
+impl Signature {
+    pub fn to_hex(&self) -> String {
+        hex_encode(&self.to_bytes())
+    }
+
+    pub fn from_hex(s: &str) -> Result<Self, ForwardSecureSignatureError> {
+        Self::from_bytes(&check_len(hex_decode(s)?, SIGNATURE_SIZE)?)
+    }
+
+    pub fn to_base64(&self) -> String {
+        base64_encode(&self.to_bytes())
+    }
+
+    pub fn from_base64(s: &str) -> Result<Self, ForwardSecureSignatureError> {
+        Self::from_bytes(&check_len(base64_decode(s)?, SIGNATURE_SIZE)?)
+    }
+}
+
+impl Verkey {
+    pub fn to_hex(&self) -> String {
+        hex_encode(&self.to_bytes())
+    }
+
+    pub fn from_hex(s: &str) -> Result<Self, ForwardSecureSignatureError> {
+        Self::from_bytes(&check_len(hex_decode(s)?, VERKEY_GROUP_SIZE)?)
+    }
+
+    pub fn to_base64(&self) -> String {
+        base64_encode(&self.to_bytes())
+    }
+
+    pub fn from_base64(s: &str) -> Result<Self, ForwardSecureSignatureError> {
+        Self::from_bytes(&check_len(base64_decode(s)?, VERKEY_GROUP_SIZE)?)
+    }
+}
+
+/// The length is checked by `GeneratorSet::from_bytes`
+impl GeneratorSet {
+    pub fn to_hex(&self) -> String {
+        hex_encode(&self.to_bytes())
+    }
+
+    pub fn from_hex(s: &str) -> Result<Self, ForwardSecureSignatureError> {
+        Self::from_bytes(&hex_decode(s)?)
+    }
+
+    pub fn to_base64(&self) -> String {
+        base64_encode(&self.to_bytes())
+    }
+
+    pub fn from_base64(s: &str) -> Result<Self, ForwardSecureSignatureError> {
+        Self::from_bytes(&base64_decode(s)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::facade::Signer;
+    use crate::keys::InMemorySigKeyDatabase;
+
+    #[test]
+    fn test_hex_and_base64() {
+        // RFC 4648 test vectors
+        for (data, b64) in &[
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ] {
+            assert_eq!(base64_encode(data.as_bytes()), *b64);
+            assert_eq!(base64_decode(b64).unwrap(), data.as_bytes());
+            let b64url = b64.trim_end_matches('=');
+            assert_eq!(base64url_encode(data.as_bytes()), b64url);
+            assert_eq!(base64url_decode(b64url).unwrap(), data.as_bytes());
+        }
+        assert_eq!(base64url_encode(&[0xfb, 0xff]), "-_8");
+        assert_eq!(hex_encode(&[0, 0xab, 0x1f]), "00ab1f");
+        assert_eq!(hex_decode("00ab1f").unwrap(), vec![0, 0xab, 0x1f]);
+        for s in &["00AB1F", "0", "0g"] {
+            assert!(hex_decode(s).is_err());
+        }
+        for s in &["Zg=", "Zh==", "Zg==Zg==", "Z===", "Zm9v!A==", "Zm8=a"] {
+            assert!(base64_decode(s).is_err());
+        }
+        for s in &["Zg==", "Z", "Zh", "+_8"] {
+            assert!(base64url_decode(s).is_err());
+        }
+
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let (mut signer, _) =
+            Signer::new(T, gens.clone(), InMemorySigKeyDatabase::new(), &mut rng).unwrap();
+        let sig = signer.sign("Hello".as_bytes(), &mut rng).unwrap();
+        let vk = signer.verkey();
+
+        assert_eq!(Signature::from_hex(&sig.to_hex()).unwrap(), sig);
+        assert_eq!(Signature::from_base64(&sig.to_base64()).unwrap(), sig);
+        assert_eq!(Verkey::from_hex(&vk.to_hex()).unwrap().value, vk.value);
+        assert_eq!(
+            Verkey::from_base64(&vk.to_base64()).unwrap().value,
+            vk.value
+        );
+        assert_eq!(
+            GeneratorSet::from_hex(&gens.to_hex()).unwrap().to_bytes(),
+            gens.to_bytes()
+        );
+        assert_eq!(
+            GeneratorSet::from_base64(&gens.to_base64())
+                .unwrap()
+                .to_bytes(),
+            gens.to_bytes()
+        );
+
+        // Strict lengths
+        let hex = sig.to_hex();
+        assert!(Signature::from_hex(&hex[..hex.len() - 2]).is_err());
+        assert!(Signature::from_hex(&format!("{}00", hex)).is_err());
+        assert!(Verkey::from_hex(&hex).is_err());
+        assert!(Verkey::from_base64(&sig.to_base64()).is_err());
+    }
+}
@@ -3,11 +3,11 @@ use rand::{CryptoRng, RngCore};
 use crate::errors::ForwardSecureSignatureError;
 use crate::keys::{Keypair, MasterSecret, ProofOfPossession, Sigkey, Verkey};
 use crate::signature::Signature;
+use crate::util::random_field_element;
 use crate::{SignatureGroup, SignatureGroupVec, VerkeyGroup, VerkeyGroupVec};
 use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
 use amcl_wrapper::group_elem::GroupElementVector;
 use secret_sharing::polynomial::Polynomial;
-use secret_sharing::shamir_secret_sharing::get_shared_secret;
 use std::collections::{HashMap, HashSet};
 use std::mem;
 
@@ -61,13 +61,43 @@ pub fn trusted_party_SSS_keygen<R: RngCore + CryptoRng>(
     gen: &VerkeyGroup,
     gens: &[SignatureGroup],
 ) -> Result<(FieldElement, Vec<Signer>), ForwardSecureSignatureError> {
-    let (secret_x, x_shares) = get_shared_secret(threshold, total);
+    let (secret_x, x_shares) = get_shared_secret(threshold, total, rng)?;
     Ok((
         secret_x,
         generate_key_from_shares(total, x_shares, rng, gen, gens)?,
     ))
 }
 
+/// Shamir secret sharing of a random secret among signers with ids 1 to `total`. Unlike
+/// `secret_sharing::shamir_secret_sharing::get_shared_secret`, the randomness comes from `rng`.
+/// Fails if `threshold` is 0 or more than `total`.
+fn get_shared_secret<R: RngCore + CryptoRng>(
+    threshold: usize,
+    total: usize,
+    rng: &mut R,
+) -> Result<(FieldElement, HashMap<usize, FieldElement>), ForwardSecureSignatureError> {
+    if threshold == 0 || threshold > total {
+        return Err(ForwardSecureSignatureError::InvalidQuorum {
+            quorum: threshold,
+            committee: total,
+        });
+    }
+    let coeffs = (0..threshold)
+        .map(|_| random_field_element(rng))
+        .collect::<Result<Vec<_>, _>>()?;
+    let shares = (1..=total)
+        .map(|id| {
+            let x = FieldElement::from(id as u64);
+            let mut value = FieldElement::zero();
+            for c in coeffs.iter().rev() {
+                value = &(&value * &x) + c;
+            }
+            (id, value)
+        })
+        .collect();
+    Ok((coeffs[0].clone(), shares))
+}
+
 pub struct ThresholdScheme {}
 
 impl ThresholdScheme {
@@ -224,7 +254,20 @@ mod tests {
             trusted_party_SSS_keygen(threshold, total, &mut rng, &generators.0, &generators.1)
                 .unwrap();
 
-        check_threshold_key_gen(threshold, secret_x, &signers, &generators.0)
+        check_threshold_key_gen(threshold, secret_x, &signers, &generators.0);
+
+        for (threshold, total) in &[(0, 5), (6, 5)] {
+            assert!(matches!(
+                trusted_party_SSS_keygen(
+                    *threshold,
+                    *total,
+                    &mut rng,
+                    &generators.0,
+                    &generators.1
+                ),
+                Err(ForwardSecureSignatureError::InvalidQuorum { .. })
+            ));
+        }
     }
 
     #[test]
@@ -0,0 +1,327 @@
+//! Protocol for a committee to move its keys from one time period to a later one together, so
+//! that no aggregate is attempted while members are on different time periods.
+//!
+//! 1. Every member announces its intent to move from `from` to `to`, signed with its key for
+//!    `from`. Aggregates for `from` can still be made.
+//! 2. Once `quorum` members announced, members update their keys to `to` and confirm, signing with
+//!    their key for `to`, that the keys before `to` are deleted. No aggregates can be made in this
+//!    phase since some members already lost their keys for `from` and others have no key for `to`.
+//! 3. Once `quorum` members confirmed the epoch has advanced and aggregates are made for `to`.
+//!
+//! A confirmation shows that the member holds the key for `to`, it can't prove that older keys
+//! were deleted.
+
+use rand::{CryptoRng, RngCore};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::errors::ForwardSecureSignatureError;
+use crate::keys::{SigKeyDb, SigManager, Verkey};
+use crate::registry::VerkeyRegistry;
+use crate::signature::Signature;
+use crate::util::{GeneratorSet, GeneratorSetRef};
+
+const TRANSITION_DST: &[u8] = b"PIXEL-EPOCH-TRANSITION-V1";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum TransitionMessageKind {
+    /// Intent to move to the next time period, signed with the key for `from`
+    Announce = 1,
+    /// Keys before `to` were deleted, signed with the key for `to`
+    ConfirmDeletion = 2,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TransitionMessage {
+    pub kind: TransitionMessageKind,
+    pub member: usize,
+    pub from: u128,
+    pub to: u128,
+}
+
+impl TransitionMessage {
+    /// Time period of the key signing the message
+    pub fn signing_period(&self) -> u128 {
+        match self.kind {
+            TransitionMessageKind::Announce => self.from,
+            TransitionMessageKind::ConfirmDeletion => self.to,
+        }
+    }
+
+    /// Sign with the member's key. Fails if the manager is not at the message's signing period.
+    pub fn sign<R: RngCore + CryptoRng>(
+        self,
+        manager: &mut SigManager,
+        gens: &GeneratorSet,
+        db: &dyn SigKeyDb,
+        rng: &mut R,
+    ) -> Result<SignedTransitionMessage, ForwardSecureSignatureError> {
+        if manager.t() != self.signing_period() {
+            return Err(ForwardSecureSignatureError::InvalidTransitionMessage {
+                member: self.member,
+                msg: format!(
+                    "signing key is for time period {} instead of {}",
+                    manager.t(),
+                    self.signing_period()
+                ),
+            });
+        }
+        let signature = manager.sign(&self.to_bytes(), gens, db, rng)?;
+        Ok(SignedTransitionMessage {
+            message: self,
+            signature,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = TRANSITION_DST.to_vec();
+        bytes.push(self.kind as u8);
+        bytes.extend_from_slice(&(self.member as u64).to_be_bytes());
+        bytes.extend_from_slice(&self.from.to_be_bytes());
+        bytes.extend_from_slice(&self.to.to_be_bytes());
+        bytes
+    }
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SignedTransitionMessage {
+    pub message: TransitionMessage,
+    pub signature: Signature,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransitionPhase {
+    /// Collecting announcements, aggregates are made for `from`
+    Announcing,
+    /// Collecting confirmations, no aggregates can be made
+    Confirming,
+    /// Aggregates are made for `to`
+    Advanced,
+}
+
+/// State of a transition of a committee from time period `from` to `to`
+pub struct EpochTransition {
+    from: u128,
+    to: u128,
+    l: u8,
+    gens: GeneratorSetRef,
+    quorum: usize,
+    committee: BTreeMap<usize, Verkey>,
+    announced: BTreeSet<usize>,
+    confirmed: BTreeSet<usize>,
+}
+
+impl EpochTransition {
+    /// Start a transition for `committee`. Verkeys are taken from `registry` so every member must
+    /// have a verified proof of possession.
+    pub fn new<G: Into<GeneratorSetRef>, V: VerkeyRegistry + ?Sized>(
+        from: u128,
+        to: u128,
+        l: u8,
+        gens: G,
+        committee: &[usize],
+        registry: &V,
+        quorum: usize,
+    ) -> Result<Self, ForwardSecureSignatureError> {
+        if to <= from {
+            return Err(ForwardSecureSignatureError::SigkeyUpdateBackward {
+                old_t: to,
+                current_t: from,
+            });
+        }
+        let mut members = BTreeMap::new();
+        for index in committee {
+            members.insert(*index, registry.aggregate_verkey(&[*index])?);
+        }
+        if quorum == 0 || quorum > members.len() {
+            return Err(ForwardSecureSignatureError::InvalidQuorum {
+                quorum,
+                committee: members.len(),
+            });
+        }
+        Ok(Self {
+            from,
+            to,
+            l,
+            gens: gens.into(),
+            quorum,
+            committee: members,
+            announced: BTreeSet::new(),
+            confirmed: BTreeSet::new(),
+        })
+    }
+
+    pub fn phase(&self) -> TransitionPhase {
+        if self.confirmed.len() >= self.quorum {
+            TransitionPhase::Advanced
+        } else if self.announced.len() >= self.quorum {
+            TransitionPhase::Confirming
+        } else {
+            TransitionPhase::Announcing
+        }
+    }
+
+    /// Time period aggregates must be made for in the current phase, `None` while confirming
+    pub fn signing_period(&self) -> Option<u128> {
+        match self.phase() {
+            TransitionPhase::Announcing => Some(self.from),
+            TransitionPhase::Confirming => None,
+            TransitionPhase::Advanced => Some(self.to),
+        }
+    }
+
+    /// Members who announced but have not confirmed yet
+    pub fn pending_confirmations(&self) -> Vec<usize> {
+        self.announced
+            .difference(&self.confirmed)
+            .cloned()
+            .collect()
+    }
+
+    /// Process a message of a member and return the resulting phase. Fails on messages for
+    /// another transition, from non members, with invalid signatures, duplicates, and
+    /// confirmations of members who did not announce.
+    pub fn handle(
+        &mut self,
+        msg: &SignedTransitionMessage,
+    ) -> Result<TransitionPhase, ForwardSecureSignatureError> {
+        let m = &msg.message;
+        let invalid = |reason: &str| ForwardSecureSignatureError::InvalidTransitionMessage {
+            member: m.member,
+            msg: String::from(reason),
+        };
+        if m.from != self.from || m.to != self.to {
+            return Err(invalid("message for another transition"));
+        }
+        let verkey = self
+            .committee
+            .get(&m.member)
+            .ok_or(ForwardSecureSignatureError::UnknownCommitteeMember { index: m.member })?;
+        let seen = match m.kind {
+            TransitionMessageKind::Announce => &self.announced,
+            TransitionMessageKind::ConfirmDeletion => {
+                if !self.announced.contains(&m.member) {
+                    return Err(invalid("confirmation before announcement"));
+                }
+                &self.confirmed
+            }
+        };
+        if seen.contains(&m.member) {
+            return Err(invalid("duplicate message"));
+        }
+        if !msg.signature.verify(
+            &m.to_bytes(),
+            m.signing_period(),
+            self.l,
+            &self.gens,
+            verkey,
+        )? {
+            return Err(invalid("signature does not verify"));
+        }
+        match m.kind {
+            TransitionMessageKind::Announce => self.announced.insert(m.member),
+            TransitionMessageKind::ConfirmDeletion => self.confirmed.insert(m.member),
+        };
+        Ok(self.phase())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{InMemorySigKeyDatabase, Keypair};
+    use crate::registry::InMemoryVerkeyRegistry;
+    use crate::util::calculate_l;
+
+    #[test]
+    fn test_epoch_transition() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let l = calculate_l(T).unwrap();
+        let gens = GeneratorSetRef::new(GeneratorSet::new(T, "test_pixel").unwrap());
+        let mut registry = InMemoryVerkeyRegistry::new();
+        let mut members = vec![];
+        for i in 1..=3 {
+            let mut db = InMemorySigKeyDatabase::new();
+            let (keypair, manager) = Keypair::new(T, &gens, &mut rng, &mut db).unwrap();
+            registry.add_member(i, keypair.ver_key).unwrap();
+            registry.verify_pop(i, &keypair.pop, &gens.0).unwrap();
+            members.push((manager, db));
+        }
+        let committee = [1, 2, 3];
+        assert!(EpochTransition::new(2, 2, l, gens.clone(), &committee, &registry, 2).is_err());
+        assert!(EpochTransition::new(1, 2, l, gens.clone(), &committee, &registry, 4).is_err());
+        let mut transition =
+            EpochTransition::new(1, 2, l, gens.clone(), &committee, &registry, 2).unwrap();
+        assert_eq!(transition.signing_period(), Some(1));
+
+        let msg = |kind, member| TransitionMessage {
+            kind,
+            member,
+            from: 1,
+            to: 2,
+        };
+        let mut announce = vec![];
+        for (i, (manager, db)) in members.iter_mut().enumerate() {
+            let m = msg(TransitionMessageKind::Announce, i + 1);
+            announce.push(m.sign(manager, &gens, db, &mut rng).unwrap());
+        }
+        // Confirming needs the key for the new time period
+        let (manager, db) = &mut members[0];
+        assert!(msg(TransitionMessageKind::ConfirmDeletion, 1)
+            .sign(manager, &gens, db, &mut rng)
+            .is_err());
+
+        assert_eq!(
+            transition.handle(&announce[0]).unwrap(),
+            TransitionPhase::Announcing
+        );
+        assert!(transition.handle(&announce[0]).is_err());
+        // Signed by another member
+        let mut forged = announce[1].clone();
+        forged.message.member = 3;
+        assert!(transition.handle(&forged).is_err());
+        assert_eq!(
+            transition.handle(&announce[1]).unwrap(),
+            TransitionPhase::Confirming
+        );
+        assert_eq!(transition.signing_period(), None);
+        assert_eq!(transition.pending_confirmations(), vec![1, 2]);
+
+        let mut confirm = vec![];
+        for (i, (manager, db)) in members.iter_mut().enumerate() {
+            manager.simple_update(&gens, &mut rng, db).unwrap();
+            let m = msg(TransitionMessageKind::ConfirmDeletion, i + 1);
+            confirm.push(m.sign(manager, &gens, db, &mut rng).unwrap());
+        }
+        // Member 3 has not announced yet
+        assert!(transition.handle(&confirm[2]).is_err());
+        // An announcement is not a confirmation
+        let mut replayed = announce[0].clone();
+        replayed.message.kind = TransitionMessageKind::ConfirmDeletion;
+        assert!(transition.handle(&replayed).is_err());
+        assert_eq!(
+            transition.handle(&confirm[0]).unwrap(),
+            TransitionPhase::Confirming
+        );
+        assert_eq!(
+            transition.handle(&confirm[1]).unwrap(),
+            TransitionPhase::Advanced
+        );
+        assert_eq!(transition.signing_period(), Some(2));
+
+        // Stragglers can still catch up
+        assert_eq!(
+            transition.handle(&announce[2]).unwrap(),
+            TransitionPhase::Advanced
+        );
+        transition.handle(&confirm[2]).unwrap();
+        assert!(transition.pending_confirmations().is_empty());
+
+        let mut other =
+            EpochTransition::new(1, 3, l, gens.clone(), &committee, &registry, 2).unwrap();
+        assert!(other.handle(&announce[0]).is_err());
+    }
+}
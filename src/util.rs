@@ -1,9 +1,19 @@
 use crate::amcl_wrapper::group_elem::GroupElement;
 use crate::errors::ForwardSecureSignatureError;
-use crate::{SignatureGroup, VerkeyGroup};
+use crate::mapped_generators::MappedGeneratorSet;
+use crate::params::{bytes_digest, GeneratorSetParams, CIPHERSUITE};
+use crate::{SignatureGroup, VerkeyGroup, SIGNATURE_GROUP_SIZE, VERKEY_GROUP_SIZE};
 use amcl_wrapper::field_elem::FieldElement;
+use memmap2::Mmap;
+use rand::{CryptoRng, RngCore};
+use std::fs;
+use std::ops::Deref;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 
 /// second element is a vector of length l+2 and is of form [h, h_0, h_1, h_2, ..., h_l]
+#[derive(Clone, Debug)]
 pub struct GeneratorSet(pub VerkeyGroup, pub Vec<SignatureGroup>);
 
 impl GeneratorSet {
@@ -28,6 +38,272 @@ impl GeneratorSet {
         }
         Ok(params)
     }
+
+    /// Serialize as the generator in the verkey group followed by all the generators in the
+    /// signature group
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(VERKEY_GROUP_SIZE + self.1.len() * SIGNATURE_GROUP_SIZE);
+        bytes.append(&mut self.0.to_bytes());
+        for h in &self.1 {
+            bytes.append(&mut h.to_bytes());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ForwardSecureSignatureError> {
+        let n = Self::num_generators_in_bytes(bytes.len())?;
+        let g = VerkeyGroup::from_bytes(&bytes[..VERKEY_GROUP_SIZE])?;
+        let mut h = Vec::with_capacity(n);
+        for b in bytes[VERKEY_GROUP_SIZE..].chunks(SIGNATURE_GROUP_SIZE) {
+            h.push(SignatureGroup::from_bytes(b)?);
+        }
+        Ok(GeneratorSet(g, h))
+    }
+
+    /// Returns the number of generators in the signature group contained in a serialized
+    /// generator set of `len` bytes
+    pub fn num_generators_in_bytes(len: usize) -> Result<usize, ForwardSecureSignatureError> {
+        if len < VERKEY_GROUP_SIZE || (len - VERKEY_GROUP_SIZE) % SIGNATURE_GROUP_SIZE != 0 {
+            return Err(ForwardSecureSignatureError::InvalidGeneratorSetBytes { len });
+        }
+        Ok((len - VERKEY_GROUP_SIZE) / SIGNATURE_GROUP_SIZE)
+    }
+}
+
+/// Source of the generators used in verification. Implemented by `GeneratorSet`, which holds
+/// them in memory, and by `MappedGeneratorSet`, which deserializes them from a mapped file when
+/// first used.
+pub trait GeneratorSource {
+    /// Generator in the verkey group
+    fn g(&self) -> &VerkeyGroup;
+
+    /// `i`th generator in the signature group
+    fn h(&self, i: usize) -> Result<&SignatureGroup, ForwardSecureSignatureError>;
+
+    /// Number of generators in the signature group
+    fn num_h(&self) -> usize;
+}
+
+impl GeneratorSource for GeneratorSet {
+    fn g(&self) -> &VerkeyGroup {
+        &self.0
+    }
+
+    fn h(&self, i: usize) -> Result<&SignatureGroup, ForwardSecureSignatureError> {
+        self.1
+            .get(i)
+            .ok_or(ForwardSecureSignatureError::NotEnoughGenerators { n: i + 1 })
+    }
+
+    fn num_h(&self) -> usize {
+        self.1.len()
+    }
+}
+
+impl<G: GeneratorSource + ?Sized> GeneratorSource for &G {
+    fn g(&self) -> &VerkeyGroup {
+        (**self).g()
+    }
+
+    fn h(&self, i: usize) -> Result<&SignatureGroup, ForwardSecureSignatureError> {
+        (**self).h(i)
+    }
+
+    fn num_h(&self) -> usize {
+        (**self).num_h()
+    }
+}
+
+/// Cheaply clonable handle to a `GeneratorSet`. Clones share the same generators so the handle
+/// can be passed across threads and contexts without copying the group elements.
+#[derive(Clone, Debug)]
+pub struct GeneratorSetRef(Arc<GeneratorSet>);
+
+impl GeneratorSetRef {
+    pub fn new(gens: GeneratorSet) -> Self {
+        Self(Arc::new(gens))
+    }
+
+    /// Returns true if both handles refer to the same generators
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl From<GeneratorSet> for GeneratorSetRef {
+    fn from(gens: GeneratorSet) -> Self {
+        Self::new(gens)
+    }
+}
+
+impl Deref for GeneratorSetRef {
+    type Target = GeneratorSet;
+
+    fn deref(&self) -> &GeneratorSet {
+        &self.0
+    }
+}
+
+impl GeneratorSource for GeneratorSetRef {
+    fn g(&self) -> &VerkeyGroup {
+        self.0.g()
+    }
+
+    fn h(&self, i: usize) -> Result<&SignatureGroup, ForwardSecureSignatureError> {
+        self.0.h(i)
+    }
+
+    fn num_h(&self) -> usize {
+        self.0.num_h()
+    }
+}
+
+/// Builder for `GeneratorSet`. Generators are created from a label and the tree depth (or
+/// equivalently the maximum time period) so verifier-only processes can create the same
+/// generators as signers. Optionally the generators are cached in a file.
+pub struct GeneratorSetBuilder {
+    label: String,
+    T: Option<u128>,
+    cache_path: Option<PathBuf>,
+}
+
+impl GeneratorSetBuilder {
+    pub fn new(label: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            T: None,
+            cache_path: None,
+        }
+    }
+
+    /// Depth `l` of the tree, supports `2^l - 1` time periods
+    pub fn depth(mut self, l: u8) -> Self {
+        self.T = Some(if l >= 128 {
+            u128::MAX
+        } else {
+            (1u128 << l) - 1
+        });
+        self
+    }
+
+    /// Maximum time period `T` to be supported. `T+1` must be a power of 2.
+    pub fn max_time_period(mut self, T: u128) -> Self {
+        self.T = Some(T);
+        self
+    }
+
+    /// File to cache the serialized generators in. If the file exists, generators are loaded
+    /// from it otherwise they are created and written to it. Loading fails if the file was
+    /// written for another label, depth or ciphersuite.
+    pub fn cache_path<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.cache_path = Some(path.into());
+        self
+    }
+
+    /// Returns all generators deserialized in memory
+    pub fn build(self) -> Result<GeneratorSet, ForwardSecureSignatureError> {
+        self.load_or_create()
+    }
+
+    fn load_or_create(&self) -> Result<GeneratorSet, ForwardSecureSignatureError> {
+        let l = self.get_l()?;
+        match &self.cache_path {
+            Some(path) if path.exists() => {
+                let bytes = fs::read(path)?;
+                let offset = self.check_cache(&bytes, l)?;
+                let gens = GeneratorSet::from_bytes(&bytes[offset..])?;
+                Self::check_num_generators(&gens.1, l)?;
+                Ok(gens)
+            }
+            Some(path) => {
+                let gens = GeneratorSet::new(self.T.unwrap(), &self.label)?;
+                let params = GeneratorSetParams::from_generators(&self.label, l, &gens);
+                let header = params.to_bytes();
+                let mut bytes = (header.len() as u32).to_be_bytes().to_vec();
+                bytes.extend_from_slice(&header);
+                bytes.extend_from_slice(&gens.to_bytes());
+                fs::write(path, bytes)?;
+                Ok(gens)
+            }
+            None => GeneratorSet::new(self.T.unwrap(), &self.label),
+        }
+    }
+
+    /// Returns generators memory mapped from the cache file and deserialized lazily. Requires
+    /// the cache path to be set.
+    pub fn build_lazy(self) -> Result<MappedGeneratorSet, ForwardSecureSignatureError> {
+        let l = self.get_l()?;
+        let path = match &self.cache_path {
+            Some(path) => path.clone(),
+            None => {
+                return Err(ForwardSecureSignatureError::IoError {
+                    msg: String::from("cache path needed for lazy generator set"),
+                })
+            }
+        };
+        if !path.exists() {
+            self.load_or_create()?;
+        }
+        let file = fs::File::open(&path)?;
+        // As in `MappedGeneratorSet::open`, cache files are not modified once written
+        let mmap = unsafe { Mmap::map(&file)? };
+        let offset = self.check_cache(&mmap, l)?;
+        let gens = MappedGeneratorSet::from_mmap(mmap, offset)?;
+        if gens.len() != l as usize + 2 {
+            return Err(ForwardSecureSignatureError::InvalidGeneratorSetBytes { len: gens.len() });
+        }
+        Ok(gens)
+    }
+
+    /// Cache files start with the 4 byte big endian length of the `GeneratorSetParams` of the
+    /// generators, then the params and then the generators as serialized by
+    /// `GeneratorSet::to_bytes`. Checks the params against the builder and the digest against
+    /// the generators, returning where the generators start.
+    fn check_cache(&self, bytes: &[u8], l: u8) -> Result<usize, ForwardSecureSignatureError> {
+        let mismatch = |msg: String| ForwardSecureSignatureError::InvalidGeneratorCache { msg };
+        if bytes.len() < 4 {
+            return Err(mismatch(String::from("missing header")));
+        }
+        let len = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize;
+        if bytes.len() - 4 < len {
+            return Err(mismatch(String::from("truncated header")));
+        }
+        let params = GeneratorSetParams::from_bytes(&bytes[4..4 + len])?;
+        if params.ciphersuite != CIPHERSUITE {
+            return Err(mismatch(format!(
+                "ciphersuite {} instead of {}",
+                params.ciphersuite, CIPHERSUITE
+            )));
+        }
+        if params.label != self.label {
+            return Err(mismatch(format!(
+                "label {} instead of {}",
+                params.label, self.label
+            )));
+        }
+        if params.depth != l {
+            return Err(mismatch(format!("depth {} instead of {}", params.depth, l)));
+        }
+        let offset = 4 + len;
+        if bytes_digest(&bytes[offset..]) != params.digest {
+            return Err(mismatch(String::from("generators do not match the digest")));
+        }
+        Ok(offset)
+    }
+
+    fn get_l(&self) -> Result<u8, ForwardSecureSignatureError> {
+        calculate_l(self.T.unwrap_or(0))
+    }
+
+    fn check_num_generators(
+        gens: &[SignatureGroup],
+        l: u8,
+    ) -> Result<(), ForwardSecureSignatureError> {
+        if gens.len() != l as usize + 2 {
+            return Err(ForwardSecureSignatureError::InvalidGeneratorSetBytes { len: gens.len() });
+        }
+        Ok(())
+    }
 }
 
 // TODO: Abstract left and right in an enum with values 1 and 2 rather than using hardcoded 1 and 2.
@@ -53,6 +329,62 @@ pub fn calculate_l(T: u128) -> Result<u8, ForwardSecureSignatureError> {
     Ok(l)
 }
 
+/// Parameters and cost estimates for supporting a given number of time periods. Returned by
+/// `periods_for`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PeriodPlan {
+    /// Number of time periods needed to cover the key lifetime
+    pub needed_periods: u128,
+    /// Smallest valid maximum time period, `T >= needed_periods`
+    pub T: u128,
+    /// Tree depth, `2^l - 1 = T`
+    pub l: u8,
+    /// Size in bytes of the serialized `GeneratorSet`
+    pub generator_set_size: usize,
+    /// Maximum number of signing keys held at any time
+    pub max_sigkeys: usize,
+    /// Maximum size in bytes of all signing keys held at any time
+    pub max_sigkeys_size: usize,
+}
+
+/// Returns the smallest valid `T` (and `l`) for a key used for `duration` with a new time period
+/// every `interval`, along with size estimates.
+pub fn periods_for(
+    duration: Duration,
+    interval: Duration,
+) -> Result<PeriodPlan, ForwardSecureSignatureError> {
+    let interval = interval.as_nanos();
+    if interval == 0 {
+        return Err(ForwardSecureSignatureError::ZeroPeriodInterval);
+    }
+    let duration = duration.as_nanos();
+    let needed_periods = std::cmp::max(1, duration.div_ceil(interval));
+
+    // Smallest l with 2^l - 1 >= needed_periods, T must be at least 3
+    let mut l = 2u8;
+    while l < 128 && ((1u128 << l) - 1) < needed_periods {
+        l += 1;
+    }
+    if l == 128 {
+        return Err(ForwardSecureSignatureError::InvalidMaxTimePeriod { T: needed_periods });
+    }
+    let T = (1u128 << l) - 1;
+    let l_usize = l as usize;
+
+    // Most keys are held when the current node is the deepest leftmost leaf, i.e. t = l. Then
+    // the successors are at depths 1 to l-1 and the key at depth k has l+1-k elements in the
+    // signature group, the current key has 2.
+    let max_sig_group_elems = l_usize * (l_usize + 1) / 2 + 1;
+    Ok(PeriodPlan {
+        needed_periods,
+        T,
+        l,
+        generator_set_size: VERKEY_GROUP_SIZE + (l_usize + 2) * SIGNATURE_GROUP_SIZE,
+        max_sigkeys: l_usize,
+        max_sigkeys_size: l_usize * VERKEY_GROUP_SIZE + max_sig_group_elems * SIGNATURE_GROUP_SIZE,
+    })
+}
+
 /// Convert path of node to node number (prefix). Path is from root to the node and
 /// `l = depth + 1` where `depth` is the depth of the tree.
 /*
@@ -109,6 +441,43 @@ pub fn from_node_num_to_path(t: u128, l: u8) -> Result<Vec<u8>, ForwardSecureSig
     }
 }
 
+/// Maximum length of a node path. Since `T < u128::max_value()`, `l` is at most 127 and a path
+/// has at most `l - 1` elements.
+pub const MAX_PATH_LEN: usize = 127;
+
+/// Same as `from_node_num_to_path` but writes the path in `path` rather than allocating.
+/// Returns the length of the path.
+pub fn node_num_to_path_into(
+    t: u128,
+    l: u8,
+    path: &mut [u8],
+) -> Result<usize, ForwardSecureSignatureError> {
+    if l == 0 || l as usize > MAX_PATH_LEN || t == 0 || t > (1u128 << l) - 1 {
+        return Err(ForwardSecureSignatureError::InvalidNodeNum { t, l });
+    }
+    let mut t = t;
+    let mut l = l;
+    let mut len = 0;
+    while t != 1 {
+        if len == path.len() {
+            return Err(ForwardSecureSignatureError::InvalidNodeNum { t, l });
+        }
+        let two_l_1 = 1u128 << (l - 1); // 2^{l-1}
+        if t <= two_l_1 {
+            // Left subtree
+            path[len] = 1;
+            t -= 1;
+        } else {
+            // Right subtree
+            path[len] = 2;
+            t -= two_l_1;
+        }
+        len += 1;
+        l -= 1;
+    }
+    Ok(len)
+}
+
 /// Returns path of all successors of the node given by time t. Successors corresponds to the set
 /// containing all the right-hand siblings of nodes on the path from t to the root.
 /// The siblings are ordered from lowest number to highest.
@@ -136,10 +505,10 @@ pub fn node_successor_paths(t: u128, l: u8) -> Result<Vec<Vec<u8>>, ForwardSecur
 }
 
 /// Calculate h_0*h_1^path[0]*h_2^path[2]*......
-pub fn calculate_path_factor_using_t_l(
+pub fn calculate_path_factor_using_t_l<G: GeneratorSource + ?Sized>(
     t: u128,
     l: u8,
-    gens: &GeneratorSet,
+    gens: &G,
 ) -> Result<SignatureGroup, ForwardSecureSignatureError> {
     // TODO: Find better name for this function
     let path = from_node_num_to_path(t, l)?;
@@ -147,35 +516,232 @@ pub fn calculate_path_factor_using_t_l(
 }
 
 /// Calculate h_0*h_1^path[0]*h_2^path[2]*......
-pub fn calculate_path_factor(
+pub fn calculate_path_factor<G: GeneratorSource + ?Sized>(
     path: Vec<u8>,
-    gens: &GeneratorSet,
+    gens: &G,
 ) -> Result<SignatureGroup, ForwardSecureSignatureError> {
     // TODO: Find better name for this function
+    calculate_path_factor_from_slice(&path, gens)
+}
 
-    if gens.1.len() < (path.len() + 2) {
+/// Same as `calculate_path_factor` but takes the path as a slice
+pub fn calculate_path_factor_from_slice<G: GeneratorSource + ?Sized>(
+    path: &[u8],
+    gens: &G,
+) -> Result<SignatureGroup, ForwardSecureSignatureError> {
+    if gens.num_h() < (path.len() + 2) {
         return Err(ForwardSecureSignatureError::NotEnoughGenerators { n: path.len() + 2 });
     }
-    let mut sigma_1_1 = gens.1[1].clone(); // h_0
+    let mut sigma_1_1 = gens.h(1)?.clone(); // h_0
 
     // h_0*h_1^path[0]*h_2^path[2]*......
     for (i, p) in path.iter().enumerate() {
         if *p == 1 {
-            sigma_1_1 += &gens.1[2 + i]
+            sigma_1_1 += gens.h(2 + i)?
         } else {
-            sigma_1_1 += &gens.1[2 + i].double()
+            sigma_1_1 += &gens.h(2 + i)?.double()
         }
     }
 
     Ok(sigma_1_1)
 }
 
+/// Bytes of entropy amcl takes from the rng for a random field element
+const FIELD_ELEMENT_ENTROPY_SIZE: usize = 256;
+
+/// Replays bytes drawn in advance. Only ever asked for exactly the bytes it holds.
+struct ReplayRng<'a>(&'a [u8]);
+
+impl RngCore for ReplayRng<'_> {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        let (head, rest) = self.0.split_at(dest.len());
+        dest.copy_from_slice(head);
+        self.0 = rest;
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl CryptoRng for ReplayRng<'_> {}
+
+/// Same as `FieldElement::random_using_rng` but draws the entropy with `try_fill_bytes`, so a
+/// failing `rng` is reported as `RngFailure` rather than a panic. Gives the same element as
+/// `FieldElement::random_using_rng` for the same rng output.
+pub fn random_field_element<R: RngCore + CryptoRng>(
+    rng: &mut R,
+) -> Result<FieldElement, ForwardSecureSignatureError> {
+    let mut entropy = [0; FIELD_ELEMENT_ENTROPY_SIZE];
+    rng.try_fill_bytes(&mut entropy)
+        .map_err(|e| ForwardSecureSignatureError::RngFailure { msg: e.to_string() })?;
+    Ok(FieldElement::random_using_rng(&mut ReplayRng(&entropy)))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::collections::HashSet;
     use std::iter::FromIterator;
 
+    #[test]
+    fn test_random_field_element() {
+        use crate::drbg::SeededDrbg;
+        use crate::entropy::{EntropyRng, ExternalEntropy};
+        use crate::keys::{InMemorySigKeyDatabase, Keypair};
+        use rand::SeedableRng;
+
+        let mut rng = SeededDrbg::from_seed([1; 32]);
+        let expected = FieldElement::random_using_rng(&mut rng);
+        let mut rng = SeededDrbg::from_seed([1; 32]);
+        assert_eq!(random_field_element(&mut rng).unwrap(), expected);
+
+        let mut failing = EntropyRng(ExternalEntropy(|_: &mut [u8]| {
+            Err(rand::Error::new("no entropy"))
+        }));
+        assert!(matches!(
+            random_field_element(&mut failing),
+            Err(ForwardSecureSignatureError::RngFailure { .. })
+        ));
+        let gens = GeneratorSet::new(7, "test_pixel").unwrap();
+        let mut db = InMemorySigKeyDatabase::new();
+        assert!(matches!(
+            Keypair::new(7, &gens, &mut failing, &mut db),
+            Err(ForwardSecureSignatureError::RngFailure { .. })
+        ));
+    }
+
+    #[test]
+    fn test_generator_set_bytes() {
+        let gens = GeneratorSet::new(7, "test_pixel").unwrap();
+        let bytes = gens.to_bytes();
+        let gens_1 = GeneratorSet::from_bytes(&bytes).unwrap();
+        assert_eq!(gens.0, gens_1.0);
+        assert_eq!(gens.1, gens_1.1);
+
+        assert!(GeneratorSet::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(GeneratorSet::from_bytes(&bytes[..VERKEY_GROUP_SIZE - 1]).is_err());
+    }
+
+    #[test]
+    fn test_generator_set_builder() {
+        let gens = GeneratorSet::new(7, "test_pixel").unwrap();
+        let gens_1 = GeneratorSetBuilder::new("test_pixel")
+            .depth(3)
+            .build()
+            .unwrap();
+        assert_eq!(gens.1, gens_1.1);
+        let gens_2 = GeneratorSetBuilder::new("test_pixel")
+            .max_time_period(7)
+            .build()
+            .unwrap();
+        assert_eq!(gens.1, gens_2.1);
+        assert!(GeneratorSetBuilder::new("test_pixel").build().is_err());
+        assert!(GeneratorSetBuilder::new("test_pixel")
+            .max_time_period(8)
+            .build()
+            .is_err());
+
+        let path = std::env::temp_dir().join(format!("fss_builder_{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+        // Creates cache file
+        let gens_3 = GeneratorSetBuilder::new("test_pixel")
+            .depth(3)
+            .cache_path(&path)
+            .build()
+            .unwrap();
+        assert_eq!(gens.1, gens_3.1);
+        assert!(path.exists());
+        // Loads from cache file
+        let gens_4 = GeneratorSetBuilder::new("test_pixel")
+            .depth(3)
+            .cache_path(&path)
+            .build()
+            .unwrap();
+        assert_eq!(gens.1, gens_4.1);
+        let mapped = GeneratorSetBuilder::new("test_pixel")
+            .depth(3)
+            .cache_path(&path)
+            .build_lazy()
+            .unwrap();
+        assert_eq!(mapped.h(4).unwrap(), &gens.1[4]);
+        // Cached generators are for a different depth
+        assert!(GeneratorSetBuilder::new("test_pixel")
+            .depth(4)
+            .cache_path(&path)
+            .build()
+            .is_err());
+        assert!(GeneratorSetBuilder::new("test_pixel")
+            .depth(3)
+            .build_lazy()
+            .is_err());
+        // Cached generators are for a different label
+        for lazy in &[false, true] {
+            let builder = GeneratorSetBuilder::new("other_label")
+                .depth(3)
+                .cache_path(&path);
+            if *lazy {
+                assert!(builder.build_lazy().is_err());
+            } else {
+                assert!(builder.build().is_err());
+            }
+        }
+        // Generators changed after the header was written
+        let mut bytes = fs::read(&path).unwrap();
+        let last = bytes.len() - 1;
+        bytes.swap(last, last - SIGNATURE_GROUP_SIZE);
+        fs::write(&path, &bytes).unwrap();
+        assert!(GeneratorSetBuilder::new("test_pixel")
+            .depth(3)
+            .cache_path(&path)
+            .build_lazy()
+            .is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_periods_for() {
+        let minute = Duration::from_secs(60);
+        // 5 years with a period every 10 minutes needs 262800 periods
+        let plan = periods_for(minute * 60 * 24 * 365 * 5, minute * 10).unwrap();
+        assert_eq!(plan.needed_periods, 262800);
+        assert_eq!(plan.T, 524287);
+        assert_eq!(plan.l, 19);
+        assert_eq!(calculate_l(plan.T).unwrap(), plan.l);
+        assert_eq!(
+            plan.generator_set_size,
+            GeneratorSet::new(plan.T, "test_pixel")
+                .unwrap()
+                .to_bytes()
+                .len()
+        );
+
+        assert_eq!(periods_for(minute * 7, minute).unwrap().T, 7);
+        assert_eq!(periods_for(minute * 8, minute).unwrap().T, 15);
+        assert_eq!(periods_for(minute * 71, minute * 10).unwrap().T, 15);
+        assert_eq!(periods_for(minute, minute).unwrap().T, 3);
+        assert_eq!(periods_for(Duration::from_secs(0), minute).unwrap().T, 3);
+        assert!(periods_for(minute, Duration::from_secs(0)).is_err());
+
+        // Largest number of keys is held at t = l
+        let plan = periods_for(minute * 15, minute).unwrap();
+        let successors = node_successor_paths(plan.l as u128, plan.l).unwrap();
+        assert_eq!(plan.max_sigkeys, successors.len() + 1);
+    }
+
     #[test]
     fn test_calculate_l() {
         assert!(calculate_l(u128::max_value()).is_err());
@@ -242,6 +808,21 @@ mod tests {
         assert_eq!(from_node_num_to_path(8, 4).unwrap(), vec![1, 2, 2]);
     }
 
+    #[test]
+    fn test_node_num_to_path_into() {
+        let mut path = [0u8; MAX_PATH_LEN];
+        for l in 2..6 {
+            for t in 1..(1u128 << l) {
+                let len = node_num_to_path_into(t, l, &mut path).unwrap();
+                assert_eq!(path[..len].to_vec(), from_node_num_to_path(t, l).unwrap());
+            }
+            assert!(node_num_to_path_into(0, l, &mut path).is_err());
+            assert!(node_num_to_path_into(1u128 << l, l, &mut path).is_err());
+        }
+        // Scratch too small for the path
+        assert!(node_num_to_path_into(4, 3, &mut [0u8; 1]).is_err());
+    }
+
     // TODO: Test to and from conversion of path and node number using randoms
 
     #[test]
@@ -0,0 +1,255 @@
+//! Encryption of a signature to an auditor with a proof that the ciphertext contains a valid
+//! signature on a given message for a given time period.
+//!
+//! The signature is first re-randomized with `r` like in `pok_sig`: `sigma_2' = sigma_2 + g2*r`
+//! and `sigma_1' = sigma_1 + (pf + h_{l+1}*m)*r`. Then `sigma_1'` is ElGamal encrypted under the
+//! auditor's public key `Y = G*y` as `(C_1, C_2) = (G*k, sigma_1' + Y*k)` and `sigma_2'` is sent
+//! in clear. A valid ciphertext satisfies
+//! `e(C_2, g2) / (e(h, vk) * e(pf + h_{l+1}*m, sigma_2')) = e(Y, g2)^k`, so the encryptor proves
+//! knowledge of `k` such that `C_1 = G*k` and the above holds with a Schnorr proof of equality of
+//! discrete logarithms.
+
+use amcl_wrapper::extension_field_gt::GT;
+use amcl_wrapper::field_elem::FieldElement;
+use amcl_wrapper::group_elem::GroupElement;
+use rand::{CryptoRng, RngCore};
+
+use crate::errors::ForwardSecureSignatureError;
+use crate::keys::Verkey;
+use crate::signature::Signature;
+use crate::util::{calculate_path_factor_using_t_l, random_field_element, GeneratorSet};
+use crate::{ate_multi_pairing, SignatureGroup, VerkeyGroup};
+
+const VERIFIABLE_ENC_DST: &[u8] = b"PIXEL-VERIFIABLE-ENC-V1";
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AuditorPublicKey(pub SignatureGroup);
+
+#[derive(Clone, Debug)]
+pub struct AuditorKeypair {
+    secret: FieldElement,
+    pub public: AuditorPublicKey,
+}
+
+impl AuditorKeypair {
+    pub fn new<R: RngCore + CryptoRng>(rng: &mut R) -> Result<Self, ForwardSecureSignatureError> {
+        let secret = random_field_element(rng)?;
+        let public = AuditorPublicKey(SignatureGroup::generator() * &secret);
+        Ok(Self { secret, public })
+    }
+
+    /// Decrypt the signature in `ciphertext`. The result is only a valid signature if the
+    /// ciphertext was verified with `SignatureCiphertext::verify` or was created honestly.
+    pub fn decrypt(&self, ciphertext: &SignatureCiphertext) -> Signature {
+        Signature {
+            sigma_1: &ciphertext.c_2 - &(&ciphertext.c_1 * &self.secret),
+            sigma_2: ciphertext.sigma_2.clone(),
+        }
+    }
+}
+
+/// Signature encrypted to an auditor along with the proof of validity
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SignatureCiphertext {
+    pub c_1: SignatureGroup,
+    pub c_2: SignatureGroup,
+    /// Re-randomized `sigma_2`
+    pub sigma_2: VerkeyGroup,
+    pub challenge: FieldElement,
+    pub response: FieldElement,
+}
+
+impl SignatureCiphertext {
+    /// Encrypt `sig` on `msg` for time period `t` to the auditor. The ciphertext of an invalid
+    /// signature does not verify.
+    pub fn new<R: RngCore + CryptoRng>(
+        sig: &Signature,
+        msg: &[u8],
+        t: u128,
+        l: u8,
+        gens: &GeneratorSet,
+        auditor: &AuditorPublicKey,
+        rng: &mut R,
+    ) -> Result<Self, ForwardSecureSignatureError> {
+        let msg_factor = msg_factor(msg, t, l, gens)?;
+        let r = random_field_element(rng)?;
+        let k = random_field_element(rng)?;
+        let sigma_2 = &sig.sigma_2 + (&gens.0 * &r);
+        let sigma_1 = &sig.sigma_1 + (&msg_factor * &r);
+        let c_1 = SignatureGroup::generator() * &k;
+        let c_2 = sigma_1 + (&auditor.0 * &k);
+
+        let w = random_field_element(rng)?;
+        let commitment_1 = SignatureGroup::generator() * &w;
+        let commitment_2 = base(auditor, gens).pow(&w);
+        let challenge = challenge(
+            &c_1,
+            &c_2,
+            &sigma_2,
+            (&commitment_1, &commitment_2),
+            msg,
+            t,
+            auditor,
+        );
+        Ok(Self {
+            c_1,
+            c_2,
+            sigma_2,
+            response: w - &challenge * &k,
+            challenge,
+        })
+    }
+
+    /// Check that the ciphertext contains a signature of `verkey` on `msg` for time period `t`
+    pub fn verify(
+        &self,
+        msg: &[u8],
+        t: u128,
+        l: u8,
+        gens: &GeneratorSet,
+        verkey: &Verkey,
+        auditor: &AuditorPublicKey,
+    ) -> Result<bool, ForwardSecureSignatureError> {
+        let msg_factor = msg_factor(msg, t, l, gens)?;
+        if self.sigma_2.is_identity() || verkey.is_identity() || auditor.0.is_identity() {
+            return Ok(false);
+        }
+        if !self.c_1.has_correct_order()
+            || !self.c_2.has_correct_order()
+            || !self.sigma_2.has_correct_order()
+        {
+            return Ok(false);
+        }
+        // z = e(C_2, g2) / (e(h, vk) * e(pf + h_{l+1}*m, sigma_2'))
+        let z = ate_multi_pairing(vec![
+            (&self.c_2, &gens.0),
+            (&gens.1[0].negation(), &verkey.value),
+            (&msg_factor.negation(), &self.sigma_2),
+        ]);
+        let commitment_1 =
+            (SignatureGroup::generator() * &self.response) + (&self.c_1 * &self.challenge);
+        let commitment_2 = base(auditor, gens).pow(&self.response) * z.pow(&self.challenge);
+        let c = challenge(
+            &self.c_1,
+            &self.c_2,
+            &self.sigma_2,
+            (&commitment_1, &commitment_2),
+            msg,
+            t,
+            auditor,
+        );
+        Ok(c == self.challenge)
+    }
+}
+
+/// Returns `pf + h_{l+1}*m`
+fn msg_factor(
+    msg: &[u8],
+    t: u128,
+    l: u8,
+    gens: &GeneratorSet,
+) -> Result<SignatureGroup, ForwardSecureSignatureError> {
+    if gens.1.len() < (l as usize + 2) {
+        return Err(ForwardSecureSignatureError::NotEnoughGenerators { n: l as usize + 2 });
+    }
+    let pf = calculate_path_factor_using_t_l(t, l, gens)?;
+    Ok(pf + (&gens.1[l as usize + 1] * Signature::hash_message(msg)))
+}
+
+/// Returns `e(Y, g2)`
+fn base(auditor: &AuditorPublicKey, gens: &GeneratorSet) -> GT {
+    ate_multi_pairing(vec![(&auditor.0, &gens.0)])
+}
+
+fn challenge(
+    c_1: &SignatureGroup,
+    c_2: &SignatureGroup,
+    sigma_2: &VerkeyGroup,
+    (commitment_1, commitment_2): (&SignatureGroup, &GT),
+    msg: &[u8],
+    t: u128,
+    auditor: &AuditorPublicKey,
+) -> FieldElement {
+    let mut bytes = VERIFIABLE_ENC_DST.to_vec();
+    bytes.append(&mut auditor.0.to_bytes());
+    bytes.append(&mut c_1.to_bytes());
+    bytes.append(&mut c_2.to_bytes());
+    bytes.append(&mut sigma_2.to_bytes());
+    bytes.append(&mut commitment_1.to_bytes());
+    bytes.append(&mut commitment_2.to_bytes());
+    bytes.extend_from_slice(&t.to_be_bytes());
+    bytes.extend_from_slice(msg);
+    FieldElement::from_msg_hash(&bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{InMemorySigKeyDatabase, Keypair};
+    use crate::util::calculate_l;
+
+    #[test]
+    fn test_verifiable_encryption() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let l = calculate_l(T).unwrap();
+        let t = 2u128;
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let mut db = InMemorySigKeyDatabase::new();
+        let (keypair, mut manager) = Keypair::new(T, &gens, &mut rng, &mut db).unwrap();
+        manager
+            .fast_forward_update(t, &gens, &mut rng, &mut db)
+            .unwrap();
+        let vk = &keypair.ver_key;
+        let sk = manager.get_current_key(&db).unwrap();
+        let msg = "Hello".as_bytes();
+        let sig = Signature::new(msg, t, l, &gens, sk, &mut rng).unwrap();
+
+        let auditor = AuditorKeypair::new(&mut rng).unwrap();
+        let ct =
+            SignatureCiphertext::new(&sig, msg, t, l, &gens, &auditor.public, &mut rng).unwrap();
+        assert!(ct.verify(msg, t, l, &gens, vk, &auditor.public).unwrap());
+        assert!(!ct.verify(b"Hi", t, l, &gens, vk, &auditor.public).unwrap());
+        assert!(!ct
+            .verify(msg, t + 1, l, &gens, vk, &auditor.public)
+            .unwrap());
+        let other_auditor = AuditorKeypair::new(&mut rng).unwrap();
+        assert!(!ct
+            .verify(msg, t, l, &gens, vk, &other_auditor.public)
+            .unwrap());
+
+        // Decryption gives a re-randomized valid signature
+        let decrypted = auditor.decrypt(&ct);
+        assert_ne!(decrypted, sig);
+        assert!(decrypted.verify(msg, t, l, &gens, vk).unwrap());
+        assert!(!other_auditor
+            .decrypt(&ct)
+            .verify(msg, t, l, &gens, vk)
+            .unwrap());
+
+        // Points outside the prime order subgroup are rejected before the pairing
+        #[cfg(feature = "VerkeyG2")]
+        {
+            let outside = crate::compression::g1_outside_subgroup();
+            let mut bad = ct.clone();
+            bad.c_1 = &bad.c_1 + &outside;
+            assert!(!bad.verify(msg, t, l, &gens, vk, &auditor.public).unwrap());
+            let mut bad = ct.clone();
+            bad.c_2 = &bad.c_2 + &outside;
+            assert!(!bad.verify(msg, t, l, &gens, vk, &auditor.public).unwrap());
+        }
+        #[cfg(feature = "VerkeyG1")]
+        {
+            let mut bad = ct.clone();
+            bad.sigma_2 = &bad.sigma_2 + &crate::compression::g1_outside_subgroup();
+            assert!(!bad.verify(msg, t, l, &gens, vk, &auditor.public).unwrap());
+        }
+
+        // Ciphertext of a signature on another message does not verify
+        let ct =
+            SignatureCiphertext::new(&sig, b"Hi", t, l, &gens, &auditor.public, &mut rng).unwrap();
+        assert!(!ct.verify(b"Hi", t, l, &gens, vk, &auditor.public).unwrap());
+    }
+}
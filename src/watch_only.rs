@@ -0,0 +1,170 @@
+//! Tracking of a remote signer without any of its secrets, for dashboards and standby
+//! infrastructure. `WatchOnlySigner` knows the verifier and the epochs of the signer, derives
+//! the time period the signer should be on from the wall clock and checks the signer's
+//! signatures and status beacons against it.
+
+use crate::beacon::StatusBeacon;
+use crate::epoch::EpochConfig;
+use crate::errors::ForwardSecureSignatureError;
+use crate::facade::Verifier;
+use crate::signature::Signature;
+use crate::util::calculate_l;
+
+pub struct WatchOnlySigner {
+    verifier: Verifier,
+    epochs: EpochConfig,
+    last_beacon: Option<StatusBeacon>,
+    /// Highest time period of a valid signature or beacon seen
+    highest_t: Option<u128>,
+}
+
+impl WatchOnlySigner {
+    /// Fails with `InconsistentPublicParams` if the epochs are for another maximum time period
+    /// than the verifier
+    pub fn new(
+        verifier: Verifier,
+        epochs: EpochConfig,
+    ) -> Result<Self, ForwardSecureSignatureError> {
+        if calculate_l(epochs.T)? != verifier.l() {
+            return Err(ForwardSecureSignatureError::InconsistentPublicParams {
+                msg: format!(
+                    "epochs for T={} but verifier for l={}",
+                    epochs.T,
+                    verifier.l()
+                ),
+            });
+        }
+        Ok(Self {
+            verifier,
+            epochs,
+            last_beacon: None,
+            highest_t: None,
+        })
+    }
+
+    pub fn verifier(&self) -> &Verifier {
+        &self.verifier
+    }
+
+    /// Time period the signer should be on at wall clock time `now`
+    pub fn expected_period(&self, now: u64) -> Result<u128, ForwardSecureSignatureError> {
+        self.epochs.period_at(now)
+    }
+
+    /// Highest time period of a valid signature or beacon seen so far
+    pub fn highest_period(&self) -> Option<u128> {
+        self.highest_t
+    }
+
+    /// Whether `sig` is a valid signature on `msg` for time period `t` that could have been
+    /// created by `now`. Signatures for later time periods than expected at `now` are rejected,
+    /// as the signer's key can't be there yet unless its clock is ahead or the key is misused.
+    pub fn verify_signature(
+        &mut self,
+        msg: &[u8],
+        t: u128,
+        sig: &Signature,
+        now: u64,
+    ) -> Result<bool, ForwardSecureSignatureError> {
+        if t > self.expected_period(now)? || !self.verifier.verify(msg, t, sig)? {
+            return Ok(false);
+        }
+        self.observe_period(t);
+        Ok(true)
+    }
+
+    /// Check `beacon` with `StatusBeacon::check` and that it is newer than the last beacon
+    /// accepted, so replayed beacons are rejected. A beacon with a lower sequence number is
+    /// accepted after a restart of the signer, shown by a lower uptime.
+    pub fn observe_beacon(
+        &mut self,
+        beacon: &StatusBeacon,
+        now: u64,
+        max_age: u64,
+    ) -> Result<(), ForwardSecureSignatureError> {
+        beacon.check(&self.verifier, &self.epochs, now, max_age)?;
+        if let Some(last) = &self.last_beacon {
+            let restarted = beacon.uptime < last.uptime;
+            if beacon.timestamp <= last.timestamp
+                || (!restarted && beacon.sequence <= last.sequence)
+            {
+                return Err(ForwardSecureSignatureError::InvalidBeacon {
+                    msg: format!(
+                        "beacon {} at {} is not newer than beacon {} at {}",
+                        beacon.sequence, beacon.timestamp, last.sequence, last.timestamp
+                    ),
+                });
+            }
+        }
+        self.observe_period(beacon.t);
+        self.last_beacon = Some(beacon.clone());
+        Ok(())
+    }
+
+    pub fn last_beacon(&self) -> Option<&StatusBeacon> {
+        self.last_beacon.as_ref()
+    }
+
+    /// Whether a beacon was accepted within `max_silence` seconds before `now`
+    pub fn is_alive(&self, now: u64, max_silence: u64) -> bool {
+        self.last_beacon
+            .as_ref()
+            .is_some_and(|b| b.timestamp <= now && now - b.timestamp <= max_silence)
+    }
+
+    fn observe_period(&mut self, t: u128) {
+        self.highest_t = Some(self.highest_t.map_or(t, |h| h.max(t)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::beacon::BeaconEmitter;
+    use crate::facade::Signer;
+    use crate::keys::InMemorySigKeyDatabase;
+    use crate::util::GeneratorSet;
+    use std::time::Duration;
+
+    #[test]
+    fn test_watch_only_signer() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let epochs = EpochConfig::new(1000, Duration::from_secs(60), T).unwrap();
+        let (mut signer, _) =
+            Signer::new(T, gens, InMemorySigKeyDatabase::new(), &mut rng).unwrap();
+        let mut watcher = WatchOnlySigner::new(signer.verifier(), epochs.clone()).unwrap();
+        assert!(WatchOnlySigner::new(
+            signer.verifier(),
+            EpochConfig::new(1000, Duration::from_secs(60), 15).unwrap()
+        )
+        .is_err());
+        assert_eq!(watcher.expected_period(1130).unwrap(), 3);
+
+        // Signatures ahead of the wall clock are rejected
+        let msg = "Hello".as_bytes();
+        signer.update_to(2, &mut rng).unwrap();
+        let sig = signer.sign(msg, &mut rng).unwrap();
+        assert!(!watcher.verify_signature(msg, 2, &sig, 1010).unwrap());
+        assert!(watcher.verify_signature(msg, 2, &sig, 1070).unwrap());
+        assert!(!watcher.verify_signature(msg, 3, &sig, 1130).unwrap());
+        assert_eq!(watcher.highest_period(), Some(2));
+
+        let mut emitter = BeaconEmitter::new(1000);
+        let first = emitter.emit(&mut signer, 1070, &mut rng).unwrap();
+        watcher.observe_beacon(&first, 1075, 30).unwrap();
+        assert!(watcher.is_alive(1080, 30));
+        assert!(!watcher.is_alive(1110, 30));
+        // Replays are rejected
+        assert!(watcher.observe_beacon(&first, 1080, 30).is_err());
+        let second = emitter.emit(&mut signer, 1090, &mut rng).unwrap();
+        watcher.observe_beacon(&second, 1090, 30).unwrap();
+        assert_eq!(watcher.last_beacon().unwrap().sequence, 1);
+
+        // After a restart numbering starts over
+        let mut restarted = BeaconEmitter::new(1095);
+        let beacon = restarted.emit(&mut signer, 1100, &mut rng).unwrap();
+        watcher.observe_beacon(&beacon, 1100, 30).unwrap();
+    }
+}
@@ -0,0 +1,141 @@
+use crate::epoch::EpochConfig;
+use crate::errors::ForwardSecureSignatureError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DriftSeverity {
+    Ok,
+    Warning,
+    Error,
+}
+
+/// Outcome of comparing the time period of a key with the time period of the wall clock
+#[derive(Clone, Debug, PartialEq)]
+pub struct DriftReport {
+    /// Current time period of the signing key
+    pub key_t: u128,
+    /// Time period according to the wall clock
+    pub expected_t: u128,
+    pub severity: DriftSeverity,
+}
+
+impl DriftReport {
+    /// Number of time periods the key lags behind the wall clock, negative if it is ahead
+    pub fn lag(&self) -> i128 {
+        if self.expected_t >= self.key_t {
+            (self.expected_t - self.key_t) as i128
+        } else {
+            -((self.key_t - self.expected_t) as i128)
+        }
+    }
+}
+
+/// Watches for drift between the time period of a signing key and the wall clock. A key lagging
+/// behind will be updated late and a key ahead of the wall clock cannot sign for the current
+/// time period anymore, so both are reported to the callback before signing fails.
+/// By default a key 1 period behind is a warning, expected just after a period boundary, and 2
+/// or more periods behind or any period ahead is an error.
+pub struct ClockSkewWatchdog<F: FnMut(&DriftReport)> {
+    epochs: EpochConfig,
+    warn_behind: u128,
+    error_behind: u128,
+    callback: F,
+}
+
+impl<F: FnMut(&DriftReport)> ClockSkewWatchdog<F> {
+    /// `callback` is called for every check that does not have severity `Ok`
+    pub fn new(epochs: EpochConfig, callback: F) -> Self {
+        Self {
+            epochs,
+            warn_behind: 1,
+            error_behind: 2,
+            callback,
+        }
+    }
+
+    /// Number of periods behind the wall clock after which a warning or an error is reported.
+    /// `error_behind` is raised to `warn_behind` if smaller.
+    pub fn with_thresholds(mut self, warn_behind: u128, error_behind: u128) -> Self {
+        self.warn_behind = warn_behind;
+        self.error_behind = std::cmp::max(warn_behind, error_behind);
+        self
+    }
+
+    /// Compare `key_t` with the time period of `wall_time`. Fails if `wall_time` is outside the
+    /// lifetime of the key.
+    pub fn check(
+        &mut self,
+        key_t: u128,
+        wall_time: u64,
+    ) -> Result<DriftReport, ForwardSecureSignatureError> {
+        let expected_t = self.epochs.period_at(wall_time)?;
+        let severity = if key_t > expected_t || expected_t - key_t >= self.error_behind {
+            DriftSeverity::Error
+        } else if expected_t - key_t >= self.warn_behind {
+            DriftSeverity::Warning
+        } else {
+            DriftSeverity::Ok
+        };
+        let report = DriftReport {
+            key_t,
+            expected_t,
+            severity,
+        };
+        if severity != DriftSeverity::Ok {
+            (self.callback)(&report);
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_clock_skew_watchdog() {
+        let epochs = EpochConfig::new(1000, Duration::from_secs(60), 15).unwrap();
+        let at = |t: u128| epochs.period_start(t).unwrap();
+        let mut reported = vec![];
+        {
+            let mut watchdog =
+                ClockSkewWatchdog::new(epochs.clone(), |r: &DriftReport| reported.push(r.clone()));
+            assert_eq!(
+                watchdog.check(3, at(3)).unwrap().severity,
+                DriftSeverity::Ok
+            );
+            assert_eq!(
+                watchdog.check(3, at(4)).unwrap().severity,
+                DriftSeverity::Warning
+            );
+            let report = watchdog.check(3, at(6)).unwrap();
+            assert_eq!(report.severity, DriftSeverity::Error);
+            assert_eq!(report.lag(), 3);
+            let report = watchdog.check(5, at(4)).unwrap();
+            assert_eq!(report.severity, DriftSeverity::Error);
+            assert_eq!(report.lag(), -1);
+            assert!(watchdog.check(1, 999).is_err());
+        }
+        assert_eq!(reported.len(), 3);
+        assert_eq!(reported[0].expected_t, 4);
+
+        let mut count = 0;
+        {
+            let mut watchdog = ClockSkewWatchdog::new(epochs.clone(), |_: &DriftReport| count += 1)
+                .with_thresholds(2, 4);
+            assert_eq!(
+                watchdog.check(3, at(4)).unwrap().severity,
+                DriftSeverity::Ok
+            );
+            assert_eq!(
+                watchdog.check(3, at(6)).unwrap().severity,
+                DriftSeverity::Warning
+            );
+            assert_eq!(
+                watchdog.check(3, at(7)).unwrap().severity,
+                DriftSeverity::Error
+            );
+        }
+        assert_eq!(count, 2);
+    }
+}
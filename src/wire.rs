@@ -0,0 +1,717 @@
+//! Compact encoding of signature shares for gossiping between signers and aggregators.
+//!
+//! A share is encoded as
+//! `varint(signer index) || varint(t) || flags || sigma_1 || sigma_2 [|| proof of possession]`
+//! where varints are unsigned LEB128 with the shortest possible encoding and bit 0 of `flags`
+//! says whether a proof of possession is attached. Points are compressed as in `compression`.
+//! Decoding never panics on malformed input and rejects trailing bytes.
+//!
+//! Signing keys have a compressed encoding for storage, `CompressedSigkey`, using the same point
+//! encoding, and `SealedSigkey` adds a checksum to it to detect corruption of stored keys.
+//! `Sigkey::to_versioned_bytes` prefixes it with magic bytes, a version and the node number so
+//! keys stored by one release can be read by later ones.
+//!
+//! Accountable aggregates are encoded as
+//! `AGGREGATE_WIRE_TAG || version || flags || varint(number of signers) || varint(signer) .. ||
+//! sigma_1 || sigma_2` with signers in increasing order. The tag identifies the bytes as an
+//! aggregate and the version and flags leave room for changes such as weights, half-aggregation
+//! or hierarchies. Decoders reject versions and flags they don't know with
+//! `UnsupportedWireVersion` or `InvalidWireBytes` rather than guessing. Peers agree on a version
+//! with `negotiate_aggregate_wire_version`.
+
+use std::convert::TryFrom;
+
+use amcl_wrapper::constants::MODBYTES;
+use amcl_wrapper::group_elem::GroupElement;
+use amcl_wrapper::group_elem_g1::G1;
+use amcl_wrapper::group_elem_g2::G2;
+use amcl_wrapper::types::GroupG1;
+
+use crate::compression::{compress_g2, decompress_g2, G2_COMPRESSED_SIZE};
+use crate::errors::ForwardSecureSignatureError;
+use crate::keys::{ProofOfPossession, Sigkey};
+use crate::session::AccountableAggregate;
+use crate::signature::Signature;
+use sha3::{Digest, Sha3_256};
+
+/// Size of a compressed point in G1
+pub const G1_COMPRESSED_SIZE: usize = MODBYTES + 1;
+
+const FLAG_POP: u8 = 1;
+
+/// First byte of an encoded accountable aggregate
+pub const AGGREGATE_WIRE_TAG: u8 = 0xa6;
+/// Version of the accountable aggregate encoding written by this crate
+pub const AGGREGATE_WIRE_VERSION: u8 = 1;
+/// Versions of the accountable aggregate encoding this crate can decode
+pub const SUPPORTED_AGGREGATE_WIRE_VERSIONS: &[u8] = &[1];
+
+/// Signature share of one signer as sent over the wire
+#[derive(Clone, Debug)]
+pub struct SignatureShare {
+    pub index: usize,
+    pub t: u128,
+    pub sig: Signature,
+    /// Proof of possession of the signer, for aggregators who have not seen it yet
+    pub pop: Option<ProofOfPossession>,
+}
+
+impl SignatureShare {
+    pub fn to_wire_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        write_varint(self.index as u128, &mut bytes);
+        write_varint(self.t, &mut bytes);
+        bytes.push(if self.pop.is_some() { FLAG_POP } else { 0 });
+        write_signature_group(&self.sig.sigma_1, &mut bytes);
+        write_verkey_group(&self.sig.sigma_2, &mut bytes);
+        if let Some(pop) = &self.pop {
+            write_signature_group(&pop.value, &mut bytes);
+        }
+        bytes
+    }
+
+    pub fn from_wire_bytes(bytes: &[u8]) -> Result<Self, ForwardSecureSignatureError> {
+        let mut reader = Reader { bytes };
+        let index = usize::try_from(reader.varint()?)
+            .map_err(|_| invalid_wire_bytes("signer index too large"))?;
+        let t = reader.varint()?;
+        let flags = reader.take(1)?[0];
+        if flags & !FLAG_POP != 0 {
+            return Err(invalid_wire_bytes("unknown flags"));
+        }
+        let sigma_1 = read_signature_group(&mut reader)?;
+        let sigma_2 = read_verkey_group(&mut reader)?;
+        let pop = if flags & FLAG_POP != 0 {
+            Some(ProofOfPossession {
+                value: read_signature_group(&mut reader)?,
+            })
+        } else {
+            None
+        };
+        if !reader.bytes.is_empty() {
+            return Err(invalid_wire_bytes("trailing bytes"));
+        }
+        Ok(Self {
+            index,
+            t,
+            sig: Signature { sigma_1, sigma_2 },
+            pop,
+        })
+    }
+}
+
+impl AccountableAggregate {
+    pub fn to_wire_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![AGGREGATE_WIRE_TAG, AGGREGATE_WIRE_VERSION, 0];
+        write_varint(self.signers.len() as u128, &mut bytes);
+        for index in &self.signers {
+            write_varint(*index as u128, &mut bytes);
+        }
+        write_signature_group(&self.signature.sigma_1, &mut bytes);
+        write_verkey_group(&self.signature.sigma_2, &mut bytes);
+        bytes
+    }
+
+    pub fn from_wire_bytes(bytes: &[u8]) -> Result<Self, ForwardSecureSignatureError> {
+        let mut reader = Reader { bytes };
+        aggregate_header(&mut reader)?;
+        if reader.take(1)?[0] != 0 {
+            return Err(invalid_wire_bytes("unknown flags"));
+        }
+        let n = reader.varint()?;
+        // Every signer takes at least one byte so this bounds the allocation
+        if n > reader.bytes.len() as u128 {
+            return Err(invalid_wire_bytes("invalid number of signers"));
+        }
+        let mut signers: Vec<usize> = Vec::with_capacity(n as usize);
+        for _ in 0..n {
+            let index = usize::try_from(reader.varint()?)
+                .map_err(|_| invalid_wire_bytes("signer index too large"))?;
+            if signers.last().is_some_and(|last| *last >= index) {
+                return Err(invalid_wire_bytes("signers not in increasing order"));
+            }
+            signers.push(index);
+        }
+        let sigma_1 = read_signature_group(&mut reader)?;
+        let sigma_2 = read_verkey_group(&mut reader)?;
+        if !reader.bytes.is_empty() {
+            return Err(invalid_wire_bytes("trailing bytes"));
+        }
+        Ok(Self {
+            signature: Signature { sigma_1, sigma_2 },
+            signers,
+        })
+    }
+}
+
+/// Version of the encoded accountable aggregate in `bytes`, without decoding the rest. Fails if
+/// the bytes are not an aggregate or the version is not supported.
+pub fn aggregate_wire_version(bytes: &[u8]) -> Result<u8, ForwardSecureSignatureError> {
+    aggregate_header(&mut Reader { bytes })
+}
+
+/// Highest version of the accountable aggregate encoding supported by both this crate and a
+/// peer supporting `peer_versions`, `None` if there is none
+pub fn negotiate_aggregate_wire_version(peer_versions: &[u8]) -> Option<u8> {
+    SUPPORTED_AGGREGATE_WIRE_VERSIONS
+        .iter()
+        .filter(|v| peer_versions.contains(v))
+        .max()
+        .copied()
+}
+
+fn aggregate_header(reader: &mut Reader) -> Result<u8, ForwardSecureSignatureError> {
+    if reader.take(1)?[0] != AGGREGATE_WIRE_TAG {
+        return Err(invalid_wire_bytes("not an accountable aggregate"));
+    }
+    let version = reader.take(1)?[0];
+    if !SUPPORTED_AGGREGATE_WIRE_VERSIONS.contains(&version) {
+        return Err(ForwardSecureSignatureError::UnsupportedWireVersion {
+            version,
+            supported: SUPPORTED_AGGREGATE_WIRE_VERSIONS.to_vec(),
+        });
+    }
+    Ok(version)
+}
+
+/// Storage form of a `Sigkey` encoded as `varint(number of elements) || c || d || e_{k+1} .. e_l`
+/// for a node at depth `k`. Like `Sigkey` it only holds the elements for the levels below the
+/// node, the generators they are derived from are not stored. Points are compressed, so it is
+/// about half the size of the uncompressed key. This is an encoding of a single key only, keys
+/// of different nodes are stored whole. Sharing components between them would need the keys to
+/// share randomness, which lets the key of their common ancestor be recomputed and breaks
+/// forward security.
+/// The bytes are validated on creation so conversion back to `Sigkey` cannot fail.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompressedSigkey {
+    bytes: Vec<u8>,
+}
+
+impl CompressedSigkey {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ForwardSecureSignatureError> {
+        decode_sigkey(bytes)?;
+        Ok(Self {
+            bytes: bytes.to_vec(),
+        })
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+}
+
+impl From<&Sigkey> for CompressedSigkey {
+    fn from(sk: &Sigkey) -> Self {
+        let mut bytes = vec![];
+        write_varint(sk.1.len() as u128, &mut bytes);
+        write_verkey_group(&sk.0, &mut bytes);
+        for e in &sk.1 {
+            write_signature_group(e, &mut bytes);
+        }
+        Self { bytes }
+    }
+}
+
+impl From<&CompressedSigkey> for Sigkey {
+    fn from(sk: &CompressedSigkey) -> Self {
+        decode_sigkey(&sk.bytes).expect("validated on creation")
+    }
+}
+
+const SEALED_SIGKEY_DST: &[u8] = b"PIXEL-SEALED-SIGKEY-V1";
+
+/// Size of the checksum of a `SealedSigkey`
+pub const SIGKEY_CHECKSUM_SIZE: usize = 32;
+
+/// Storage form of a `Sigkey` for time period `t` with an authenticated checksum, the
+/// `CompressedSigkey` bytes followed by
+/// `SHA3-256(DST || len(mac_key) || mac_key || t || compact bytes)` with lengths and `t` big
+/// endian. SHA3 is not open to length extension so prefixing the key makes the checksum a MAC.
+/// Keys that were corrupted or are stored under another time period fail to open.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SealedSigkey {
+    bytes: Vec<u8>,
+}
+
+fn sigkey_checksum(t: u128, compact: &[u8], mac_key: &[u8]) -> [u8; SIGKEY_CHECKSUM_SIZE] {
+    let mut hasher = Sha3_256::new();
+    hasher.input(SEALED_SIGKEY_DST);
+    hasher.input((mac_key.len() as u64).to_be_bytes());
+    hasher.input(mac_key);
+    hasher.input(t.to_be_bytes());
+    hasher.input(compact);
+    let mut checksum = [0; SIGKEY_CHECKSUM_SIZE];
+    checksum.copy_from_slice(&hasher.result());
+    checksum
+}
+
+impl SealedSigkey {
+    pub fn seal(t: u128, sk: &Sigkey, mac_key: &[u8]) -> Self {
+        let mut bytes = CompressedSigkey::from(sk).bytes;
+        let checksum = sigkey_checksum(t, &bytes, mac_key);
+        bytes.extend_from_slice(&checksum);
+        Self { bytes }
+    }
+
+    /// Wrap stored bytes, checked by `open`
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
+        Self { bytes }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Verify the checksum and decode the key for time period `t`. Fails with `SigkeyCorrupted`
+    /// if the checksum does not match.
+    pub fn open(&self, t: u128, mac_key: &[u8]) -> Result<Sigkey, ForwardSecureSignatureError> {
+        if self.bytes.len() < SIGKEY_CHECKSUM_SIZE {
+            return Err(ForwardSecureSignatureError::SigkeyCorrupted { t });
+        }
+        let (compact, checksum) = self.bytes.split_at(self.bytes.len() - SIGKEY_CHECKSUM_SIZE);
+        let expected = sigkey_checksum(t, compact, mac_key);
+        // Compare without exiting early
+        let diff = expected
+            .iter()
+            .zip(checksum)
+            .fold(0, |acc, (a, b)| acc | (a ^ b));
+        if diff != 0 {
+            return Err(ForwardSecureSignatureError::SigkeyCorrupted { t });
+        }
+        decode_sigkey(compact)
+    }
+}
+
+/// First bytes of a key in the versioned encoding
+pub const SIGKEY_MAGIC: &[u8; 4] = b"PXSK";
+/// Version of the versioned key encoding written by this crate
+pub const SIGKEY_FORMAT_VERSION: u8 = 1;
+/// Versions of the versioned key encoding this crate can decode
+pub const SUPPORTED_SIGKEY_FORMAT_VERSIONS: &[u8] = &[1];
+
+impl Sigkey {
+    /// Self-describing storage form of the key of node `t`, for databases keeping keys across
+    /// releases, encoded as `SIGKEY_MAGIC || version || varint(t) || key` with the key in the
+    /// `CompressedSigkey` encoding for version 1. Later versions may change what follows the
+    /// version, older versions stay readable.
+    pub fn to_versioned_bytes(&self, t: u128) -> Vec<u8> {
+        let mut bytes = SIGKEY_MAGIC.to_vec();
+        bytes.push(SIGKEY_FORMAT_VERSION);
+        write_varint(t, &mut bytes);
+        bytes.append(&mut CompressedSigkey::from(self).bytes);
+        bytes
+    }
+
+    /// Decode a key encoded with `to_versioned_bytes` by this or an earlier release, returning
+    /// its node number. Fails with `UnsupportedWireVersion` for versions of later releases.
+    pub fn from_versioned_bytes(bytes: &[u8]) -> Result<(u128, Self), ForwardSecureSignatureError> {
+        let mut reader = Reader { bytes };
+        if reader.take(SIGKEY_MAGIC.len())? != SIGKEY_MAGIC {
+            return Err(invalid_wire_bytes("not a versioned signing key"));
+        }
+        let version = reader.take(1)?[0];
+        if !SUPPORTED_SIGKEY_FORMAT_VERSIONS.contains(&version) {
+            return Err(ForwardSecureSignatureError::UnsupportedWireVersion {
+                version,
+                supported: SUPPORTED_SIGKEY_FORMAT_VERSIONS.to_vec(),
+            });
+        }
+        let t = reader.varint()?;
+        Ok((t, decode_sigkey(reader.bytes)?))
+    }
+}
+
+pub(crate) fn decode_sigkey(bytes: &[u8]) -> Result<Sigkey, ForwardSecureSignatureError> {
+    let mut reader = Reader { bytes };
+    let n = reader.varint()?;
+    // Every element takes at least one byte so this bounds the allocation
+    if n == 0 || n > reader.bytes.len() as u128 {
+        return Err(invalid_wire_bytes("invalid number of key elements"));
+    }
+    let c = read_verkey_group(&mut reader)?;
+    let mut elems = Vec::with_capacity(n as usize);
+    for _ in 0..n {
+        elems.push(read_signature_group(&mut reader)?);
+    }
+    if !reader.bytes.is_empty() {
+        return Err(invalid_wire_bytes("trailing bytes"));
+    }
+    Ok(Sigkey(c, elems))
+}
+
+fn invalid_wire_bytes(msg: &str) -> ForwardSecureSignatureError {
+    ForwardSecureSignatureError::InvalidWireBytes {
+        msg: msg.to_string(),
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], ForwardSecureSignatureError> {
+        if self.bytes.len() < n {
+            return Err(invalid_wire_bytes("unexpected end of input"));
+        }
+        let (head, tail) = self.bytes.split_at(n);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    /// Read unsigned LEB128, rejecting encodings that overflow or are not the shortest
+    fn varint(&mut self) -> Result<u128, ForwardSecureSignatureError> {
+        let mut value = 0u128;
+        let mut shift = 0;
+        loop {
+            let byte = self.take(1)?[0];
+            if shift == 126 && byte > 0x03 {
+                return Err(invalid_wire_bytes("varint overflow"));
+            }
+            value |= ((byte & 0x7f) as u128) << shift;
+            if byte & 0x80 == 0 {
+                if byte == 0 && shift > 0 {
+                    return Err(invalid_wire_bytes("non canonical varint"));
+                }
+                return Ok(value);
+            }
+            shift += 7;
+        }
+    }
+}
+
+pub(crate) fn write_varint(mut value: u128, out: &mut Vec<u8>) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+pub fn g1_to_compressed_bytes(p: &G1) -> [u8; G1_COMPRESSED_SIZE] {
+    let mut bytes = [0; G1_COMPRESSED_SIZE];
+    p.to_ecp().tobytes(&mut bytes, true);
+    bytes
+}
+
+/// Decompress a point in G1. Fails if the bytes are not a valid compressed point or are the
+/// point at infinity.
+pub fn g1_from_compressed_bytes(bytes: &[u8]) -> Result<G1, ForwardSecureSignatureError> {
+    if bytes.len() != G1_COMPRESSED_SIZE || (bytes[0] != 0x02 && bytes[0] != 0x03) {
+        return Err(invalid_wire_bytes("invalid compressed G1 point"));
+    }
+    let p: G1 = GroupG1::frombytes(bytes).into();
+    if p.is_identity() {
+        return Err(invalid_wire_bytes("invalid compressed G1 point"));
+    }
+    Ok(p)
+}
+
+fn write_g1(p: &G1, out: &mut Vec<u8>) {
+    out.extend_from_slice(&g1_to_compressed_bytes(p));
+}
+
+fn read_g1(reader: &mut Reader) -> Result<G1, ForwardSecureSignatureError> {
+    g1_from_compressed_bytes(reader.take(G1_COMPRESSED_SIZE)?)
+}
+
+fn write_g2(p: &G2, out: &mut Vec<u8>) {
+    out.append(&mut compress_g2(p));
+}
+
+fn read_g2(reader: &mut Reader) -> Result<G2, ForwardSecureSignatureError> {
+    decompress_g2(reader.take(G2_COMPRESSED_SIZE)?)
+}
+
+#[cfg(feature = "VerkeyG2")]
+use self::{read_g1 as read_signature_group, read_g2 as read_verkey_group};
+#[cfg(feature = "VerkeyG1")]
+use self::{read_g1 as read_verkey_group, read_g2 as read_signature_group};
+#[cfg(feature = "VerkeyG2")]
+use self::{write_g1 as write_signature_group, write_g2 as write_verkey_group};
+#[cfg(feature = "VerkeyG1")]
+use self::{write_g1 as write_verkey_group, write_g2 as write_signature_group};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{InMemorySigKeyDatabase, Keypair, SigKeyDb, SigManager};
+    use crate::util::{calculate_l, GeneratorSet};
+    use crate::{SIGNATURE_GROUP_SIZE, VERKEY_GROUP_SIZE};
+    use rand::RngCore;
+
+    #[test]
+    fn test_varint() {
+        for v in [0u128, 1, 127, 128, 300, u64::MAX as u128, u128::MAX].iter() {
+            let mut bytes = vec![];
+            write_varint(*v, &mut bytes);
+            let mut reader = Reader { bytes: &bytes };
+            assert_eq!(reader.varint().unwrap(), *v);
+            assert!(reader.bytes.is_empty());
+        }
+        let mut bytes = vec![];
+        write_varint(300, &mut bytes);
+        assert_eq!(bytes, vec![0xac, 0x02]);
+
+        // Non canonical, truncated and overflowing encodings
+        assert!(Reader {
+            bytes: &[0x80, 0x00]
+        }
+        .varint()
+        .is_err());
+        assert!(Reader { bytes: &[0x80] }.varint().is_err());
+        let mut max = vec![0xff; 18];
+        max.push(0x03);
+        assert_eq!(Reader { bytes: &max }.varint().unwrap(), u128::MAX);
+        max[18] = 0x04;
+        assert!(Reader { bytes: &max }.varint().is_err());
+    }
+
+    #[test]
+    fn test_aggregate_wire_format() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let l = calculate_l(T).unwrap();
+        let t = 1u128;
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let msg = "Hello".as_bytes();
+        let mut sigs = vec![];
+        for _ in 0..3 {
+            let mut db = InMemorySigKeyDatabase::new();
+            Keypair::new(T, &gens, &mut rng, &mut db).unwrap();
+            let sk = SigManager::get_key(t, &db).unwrap();
+            sigs.push(Signature::new(msg, t, l, &gens, sk, &mut rng).unwrap());
+        }
+        let aggr = AccountableAggregate {
+            signature: Signature::aggregate(sigs.iter().collect()),
+            signers: vec![1, 5, 300],
+        };
+        let bytes = aggr.to_wire_bytes();
+        assert_eq!(bytes[..3], [AGGREGATE_WIRE_TAG, AGGREGATE_WIRE_VERSION, 0]);
+        assert_eq!(aggregate_wire_version(&bytes).unwrap(), 1);
+        let decoded = AccountableAggregate::from_wire_bytes(&bytes).unwrap();
+        assert_eq!(decoded.signers, aggr.signers);
+        assert_eq!(decoded.signature, aggr.signature);
+
+        // A future version is identified and rejected instead of misread
+        let mut future = bytes.clone();
+        future[1] = 2;
+        match AccountableAggregate::from_wire_bytes(&future) {
+            Err(ForwardSecureSignatureError::UnsupportedWireVersion { version, .. }) => {
+                assert_eq!(version, 2)
+            }
+            _ => panic!("future version must be rejected"),
+        }
+        assert!(aggregate_wire_version(&future).is_err());
+        let mut flagged = bytes.clone();
+        flagged[2] = 1;
+        assert!(AccountableAggregate::from_wire_bytes(&flagged).is_err());
+        // A signature share is not an aggregate
+        let share = SignatureShare {
+            index: 1,
+            t,
+            sig: sigs[0].clone(),
+            pop: None,
+        };
+        assert!(aggregate_wire_version(&share.to_wire_bytes()).is_err());
+
+        let unordered = AccountableAggregate {
+            signature: aggr.signature.clone(),
+            signers: vec![5, 1],
+        };
+        assert!(AccountableAggregate::from_wire_bytes(&unordered.to_wire_bytes()).is_err());
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(AccountableAggregate::from_wire_bytes(&trailing).is_err());
+        for i in 0..bytes.len() {
+            assert!(AccountableAggregate::from_wire_bytes(&bytes[..i]).is_err());
+        }
+
+        assert_eq!(negotiate_aggregate_wire_version(&[1, 2, 3]), Some(1));
+        assert_eq!(negotiate_aggregate_wire_version(&[2]), None);
+    }
+
+    #[test]
+    fn test_share_wire_format() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let l = calculate_l(T).unwrap();
+        let t = 1u128;
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let msg = "Hello".as_bytes();
+        let mut db = InMemorySigKeyDatabase::new();
+        let (keypair, _) = Keypair::new(T, &gens, &mut rng, &mut db).unwrap();
+        let sk = SigManager::get_key(t, &db).unwrap();
+        let sig = Signature::new(msg, t, l, &gens, sk, &mut rng).unwrap();
+
+        let mut share = SignatureShare {
+            index: 300,
+            t,
+            sig,
+            pop: None,
+        };
+        let bytes = share.to_wire_bytes();
+        let len_without_pop = bytes.len();
+        assert!(len_without_pop < SIGNATURE_GROUP_SIZE + VERKEY_GROUP_SIZE);
+        let decoded = SignatureShare::from_wire_bytes(&bytes).unwrap();
+        assert_eq!(decoded.index, 300);
+        assert_eq!(decoded.t, t);
+        assert_eq!(decoded.sig, share.sig);
+        assert!(decoded.pop.is_none());
+        assert!(decoded
+            .sig
+            .verify(msg, t, l, &gens, &keypair.ver_key)
+            .unwrap());
+
+        share.pop = Some(keypair.pop.clone());
+        let bytes = share.to_wire_bytes();
+        let decoded = SignatureShare::from_wire_bytes(&bytes).unwrap();
+        assert!(Keypair::verify_pop(
+            &decoded.pop.unwrap(),
+            &keypair.ver_key,
+            &gens.0
+        ));
+
+        // Every truncation, trailing bytes and unknown flags are rejected
+        for i in 0..bytes.len() {
+            assert!(SignatureShare::from_wire_bytes(&bytes[..i]).is_err());
+        }
+        let mut extra = bytes.clone();
+        extra.push(0);
+        assert!(SignatureShare::from_wire_bytes(&extra).is_err());
+        let mut bad_flags = bytes.clone();
+        bad_flags[3] = 0x02;
+        assert!(SignatureShare::from_wire_bytes(&bad_flags).is_err());
+
+        // Random points do not panic
+        for _ in 0..100 {
+            let mut random = vec![0; len_without_pop - 3];
+            rng.fill_bytes(&mut random);
+            random.splice(0..0, vec![1, 1, 0]);
+            let _ = SignatureShare::from_wire_bytes(&random);
+        }
+    }
+
+    #[test]
+    fn test_compressed_sigkey() {
+        let mut rng = rand::thread_rng();
+        let T = 15;
+        let l = calculate_l(T).unwrap();
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let mut db = InMemorySigKeyDatabase::new();
+        let (keypair, mut manager) = Keypair::new(T, &gens, &mut rng, &mut db).unwrap();
+        manager
+            .fast_forward_update(3, &gens, &mut rng, &mut db)
+            .unwrap();
+
+        for t in db.get_key_indices() {
+            let sk = SigManager::get_key(t, &db).unwrap();
+            let compact = CompressedSigkey::from(sk);
+            #[cfg(feature = "VerkeyG2")]
+            assert_eq!(
+                compact.len(),
+                1 + G2_COMPRESSED_SIZE + sk.1.len() * G1_COMPRESSED_SIZE
+            );
+            let decoded = CompressedSigkey::from_bytes(compact.as_bytes()).unwrap();
+            let sk_1 = Sigkey::from(&decoded);
+            assert_eq!(sk_1.0, sk.0);
+            assert_eq!(sk_1.1, sk.1);
+        }
+
+        // Key restored from the compressed form signs
+        let compact = CompressedSigkey::from(manager.get_current_key(&db).unwrap());
+        let sk = Sigkey::from(&compact);
+        let msg = "Hello".as_bytes();
+        let sig = Signature::new(msg, 3, l, &gens, &sk, &mut rng).unwrap();
+        assert!(sig.verify(msg, 3, l, &gens, &keypair.ver_key).unwrap());
+
+        let bytes = compact.as_bytes();
+        for i in 0..bytes.len() {
+            assert!(CompressedSigkey::from_bytes(&bytes[..i]).is_err());
+        }
+        let mut extra = bytes.to_vec();
+        extra.push(0);
+        assert!(CompressedSigkey::from_bytes(&extra).is_err());
+        assert!(CompressedSigkey::from_bytes(&[0]).is_err());
+    }
+
+    #[test]
+    fn test_sealed_sigkey() {
+        let mut rng = rand::thread_rng();
+        let T = 7;
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let mut db = InMemorySigKeyDatabase::new();
+        let (_, manager) = Keypair::new(T, &gens, &mut rng, &mut db).unwrap();
+        let sk = manager.get_current_key(&db).unwrap();
+        let mac_key = b"storage key";
+
+        let sealed = SealedSigkey::seal(1, sk, mac_key);
+        let stored = SealedSigkey::from_bytes(sealed.as_bytes().to_vec());
+        let sk_1 = stored.open(1, mac_key).unwrap();
+        assert_eq!(sk_1.0, sk.0);
+        assert_eq!(sk_1.1, sk.1);
+
+        // Stored under another time period
+        assert!(matches!(
+            stored.open(2, mac_key),
+            Err(ForwardSecureSignatureError::SigkeyCorrupted { t: 2 })
+        ));
+        assert!(stored.open(1, b"other key").is_err());
+        let bytes = sealed.as_bytes();
+        for i in [0, 10, bytes.len() - 1] {
+            let mut flipped = bytes.to_vec();
+            flipped[i] ^= 1;
+            assert!(SealedSigkey::from_bytes(flipped).open(1, mac_key).is_err());
+        }
+        assert!(SealedSigkey::from_bytes(bytes[..20].to_vec())
+            .open(1, mac_key)
+            .is_err());
+    }
+
+    #[test]
+    fn test_versioned_sigkey() {
+        let mut rng = rand::thread_rng();
+        let T = 15;
+        let gens = GeneratorSet::new(T, "test_pixel").unwrap();
+        let mut db = InMemorySigKeyDatabase::new();
+        let (_, mut manager) = Keypair::new(T, &gens, &mut rng, &mut db).unwrap();
+        manager
+            .fast_forward_update(3, &gens, &mut rng, &mut db)
+            .unwrap();
+
+        for t in db.get_key_indices() {
+            let sk = SigManager::get_key(t, &db).unwrap();
+            let bytes = sk.to_versioned_bytes(t);
+            assert_eq!(bytes[..5], [b'P', b'X', b'S', b'K', SIGKEY_FORMAT_VERSION]);
+            let (t_1, sk_1) = Sigkey::from_versioned_bytes(&bytes).unwrap();
+            assert_eq!(t_1, t);
+            assert_eq!(sk_1.0, sk.0);
+            assert_eq!(sk_1.1, sk.1);
+        }
+
+        let bytes = manager.get_current_key(&db).unwrap().to_versioned_bytes(3);
+        let mut later = bytes.clone();
+        later[4] = 2;
+        assert!(matches!(
+            Sigkey::from_versioned_bytes(&later),
+            Err(ForwardSecureSignatureError::UnsupportedWireVersion { version: 2, .. })
+        ));
+        let mut other = bytes.clone();
+        other[0] = b'Q';
+        assert!(Sigkey::from_versioned_bytes(&other).is_err());
+        assert!(Sigkey::from_versioned_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Sigkey::from_versioned_bytes(&bytes[..6]).is_err());
+    }
+}